@@ -1,5 +1,7 @@
 use std::fmt;
+use std::io;
 
+use crate::bundle::{has_extension, TraceBundle, TraceFile};
 use crate::common::{
     BoundaryClassification, Completeness, ConfidenceMeta, ControlledVariable, ExecutionOutcome,
     ExperimentRef, Layer, NumericalEventType, ObservationMode, ProvenanceAnchor, Severity,
@@ -8,14 +10,21 @@ use crate::common::{
 use crate::convergence;
 use crate::event_kinds::EventKind;
 use crate::lel::{
-    ExperimentSpec, LayeredEventLog, LayeredEventLogBuilder, TraceEventBuilder,
+    ExperimentSpec, LayeredEventLog, LayeredEventLogBuilder, TraceEventBuildError,
+    TraceEventBuilder,
 };
+use crate::provenance::SourceText;
+use crate::symbol::Symbol;
 
 /// Error type for adapter operations.
 #[derive(Debug)]
 pub enum AdapterError {
     ParseError(String),
     UnsupportedFormat(String),
+    /// The adapter assembled an event that failed `TraceEventBuilder::try_build`.
+    InvalidEvent(TraceEventBuildError),
+    /// Reading a streamed source, or writing to an `EventSink`, failed.
+    Io(io::Error),
 }
 
 impl fmt::Display for AdapterError {
@@ -25,17 +34,124 @@ impl fmt::Display for AdapterError {
             AdapterError::UnsupportedFormat(msg) => {
                 write!(f, "Unsupported format: {}", msg)
             }
+            AdapterError::InvalidEvent(err) => write!(f, "Invalid event: {}", err),
+            AdapterError::Io(err) => write!(f, "I/O error: {}", err),
         }
     }
 }
 
-impl std::error::Error for AdapterError {}
+impl std::error::Error for AdapterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdapterError::InvalidEvent(err) => Some(err),
+            AdapterError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AdapterError {
+    fn from(err: io::Error) -> Self {
+        AdapterError::Io(err)
+    }
+}
+
+impl From<TraceEventBuildError> for AdapterError {
+    fn from(err: TraceEventBuildError) -> Self {
+        AdapterError::InvalidEvent(err)
+    }
+}
 
 /// Trait for DSL framework adapters.
 /// Each adapter translates framework-specific trace output into
 /// a LayeredEventLog.
 pub trait DslAdapter {
     fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError>;
+
+    /// Parse a run supplied as separate files, anchoring provenance to each
+    /// file's path. The default accepts a single file and passes its
+    /// contents to `parse_trace`, so provenance keeps whatever file name
+    /// `parse_trace` records; adapters override it to record the path.
+    fn parse_bundle(&self, bundle: &TraceBundle) -> Result<LayeredEventLog, AdapterError> {
+        match bundle.files() {
+            [file] => self.parse_trace(&file.contents),
+            files => Err(AdapterError::UnsupportedFormat(format!(
+                "{} adapter takes a single file, bundle has {}",
+                self.framework(),
+                files.len()
+            ))),
+        }
+    }
+
+    /// Short framework name, e.g. "gromacs".
+    fn framework(&self) -> &'static str;
+
+    /// How likely `raw` is input for this adapter, judged from textual
+    /// signatures. The default recognises nothing.
+    fn detect(&self, _raw: &str) -> Detection {
+        Detection::default()
+    }
+
+    /// How likely `file`, one file of a bundle, is input for this adapter,
+    /// judged from its name and contents. The default judges the contents
+    /// alone with `detect`.
+    fn detect_file(&self, file: &TraceFile) -> Detection {
+        self.detect(&file.contents)
+    }
+}
+
+/// Outcome of `DslAdapter::detect`: a confidence in `[0, 1]` and the
+/// signatures that support it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Detection {
+    pub confidence: f64,
+    pub signals: Vec<String>,
+}
+
+impl Detection {
+    /// Record a matched signature of `weight` in `[0, 1]`. Signatures count
+    /// as independent evidence: the confidence becomes `1 - prod(1 - w)`.
+    pub fn signal(&mut self, weight: f64, description: impl Into<String>) -> &mut Self {
+        self.confidence = 1.0 - (1.0 - self.confidence) * (1.0 - weight.clamp(0.0, 1.0));
+        self.signals.push(description.into());
+        self
+    }
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "confidence {:.2}", self.confidence)?;
+        if !self.signals.is_empty() {
+            write!(f, " ({})", self.signals.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys of `key = value` lines, with text after any of `comment` dropped.
+pub(crate) fn assignment_keys<'a>(
+    raw: &'a str,
+    comment: &'a [char],
+) -> impl Iterator<Item = &'a str> + 'a {
+    raw.lines().filter_map(move |line| {
+        let line = line.split(comment).next().unwrap_or("");
+        let (key, _) = line.split_once('=')?;
+        let key = key.trim();
+        (!key.is_empty() && !key.contains(char::is_whitespace)).then_some(key)
+    })
+}
+
+/// Weight for `count` recognised parameter keys: 0.15 each, at most 0.6.
+pub(crate) fn key_weight(count: usize) -> f64 {
+    (0.15 * count as f64).min(0.6)
+}
+
+/// True for an OpenMM `StateDataReporter` CSV header line.
+fn is_state_data_header(line: &str) -> bool {
+    let normalized = line.trim_start_matches('\u{feff}').trim_start_matches('#');
+    normalized.contains(',')
+        && normalized.contains("Step")
+        && normalized.contains("Potential Energy")
 }
 
 pub(crate) fn parse_openmm_energy_series(raw: &str) -> Vec<(u64, f64)> {
@@ -45,12 +161,7 @@ pub(crate) fn parse_openmm_energy_series(raw: &str) -> Vec<(u64, f64)> {
         .filter(|line| !line.is_empty())
         .collect();
     if let Some(first_line) = non_empty_lines.first().copied() {
-        let normalized_header =
-            first_line.trim_start_matches('\u{feff}').trim_start_matches('#');
-        let header_is_csv = normalized_header.contains(',')
-            && normalized_header.contains("Step")
-            && normalized_header.contains("Potential Energy");
-        if header_is_csv {
+        if is_state_data_header(first_line) {
             return parse_openmm_csv_energy_series(&non_empty_lines);
         }
     }
@@ -125,8 +236,80 @@ fn parse_openmm_csv_energy_series(lines: &[&str]) -> Vec<(u64, f64)> {
 /// Demonstrates layer diversity, temporal ordering, and Hybrid upgrade fields.
 pub struct MockOpenMmAdapter;
 
+/// File name recorded in provenance when input comes from `parse_trace`.
+const DEFAULT_OPENMM_LOG_FILE: &str = "simulation.log";
+
 impl DslAdapter for MockOpenMmAdapter {
+    fn framework(&self) -> &'static str {
+        "openmm"
+    }
+
+    /// `StateDataReporter` CSV headers, or a bare two-column step/energy
+    /// series.
+    fn detect(&self, raw: &str) -> Detection {
+        let mut detection = Detection::default();
+        let mut lines = raw.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next().is_some_and(is_state_data_header) {
+            detection.signal(0.8, "StateDataReporter CSV header");
+        }
+        let mut data_lines = raw
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+        let two_columns = data_lines.peek().is_some()
+            && data_lines.all(|line| {
+                let tokens: Vec<&str> = line.split_whitespace().collect();
+                tokens.len() == 2
+                    && tokens[0].parse::<u64>().is_ok()
+                    && tokens[1].parse::<f64>().is_ok()
+            });
+        if two_columns {
+            detection.signal(0.5, "two-column step/energy series");
+        }
+        detection
+    }
+
+    /// `detect` on the contents, plus the `.csv` extension reporters write.
+    fn detect_file(&self, file: &TraceFile) -> Detection {
+        let mut detection = self.detect(&file.contents);
+        if has_extension(&file.path, "csv") {
+            detection.signal(0.2, format!("file name {}", file.file_name()));
+        }
+        detection
+    }
+
     fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError> {
+        self.parse_file(DEFAULT_OPENMM_LOG_FILE, raw)
+    }
+
+    /// A single reporter file, anchored to its path.
+    fn parse_bundle(&self, bundle: &TraceBundle) -> Result<LayeredEventLog, AdapterError> {
+        match bundle.files() {
+            [file] => self.parse_file(&file.path, &file.contents),
+            files => Err(AdapterError::UnsupportedFormat(format!(
+                "{} adapter takes a single file, bundle has {}",
+                self.framework(),
+                files.len()
+            ))),
+        }
+    }
+}
+
+impl MockOpenMmAdapter {
+    /// Parse reporter output read from `path`. The mock events are not tied
+    /// to particular lines, so every event is anchored to the whole file.
+    pub fn parse_file(&self, path: &str, raw: &str) -> Result<LayeredEventLog, AdapterError> {
+        let source = SourceText::new(raw);
+        let default_provenance = source.anchor(&Symbol::from(path), 1, source.line_count() as u32);
+        self.parse_with_provenance(raw, default_provenance)
+    }
+
+    fn parse_with_provenance(
+        &self,
+        raw: &str,
+        mut default_provenance: ProvenanceAnchor,
+    ) -> Result<LayeredEventLog, AdapterError> {
         let experiment_ref = ExperimentRef {
             experiment_id: "openmm-mock-001".to_string(),
             cycle_id: 0,
@@ -141,33 +324,38 @@ impl DslAdapter for MockOpenMmAdapter {
             controlled_variables: vec![ControlledVariable {
                 id: SpecElementId(1),
                 parameter: "temperature".to_string(),
-                held_value: Value::Known(300.0, "K".to_string()),
+                held_value: Value::Known(300.0, "K".into()),
             }],
             dag_refs: Vec::new(),
             provenance: ProvenanceAnchor {
-                source_file: "experiment_spec.json".to_string(),
+                source_file: "experiment_spec.json".into(),
                 source_location: SourceLocation::ExternalInput,
                 raw_hash: 0xDEAD_BEEF,
             },
         };
 
-        let default_provenance = ProvenanceAnchor {
-            source_file: "simulation.log".to_string(),
-            source_location: SourceLocation::LineRange { start: 1, end: 10 },
-            raw_hash: 0xCAFE_BABE,
-        };
-
         let default_confidence = ConfidenceMeta {
             completeness: Completeness::FullyObserved,
             field_coverage: 1.0,
             notes: Vec::new(),
         };
 
+        // Names and units come from the log's symbol table, so the energy
+        // series shares one handle per name and unit.
+        let mut log_builder = LayeredEventLogBuilder::new(experiment_ref, spec);
+        log_builder
+            .symbols()
+            .intern_provenance(&mut default_provenance);
+        let picoseconds = log_builder.symbols().unit("ps");
+        let kj_per_mol = log_builder.symbols().unit("kJ/mol");
+        let kinetic = log_builder.symbols().intern("kinetic");
+        let potential = log_builder.symbols().intern("potential");
+
         // Event 1: Theory layer — force field selection (ParameterRecord)
         let event1 = TraceEventBuilder::new()
             .layer(Layer::Theory)
             .kind(EventKind::ParameterRecord {
-                name: "force_field".to_string(),
+                name: log_builder.symbols().intern("force_field"),
                 specified_value: Some(Value::KnownCat("amber14-all".to_string())),
                 actual_value: Value::KnownCat("amber14-all".to_string()),
                 units: None,
@@ -180,7 +368,7 @@ impl DslAdapter for MockOpenMmAdapter {
             })
             .provenance(default_provenance.clone())
             .confidence(default_confidence.clone())
-            .build();
+            .try_build(log_builder.ids())?;
 
         // Event 2: Methodology layer — timestep configuration (ParameterRecord)
         let event2 = TraceEventBuilder::new()
@@ -191,10 +379,10 @@ impl DslAdapter for MockOpenMmAdapter {
                     "Timestep affects both sampling methodology and numerical stability".to_string(),
             })
             .kind(EventKind::ParameterRecord {
-                name: "timestep".to_string(),
-                specified_value: Some(Value::Known(0.002, "ps".to_string())),
-                actual_value: Value::Known(0.002, "ps".to_string()),
-                units: Some("ps".to_string()),
+                name: log_builder.symbols().intern("timestep"),
+                specified_value: Some(Value::Known(0.002, picoseconds.clone())),
+                actual_value: Value::Known(0.002, picoseconds.clone()),
+                units: Some(picoseconds),
                 observation_mode: ObservationMode::Observational,
             })
             .temporal(TemporalCoord {
@@ -204,7 +392,7 @@ impl DslAdapter for MockOpenMmAdapter {
             })
             .provenance(default_provenance.clone())
             .confidence(default_confidence.clone())
-            .build();
+            .try_build(log_builder.ids())?;
 
         // Event 3: Implementation layer — platform/resource status
         let event3 = TraceEventBuilder::new()
@@ -212,7 +400,7 @@ impl DslAdapter for MockOpenMmAdapter {
             .kind(EventKind::ResourceStatus {
                 platform_type: "CUDA".to_string(),
                 device_ids: vec!["GPU:0".to_string()],
-                memory_allocated: Some(Value::Known(2048.0, "MB".to_string())),
+                memory_allocated: Some(Value::Known(2048.0, log_builder.symbols().unit("MB"))),
                 memory_peak: None,
                 parallelization: Some("SingleGPU".to_string()),
                 warnings: Vec::new(),
@@ -224,7 +412,7 @@ impl DslAdapter for MockOpenMmAdapter {
             })
             .provenance(default_provenance.clone())
             .confidence(default_confidence.clone())
-            .build();
+            .try_build(log_builder.ids())?;
 
         let mut events = Vec::new();
         events.push(event1);
@@ -246,15 +434,12 @@ impl DslAdapter for MockOpenMmAdapter {
             let energy_event = TraceEventBuilder::new()
                 .layer(Layer::Implementation)
                 .kind(EventKind::EnergyRecord {
-                    total: Value::Known(total_energy, "kJ/mol".to_string()),
+                    total: Value::Known(total_energy, kj_per_mol.clone()),
                     components: vec![
+                        (kinetic.clone(), Value::Known(12500.3, kj_per_mol.clone())),
                         (
-                            "kinetic".to_string(),
-                            Value::Known(12500.3, "kJ/mol".to_string()),
-                        ),
-                        (
-                            "potential".to_string(),
-                            Value::Known(total_energy - 12500.3, "kJ/mol".to_string()),
+                            potential.clone(),
+                            Value::Known(total_energy - 12500.3, kj_per_mol.clone()),
                         ),
                     ],
                 })
@@ -266,7 +451,7 @@ impl DslAdapter for MockOpenMmAdapter {
                 .causal_refs(vec![resource_event_id])
                 .provenance(default_provenance.clone())
                 .confidence(default_confidence.clone())
-                .build();
+                .try_build(log_builder.ids())?;
             logical_sequence += 1;
             let energy_event_id = energy_event.id;
             events.push(energy_event);
@@ -300,7 +485,7 @@ impl DslAdapter for MockOpenMmAdapter {
                     .causal_refs(vec![energy_event_id])
                     .provenance(default_provenance.clone())
                     .confidence(default_confidence.clone())
-                    .build();
+                    .try_build(log_builder.ids())?;
                 logical_sequence += 1;
                 events.push(numerical_event);
             }
@@ -318,7 +503,7 @@ impl DslAdapter for MockOpenMmAdapter {
                 wall_clock_ns: Some(15_000_000),
                 logical_sequence,
             })
-            .provenance(default_provenance)
+            .provenance(default_provenance.clone())
             .confidence(default_confidence);
 
         if let Some(last_energy_id) = events
@@ -330,18 +515,17 @@ impl DslAdapter for MockOpenMmAdapter {
             execution_builder = execution_builder.causal_refs(vec![last_energy_id]);
         }
 
-        events.push(execution_builder.build());
+        events.push(execution_builder.try_build(log_builder.ids())?);
 
-        if let Some(summary_event) =
-            convergence::derive_energy_convergence_summary(&events, "simulation.log")
-        {
+        if let Some(summary_event) = convergence::derive_energy_convergence_summary(
+            &events,
+            &default_provenance.source_file,
+            log_builder.ids(),
+        )? {
             events.push(summary_event);
         }
 
-        let mut log_builder = LayeredEventLogBuilder::new(experiment_ref, spec);
-        for event in events {
-            log_builder = log_builder.add_event(event);
-        }
+        log_builder.extend(events);
         let log = log_builder.build();
 
         Ok(log)
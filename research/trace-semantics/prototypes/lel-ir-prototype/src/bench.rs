@@ -1,19 +1,78 @@
 //! CausalOverlay construction benchmark for Thread #31 resolution.
 //!
 //! Measures wall-clock time for LEL log construction and real
-//! `CausalOverlay::from_log` construction at 4 scales (10^3 to 10^6 events).
+//! `CausalOverlay::from_log` construction at 4 scales (10^3 to 10^6 events),
+//! plus VASP ingestion of a synthetic OSZICAR of the same size. Heap
+//! allocations are counted by a wrapper around the system allocator.
 //! Zero external dependencies — uses std::time::Instant only.
 
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
+use lel_ir_prototype::adapter::DslAdapter;
 use lel_ir_prototype::common::{
     ExperimentRef, Layer, ObservationMode, ProvenanceAnchor, SourceLocation, TemporalCoord, Value,
 };
 use lel_ir_prototype::event_kinds::EventKind;
-use lel_ir_prototype::lel::{
-    reset_event_id_counter, ExperimentSpec, LayeredEventLogBuilder, TraceEventBuilder,
-};
+use lel_ir_prototype::lel::{ExperimentSpec, LayeredEventLogBuilder, TraceEventBuilder};
 use lel_ir_prototype::overlay::CausalOverlay;
+use lel_ir_prototype::symbol::Symbol;
+use lel_ir_prototype::vasp_adapter::VaspAdapter;
+
+/// `System`, counting allocations and allocated bytes.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Time and allocations of one benchmark phase.
+struct Phase {
+    start: Instant,
+    allocations: usize,
+    bytes: usize,
+}
+
+impl Phase {
+    fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// `(milliseconds, allocations, allocated MiB)` since `start`.
+    fn finish(&self) -> (f64, usize, f64) {
+        (
+            self.start.elapsed().as_secs_f64() * 1_000.0,
+            ALLOCATIONS.load(Ordering::Relaxed) - self.allocations,
+            (ALLOCATED_BYTES.load(Ordering::Relaxed) - self.bytes) as f64 / (1024.0 * 1024.0),
+        )
+    }
+}
 
 fn main() {
     println!("LEL IR Prototype — CausalOverlay Construction Benchmark");
@@ -30,9 +89,6 @@ fn main() {
 }
 
 fn run_benchmark(n: usize) {
-    // Reset global event ID counter for each run
-    reset_event_id_counter();
-
     // Simple deterministic LCG (no extra dependencies)
     let mut rng_state: u64 = 0x5DEE_CE66_D1A4_F681;
     let mut next = || -> u64 {
@@ -43,7 +99,7 @@ fn run_benchmark(n: usize) {
     };
 
     // ── Phase 1: Log construction ──────────────────────────────
-    let t_log_start = Instant::now();
+    let log_phase = Phase::start();
 
     let exp_ref = ExperimentRef {
         experiment_id: format!("bench-{n}"),
@@ -58,7 +114,7 @@ fn run_benchmark(n: usize) {
         controlled_variables: vec![],
         dag_refs: vec![],
         provenance: ProvenanceAnchor {
-            source_file: "bench".to_string(),
+            source_file: "bench".into(),
             source_location: SourceLocation::ExternalInput,
             raw_hash: 0,
         },
@@ -66,6 +122,22 @@ fn run_benchmark(n: usize) {
 
     let mut builder = LayeredEventLogBuilder::new(exp_ref, spec);
 
+    // Names and units come from the log's symbol table, so each event
+    // shares one allocation per distinct string.
+    let symbols = builder.symbols();
+    let param_names: Vec<Symbol> = (0..20)
+        .map(|k| symbols.intern(&format!("param_{k}")))
+        .collect();
+    let node_names: Vec<Symbol> = (0..50)
+        .map(|k| symbols.intern(&format!("node_{k}")))
+        .collect();
+    let nm = symbols.unit("nm");
+    let provenance = ProvenanceAnchor {
+        source_file: symbols.intern("bench"),
+        source_location: SourceLocation::ExternalInput,
+        raw_hash: 0,
+    };
+
     for i in 0..n {
         // Layer distribution: 70% Implementation, 20% Methodology, 10% Theory
         let layer = match next() % 10 {
@@ -76,7 +148,7 @@ fn run_benchmark(n: usize) {
 
         // ~30% of events carry dag_node_ref (50 unique node names)
         let dag_ref = if next() % 100 < 30 {
-            Some(format!("node_{}", next() % 50))
+            Some(node_names[(next() % 50) as usize].clone())
         } else {
             None
         };
@@ -94,10 +166,10 @@ fn run_benchmark(n: usize) {
         let mut eb = TraceEventBuilder::new()
             .layer(layer)
             .kind(EventKind::ParameterRecord {
-                name: format!("param_{}", i % 20),
+                name: param_names[i % 20].clone(),
                 specified_value: None,
-                actual_value: Value::Known(1.0, "nm".to_string()),
-                units: Some("nm".to_string()),
+                actual_value: Value::Known(1.0, nm.clone()),
+                units: Some(nm.clone()),
                 observation_mode: ObservationMode::Observational,
             })
             .temporal(TemporalCoord {
@@ -105,17 +177,20 @@ fn run_benchmark(n: usize) {
                 wall_clock_ns: Some(i as u64 * 1_000),
                 logical_sequence: i as u64,
             })
-            .causal_refs(causal_refs);
+            .causal_refs(causal_refs)
+            .provenance(provenance.clone());
 
         if let Some(d) = dag_ref {
             eb = eb.dag_node_ref(d);
         }
 
-        builder = builder.add_event(eb.build());
+        // IDs come from the log builder's own allocator, starting at 1 each run
+        let event = eb.build(builder.ids());
+        builder.push(event);
     }
 
     let log = builder.build();
-    let log_ms = t_log_start.elapsed().as_secs_f64() * 1_000.0;
+    let (log_ms, log_allocations, log_mib) = log_phase.finish();
 
     // ── Phase 2: Overlay construction (single O(n) pass) ───────
     let t_overlay_start = Instant::now();
@@ -128,11 +203,19 @@ fn run_benchmark(n: usize) {
 
     let overlay_ms = t_overlay_start.elapsed().as_secs_f64() * 1_000.0;
 
+    // ── Phase 3: Adapter ingestion (VASP OSZICAR) ──────────────
+    let raw = synthetic_vasp_run(n);
+    let ingest_phase = Phase::start();
+    let ingested = VaspAdapter
+        .parse_trace(&raw)
+        .expect("synthetic VASP run parses");
+    let (ingest_ms, ingest_allocations, ingest_mib) = ingest_phase.finish();
+
     // ── Report ─────────────────────────────────────────────────
     println!("Scale: {:>10} events", n);
     println!(
-        "  Log construction:     {:>10.2} ms",
-        log_ms
+        "  Log construction:     {:>10.2} ms  ({} allocations, {:.1} MiB)",
+        log_ms, log_allocations, log_mib
     );
     println!(
         "  Overlay construction: {:>10.2} ms",
@@ -152,5 +235,44 @@ fn run_benchmark(n: usize) {
         "  DAG node groups:      {:>10}",
         overlay.entity_by_dag_node.len()
     );
+    println!("  Interned symbols:     {:>10}", log.symbols.len());
+    println!(
+        "  VASP ingestion:       {:>10.2} ms  ({} events, {} allocations, {:.1} MiB)",
+        ingest_ms,
+        ingested.events.len(),
+        ingest_allocations,
+        ingest_mib
+    );
     println!();
 }
+
+/// INCAR plus an OSZICAR of about `n` lines: ionic steps of nine SCF
+/// iterations, each a `ConvergencePoint`, closed by an energy line.
+fn synthetic_vasp_run(n: usize) -> String {
+    let mut raw = String::from("--- INCAR ---\nENCUT = 520\nEDIFF = 1E-6\n--- OSZICAR ---\n");
+    let mut ionic = 0;
+    let mut lines = 0;
+    while lines < n {
+        ionic += 1;
+        for scf in 1..=9 {
+            let delta = 10f64.powi(-scf);
+            writeln!(
+                raw,
+                "DAV: {:>3}   -0.{:03}E+03   {:.5E}   -0.100E-01   240   0.800E+01",
+                scf,
+                ionic % 1000,
+                -delta
+            )
+            .unwrap();
+        }
+        writeln!(
+            raw,
+            "{:>4} F= -.11401725E+03 E0= -.11400000E+03  d E =-.{:08}E-03",
+            ionic,
+            ionic % 100_000_000
+        )
+        .unwrap();
+        lines += 10;
+    }
+    raw
+}
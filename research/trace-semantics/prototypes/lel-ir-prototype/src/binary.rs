@@ -0,0 +1,1226 @@
+//! Compact binary container for `LayeredEventLog`.
+//!
+//! Layout: the magic bytes `LELB`, a little-endian `u16` schema version, then
+//! a sequence of sections, each `tag: u8, compression: u8, len: u64 LE,
+//! payload`. Sections are:
+//!
+//! - `STRINGS`: strings appended to the string table. Every string in the
+//!   payloads below is a varint index into that table, so repeated names and
+//!   units are stored once. A table delta precedes each section that
+//!   introduces new strings, which lets the writer stream.
+//! - `HEADER`: the `ExperimentRef` and `ExperimentSpec`.
+//! - `EVENTS`: a varint count followed by that many `TraceEvent`s. A log is
+//!   split into blocks of `BinaryOptions::block_size` events, so a reader
+//!   holds one block at a time.
+//! - `INDEX` (optional, version 2): for each `EVENTS` block, its event count
+//!   and the byte offset of every event in the block payload, followed by
+//!   the log's `EventIndexes`. Lets `mapped::MappedLog` find and decode one
+//!   event without reading the rest. Streaming readers skip it.
+//! - `END`: marks a complete file.
+//!
+//! Payloads use a non-self-describing serde encoding: varints for integers,
+//! raw IEEE-754 bits for floats (so NaN payloads and infinities survive),
+//! and declaration order for struct fields and enum variants. `decode_log`
+//! rebuilds indexes rather than trusting `INDEX`. Each section is deflate-compressed
+//! only when `BinaryOptions::compression` asks for it and it actually
+//! shrinks the section.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use serde::Deserialize;
+
+use crate::common::{EventId, EventKindTag, ExperimentRef, Layer};
+use crate::lel::{
+    EventIndexes, ExperimentSpec, LayeredEventLog, LayeredEventLogBuilder, TraceEvent,
+};
+use crate::symbol::Symbol;
+
+/// File signature.
+pub const BINARY_MAGIC: [u8; 4] = *b"LELB";
+
+/// Version of the section layout and payload encoding written by this build.
+///
+/// - 1: strings, header, events.
+/// - 2: optional `INDEX` section.
+pub const BINARY_SCHEMA_VERSION: u16 = 2;
+
+pub(crate) const SECTION_END: u8 = 0;
+pub(crate) const SECTION_STRINGS: u8 = 1;
+pub(crate) const SECTION_HEADER: u8 = 2;
+pub(crate) const SECTION_EVENTS: u8 = 3;
+pub(crate) const SECTION_INDEX: u8 = 4;
+
+pub(crate) const COMPRESSION_NONE: u8 = 0;
+pub(crate) const COMPRESSION_DEFLATE: u8 = 1;
+
+/// Section compression requested by the writer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+/// Writer settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryOptions {
+    pub compression: Compression,
+    /// Events per `EVENTS` section.
+    pub block_size: usize,
+    /// Write an `INDEX` section for random access.
+    pub index: bool,
+}
+
+impl Default for BinaryOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::None,
+            block_size: 4096,
+            index: true,
+        }
+    }
+}
+
+/// Errors reading or writing the binary format.
+#[derive(Debug)]
+pub enum BinaryError {
+    Io(io::Error),
+    /// The input does not start with `BINARY_MAGIC`.
+    BadMagic([u8; 4]),
+    /// The file was written by a newer schema version.
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+    },
+    /// Structurally invalid input.
+    Corrupt(String),
+    /// A value could not be encoded or decoded.
+    Serde(String),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::Io(err) => write!(f, "I/O error: {}", err),
+            BinaryError::BadMagic(found) => write!(f, "not a LEL binary file (magic {:?})", found),
+            BinaryError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "binary schema version {} is newer than supported version {}",
+                found, supported
+            ),
+            BinaryError::Corrupt(detail) => write!(f, "corrupt binary log: {}", detail),
+            BinaryError::Serde(detail) => write!(f, "binary encoding error: {}", detail),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BinaryError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BinaryError {
+    fn from(err: io::Error) -> Self {
+        BinaryError::Io(err)
+    }
+}
+
+impl ser::Error for BinaryError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryError::Serde(msg.to_string())
+    }
+}
+
+impl de::Error for BinaryError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        BinaryError::Serde(msg.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, BinaryError>;
+
+// ============================================================
+// Writing
+// ============================================================
+
+/// Encode `log` into a byte vector.
+pub fn encode_log(log: &LayeredEventLog, options: BinaryOptions) -> Result<Vec<u8>> {
+    let mut writer = BinaryLogWriter::new(Vec::new(), options)?;
+    writer.write_header(&log.experiment_ref, &log.spec)?;
+    writer.write_events(&log.events)?;
+    writer.finish()
+}
+
+/// Streaming writer: header first, then any number of event batches.
+pub struct BinaryLogWriter<W: Write> {
+    out: W,
+    options: BinaryOptions,
+    strings: HashMap<String, u64>,
+    /// Strings interned since the last `STRINGS` section.
+    pending: Vec<String>,
+    /// Contents of the `INDEX` section, when requested.
+    index: Option<BlockIndex>,
+}
+
+/// `EventIndexes` with its hash maps in key order, so the `INDEX` section is
+/// deterministic. Encodes exactly as `EventIndexes` does.
+#[derive(serde::Serialize)]
+struct SortedIndexes<'a> {
+    by_id: BTreeMap<&'a EventId, &'a usize>,
+    by_layer: BTreeMap<&'a Layer, &'a Vec<EventId>>,
+    by_kind: BTreeMap<&'a EventKindTag, &'a Vec<EventId>>,
+    by_time_range: &'a BTreeMap<u64, EventId>,
+    by_step: &'a BTreeMap<u64, Vec<EventId>>,
+    by_variable: BTreeMap<&'a Symbol, &'a Vec<EventId>>,
+    by_dag_node: BTreeMap<&'a Symbol, &'a Vec<EventId>>,
+}
+
+impl<'a> SortedIndexes<'a> {
+    fn new(indexes: &'a EventIndexes) -> Self {
+        Self {
+            by_id: indexes.by_id.iter().collect(),
+            by_layer: indexes.by_layer.iter().collect(),
+            by_kind: indexes.by_kind.iter().collect(),
+            by_time_range: &indexes.by_time_range,
+            by_step: &indexes.by_step,
+            by_variable: indexes.by_variable.iter().collect(),
+            by_dag_node: indexes.by_dag_node.iter().collect(),
+        }
+    }
+}
+
+/// Event offsets per block and the indexes of everything written so far.
+struct BlockIndex {
+    offsets: Vec<Vec<u64>>,
+    indexes: EventIndexes,
+    position: usize,
+}
+
+impl<W: Write> BinaryLogWriter<W> {
+    /// Write the magic bytes and schema version.
+    pub fn new(mut out: W, options: BinaryOptions) -> Result<Self> {
+        out.write_all(&BINARY_MAGIC)?;
+        out.write_all(&BINARY_SCHEMA_VERSION.to_le_bytes())?;
+        Ok(Self {
+            out,
+            options,
+            strings: HashMap::new(),
+            pending: Vec::new(),
+            index: options.index.then(|| BlockIndex {
+                offsets: Vec::new(),
+                indexes: EventIndexes::new(),
+                position: 0,
+            }),
+        })
+    }
+
+    pub fn write_header(
+        &mut self,
+        experiment_ref: &ExperimentRef,
+        spec: &ExperimentSpec,
+    ) -> Result<()> {
+        let mut payload = Vec::new();
+        let mut encoder = Encoder {
+            out: &mut payload,
+            strings: &mut self.strings,
+            pending: &mut self.pending,
+        };
+        experiment_ref.serialize(&mut encoder)?;
+        spec.serialize(&mut encoder)?;
+        self.write_section(SECTION_HEADER, payload)
+    }
+
+    /// Append events, split into blocks of `block_size`.
+    pub fn write_events(&mut self, events: &[TraceEvent]) -> Result<()> {
+        for block in events.chunks(self.options.block_size.max(1)) {
+            let mut payload = Vec::new();
+            write_varint(&mut payload, block.len() as u64);
+            let mut encoder = Encoder {
+                out: &mut payload,
+                strings: &mut self.strings,
+                pending: &mut self.pending,
+            };
+            let mut offsets = Vec::with_capacity(block.len());
+            for event in block {
+                offsets.push(encoder.out.len() as u64);
+                event.serialize(&mut encoder)?;
+            }
+            if let Some(index) = &mut self.index {
+                for event in block {
+                    index.indexes.index_event(event, index.position);
+                    index.position += 1;
+                }
+                index.offsets.push(offsets);
+            }
+            self.write_section(SECTION_EVENTS, payload)?;
+        }
+        Ok(())
+    }
+
+    /// Write the index, if requested, and the end marker, and return the
+    /// underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if let Some(index) = self.index.take() {
+            let mut payload = Vec::new();
+            write_varint(&mut payload, index.offsets.len() as u64);
+            for offsets in &index.offsets {
+                write_varint(&mut payload, offsets.len() as u64);
+                for offset in offsets {
+                    write_varint(&mut payload, *offset);
+                }
+            }
+            let mut encoder = Encoder {
+                out: &mut payload,
+                strings: &mut self.strings,
+                pending: &mut self.pending,
+            };
+            SortedIndexes::new(&index.indexes).serialize(&mut encoder)?;
+            self.write_section(SECTION_INDEX, payload)?;
+        }
+        self.write_raw_section(SECTION_END, COMPRESSION_NONE, &[])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Emit pending strings, then the section itself.
+    fn write_section(&mut self, tag: u8, payload: Vec<u8>) -> Result<()> {
+        if !self.pending.is_empty() {
+            let mut strings = Vec::new();
+            write_varint(&mut strings, self.pending.len() as u64);
+            for string in self.pending.drain(..) {
+                write_varint(&mut strings, string.len() as u64);
+                strings.extend_from_slice(string.as_bytes());
+            }
+            self.write_compressible(SECTION_STRINGS, strings)?;
+        }
+        self.write_compressible(tag, payload)
+    }
+
+    fn write_compressible(&mut self, tag: u8, payload: Vec<u8>) -> Result<()> {
+        if self.options.compression == Compression::Deflate {
+            let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&payload)?;
+            let compressed = encoder.finish()?;
+            if compressed.len() < payload.len() {
+                return self.write_raw_section(tag, COMPRESSION_DEFLATE, &compressed);
+            }
+        }
+        self.write_raw_section(tag, COMPRESSION_NONE, &payload)
+    }
+
+    fn write_raw_section(&mut self, tag: u8, compression: u8, payload: &[u8]) -> Result<()> {
+        self.out.write_all(&[tag, compression])?;
+        self.out.write_all(&(payload.len() as u64).to_le_bytes())?;
+        self.out.write_all(payload)?;
+        Ok(())
+    }
+}
+
+// ============================================================
+// Reading
+// ============================================================
+
+/// Decode a complete log from bytes, rebuilding indexes.
+pub fn decode_log(bytes: &[u8]) -> Result<LayeredEventLog> {
+    BinaryLogReader::new(bytes)?.read_log()
+}
+
+/// Streaming reader. Construction reads up to and including the header;
+/// iterating yields events one block at a time.
+pub struct BinaryLogReader<R: Read> {
+    input: R,
+    strings: Vec<String>,
+    experiment_ref: ExperimentRef,
+    spec: ExperimentSpec,
+    /// Decoded events of the current block not yet yielded, in reverse.
+    block: Vec<TraceEvent>,
+    finished: bool,
+}
+
+impl<R: Read> BinaryLogReader<R> {
+    pub fn new(mut input: R) -> Result<Self> {
+        read_preamble(&mut input)?;
+
+        let mut strings = Vec::new();
+        loop {
+            let (tag, payload) = read_section(&mut input)?
+                .ok_or_else(|| BinaryError::Corrupt("missing header section".to_string()))?;
+            match tag {
+                SECTION_STRINGS => extend_strings(&mut strings, &payload)?,
+                SECTION_HEADER => {
+                    let mut decoder = Decoder::new(&payload, &strings);
+                    let experiment_ref = ExperimentRef::deserialize(&mut decoder)?;
+                    let spec = ExperimentSpec::deserialize(&mut decoder)?;
+                    decoder.finish()?;
+                    return Ok(Self {
+                        input,
+                        strings,
+                        experiment_ref,
+                        spec,
+                        block: Vec::new(),
+                        finished: false,
+                    });
+                }
+                other => {
+                    return Err(BinaryError::Corrupt(format!(
+                        "section {} before header",
+                        other
+                    )))
+                }
+            }
+        }
+    }
+
+    pub fn experiment_ref(&self) -> &ExperimentRef {
+        &self.experiment_ref
+    }
+
+    pub fn spec(&self) -> &ExperimentSpec {
+        &self.spec
+    }
+
+    /// Read the remaining events into a log with rebuilt indexes.
+    pub fn read_log(mut self) -> Result<LayeredEventLog> {
+        let mut events = Vec::new();
+        for event in &mut self {
+            events.push(event?);
+        }
+        let mut builder = LayeredEventLogBuilder::new(self.experiment_ref, self.spec);
+        builder.extend(events);
+        Ok(builder.build())
+    }
+
+    /// Decode the next `EVENTS` block; false at the end marker.
+    fn next_block(&mut self) -> Result<bool> {
+        loop {
+            let (tag, payload) = read_section(&mut self.input)?
+                .ok_or_else(|| BinaryError::Corrupt("missing end marker".to_string()))?;
+            match tag {
+                SECTION_END => return Ok(false),
+                SECTION_STRINGS => extend_strings(&mut self.strings, &payload)?,
+                SECTION_INDEX => {}
+                SECTION_EVENTS => {
+                    let mut decoder = Decoder::new(&payload, &self.strings);
+                    let count = decoder.read_varint()?;
+                    let mut block = Vec::with_capacity(count.min(1 << 16) as usize);
+                    for _ in 0..count {
+                        block.push(TraceEvent::deserialize(&mut decoder)?);
+                    }
+                    decoder.finish()?;
+                    block.reverse();
+                    self.block = block;
+                    return Ok(true);
+                }
+                other => {
+                    return Err(BinaryError::Corrupt(format!(
+                        "unexpected section {}",
+                        other
+                    )))
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for BinaryLogReader<R> {
+    type Item = Result<TraceEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.block.is_empty() {
+            if self.finished {
+                return None;
+            }
+            match self.next_block() {
+                Ok(true) => {}
+                Ok(false) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.block.pop().map(Ok)
+    }
+}
+
+/// Check the magic bytes and schema version; returns the version.
+pub(crate) fn read_preamble<R: Read>(input: &mut R) -> Result<u16> {
+    let mut magic = [0_u8; 4];
+    input.read_exact(&mut magic)?;
+    if magic != BINARY_MAGIC {
+        return Err(BinaryError::BadMagic(magic));
+    }
+    let mut version = [0_u8; 2];
+    input.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+    if version > BINARY_SCHEMA_VERSION {
+        return Err(BinaryError::UnsupportedVersion {
+            found: version,
+            supported: BINARY_SCHEMA_VERSION,
+        });
+    }
+    Ok(version)
+}
+
+/// Read one section, decompressed. `None` at a clean end of input.
+fn read_section<R: Read>(input: &mut R) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut prefix = [0_u8; 10];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        let read = input.read(&mut prefix[filled..])?;
+        if read == 0 {
+            if filled == 0 {
+                return Ok(None);
+            }
+            return Err(BinaryError::Corrupt("truncated section header".to_string()));
+        }
+        filled += read;
+    }
+    let (tag, compression) = (prefix[0], prefix[1]);
+    let len = u64::from_le_bytes(prefix[2..10].try_into().expect("8-byte slice"));
+
+    let mut payload = Vec::new();
+    input.take(len).read_to_end(&mut payload)?;
+    if payload.len() as u64 != len {
+        return Err(BinaryError::Corrupt(format!(
+            "section {} truncated: {} of {} bytes",
+            tag,
+            payload.len(),
+            len
+        )));
+    }
+    let payload = match decompress(compression, &payload)? {
+        Cow::Borrowed(_) => payload,
+        Cow::Owned(inflated) => inflated,
+    };
+    Ok(Some((tag, payload)))
+}
+
+/// A section payload as stored with `compression`, decompressed.
+pub(crate) fn decompress(compression: u8, payload: &[u8]) -> Result<Cow<'_, [u8]>> {
+    match compression {
+        COMPRESSION_NONE => Ok(Cow::Borrowed(payload)),
+        COMPRESSION_DEFLATE => {
+            let mut inflated = Vec::new();
+            DeflateDecoder::new(payload).read_to_end(&mut inflated)?;
+            Ok(Cow::Owned(inflated))
+        }
+        other => Err(BinaryError::Corrupt(format!(
+            "unknown compression {}",
+            other
+        ))),
+    }
+}
+
+pub(crate) fn extend_strings(strings: &mut Vec<String>, payload: &[u8]) -> Result<()> {
+    let mut cursor = payload;
+    let count = read_varint(&mut cursor)?;
+    for _ in 0..count {
+        let len = read_varint(&mut cursor)? as usize;
+        if cursor.len() < len {
+            return Err(BinaryError::Corrupt("truncated string table".to_string()));
+        }
+        let (bytes, rest) = cursor.split_at(len);
+        let string = std::str::from_utf8(bytes)
+            .map_err(|err| BinaryError::Corrupt(format!("string table: {}", err)))?;
+        strings.push(string.to_string());
+        cursor = rest;
+    }
+    Ok(())
+}
+
+// ============================================================
+// Varints
+// ============================================================
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn read_varint(input: &mut &[u8]) -> Result<u64> {
+    let mut value = 0_u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or_else(|| BinaryError::Corrupt("truncated varint".to_string()))?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(BinaryError::Corrupt("varint overflow".to_string()))
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+// ============================================================
+// Serde encoder
+// ============================================================
+
+struct Encoder<'a> {
+    out: &'a mut Vec<u8>,
+    strings: &'a mut HashMap<String, u64>,
+    pending: &'a mut Vec<String>,
+}
+
+impl Encoder<'_> {
+    fn varint(&mut self, value: u64) {
+        write_varint(self.out, value);
+    }
+
+    fn intern(&mut self, value: &str) -> u64 {
+        if let Some(index) = self.strings.get(value) {
+            return *index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.insert(value.to_string(), index);
+        self.pending.push(value.to_string());
+        index
+    }
+
+    fn length(&mut self, len: Option<usize>) -> Result<()> {
+        let len = len.ok_or_else(|| BinaryError::Serde("sequence length required".to_string()))?;
+        self.varint(len as u64);
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::Serializer for &'a mut Encoder<'b> {
+    type Ok = ();
+    type Error = BinaryError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.out.push(u8::from(v));
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.varint(zigzag(v));
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.varint(u64::from(v));
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.varint(u64::from(v));
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.varint(u64::from(v));
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.varint(v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.out.extend_from_slice(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.out.extend_from_slice(&v.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.varint(u64::from(v));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        let index = self.intern(v);
+        self.varint(index);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.varint(v.len() as u64);
+        self.out.extend_from_slice(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.varint(u64::from(variant_index));
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.length(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.varint(u64::from(variant_index));
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.length(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.varint(u64::from(variant_index));
+        Ok(self)
+    }
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder<'_> {
+    type Ok = ();
+    type Error = BinaryError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// ============================================================
+// Serde decoder
+// ============================================================
+
+pub(crate) struct Decoder<'a> {
+    input: &'a [u8],
+    strings: &'a [String],
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(input: &'a [u8], strings: &'a [String]) -> Self {
+        Self { input, strings }
+    }
+
+    /// Bytes not yet consumed.
+    pub(crate) fn remaining(&self) -> usize {
+        self.input.len()
+    }
+
+    /// Fail if the payload has bytes left over.
+    pub(crate) fn finish(&self) -> Result<()> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(BinaryError::Corrupt(format!(
+                "{} trailing bytes in section",
+                self.input.len()
+            )))
+        }
+    }
+
+    pub(crate) fn read_varint(&mut self) -> Result<u64> {
+        read_varint(&mut self.input)
+    }
+
+    fn read_byte(&mut self) -> Result<u8> {
+        let (&byte, rest) = self
+            .input
+            .split_first()
+            .ok_or_else(|| BinaryError::Corrupt("unexpected end of section".to_string()))?;
+        self.input = rest;
+        Ok(byte)
+    }
+
+    fn read_fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.input.len() < N {
+            return Err(BinaryError::Corrupt(
+                "unexpected end of section".to_string(),
+            ));
+        }
+        let (bytes, rest) = self.input.split_at(N);
+        self.input = rest;
+        Ok(bytes.try_into().expect("length checked"))
+    }
+
+    fn read_str(&mut self) -> Result<&'a str> {
+        let index = self.read_varint()? as usize;
+        self.strings
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| BinaryError::Corrupt(format!("string index {} out of range", index)))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_varint()?;
+        // Every element takes at least one byte, except zero-sized ones,
+        // which this crate's types do not use.
+        if len > self.input.len() as u64 {
+            return Err(BinaryError::Corrupt(format!(
+                "length {} exceeds section",
+                len
+            )));
+        }
+        Ok(len as usize)
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for &mut Decoder<'a> {
+    type Error = BinaryError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(BinaryError::Serde(
+            "binary format is not self-describing".to_string(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_byte()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            other => Err(BinaryError::Corrupt(format!("invalid bool {}", other))),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = unzigzag(self.read_varint()?);
+        visitor.visit_i64(value)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.read_varint()?;
+        visitor.visit_u64(value)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bits = u32::from_le_bytes(self.read_fixed()?);
+        visitor.visit_f32(f32::from_bits(bits))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bits = u64::from_le_bytes(self.read_fixed()?);
+        visitor.visit_f64(f64::from_bits(bits))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let code = self.read_varint()?;
+        let value = u32::try_from(code)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| BinaryError::Corrupt(format!("invalid char {}", code)))?;
+        visitor.visit_char(value)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let value = self.read_str()?;
+        visitor.visit_str(value)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        visitor.visit_bytes(bytes)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.read_byte()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            other => Err(BinaryError::Corrupt(format!(
+                "invalid option tag {}",
+                other
+            ))),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_seq(Elements {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Elements {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_map(Elements {
+            decoder: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(BinaryError::Serde(
+            "binary format cannot skip unknown values".to_string(),
+        ))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Sequence, tuple, struct and map access over a known element count.
+struct Elements<'a, 'b> {
+    decoder: &'a mut Decoder<'b>,
+    remaining: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_, '_> {
+    type Error = BinaryError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Elements<'_, '_> {
+    type Error = BinaryError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.decoder)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'_> {
+    type Error = BinaryError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = self.read_varint()?;
+        let index = u32::try_from(index)
+            .map_err(|_| BinaryError::Corrupt(format!("variant index {} out of range", index)))?;
+        let value = seed.deserialize(IntoDeserializer::<BinaryError>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'_> {
+    type Error = BinaryError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
@@ -0,0 +1,153 @@
+//! Multi-file adapter input.
+//!
+//! A run is several files: GROMACS writes an `.mdp` and an `md.log`, VASP an
+//! INCAR, OSZICAR and OUTCAR. `TraceBundle` carries them separately, each
+//! under the path it was read from, so `DslAdapter::parse_bundle` can pick
+//! files by name and anchor provenance to the real path. The
+//! marker-concatenated strings accepted by `parse_trace` are split into a
+//! bundle with the adapters' default file names.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::provenance::section_body;
+use crate::streaming::StreamSource;
+
+/// One file of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFile {
+    /// Path as recorded in `ProvenanceAnchor::source_file`.
+    pub path: String,
+    pub contents: String,
+}
+
+impl TraceFile {
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            contents: contents.into(),
+        }
+    }
+
+    /// Last component of `path`, e.g. "OUTCAR" for "relax/OUTCAR".
+    pub fn file_name(&self) -> &str {
+        file_name(&self.path)
+    }
+
+    /// Extension of `file_name`, without the dot.
+    pub fn extension(&self) -> Option<&str> {
+        extension(&self.path)
+    }
+}
+
+pub(crate) fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+pub(crate) fn extension(path: &str) -> Option<&str> {
+    let (stem, extension) = file_name(path).rsplit_once('.')?;
+    (!stem.is_empty()).then_some(extension)
+}
+
+/// Whether `path` has extension `wanted`, compared case-insensitively.
+pub(crate) fn has_extension(path: &str, wanted: &str) -> bool {
+    extension(path).is_some_and(|found| found.eq_ignore_ascii_case(wanted))
+}
+
+/// The files of one run, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceBundle {
+    files: Vec<TraceFile>,
+}
+
+impl TraceBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read each of `paths` from disk, recording it as given.
+    pub fn from_paths<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> io::Result<Self> {
+        let mut bundle = Self::new();
+        for path in paths {
+            let path = path.as_ref();
+            bundle.add(path.display().to_string(), std::fs::read_to_string(path)?);
+        }
+        Ok(bundle)
+    }
+
+    /// Split marker-delimited `raw` into files. Each `(marker, path)` found
+    /// starts a file named `path` that runs to the next marker.
+    pub(crate) fn from_sections(raw: &str, markers: &[(&str, &str)]) -> Self {
+        let mut found: Vec<(usize, &str, &str)> = markers
+            .iter()
+            .filter_map(|(marker, path)| Some((raw.find(marker)?, *marker, *path)))
+            .collect();
+        found.sort_by_key(|(position, ..)| *position);
+
+        let mut bundle = Self::new();
+        for (idx, (position, marker, path)) in found.iter().enumerate() {
+            let end = found
+                .get(idx + 1)
+                .map_or(raw.len(), |(next_position, ..)| *next_position);
+            bundle.add(*path, section_body(&raw[position + marker.len()..end]));
+        }
+        bundle
+    }
+
+    pub fn add(&mut self, path: impl Into<String>, contents: impl Into<String>) -> &mut Self {
+        self.files.push(TraceFile::new(path, contents));
+        self
+    }
+
+    /// Add a file whose contents are read to the end from `reader`.
+    pub fn add_reader(
+        &mut self,
+        path: impl Into<String>,
+        mut reader: impl Read,
+    ) -> io::Result<&mut Self> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(self.add(path, contents))
+    }
+
+    pub fn files(&self) -> &[TraceFile] {
+        &self.files
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// First file whose `file_name` is `name`.
+    pub fn file_named(&self, name: &str) -> Option<&TraceFile> {
+        self.files.iter().find(|file| file.file_name() == name)
+    }
+
+    /// First file with extension `extension`, compared case-insensitively.
+    pub fn with_extension(&self, extension: &str) -> Option<&TraceFile> {
+        self.files
+            .iter()
+            .find(|file| has_extension(&file.path, extension))
+    }
+
+    /// The files as `StreamSource`s reading from the held contents.
+    pub fn stream_sources(&self) -> Vec<StreamSource<'_>> {
+        self.files
+            .iter()
+            .map(|file| StreamSource::new(file.path.clone(), file.contents.as_bytes()))
+            .collect()
+    }
+
+    /// Contents keyed by path, as `provenance::verify_provenance` takes them.
+    pub fn sources(&self) -> HashMap<String, String> {
+        self.files
+            .iter()
+            .map(|file| (file.path.clone(), file.contents.clone()))
+            .collect()
+    }
+}
@@ -1,34 +1,42 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::symbol::Symbol;
+use crate::units::{Quantity, UnitError};
+
 // ============================================================
 // Supporting types (referenced in §1/§2 but not formally defined)
 // ============================================================
 
 /// Unique, monotonic event identifier. SSA-like: each event is assigned once.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub struct EventId(pub u64);
 
 /// Identifies a spec element (precondition, prediction, intervention, etc.).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct SpecElementId(pub u64);
 
 /// Generic element reference for Completeness::Derived.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct ElementId(pub u64);
 
-/// Physical units — string alias for prototype flexibility.
-pub type Unit = String;
+/// Physical units, parsed into dimension and scale (see `crate::units`).
+pub use crate::units::Unit;
 
 /// Expected type for Value::Havoc.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum ValueType {
     Scalar,
     Vector,
     Categorical,
+    Grid,
+    DataRef,
 }
 
 /// R1 completion/failure states.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum ExecutionOutcome {
     Success,
     CrashDivergent,
@@ -36,8 +44,8 @@ pub enum ExecutionOutcome {
     FrameworkError,
 }
 
-/// Standard severity ladder.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Standard severity ladder, ordered from least to most severe.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
 pub enum Severity {
     Info,
     Warning,
@@ -46,7 +54,7 @@ pub enum Severity {
 }
 
 /// R6 numerical health events from OpenMM context.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum NumericalEventType {
     NaNDetected,
     InfDetected,
@@ -56,7 +64,7 @@ pub enum NumericalEventType {
 }
 
 /// R5 parameter validation status.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum MatchStatus {
     Exact,
     WithinTolerance { deviation: f64 },
@@ -64,7 +72,7 @@ pub enum MatchStatus {
 }
 
 /// State snapshot variants.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum SnapshotType {
     Coordinates,
     Velocities,
@@ -74,7 +82,9 @@ pub enum SnapshotType {
 
 /// Discriminant tag mirroring EventKind variant names (no payload).
 /// Used as a key in EventIndexes::by_kind.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum EventKindTag {
     ExecutionStatus,
     ExceptionEvent,
@@ -90,25 +100,78 @@ pub enum EventKindTag {
     EnergyRecord,
 }
 
-/// Minimal precondition/postcondition contract term.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// Precondition/postcondition contract term.
+/// `target` carries the expected value (WDK#35); `predicate` makes the
+/// term machine-checkable by `contracts::evaluate_contracts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ContractTerm {
     pub id: SpecElementId,
     pub description: String,
     pub layer: Layer,
+    #[serde(default)]
+    pub target: Option<Value>,
+    #[serde(default)]
+    pub predicate: Option<ContractPredicate>,
+}
+
+/// Comparison operator for contract predicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ComparisonOp {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+/// Small predicate language over trace variables (`EventIndexes::by_variable`).
+/// Predicates that need an expected value read it from `ContractTerm::target`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ContractPredicate {
+    /// Parameter's actual value equals the target (numeric or categorical).
+    ParameterEquals { parameter: String },
+    /// Variable's value compared against the numeric target.
+    Compare { variable: String, op: ComparisonOp },
+    /// Variable's value lies in the closed interval `[min, max]`.
+    InRange {
+        variable: String,
+        min: f64,
+        max: f64,
+        /// Unit of `min` and `max`. Observed values are converted to it;
+        /// `None` compares them as recorded.
+        #[serde(default)]
+        unit: Option<Unit>,
+    },
+    /// Observable lies within an absolute tolerance of the numeric target.
+    WithinTolerance { observable: String, tolerance: f64 },
+}
+
+impl ContractPredicate {
+    /// The trace variable this predicate inspects.
+    pub fn variable(&self) -> &str {
+        match self {
+            ContractPredicate::ParameterEquals { parameter } => parameter,
+            ContractPredicate::Compare { variable, .. } => variable,
+            ContractPredicate::InRange { variable, .. } => variable,
+            ContractPredicate::WithinTolerance { observable, .. } => observable,
+        }
+    }
 }
 
 /// R15 prediction record.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct PredictionRecord {
     pub id: SpecElementId,
     pub variable: String,
     pub predicted_value: Value,
     pub tolerance: Option<f64>,
+    #[serde(default)]
+    pub uncertainty: Option<UncertaintySummary>,
 }
 
 /// R10 intervention record.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct InterventionRecord {
     pub id: SpecElementId,
     pub parameter: String,
@@ -116,7 +179,7 @@ pub struct InterventionRecord {
 }
 
 /// R13 controlled variable.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ControlledVariable {
     pub id: SpecElementId,
     pub parameter: String,
@@ -124,22 +187,25 @@ pub struct ControlledVariable {
 }
 
 /// R9/R11 DAG cross-reference.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct DagReference {
     pub node_id: String,
     pub edge_ids: Vec<String>,
 }
 
 /// R17 comparison outcome.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ComparisonOutcome {
     pub agreement: bool,
     pub divergence: Option<DivergenceMeasure>,
     pub detail: String,
+    /// Estimation uncertainty of the divergence value (not its semantics).
+    #[serde(default)]
+    pub uncertainty: Option<UncertaintySummary>,
 }
 
 /// Pluggable divergence measure for prediction-observation comparison (from §3).
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum DivergenceMeasure {
     AbsoluteDifference(f64),
     ZScore(f64),
@@ -149,6 +215,223 @@ pub enum DivergenceMeasure {
     Custom { name: String, value: f64 },
 }
 
+/// WDK#40 measurement uncertainty (Step 14 Candidate C): a mandatory point
+/// layer plus an optional distribution payload. Distinct from `ConfidenceMeta`,
+/// which describes data completeness rather than estimation uncertainty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UncertaintySummary {
+    pub point: PointUncertainty,
+    pub distribution: Option<DistributionPayload>,
+}
+
+/// Point layer of an `UncertaintySummary`. Always present so that missing
+/// uncertainty is explicit (G5) rather than silently omitted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum PointUncertainty {
+    Summary {
+        estimate: f64,
+        standard_error: Option<f64>,
+        interval: Option<IntervalEstimate>,
+        sample_size: Option<u64>,
+        /// Estimator or interval method identifier (G4 auditability).
+        method_ref: String,
+    },
+    NoUncertainty {
+        reason: UncertaintyUnavailableReason,
+    },
+}
+
+/// Confidence/credible interval with its coverage level (e.g., 0.95).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct IntervalEstimate {
+    pub lower: f64,
+    pub upper: f64,
+    pub level: f64,
+}
+
+/// Why a quantity carries no uncertainty estimate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum UncertaintyUnavailableReason {
+    AdapterCannotObserve,
+    InsufficientSamples,
+    NotComputed,
+    Invalidated,
+}
+
+/// Optional richer description of the estimate's distribution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum DistributionPayload {
+    Samples(Vec<f64>),
+    Histogram {
+        bin_edges: Vec<f64>,
+        counts: Vec<u64>,
+    },
+    /// (cumulative probability, value) pairs.
+    Quantiles(Vec<(f64, f64)>),
+    Parametric {
+        family: String,
+        parameters: Vec<(String, f64)>,
+    },
+}
+
+/// Two-sided 95% normal quantile used for sample-derived intervals.
+const Z_95: f64 = 1.959_963_984_540_054;
+
+impl UncertaintySummary {
+    /// Explicit absence of uncertainty with a reason.
+    pub fn unavailable(reason: UncertaintyUnavailableReason) -> Self {
+        Self {
+            point: PointUncertainty::NoUncertainty { reason },
+            distribution: None,
+        }
+    }
+
+    /// Mean, standard error of the mean and normal-approximation 95% interval,
+    /// keeping the samples as the distribution payload.
+    pub fn from_samples(samples: &[f64], method_ref: &str) -> Self {
+        if samples.len() < 2 {
+            return Self {
+                point: PointUncertainty::NoUncertainty {
+                    reason: UncertaintyUnavailableReason::InsufficientSamples,
+                },
+                distribution: Some(DistributionPayload::Samples(samples.to_vec())),
+            };
+        }
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let standard_error = (variance / n).sqrt();
+
+        Self {
+            point: PointUncertainty::Summary {
+                estimate: mean,
+                standard_error: Some(standard_error),
+                interval: Some(IntervalEstimate {
+                    lower: mean - Z_95 * standard_error,
+                    upper: mean + Z_95 * standard_error,
+                    level: 0.95,
+                }),
+                sample_size: Some(samples.len() as u64),
+                method_ref: method_ref.to_string(),
+            },
+            distribution: Some(DistributionPayload::Samples(samples.to_vec())),
+        }
+    }
+
+    pub fn estimate(&self) -> Option<f64> {
+        match &self.point {
+            PointUncertainty::Summary { estimate, .. } => Some(*estimate),
+            PointUncertainty::NoUncertainty { .. } => None,
+        }
+    }
+
+    /// Standard error from the point layer, or from a symmetric 95% interval.
+    pub fn standard_error(&self) -> Option<f64> {
+        match &self.point {
+            PointUncertainty::Summary {
+                standard_error: Some(standard_error),
+                ..
+            } => Some(*standard_error),
+            PointUncertainty::Summary {
+                interval: Some(interval),
+                ..
+            } if (interval.level - 0.95).abs() < 1e-9 => {
+                Some((interval.upper - interval.lower) / (2.0 * Z_95))
+            }
+            _ => None,
+        }
+    }
+
+    /// The same summary expressed in a unit `factor` times smaller, e.g.
+    /// after converting the measured value with `Unit::conversion_factor`.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let point = match &self.point {
+            PointUncertainty::Summary {
+                estimate,
+                standard_error,
+                interval,
+                sample_size,
+                method_ref,
+            } => PointUncertainty::Summary {
+                estimate: estimate * factor,
+                standard_error: standard_error.map(|se| se * factor.abs()),
+                interval: interval.as_ref().map(|interval| {
+                    let (a, b) = (interval.lower * factor, interval.upper * factor);
+                    IntervalEstimate {
+                        lower: a.min(b),
+                        upper: a.max(b),
+                        level: interval.level,
+                    }
+                }),
+                sample_size: *sample_size,
+                method_ref: method_ref.clone(),
+            },
+            no_uncertainty => no_uncertainty.clone(),
+        };
+        let distribution = self.distribution.as_ref().map(|payload| match payload {
+            DistributionPayload::Samples(samples) => {
+                DistributionPayload::Samples(samples.iter().map(|x| x * factor).collect())
+            }
+            DistributionPayload::Histogram { bin_edges, counts } => {
+                DistributionPayload::Histogram {
+                    bin_edges: bin_edges.iter().map(|x| x * factor).collect(),
+                    counts: counts.clone(),
+                }
+            }
+            DistributionPayload::Quantiles(quantiles) => DistributionPayload::Quantiles(
+                quantiles.iter().map(|(p, x)| (*p, x * factor)).collect(),
+            ),
+            parametric @ DistributionPayload::Parametric { .. } => parametric.clone(),
+        });
+        Self {
+            point,
+            distribution,
+        }
+    }
+
+    /// z-score of `observed - predicted`, combining the available standard
+    /// errors in quadrature. `None` when no uncertainty is known.
+    pub fn z_score(
+        observed: f64,
+        predicted: f64,
+        observation: Option<&UncertaintySummary>,
+        prediction: Option<&UncertaintySummary>,
+    ) -> Option<f64> {
+        let variance = [observation, prediction]
+            .into_iter()
+            .flatten()
+            .filter_map(UncertaintySummary::standard_error)
+            .map(|standard_error| standard_error * standard_error)
+            .sum::<f64>();
+        if variance <= 0.0 {
+            return None;
+        }
+        Some((observed - predicted) / variance.sqrt())
+    }
+
+    /// Gaussian Bayes factor BF01 of the point prediction (H0: mean equals
+    /// `predicted`) against a diffuse alternative (H1: mean drawn from
+    /// N(`predicted`, tau^2)), where sigma is the observation's standard error
+    /// and tau the prediction's. Values above 1 favour the prediction.
+    pub fn gaussian_bayes_factor(
+        observed: f64,
+        predicted: f64,
+        observation: &UncertaintySummary,
+        prediction: &UncertaintySummary,
+    ) -> Option<f64> {
+        let sigma = observation.standard_error().filter(|se| *se > 0.0)?;
+        let tau = prediction.standard_error().filter(|se| *se > 0.0)?;
+        let null_variance = sigma * sigma;
+        let alternative_variance = null_variance + tau * tau;
+        let delta_sq = (observed - predicted).powi(2);
+        Some(
+            (alternative_variance / null_variance).sqrt()
+                * (-0.5 * delta_sq / null_variance + 0.5 * delta_sq / alternative_variance).exp(),
+        )
+    }
+}
+
 // ============================================================
 // Section 1: Common Structural Foundation (verbatim from schemas)
 // ============================================================
@@ -156,7 +439,9 @@ pub enum DivergenceMeasure {
 /// R19: The load-bearing structural distinction.
 /// Every IR element is tagged with exactly one primary layer.
 /// DSL API separation makes this possible (ARCHITECTURE.md §3.1).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum Layer {
     Theory,
     Methodology,
@@ -168,7 +453,7 @@ pub enum Layer {
 /// The primary layer is assigned based on where the parameter's failure
 /// would have the most diagnostic impact, with an explicit annotation
 /// for dual-nature parameters.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum BoundaryClassification {
     /// Unambiguously belongs to one layer.
     PrimaryLayer,
@@ -188,7 +473,7 @@ pub enum BoundaryClassification {
 }
 
 /// R28: Interventional vs. observational distinction.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum ObservationMode {
     Interventional,
     Observational,
@@ -197,14 +482,44 @@ pub enum ObservationMode {
 /// R26: Observability gap representation.
 /// From Boogie pattern P6 — explicitly unknown state is represented
 /// structurally rather than silently omitted.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+///
+/// Numeric payloads serialize losslessly: see `lossless_f64`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Value {
     /// Known value with units.
-    Known(f64, Unit),
+    Known(
+        #[serde(with = "lossless_f64")]
+        #[schemars(with = "lossless_f64::Lossless")]
+        f64,
+        Unit,
+    ),
     /// Known vector (e.g., forces, coordinates).
-    KnownVec(Vec<f64>, Unit),
+    KnownVec(
+        #[serde(with = "lossless_f64_vec")]
+        #[schemars(with = "Vec<lossless_f64::Lossless>")]
+        Vec<f64>,
+        Unit,
+    ),
     /// Known categorical value (e.g., ensemble type, functional name).
     KnownCat(String),
+    /// Known n-dimensional grid (e.g., DOS, band structure, RDF).
+    /// `values` is flattened row-major over `axes`.
+    KnownGrid {
+        axes: Vec<GridAxis>,
+        #[serde(with = "lossless_f64_vec")]
+        #[schemars(with = "Vec<lossless_f64::Lossless>")]
+        values: Vec<f64>,
+        value_unit: Unit,
+    },
+    /// Reference to external volumetric data (e.g., CHGCAR, LOCPOT).
+    /// Follows the `StateSnapshot.data_ref` pattern for data too large to inline.
+    DataRef {
+        path: String,
+        data_type: String,
+        shape: Vec<usize>,
+        unit: Unit,
+        checksum: Option<u64>,
+    },
     /// Explicitly unknown — the trace does not contain this value.
     Havoc {
         expected_type: ValueType,
@@ -212,8 +527,440 @@ pub enum Value {
     },
 }
 
+impl Value {
+    /// Shape of the carried data: `[]` for scalars, `[n]` for vectors,
+    /// axis lengths for grids, declared shape for data references.
+    /// Returns `None` for categorical and havoc values.
+    pub fn shape(&self) -> Option<Vec<usize>> {
+        match self {
+            Value::Known(..) => Some(Vec::new()),
+            Value::KnownVec(values, _) => Some(vec![values.len()]),
+            Value::KnownGrid { axes, .. } => {
+                Some(axes.iter().map(|axis| axis.coordinates.len()).collect())
+            }
+            Value::DataRef { shape, .. } => Some(shape.clone()),
+            Value::KnownCat(_) | Value::Havoc { .. } => None,
+        }
+    }
+
+    /// The scalar as a `Quantity`, for unit-aware comparison.
+    pub fn quantity(&self) -> Option<Quantity> {
+        match self {
+            Value::Known(value, unit) => Some(Quantity {
+                value: *value,
+                unit: unit.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Convert numeric payloads to `unit`. Grids convert their values, not
+    /// their axes; categorical, havoc and data-reference values are rejected.
+    pub fn convert_to(&self, unit: &Unit) -> Result<Value, UnitError> {
+        match self {
+            Value::Known(value, from) => {
+                Ok(Value::Known(from.convert(*value, unit)?, unit.clone()))
+            }
+            Value::KnownVec(values, from) => {
+                let factor = from.conversion_factor(unit)?;
+                Ok(Value::KnownVec(
+                    values.iter().map(|value| value * factor).collect(),
+                    unit.clone(),
+                ))
+            }
+            Value::KnownGrid {
+                axes,
+                values,
+                value_unit,
+            } => {
+                let factor = value_unit.conversion_factor(unit)?;
+                Ok(Value::KnownGrid {
+                    axes: axes.clone(),
+                    values: values.iter().map(|value| value * factor).collect(),
+                    value_unit: unit.clone(),
+                })
+            }
+            Value::KnownCat(_) | Value::Havoc { .. } | Value::DataRef { .. } => {
+                Err(UnitError::NotNumeric(format!("{:?}", self)))
+            }
+        }
+    }
+}
+
+/// One labelled axis of a `Value::KnownGrid` (e.g., k-point, energy, ion index).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct GridAxis {
+    pub label: String,
+    #[serde(with = "lossless_f64_vec")]
+    #[schemars(with = "Vec<lossless_f64::Lossless>")]
+    pub coordinates: Vec<f64>,
+    pub unit: Unit,
+}
+
+/// Equality that compares floats by bit pattern, so a NaN matches itself.
+/// Used by `diff` and `merge_logs` to decide whether two definitions agree.
+pub(crate) trait BitwiseEq {
+    fn bitwise_eq(&self, other: &Self) -> bool;
+}
+
+impl BitwiseEq for f64 {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl<T: BitwiseEq> BitwiseEq for Option<T> {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Some(a), Some(b)) => a.bitwise_eq(b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<T: BitwiseEq> BitwiseEq for [T] {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other).all(|(a, b)| a.bitwise_eq(b))
+    }
+}
+
+impl<T: BitwiseEq> BitwiseEq for Vec<T> {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.as_slice().bitwise_eq(other.as_slice())
+    }
+}
+
+impl<T: BitwiseEq + ?Sized> BitwiseEq for &T {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        (**self).bitwise_eq(*other)
+    }
+}
+
+impl BitwiseEq for Value {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Known(a, a_unit), Value::Known(b, b_unit)) => {
+                a.bitwise_eq(b) && a_unit == b_unit
+            }
+            (Value::KnownVec(a, a_unit), Value::KnownVec(b, b_unit)) => {
+                a.bitwise_eq(b) && a_unit == b_unit
+            }
+            (
+                Value::KnownGrid {
+                    axes: a_axes,
+                    values: a_values,
+                    value_unit: a_unit,
+                },
+                Value::KnownGrid {
+                    axes: b_axes,
+                    values: b_values,
+                    value_unit: b_unit,
+                },
+            ) => a_axes.bitwise_eq(b_axes) && a_values.bitwise_eq(b_values) && a_unit == b_unit,
+            // The remaining variants carry no floats.
+            _ => self == other,
+        }
+    }
+}
+
+impl BitwiseEq for GridAxis {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.label == other.label
+            && self.coordinates.bitwise_eq(&other.coordinates)
+            && self.unit == other.unit
+    }
+}
+
+impl BitwiseEq for ContractTerm {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.description == other.description
+            && self.layer == other.layer
+            && self.target.bitwise_eq(&other.target)
+            && self.predicate.bitwise_eq(&other.predicate)
+    }
+}
+
+impl BitwiseEq for ContractPredicate {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                ContractPredicate::InRange {
+                    variable: a_variable,
+                    min: a_min,
+                    max: a_max,
+                    unit: a_unit,
+                },
+                ContractPredicate::InRange {
+                    variable: b_variable,
+                    min: b_min,
+                    max: b_max,
+                    unit: b_unit,
+                },
+            ) => {
+                a_variable == b_variable
+                    && a_min.bitwise_eq(b_min)
+                    && a_max.bitwise_eq(b_max)
+                    && a_unit == b_unit
+            }
+            (
+                ContractPredicate::WithinTolerance {
+                    observable: a_observable,
+                    tolerance: a_tolerance,
+                },
+                ContractPredicate::WithinTolerance {
+                    observable: b_observable,
+                    tolerance: b_tolerance,
+                },
+            ) => a_observable == b_observable && a_tolerance.bitwise_eq(b_tolerance),
+            _ => self == other,
+        }
+    }
+}
+
+impl BitwiseEq for PredictionRecord {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.variable == other.variable
+            && self.predicted_value.bitwise_eq(&other.predicted_value)
+            && self.tolerance.bitwise_eq(&other.tolerance)
+            && self.uncertainty.bitwise_eq(&other.uncertainty)
+    }
+}
+
+impl BitwiseEq for UncertaintySummary {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.point.bitwise_eq(&other.point) && self.distribution.bitwise_eq(&other.distribution)
+    }
+}
+
+impl BitwiseEq for PointUncertainty {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                PointUncertainty::Summary {
+                    estimate: a_estimate,
+                    standard_error: a_error,
+                    interval: a_interval,
+                    sample_size: a_size,
+                    method_ref: a_method,
+                },
+                PointUncertainty::Summary {
+                    estimate: b_estimate,
+                    standard_error: b_error,
+                    interval: b_interval,
+                    sample_size: b_size,
+                    method_ref: b_method,
+                },
+            ) => {
+                a_estimate.bitwise_eq(b_estimate)
+                    && a_error.bitwise_eq(b_error)
+                    && a_interval.bitwise_eq(b_interval)
+                    && a_size == b_size
+                    && a_method == b_method
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl BitwiseEq for IntervalEstimate {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.lower.bitwise_eq(&other.lower)
+            && self.upper.bitwise_eq(&other.upper)
+            && self.level.bitwise_eq(&other.level)
+    }
+}
+
+impl BitwiseEq for DistributionPayload {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DistributionPayload::Samples(a), DistributionPayload::Samples(b)) => a.bitwise_eq(b),
+            (
+                DistributionPayload::Histogram {
+                    bin_edges: a_edges,
+                    counts: a_counts,
+                },
+                DistributionPayload::Histogram {
+                    bin_edges: b_edges,
+                    counts: b_counts,
+                },
+            ) => a_edges.bitwise_eq(b_edges) && a_counts == b_counts,
+            (DistributionPayload::Quantiles(a), DistributionPayload::Quantiles(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|((a_p, a_v), (b_p, b_v))| a_p.bitwise_eq(b_p) && a_v.bitwise_eq(b_v))
+            }
+            (
+                DistributionPayload::Parametric {
+                    family: a_family,
+                    parameters: a_parameters,
+                },
+                DistributionPayload::Parametric {
+                    family: b_family,
+                    parameters: b_parameters,
+                },
+            ) => {
+                a_family == b_family
+                    && a_parameters.len() == b_parameters.len()
+                    && a_parameters.iter().zip(b_parameters).all(
+                        |((a_name, a_value), (b_name, b_value))| {
+                            a_name == b_name && a_value.bitwise_eq(b_value)
+                        },
+                    )
+            }
+            _ => false,
+        }
+    }
+}
+
+impl BitwiseEq for InterventionRecord {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.parameter == other.parameter
+            && self.values.bitwise_eq(&other.values)
+    }
+}
+
+impl BitwiseEq for ControlledVariable {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.parameter == other.parameter
+            && self.held_value.bitwise_eq(&other.held_value)
+    }
+}
+
+impl BitwiseEq for DagReference {
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+/// Serde representation of `Value` floats that survives NaN and infinities.
+///
+/// Human-readable formats write finite values as numbers and non-finite
+/// ones as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`; `null`,
+/// which serde_json used to emit for all three, reads back as NaN. Binary
+/// formats store the IEEE-754 value unchanged, NaN payload included.
+mod lossless_f64 {
+    use std::borrow::Cow;
+    use std::fmt;
+
+    use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+    use serde::de::{self, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_f64(*value);
+        }
+        match *value {
+            value if value.is_nan() => serializer.serialize_str("NaN"),
+            f64::INFINITY => serializer.serialize_str("Infinity"),
+            f64::NEG_INFINITY => serializer.serialize_str("-Infinity"),
+            value => serializer.serialize_f64(value),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(LosslessVisitor)
+        } else {
+            f64::deserialize(deserializer)
+        }
+    }
+
+    /// Element wrapper for `lossless_f64_vec`; also names the JSON Schema
+    /// of a lossless float.
+    pub struct Lossless(pub f64);
+
+    impl JsonSchema for Lossless {
+        fn schema_name() -> Cow<'static, str> {
+            "LosslessF64".into()
+        }
+
+        fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+            json_schema!({
+                "anyOf": [
+                    { "type": "number" },
+                    { "enum": ["NaN", "Infinity", "-Infinity", null] }
+                ]
+            })
+        }
+    }
+
+    impl Serialize for Lossless {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Lossless {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(Lossless)
+        }
+    }
+
+    struct LosslessVisitor;
+
+    impl<'de> Visitor<'de> for LosslessVisitor {
+        type Value = f64;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "a number, \"NaN\", \"Infinity\", \"-Infinity\" or null")
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<f64, E> {
+            Ok(value)
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<f64, E> {
+            Ok(value as f64)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<f64, E> {
+            match value {
+                "NaN" => Ok(f64::NAN),
+                "Infinity" => Ok(f64::INFINITY),
+                "-Infinity" => Ok(f64::NEG_INFINITY),
+                other => Err(E::invalid_value(de::Unexpected::Str(other), &self)),
+            }
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<f64, E> {
+            Ok(f64::NAN)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<f64, E> {
+            Ok(f64::NAN)
+        }
+    }
+}
+
+/// `lossless_f64` for every element of a `Vec<f64>`.
+mod lossless_f64_vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::lossless_f64::Lossless;
+
+    pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|value| Lossless(*value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        let values = Vec::<Lossless>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|Lossless(value)| value).collect())
+    }
+}
+
 /// Reason a value is unknown.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum HavocReason {
     NotLogged,
     FrameworkLimitation,
@@ -226,7 +973,7 @@ pub enum HavocReason {
 }
 
 /// R21: Temporal ordering. Three coordinate systems.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct TemporalCoord {
     /// Simulation step (MD) or ionic/SCF iteration (DFT).
     pub simulation_step: u64,
@@ -237,15 +984,15 @@ pub struct TemporalCoord {
 }
 
 /// R20: Every IR element is traceable to its source in raw trace data.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ProvenanceAnchor {
-    pub source_file: String,
+    pub source_file: Symbol,
     pub source_location: SourceLocation,
     pub raw_hash: u64,
 }
 
 /// Source location variant within a trace file.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum SourceLocation {
     LineRange { start: u32, end: u32 },
     XPath(String),
@@ -255,7 +1002,7 @@ pub enum SourceLocation {
 }
 
 /// R22, R29: Links every IR element to its experiment and cycle context.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ExperimentRef {
     pub experiment_id: String,
     pub cycle_id: u32,
@@ -263,7 +1010,7 @@ pub struct ExperimentRef {
 }
 
 /// R25: Classification confidence for each IR element.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ConfidenceMeta {
     pub completeness: Completeness,
     pub field_coverage: f32,
@@ -271,7 +1018,7 @@ pub struct ConfidenceMeta {
 }
 
 /// Completeness classification.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Completeness {
     FullyObserved,
     PartiallyInferred { inference_method: String },
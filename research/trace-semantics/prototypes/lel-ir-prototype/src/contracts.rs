@@ -0,0 +1,335 @@
+//! Machine-checkable evaluation of `ExperimentSpec` pre/postconditions.
+//!
+//! Each `ContractTerm` with a `predicate` is checked against every trace event
+//! recording its variable (`ParameterRecord` actual values and
+//! `ObservableMeasurement` values). One `ValidationResult` event is emitted per
+//! checked term, with causal refs to the events it inspected.
+//!
+//! Match status convention: a satisfied check with zero deviation is `Exact`,
+//! a satisfied check with non-zero deviation is `WithinTolerance`, and a
+//! violated check is `Mismatch`. Deviation is the absolute distance to the
+//! target (or to the nearest bound for `InRange`), measured in the target's
+//! unit after converting the observed value; categorical mismatches report a
+//! deviation of 1.0. Numeric equality allows for rounding in the unit
+//! conversion. Non-finite observations always violate the term, and a NaN
+//! deviation is reported as such rather than dropped.
+
+use crate::common::{
+    ComparisonOp, Completeness, ConfidenceMeta, ContractPredicate, ContractTerm, ElementId,
+    EventId, MatchStatus, ProvenanceAnchor, SourceLocation, SpecElementId, TemporalCoord, Unit,
+    Value,
+};
+use crate::event_kinds::EventKind;
+use crate::lel::{
+    EventIdAllocator, LayeredEventLog, TraceEvent, TraceEventBuildError, TraceEventBuilder,
+};
+
+/// Relative difference below which converted numbers count as equal.
+const EQUALITY_EPSILON: f64 = 1e-12;
+
+/// Result of evaluating all contracts of a log's spec.
+#[derive(Debug, Clone)]
+pub struct ContractEvaluation {
+    /// Derived `ValidationResult` events, ready to append to the log.
+    pub validation_events: Vec<TraceEvent>,
+    /// Terms that could not be checked, with the reason.
+    pub unchecked: Vec<UncheckedContract>,
+}
+
+/// A contract term the evaluator could not check against the trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncheckedContract {
+    pub term_id: SpecElementId,
+    pub reason: String,
+}
+
+impl ComparisonOp {
+    /// Whether `lhs op rhs`, counting values within `EQUALITY_EPSILON` of
+    /// each other as equal so unit-conversion rounding cannot flip a result.
+    pub fn holds(self, lhs: f64, rhs: f64) -> bool {
+        let equal = approx_eq(lhs, rhs);
+        match self {
+            ComparisonOp::Lt => lhs < rhs && !equal,
+            ComparisonOp::Le => lhs < rhs || equal,
+            ComparisonOp::Eq => equal,
+            ComparisonOp::Ne => !equal,
+            ComparisonOp::Ge => lhs > rhs || equal,
+            ComparisonOp::Gt => lhs > rhs && !equal,
+        }
+    }
+}
+
+fn observed_value(event: &TraceEvent) -> Option<&Value> {
+    match &event.kind {
+        EventKind::ParameterRecord { actual_value, .. } => Some(actual_value),
+        EventKind::ObservableMeasurement { value, .. } => Some(value),
+        _ => None,
+    }
+}
+
+fn numeric<'a>(value: &'a Value, role: &str) -> Result<(f64, &'a Unit), String> {
+    match value {
+        Value::Known(number, unit) => Ok((*number, unit)),
+        other => Err(format!("{} is not a scalar value: {:?}", role, other)),
+    }
+}
+
+fn numeric_target(target: Option<&Value>) -> Result<(f64, &Unit), String> {
+    numeric(target.ok_or("term has no target value")?, "target")
+}
+
+/// Express an observed number in the target's unit.
+fn in_target_unit(actual: f64, actual_unit: &Unit, target_unit: &Unit) -> Result<f64, String> {
+    actual_unit
+        .convert(actual, target_unit)
+        .map_err(|err| format!("unit mismatch: {}", err))
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= EQUALITY_EPSILON * a.abs().max(b.abs())
+}
+
+/// `a.max(b)`, except that NaN wins instead of being ignored.
+fn max_deviation_of(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else {
+        a.max(b)
+    }
+}
+
+/// Check one observed value. Returns `(satisfied, deviation)`, or an error
+/// when the value cannot be checked against this predicate.
+fn check_value(
+    predicate: &ContractPredicate,
+    target: Option<&Value>,
+    observed: &Value,
+) -> Result<(bool, f64), String> {
+    match predicate {
+        ContractPredicate::ParameterEquals { .. } => {
+            let target = target.ok_or("term has no target value")?;
+            match (observed, target) {
+                (Value::Known(actual, actual_unit), Value::Known(expected, expected_unit)) => {
+                    let actual = in_target_unit(*actual, actual_unit, expected_unit)?;
+                    if !actual.is_finite() {
+                        return Ok((false, (actual - expected).abs()));
+                    }
+                    if approx_eq(actual, *expected) {
+                        Ok((true, 0.0))
+                    } else {
+                        Ok((false, (actual - expected).abs()))
+                    }
+                }
+                (Value::Havoc { .. }, _) => Err("observed value is Havoc".to_string()),
+                _ => {
+                    let equal = observed == target;
+                    Ok((equal, if equal { 0.0 } else { 1.0 }))
+                }
+            }
+        }
+        ContractPredicate::Compare { op, .. } => {
+            let (expected, expected_unit) = numeric_target(target)?;
+            let (actual, actual_unit) = numeric(observed, "observed value")?;
+            let actual = in_target_unit(actual, actual_unit, expected_unit)?;
+            let holds = actual.is_finite() && op.holds(actual, expected);
+            let deviation = if approx_eq(actual, expected) {
+                0.0
+            } else {
+                (actual - expected).abs()
+            };
+            Ok((holds, deviation))
+        }
+        ContractPredicate::InRange { min, max, unit, .. } => {
+            let (actual, actual_unit) = numeric(observed, "observed value")?;
+            let actual = match unit {
+                Some(unit) => in_target_unit(actual, actual_unit, unit)?,
+                None => actual,
+            };
+            if !actual.is_finite() {
+                return Ok((false, f64::NAN));
+            }
+            let deviation = if actual < *min {
+                min - actual
+            } else if actual > *max {
+                actual - max
+            } else {
+                0.0
+            };
+            Ok((deviation == 0.0, deviation))
+        }
+        ContractPredicate::WithinTolerance { tolerance, .. } => {
+            let (expected, expected_unit) = numeric_target(target)?;
+            let (actual, actual_unit) = numeric(observed, "observed value")?;
+            let actual = in_target_unit(actual, actual_unit, expected_unit)?;
+            let deviation = (actual - expected).abs();
+            Ok((actual.is_finite() && deviation <= *tolerance, deviation))
+        }
+    }
+}
+
+/// Check one term against the log. Returns the aggregated status, a detail
+/// string and the IDs of the events that were checked.
+fn check_term(
+    term: &ContractTerm,
+    predicate: &ContractPredicate,
+    log: &LayeredEventLog,
+) -> Result<(MatchStatus, String, Vec<EventId>), String> {
+    let variable = predicate.variable();
+    let candidate_ids = log
+        .indexes
+        .by_variable
+        .get(variable)
+        .ok_or_else(|| format!("no events record variable '{}'", variable))?;
+
+    let mut checked = Vec::new();
+    let mut violations = Vec::new();
+    let mut max_deviation = 0.0_f64;
+    let mut max_violation = 0.0_f64;
+    let mut skipped = Vec::new();
+
+    for event_id in candidate_ids {
+        let Some(event) = log
+            .indexes
+            .by_id
+            .get(event_id)
+            .map(|&position| &log.events[position])
+        else {
+            continue;
+        };
+        let Some(observed) = observed_value(event) else {
+            continue;
+        };
+
+        match check_value(predicate, term.target.as_ref(), observed) {
+            Ok((satisfied, deviation)) => {
+                checked.push(event.id);
+                max_deviation = max_deviation_of(max_deviation, deviation);
+                if !satisfied {
+                    max_violation = max_deviation_of(max_violation, deviation);
+                    violations.push(event.id);
+                }
+            }
+            Err(reason) => skipped.push(reason),
+        }
+    }
+
+    if checked.is_empty() {
+        return Err(skipped
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| format!("no checkable values for variable '{}'", variable)));
+    }
+
+    let status = if !violations.is_empty() {
+        MatchStatus::Mismatch {
+            deviation: max_violation,
+        }
+    } else if max_deviation == 0.0 {
+        MatchStatus::Exact
+    } else {
+        MatchStatus::WithinTolerance {
+            deviation: max_deviation,
+        }
+    };
+
+    let mut detail = format!(
+        "{}: {} event(s) checked, {} violation(s)",
+        term.description,
+        checked.len(),
+        violations.len()
+    );
+    if !skipped.is_empty() {
+        detail.push_str(&format!("; {} event(s) not checkable", skipped.len()));
+    }
+
+    Ok((status, detail, checked))
+}
+
+/// Evaluate every precondition and postcondition of `log.spec`.
+///
+/// Emitted events continue the log's IDs and logical sequence and are tagged
+/// with the term's layer and `spec_ref`. The log itself is not modified.
+pub fn evaluate_contracts(
+    log: &LayeredEventLog,
+) -> Result<ContractEvaluation, TraceEventBuildError> {
+    let mut validation_events = Vec::new();
+    let mut unchecked = Vec::new();
+    let mut ids = EventIdAllocator::following(&log.events);
+    let mut logical_sequence = log
+        .events
+        .iter()
+        .map(|event| event.temporal.logical_sequence)
+        .max()
+        .map_or(1, |last| last + 1);
+
+    for term in log
+        .spec
+        .preconditions
+        .iter()
+        .chain(&log.spec.postconditions)
+    {
+        let Some(predicate) = &term.predicate else {
+            unchecked.push(UncheckedContract {
+                term_id: term.id,
+                reason: "term has no predicate".to_string(),
+            });
+            continue;
+        };
+
+        let (match_status, detail, checked) = match check_term(term, predicate, log) {
+            Ok(result) => result,
+            Err(reason) => {
+                unchecked.push(UncheckedContract {
+                    term_id: term.id,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let simulation_step = checked
+            .iter()
+            .filter_map(|event_id| log.indexes.by_id.get(event_id))
+            .map(|&position| log.events[position].temporal.simulation_step)
+            .max()
+            .unwrap_or(0);
+        let from_elements = checked
+            .iter()
+            .map(|event_id| ElementId(event_id.0))
+            .collect();
+
+        let event = TraceEventBuilder::new()
+            .layer(term.layer)
+            .kind(EventKind::ValidationResult {
+                parameter_name: predicate.variable().to_string(),
+                match_status,
+                deviation_detail: Some(detail),
+            })
+            .temporal(TemporalCoord {
+                simulation_step,
+                wall_clock_ns: None,
+                logical_sequence,
+            })
+            .causal_refs(checked)
+            .spec_ref(term.id)
+            .provenance(ProvenanceAnchor {
+                source_file: log.spec.provenance.source_file.clone(),
+                source_location: SourceLocation::ExternalInput,
+                raw_hash: 0,
+            })
+            .confidence(ConfidenceMeta {
+                completeness: Completeness::Derived { from_elements },
+                field_coverage: 1.0,
+                notes: vec![format!("contract term {}", term.id.0)],
+            })
+            .try_build(&mut ids)?;
+
+        logical_sequence += 1;
+        validation_events.push(event);
+    }
+
+    Ok(ContractEvaluation {
+        validation_events,
+        unchecked,
+    })
+}
@@ -1,9 +1,15 @@
+use std::collections::VecDeque;
+
 use crate::common::{
-    Completeness, ConfidenceMeta, ElementId, ExecutionOutcome, Layer, NumericalEventType,
-    ProvenanceAnchor, SourceLocation, TemporalCoord, Value,
+    Completeness, ConfidenceMeta, ElementId, EventId, ExecutionOutcome, Layer, NumericalEventType,
+    ProvenanceAnchor, SourceLocation, TemporalCoord, UncertaintySummary,
+    UncertaintyUnavailableReason, Unit, Value,
 };
 use crate::event_kinds::EventKind;
-use crate::lel::{LayeredEventLog, TraceEvent, TraceEventBuilder};
+use crate::lel::{
+    EventIdAllocator, LayeredEventLog, TraceEvent, TraceEventBuildError, TraceEventBuilder,
+};
+use crate::series::convert_sample;
 
 pub const MIN_CONVERGENCE_WINDOW: usize = 4;
 pub const REL_DELTA_THRESHOLD: f64 = 1.0e-4;
@@ -32,106 +38,131 @@ pub struct CanonicalConvergence {
     pub source_framework: String,
 }
 
-fn energy_total(event: &TraceEvent) -> Option<f64> {
-    match &event.kind {
-        EventKind::EnergyRecord {
-            total: Value::Known(total, _),
-            ..
-        } => Some(*total),
-        _ => None,
-    }
-}
-
 pub fn derive_energy_convergence_summary(
     events: &[TraceEvent],
     source_file: &str,
-) -> Option<TraceEvent> {
-    let energy_events: Vec<(&TraceEvent, f64)> = events
-        .iter()
-        .filter_map(|event| energy_total(event).map(|total| (event, total)))
-        .collect();
-
-    if energy_events.len() < MIN_CONVERGENCE_WINDOW {
-        return None;
+    ids: &mut EventIdAllocator,
+) -> Result<Option<TraceEvent>, TraceEventBuildError> {
+    let mut window = ConvergenceWindow::new();
+    for event in events {
+        window.record(event);
     }
+    window.summary(source_file, ids)
+}
 
-    let window = &energy_events[energy_events.len() - MIN_CONVERGENCE_WINDOW..];
-    let deltas: Vec<f64> = window.windows(2).map(|pair| pair[1].1 - pair[0].1).collect();
-    if deltas.is_empty() {
-        return None;
+/// What `derive_energy_convergence_summary` needs from a log, gathered one
+/// event at a time: the last `MIN_CONVERGENCE_WINDOW` energy totals and the
+/// latest status events. Streaming adapters feed it as they emit.
+#[derive(Debug, Clone, Default)]
+pub struct ConvergenceWindow {
+    /// Unit of the first energy total; later totals are converted to it
+    /// and skipped when incompatible.
+    unit: Option<Unit>,
+    /// `(step, event ID, total)`, oldest first.
+    totals: VecDeque<(u64, EventId, f64)>,
+    last_execution: Option<EventId>,
+    last_numerical: Option<EventId>,
+    last_sequence: Option<u64>,
+}
+
+impl ConvergenceWindow {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    let energy_scale =
-        (window.iter().map(|(_, value)| value.abs()).sum::<f64>() / window.len() as f64).max(1.0);
-    let rel_abs_deltas: Vec<f64> = deltas
-        .iter()
-        .map(|delta| delta.abs() / energy_scale)
-        .collect();
-    let max_rel_delta = rel_abs_deltas.iter().copied().fold(0.0_f64, f64::max);
-    let mean_rel_delta = rel_abs_deltas.iter().sum::<f64>() / rel_abs_deltas.len() as f64;
-    let sign_changes = deltas
-        .windows(2)
-        .filter(|pair| pair[0] * pair[1] < 0.0)
-        .count();
+    pub fn record(&mut self, event: &TraceEvent) {
+        self.last_sequence = Some(event.temporal.logical_sequence);
+        match &event.kind {
+            EventKind::EnergyRecord { total, .. } => {
+                if let Some(total) = convert_sample(&mut self.unit, total) {
+                    if self.totals.len() == MIN_CONVERGENCE_WINDOW {
+                        self.totals.pop_front();
+                    }
+                    self.totals
+                        .push_back((event.temporal.simulation_step, event.id, total));
+                }
+            }
+            EventKind::ExecutionStatus { .. } => self.last_execution = Some(event.id),
+            EventKind::NumericalStatus { .. } => self.last_numerical = Some(event.id),
+            _ => {}
+        }
+    }
 
-    let (metric_name, metric_value, converged, note) =
-        if sign_changes >= 2 && mean_rel_delta > REL_DELTA_THRESHOLD {
-            (
-                "derived_oscillation_rel_delta_mean",
-                mean_rel_delta,
-                Some(false),
-                "energy deltas alternate sign across the derivation window",
-            )
-        } else if max_rel_delta <= REL_DELTA_THRESHOLD {
-            (
-                "derived_convergence_rel_delta_max",
-                max_rel_delta,
-                Some(true),
-                "max relative energy delta is below convergence threshold",
-            )
-        } else {
-            (
-                "derived_stall_rel_delta_mean",
-                mean_rel_delta,
-                Some(false),
-                "energy deltas remain above threshold without oscillation",
-            )
+    /// The derived `ConvergencePoint` for the events recorded so far, or
+    /// `None` with fewer than `MIN_CONVERGENCE_WINDOW` totals. Errors from
+    /// `TraceEventBuilder::try_build` are returned, not panicked on.
+    pub fn summary(
+        &self,
+        source_file: &str,
+        ids: &mut EventIdAllocator,
+    ) -> Result<Option<TraceEvent>, TraceEventBuildError> {
+        let Some(&(simulation_step, ..)) = self.totals.back() else {
+            return Ok(None);
         };
+        if self.totals.len() < MIN_CONVERGENCE_WINDOW {
+            return Ok(None);
+        }
 
-    let mut causal_refs = window
-        .iter()
-        .map(|(event, _)| event.id)
-        .collect::<Vec<_>>();
-    if let Some(exec_event) = events
-        .iter()
-        .rev()
-        .find(|event| matches!(event.kind, EventKind::ExecutionStatus { .. }))
-    {
-        causal_refs.push(exec_event.id);
-    }
-    if let Some(numerical_event) = events
-        .iter()
-        .rev()
-        .find(|event| matches!(event.kind, EventKind::NumericalStatus { .. }))
-    {
-        causal_refs.push(numerical_event.id);
-    }
+        let window: Vec<f64> = self.totals.iter().map(|(_, _, total)| *total).collect();
+        let deltas: Vec<f64> = window.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        if deltas.is_empty() {
+            return Ok(None);
+        }
+
+        let energy_scale =
+            (window.iter().map(|value| value.abs()).sum::<f64>() / window.len() as f64).max(1.0);
+        let rel_abs_deltas: Vec<f64> = deltas
+            .iter()
+            .map(|delta| delta.abs() / energy_scale)
+            .collect();
+        let max_rel_delta = rel_abs_deltas.iter().copied().fold(0.0_f64, f64::max);
+        let mean_rel_delta = rel_abs_deltas.iter().sum::<f64>() / rel_abs_deltas.len() as f64;
+        let sign_changes = deltas
+            .windows(2)
+            .filter(|pair| pair[0] * pair[1] < 0.0)
+            .count();
 
-    let from_elements = causal_refs.iter().map(|id| ElementId(id.0)).collect();
-    let simulation_step = window.last().map(|(event, _)| event.temporal.simulation_step)?;
-    let logical_sequence = events
-        .last()
-        .map(|event| event.temporal.logical_sequence + 1)
-        .unwrap_or(1);
+        let (metric_name, metric_value, converged, note) =
+            if sign_changes >= 2 && mean_rel_delta > REL_DELTA_THRESHOLD {
+                (
+                    "derived_oscillation_rel_delta_mean",
+                    mean_rel_delta,
+                    Some(false),
+                    "energy deltas alternate sign across the derivation window",
+                )
+            } else if max_rel_delta <= REL_DELTA_THRESHOLD {
+                (
+                    "derived_convergence_rel_delta_max",
+                    max_rel_delta,
+                    Some(true),
+                    "max relative energy delta is below convergence threshold",
+                )
+            } else {
+                (
+                    "derived_stall_rel_delta_mean",
+                    mean_rel_delta,
+                    Some(false),
+                    "energy deltas remain above threshold without oscillation",
+                )
+            };
+
+        let mut causal_refs: Vec<EventId> = self.totals.iter().map(|(_, id, _)| *id).collect();
+        causal_refs.extend(self.last_execution);
+        causal_refs.extend(self.last_numerical);
+
+        let from_elements = causal_refs.iter().map(|id| ElementId(id.0)).collect();
+        let logical_sequence = self.last_sequence.map_or(1, |sequence| sequence + 1);
 
-    Some(
         TraceEventBuilder::new()
             .layer(Layer::Methodology)
             .kind(EventKind::ConvergencePoint {
                 iteration: simulation_step,
-                metric_name: metric_name.to_string(),
-                metric_value: Value::Known(metric_value, "relative".to_string()),
+                metric_name: metric_name.into(),
+                metric_value: Value::Known(metric_value, "relative".into()),
                 converged,
+                uncertainty: Some(UncertaintySummary::unavailable(
+                    UncertaintyUnavailableReason::NotComputed,
+                )),
             })
             .temporal(TemporalCoord {
                 simulation_step,
@@ -140,7 +171,7 @@ pub fn derive_energy_convergence_summary(
             })
             .causal_refs(causal_refs)
             .provenance(ProvenanceAnchor {
-                source_file: source_file.to_string(),
+                source_file: source_file.into(),
                 source_location: SourceLocation::ExternalInput,
                 raw_hash: 0,
             })
@@ -149,8 +180,9 @@ pub fn derive_energy_convergence_summary(
                 field_coverage: 1.0,
                 notes: vec![note.to_string()],
             })
-            .build(),
-    )
+            .try_build(ids)
+            .map(Some)
+    }
 }
 
 fn confidence_from_completeness(completeness: &Completeness) -> ConvergenceConfidence {
@@ -183,7 +215,7 @@ pub fn classify_convergence(
 ) -> CanonicalConvergence {
     let source_framework = framework.to_string();
     let source_metric = match &event.kind {
-        EventKind::ConvergencePoint { metric_name, .. } => metric_name.clone(),
+        EventKind::ConvergencePoint { metric_name, .. } => metric_name.to_string(),
         _ => "unknown".to_string(),
     };
     let completeness_confidence = confidence_from_completeness(&event.confidence.completeness);
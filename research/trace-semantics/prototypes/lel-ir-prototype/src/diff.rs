@@ -0,0 +1,504 @@
+//! Structural diff between two logs of one experiment.
+//!
+//! `diff(a, b)` answers the first questions asked when an intervention flips
+//! an outcome: which parameters changed, which event kinds appeared or
+//! vanished, where the energy and convergence series part ways, and how the
+//! outcomes and specs differ. Everything is reported from `a` to `b`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::common::{
+    BitwiseEq, ContractTerm, ControlledVariable, DagReference, EventKindTag, ExecutionOutcome,
+    InterventionRecord, PredictionRecord, Unit, Value,
+};
+use crate::event_kinds::EventKind;
+use crate::lel::{ExperimentSpec, LayeredEventLog};
+use crate::series::Series;
+
+/// Relative tolerance below which two trajectory values count as equal.
+pub const TRAJECTORY_RELATIVE_TOLERANCE: f64 = 1e-9;
+
+/// How an element differs between `a` and `b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Specified and actual value of a `ParameterRecord`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterValues {
+    pub specified: Option<Value>,
+    pub actual: Value,
+}
+
+impl ParameterValues {
+    /// Equality by bit pattern, so NaN values match themselves.
+    fn same_as(&self, other: &ParameterValues) -> bool {
+        self.specified.bitwise_eq(&other.specified) && self.actual.bitwise_eq(&other.actual)
+    }
+}
+
+/// A parameter, matched by name, whose values differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub before: Option<ParameterValues>,
+    pub after: Option<ParameterValues>,
+}
+
+/// An event kind whose count differs. A zero count means the kind was added
+/// or removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KindCountChange {
+    pub kind: EventKindTag,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Values of one trajectory at a simulation step, in the trajectory's unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepDelta {
+    pub step: u64,
+    /// Solver iteration within the step for convergence metrics; `None` for
+    /// energies.
+    pub iteration: Option<u64>,
+    pub before: Option<f64>,
+    pub after: Option<f64>,
+}
+
+impl StepDelta {
+    /// Whether the two sides disagree, including one side being absent.
+    pub fn diverges(&self) -> bool {
+        match (self.before, self.after) {
+            (Some(before), Some(after)) => !values_match(before, after),
+            (None, None) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Two series aligned by `simulation_step`, and for convergence metrics by
+/// iteration within the step, so every SCF iteration of an ionic step is
+/// compared. If a key has several records the last one is used; `b` is
+/// converted to `a`'s unit where possible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectoryDiff {
+    pub unit: Unit,
+    pub points: Vec<StepDelta>,
+    /// First step where the series disagree, if any.
+    pub first_divergent_step: Option<u64>,
+}
+
+/// An `ExecutionStatus` outcome that differs, matched by order of occurrence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutcomeChange {
+    pub index: usize,
+    pub before: Option<ExecutionOutcome>,
+    pub after: Option<ExecutionOutcome>,
+}
+
+/// A spec element, matched by ID (node ID for `dag_refs`), that differs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecChange {
+    /// `ExperimentSpec` field, e.g. "controlled_variables".
+    pub section: &'static str,
+    pub key: String,
+    pub kind: ChangeKind,
+}
+
+/// Result of `diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogDiff {
+    pub parameter_changes: Vec<ParameterChange>,
+    pub kind_changes: Vec<KindCountChange>,
+    pub energy: TrajectoryDiff,
+    /// Per `EnergyRecord` component, sorted by name.
+    pub energy_components: Vec<(String, TrajectoryDiff)>,
+    /// Per `ConvergencePoint` metric name, sorted by name.
+    pub convergence: Vec<(String, TrajectoryDiff)>,
+    pub outcome_changes: Vec<OutcomeChange>,
+    pub spec_changes: Vec<SpecChange>,
+}
+
+impl LogDiff {
+    /// True when no difference was found.
+    pub fn is_empty(&self) -> bool {
+        self.parameter_changes.is_empty()
+            && self.kind_changes.is_empty()
+            && self.energy.first_divergent_step.is_none()
+            && self
+                .energy_components
+                .iter()
+                .all(|(_, trajectory)| trajectory.first_divergent_step.is_none())
+            && self
+                .convergence
+                .iter()
+                .all(|(_, trajectory)| trajectory.first_divergent_step.is_none())
+            && self.outcome_changes.is_empty()
+            && self.spec_changes.is_empty()
+    }
+}
+
+/// Compare two logs, typically two cycles or hypotheses of one experiment.
+pub fn diff(a: &LayeredEventLog, b: &LayeredEventLog) -> LogDiff {
+    let energy = diff_trajectories(Some(a.series.energy_total()), Some(b.series.energy_total()));
+    let energy_components = diff_named_series(
+        a.series.energy_components(),
+        b.series.energy_components(),
+        |name| {
+            diff_trajectories(
+                a.series.energy_component(name),
+                b.series.energy_component(name),
+            )
+        },
+    );
+    let convergence = diff_named_series(
+        a.series.convergence_metrics(),
+        b.series.convergence_metrics(),
+        |metric| diff_trajectories(a.series.convergence(metric), b.series.convergence(metric)),
+    );
+
+    LogDiff {
+        parameter_changes: diff_parameters(a, b),
+        kind_changes: diff_kind_counts(a, b),
+        energy,
+        energy_components,
+        convergence,
+        outcome_changes: diff_outcomes(a, b),
+        spec_changes: diff_specs(&a.spec, &b.spec),
+    }
+}
+
+/// `diff_one` for each name with samples on either side, sorted by name.
+fn diff_named_series<'a>(
+    before: impl Iterator<Item = &'a Series>,
+    after: impl Iterator<Item = &'a Series>,
+    diff_one: impl Fn(&str) -> TrajectoryDiff,
+) -> Vec<(String, TrajectoryDiff)> {
+    let mut names: Vec<&str> = before
+        .chain(after)
+        .filter(|series| !series.is_empty())
+        .map(Series::name)
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| (name.to_string(), diff_one(name)))
+        .collect()
+}
+
+fn values_match(before: f64, after: f64) -> bool {
+    if before.is_nan() || after.is_nan() {
+        return before.is_nan() && after.is_nan();
+    }
+    if before == after {
+        return true;
+    }
+    let scale = before.abs().max(after.abs()).max(1.0);
+    (before - after).abs() <= TRAJECTORY_RELATIVE_TOLERANCE * scale
+}
+
+/// First `ParameterRecord` per name.
+fn parameters(log: &LayeredEventLog) -> BTreeMap<&str, ParameterValues> {
+    let mut parameters = BTreeMap::new();
+    for event in &log.events {
+        if let EventKind::ParameterRecord {
+            name,
+            specified_value,
+            actual_value,
+            ..
+        } = &event.kind
+        {
+            parameters
+                .entry(name.as_str())
+                .or_insert_with(|| ParameterValues {
+                    specified: specified_value.clone(),
+                    actual: actual_value.clone(),
+                });
+        }
+    }
+    parameters
+}
+
+fn diff_parameters(a: &LayeredEventLog, b: &LayeredEventLog) -> Vec<ParameterChange> {
+    let (before, after) = (parameters(a), parameters(b));
+    let mut names: Vec<&str> = before.keys().chain(after.keys()).copied().collect();
+    names.sort_unstable();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (old, new) = (before.get(name), after.get(name));
+            let kind = match (old, new) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(old), Some(new)) if !old.same_as(new) => ChangeKind::Modified,
+                _ => return None,
+            };
+            Some(ParameterChange {
+                name: name.to_string(),
+                kind,
+                before: old.cloned(),
+                after: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+fn diff_kind_counts(a: &LayeredEventLog, b: &LayeredEventLog) -> Vec<KindCountChange> {
+    let count = |log: &LayeredEventLog, kind: &EventKindTag| {
+        log.indexes.by_kind.get(kind).map_or(0, Vec::len)
+    };
+    let mut kinds: Vec<EventKindTag> = a
+        .indexes
+        .by_kind
+        .keys()
+        .chain(b.indexes.by_kind.keys())
+        .copied()
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+
+    kinds
+        .into_iter()
+        .map(|kind| KindCountChange {
+            before: count(a, &kind),
+            after: count(b, &kind),
+            kind,
+        })
+        .filter(|change| change.before != change.after)
+        .collect()
+}
+
+/// Align by `(step, iteration)` in `before`'s unit. An `after` series that
+/// cannot be converted is reported as absent, which counts as a divergence.
+fn diff_trajectories(before: Option<&Series>, after: Option<&Series>) -> TrajectoryDiff {
+    let unit = before
+        .and_then(Series::unit)
+        .or(after.and_then(Series::unit))
+        .cloned()
+        .unwrap_or_default();
+
+    let mut aligned: BTreeMap<SampleKey, StepDelta> = BTreeMap::new();
+    for (side, series) in [(Side::Before, before), (Side::After, after)] {
+        for ((step, iteration), value) in samples_in(series, &unit) {
+            let point = aligned.entry((step, iteration)).or_insert(StepDelta {
+                step,
+                iteration,
+                before: None,
+                after: None,
+            });
+            match side {
+                Side::Before => point.before = value,
+                Side::After => point.after = value,
+            }
+        }
+    }
+
+    let points: Vec<StepDelta> = aligned.into_values().collect();
+    let first_divergent_step = points
+        .iter()
+        .find(|point| point.diverges())
+        .map(|point| point.step);
+    TrajectoryDiff {
+        unit,
+        points,
+        first_divergent_step,
+    }
+}
+
+/// `(simulation_step, iteration)` of a trajectory sample.
+type SampleKey = (u64, Option<u64>);
+
+enum Side {
+    Before,
+    After,
+}
+
+/// `((step, iteration), value in unit)` for each sample; the value is `None`
+/// when the series unit cannot be converted to `unit`.
+fn samples_in(series: Option<&Series>, unit: &Unit) -> Vec<(SampleKey, Option<f64>)> {
+    let Some(series) = series else {
+        return Vec::new();
+    };
+    let factor = series
+        .unit()
+        .and_then(|from| from.conversion_factor(unit).ok());
+    let iterations = series.iterations();
+    series
+        .steps()
+        .iter()
+        .zip(series.values())
+        .enumerate()
+        .map(|(index, (step, value))| {
+            let key = (*step, iterations.get(index).copied());
+            (key, factor.map(|factor| value * factor))
+        })
+        .collect()
+}
+
+fn outcomes(log: &LayeredEventLog) -> Vec<&ExecutionOutcome> {
+    log.events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::ExecutionStatus { status, .. } => Some(status),
+            _ => None,
+        })
+        .collect()
+}
+
+fn diff_outcomes(a: &LayeredEventLog, b: &LayeredEventLog) -> Vec<OutcomeChange> {
+    let (before, after) = (outcomes(a), outcomes(b));
+    (0..before.len().max(after.len()))
+        .filter_map(|index| {
+            let (old, new) = (before.get(index), after.get(index));
+            (old != new).then(|| OutcomeChange {
+                index,
+                before: old.map(|outcome| (*outcome).clone()),
+                after: new.map(|outcome| (*outcome).clone()),
+            })
+        })
+        .collect()
+}
+
+fn diff_specs(a: &ExperimentSpec, b: &ExperimentSpec) -> Vec<SpecChange> {
+    let sections = pair_spec_sections(a, b);
+    let mut changes = Vec::new();
+    section_changes(&mut changes, sections.preconditions);
+    section_changes(&mut changes, sections.postconditions);
+    section_changes(&mut changes, sections.predictions);
+    section_changes(&mut changes, sections.interventions);
+    section_changes(&mut changes, sections.controlled_variables);
+    section_changes(&mut changes, sections.dag_refs);
+    changes
+}
+
+fn section_changes<T>(changes: &mut Vec<SpecChange>, section: SectionPairs<'_, T>) {
+    changes.extend(section.pairs.into_iter().filter_map(|pair| {
+        Some(SpecChange {
+            section: section.name,
+            key: pair.key,
+            kind: pair.change?,
+        })
+    }));
+}
+
+/// One key of an `ExperimentSpec` section, matched across two specs by
+/// `pair_spec_sections`.
+pub(crate) struct KeyedPair<'a, T> {
+    pub key: String,
+    /// `None` when both specs define the element identically.
+    pub change: Option<ChangeKind>,
+    /// The element as the second spec defines it.
+    pub theirs: Option<&'a T>,
+}
+
+/// Pairs of one `ExperimentSpec` section, named after its field.
+pub(crate) struct SectionPairs<'a, T> {
+    pub name: &'static str,
+    pub pairs: Vec<KeyedPair<'a, T>>,
+}
+
+/// Result of `pair_spec_sections`, one entry per `ExperimentSpec` section.
+pub(crate) struct SpecPairs<'a> {
+    pub preconditions: SectionPairs<'a, ContractTerm>,
+    pub postconditions: SectionPairs<'a, ContractTerm>,
+    pub predictions: SectionPairs<'a, PredictionRecord>,
+    pub interventions: SectionPairs<'a, InterventionRecord>,
+    pub controlled_variables: SectionPairs<'a, ControlledVariable>,
+    pub dag_refs: SectionPairs<'a, DagReference>,
+}
+
+/// Match each section of `ours` against `theirs`, keyed by element ID
+/// (node ID for `dag_refs`). Elements are compared with `BitwiseEq`. Pairs
+/// list the keys of `ours` in order, then keys only `theirs` has. Shared by
+/// `diff` and `merge_logs` so both match spec elements alike.
+pub(crate) fn pair_spec_sections<'a>(
+    ours: &ExperimentSpec,
+    theirs: &'a ExperimentSpec,
+) -> SpecPairs<'a> {
+    let term_key = |term: &ContractTerm| term.id.0.to_string();
+    SpecPairs {
+        preconditions: pair_by_key(
+            "preconditions",
+            &ours.preconditions,
+            &theirs.preconditions,
+            term_key,
+        ),
+        postconditions: pair_by_key(
+            "postconditions",
+            &ours.postconditions,
+            &theirs.postconditions,
+            term_key,
+        ),
+        predictions: pair_by_key(
+            "predictions",
+            &ours.predictions,
+            &theirs.predictions,
+            |prediction| prediction.id.0.to_string(),
+        ),
+        interventions: pair_by_key(
+            "interventions",
+            &ours.interventions,
+            &theirs.interventions,
+            |intervention| intervention.id.0.to_string(),
+        ),
+        controlled_variables: pair_by_key(
+            "controlled_variables",
+            &ours.controlled_variables,
+            &theirs.controlled_variables,
+            |variable| variable.id.0.to_string(),
+        ),
+        dag_refs: pair_by_key("dag_refs", &ours.dag_refs, &theirs.dag_refs, |dag_ref| {
+            dag_ref.node_id.clone()
+        }),
+    }
+}
+
+/// Keys are built once per element; `theirs` is looked up through a map.
+/// If a key repeats within one side, its first element is used.
+fn pair_by_key<'a, T: BitwiseEq>(
+    name: &'static str,
+    ours: &[T],
+    theirs: &'a [T],
+    key: impl Fn(&T) -> String,
+) -> SectionPairs<'a, T> {
+    let their_keys: Vec<String> = theirs.iter().map(&key).collect();
+    let mut by_key: HashMap<&str, &'a T> = HashMap::with_capacity(theirs.len());
+    for (their_key, new) in their_keys.iter().zip(theirs) {
+        by_key.entry(their_key.as_str()).or_insert(new);
+    }
+
+    let mut ours_keys = HashSet::with_capacity(ours.len());
+    let mut pairs = Vec::new();
+    for old in ours {
+        let old_key = key(old);
+        let new = by_key.get(old_key.as_str()).copied();
+        let change = match new {
+            None => Some(ChangeKind::Removed),
+            Some(new) if !new.bitwise_eq(old) => Some(ChangeKind::Modified),
+            Some(_) => None,
+        };
+        pairs.push(KeyedPair {
+            key: old_key.clone(),
+            change,
+            theirs: new,
+        });
+        ours_keys.insert(old_key);
+    }
+    for (new_key, new) in their_keys.into_iter().zip(theirs) {
+        if !ours_keys.contains(&new_key) {
+            pairs.push(KeyedPair {
+                key: new_key,
+                change: Some(ChangeKind::Added),
+                theirs: Some(new),
+            });
+        }
+    }
+    SectionPairs { name, pairs }
+}
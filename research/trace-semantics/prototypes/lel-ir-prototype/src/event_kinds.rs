@@ -1,13 +1,15 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::common::{
     ComparisonOutcome, EventId, EventKindTag, ExecutionOutcome, MatchStatus, NumericalEventType,
-    ObservationMode, Severity, SnapshotType, Value,
+    ObservationMode, Severity, SnapshotType, UncertaintySummary, Unit, Value,
 };
+use crate::symbol::Symbol;
 
 /// Event types mapped to requirements R1-R7, R8, R12, R16, R17.
 /// One variant per requirement class, ensuring exhaustive coverage.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum EventKind {
     // === Stage 1: Implementation Audit (R1-R7) ===
 
@@ -28,10 +30,11 @@ pub enum EventKind {
 
     /// R3/R4: Input parameter — specification value and/or actual value.
     ParameterRecord {
-        name: String,
+        name: Symbol,
         specified_value: Option<Value>,
         actual_value: Value,
-        units: Option<String>,
+        #[schemars(with = "Option<String>")]
+        units: Option<Unit>,
         observation_mode: ObservationMode,
     },
 
@@ -64,10 +67,10 @@ pub enum EventKind {
 
     /// R8/R16: Observable measurement.
     ObservableMeasurement {
-        variable_name: String,
+        variable_name: Symbol,
         measurement_method: String,
         value: Value,
-        uncertainty: Option<Value>,
+        uncertainty: Option<UncertaintySummary>,
         conditions: String,
         observation_mode: ObservationMode,
     },
@@ -95,9 +98,11 @@ pub enum EventKind {
     /// Convergence trajectory point (SCF, ionic, constraint).
     ConvergencePoint {
         iteration: u64,
-        metric_name: String,
+        metric_name: Symbol,
         metric_value: Value,
         converged: Option<bool>,
+        #[serde(default)]
+        uncertainty: Option<UncertaintySummary>,
     },
 
     /// State snapshot (coordinates, velocities, forces at a timestep).
@@ -109,7 +114,7 @@ pub enum EventKind {
     /// Energy decomposition at a timestep.
     EnergyRecord {
         total: Value,
-        components: Vec<(String, Value)>,
+        components: Vec<(Symbol, Value)>,
     },
 }
 
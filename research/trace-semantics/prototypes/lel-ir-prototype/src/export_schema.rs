@@ -0,0 +1,25 @@
+//! Writes the JSON Schema of serialized `LayeredEventLog` documents.
+//!
+//! Usage: `export-schema [OUTPUT]`. Prints to stdout when no output path is
+//! given.
+
+use std::process::ExitCode;
+
+use lel_ir_prototype::schema::log_json_schema;
+
+fn main() -> ExitCode {
+    let schema = serde_json::to_string_pretty(&log_json_schema()).expect("schema is valid JSON");
+    match std::env::args().nth(1) {
+        None => {
+            println!("{}", schema);
+            ExitCode::SUCCESS
+        }
+        Some(path) => match std::fs::write(&path, schema + "\n") {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("export-schema: cannot write {}: {}", path, err);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
@@ -1,13 +1,24 @@
-use crate::adapter::{AdapterError, DslAdapter};
+use std::io::{BufRead, Read};
+
+use crate::adapter::{assignment_keys, key_weight, AdapterError, Detection, DslAdapter};
+use crate::bundle::{has_extension, TraceBundle, TraceFile};
 use crate::common::*;
-use crate::convergence;
+use crate::convergence::ConvergenceWindow;
 use crate::event_kinds::EventKind;
 use crate::lel::*;
+use crate::provenance::{line_anchor, LineHasher, SourceText};
+use crate::streaming::{
+    source_paths, take_source, EventSink, Line, Lines, StreamSource, StreamingAdapter,
+};
+use crate::symbol::{Symbol, SymbolTable};
 
 pub struct GromacsAdapter;
 
 const MDP_MARKER: &str = "--- MDP ---";
 const LOG_MARKER: &str = "--- LOG ---";
+/// File names given to marker sections and unnamed input by `parse_trace`.
+const DEFAULT_MDP_FILE: &str = "input.mdp";
+const DEFAULT_LOG_FILE: &str = "simulation.log";
 
 pub fn classify_mdp_parameter(
     key: &str,
@@ -81,7 +92,23 @@ pub fn classify_mdp_parameter(
     }
 }
 
-pub fn parse_mdp(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
+pub fn parse_mdp(
+    content: &str,
+    ids: &mut EventIdAllocator,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    parse_mdp_file(DEFAULT_MDP_FILE, content, ids, &mut SymbolTable::new())
+}
+
+/// `parse_mdp`, anchoring provenance to `path` and interning names and
+/// units into `symbols`.
+pub fn parse_mdp_file(
+    path: &str,
+    content: &str,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    let source = SourceText::new(content);
+    let path = symbols.intern(path);
     let mut events = Vec::new();
     let mut logical_sequence = 1_u64;
 
@@ -100,9 +127,11 @@ pub fn parse_mdp(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
         let key = raw_key.trim();
         let value = raw_value.split(';').next().unwrap_or("").trim();
         let (layer, boundary, unit) = classify_mdp_parameter(key, value);
+        let name = symbols.intern(key);
+        let unit = unit.map(|symbol| symbols.unit(symbol));
 
         let parsed_value = match value.parse::<f64>() {
-            Ok(numeric) => Value::Known(numeric, unit.unwrap_or("").to_string()),
+            Ok(numeric) => Value::Known(numeric, unit.clone().unwrap_or_else(|| symbols.unit(""))),
             Err(_) => Value::KnownCat(value.to_string()),
         };
 
@@ -110,10 +139,10 @@ pub fn parse_mdp(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
             .layer(layer)
             .boundary(boundary)
             .kind(EventKind::ParameterRecord {
-                name: key.to_string(),
+                name: name.clone(),
                 specified_value: None,
                 actual_value: parsed_value,
-                units: unit.map(|u| u.to_string()),
+                units: unit,
                 observation_mode: ObservationMode::Observational,
             })
             .temporal(TemporalCoord {
@@ -121,16 +150,9 @@ pub fn parse_mdp(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
                 wall_clock_ns: None,
                 logical_sequence,
             })
-            .provenance(ProvenanceAnchor {
-                source_file: "input.mdp".to_string(),
-                source_location: SourceLocation::LineRange {
-                    start: line_num,
-                    end: line_num,
-                },
-                raw_hash: 0,
-            })
-            .dag_node_ref(key.to_string())
-            .build();
+            .provenance(source.anchor(&path, line_num, line_num))
+            .dag_node_ref(name)
+            .try_build(ids)?;
 
         logical_sequence += 1;
         events.push(event);
@@ -256,52 +278,268 @@ fn parse_energy_row(header_line: &str, value_line: &str) -> Option<Vec<(String,
     Some(headers.into_iter().zip(values).collect())
 }
 
-pub fn parse_log(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, AdapterError> {
-    let lines: Vec<&str> = content.lines().collect();
+pub fn parse_log(
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    parse_log_file(
+        DEFAULT_LOG_FILE,
+        content,
+        seq_offset,
+        ids,
+        &mut SymbolTable::new(),
+    )
+}
+
+/// `parse_log`, anchoring provenance to `path` and interning names and
+/// units into `symbols`.
+pub fn parse_log_file(
+    path: &str,
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<TraceEvent>, AdapterError> {
     let mut events = Vec::new();
-    let mut logical_sequence = seq_offset + 1;
-    let mut current_step = 0_u64;
+    stream_log_file(
+        path,
+        content.as_bytes(),
+        seq_offset,
+        ids,
+        symbols,
+        |event| {
+            events.push(event);
+            Ok(())
+        },
+    )?;
+    Ok(events)
+}
+
+/// `parse_log_file` over a reader, passing each event to `emit` as soon as
+/// it is complete. Holds at most one energy block.
+///
+/// The `ResourceStatus` event comes from the first `GROMACS` and `CPU` or
+/// `GPU` lines seen before the first energy block.
+pub fn stream_log_file(
+    path: &str,
+    reader: impl BufRead,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+    emit: impl FnMut(TraceEvent) -> Result<(), AdapterError>,
+) -> Result<(), AdapterError> {
+    let mut parser = LogParser::new(path, seq_offset, ids, symbols, emit);
+    let mut lines = Lines::new(reader);
+    while let Some(line) = lines.next_line()? {
+        parser.line(&line)?;
+    }
+    parser.finish(lines.count())
+}
+
+fn is_energy_block_end(header_line: &str) -> bool {
+    header_line.contains("Energies (kJ/mol)")
+        || header_line.contains("Step")
+        || header_line.contains("Finished mdrun")
+        || header_line.contains("Fatal error")
+}
+
+/// Version and platform lines found so far, and the span they cover.
+#[derive(Default)]
+struct ResourceLines {
+    version: Option<String>,
+    platform: Option<String>,
+    /// First and last matching line, the hash of `start..=end`, and a
+    /// running hash from `start` while the other line is still missing.
+    span: Option<(u32, u32, u64, LineHasher)>,
+    emitted: bool,
+}
+
+/// An `Energies (kJ/mol)` block being read.
+struct EnergyBlock {
+    start: u32,
+    start_hash: u64,
+    /// Last line of the last parsed row, and the hash of `start..=end`.
+    end: u32,
+    end_hash: u64,
+    lines: LineHasher,
+    pairs: Vec<(String, f64)>,
+    /// Column header waiting for its value line.
+    header: Option<String>,
+}
+
+struct LogParser<'a, E> {
+    path: Symbol,
+    ids: &'a mut EventIdAllocator,
+    emit: E,
+    logical_sequence: u64,
+    current_step: u64,
+    /// A `Step  Time` header was seen; the step is on the next line.
+    step_on_next_line: bool,
+    // Energy blocks repeat the same component names and unit every step.
+    symbols: &'a mut SymbolTable,
+    kj_per_mol: Unit,
+    resource: ResourceLines,
+    block: Option<EnergyBlock>,
+    completion: Option<(u32, u64, ExecutionOutcome)>,
+    last_line_hash: u64,
+}
+
+impl<'a, E> LogParser<'a, E>
+where
+    E: FnMut(TraceEvent) -> Result<(), AdapterError>,
+{
+    fn new(
+        path: &str,
+        seq_offset: u64,
+        ids: &'a mut EventIdAllocator,
+        symbols: &'a mut SymbolTable,
+        emit: E,
+    ) -> Self {
+        let kj_per_mol = symbols.unit("kJ/mol");
+        Self {
+            path: symbols.intern(path),
+            ids,
+            emit,
+            logical_sequence: seq_offset + 1,
+            current_step: 0,
+            step_on_next_line: false,
+            symbols,
+            kj_per_mol,
+            resource: ResourceLines::default(),
+            block: None,
+            completion: None,
+            last_line_hash: LineHasher::new().finish(),
+        }
+    }
+
+    fn line(&mut self, line: &Line) -> Result<(), AdapterError> {
+        let hash = LineHasher::of(line.raw);
+        self.last_line_hash = hash.finish();
+        self.scan_resource(line, hash)?;
+
+        if self.step_on_next_line {
+            self.step_on_next_line = false;
+            if let Some(step) = parse_step_from_line(line.text) {
+                self.current_step = step;
+            }
+        }
+
+        if let Some(block) = &mut self.block {
+            match block.header.take() {
+                Some(header_line) => {
+                    if let Some(row_pairs) = parse_energy_row(&header_line, line.text) {
+                        block.lines.push(line.raw);
+                        block.end = line.number;
+                        block.end_hash = block.lines.finish();
+                        block.pairs.extend(row_pairs);
+                        return Ok(());
+                    }
+                    self.finish_block()?;
+                }
+                None if line.text.trim().is_empty() => {
+                    block.lines.push(line.raw);
+                    return Ok(());
+                }
+                None if is_energy_block_end(line.text) => self.finish_block()?,
+                None => {
+                    block.lines.push(line.raw);
+                    block.header = Some(line.text.to_string());
+                    return Ok(());
+                }
+            }
+        }
 
-    let mut version_line: Option<u32> = None;
-    let mut hardware_line: Option<u32> = None;
-    let mut version_string: Option<String> = None;
-    let mut platform_type: Option<String> = None;
+        let text = line.text;
+        if text.trim_start().starts_with("Step") {
+            if let Some(step) = parse_step_from_line(text) {
+                self.current_step = step;
+            } else if text.contains("Time") {
+                self.step_on_next_line = true;
+            }
+        }
 
-    for (idx, line) in lines.iter().enumerate() {
-        if version_string.is_none() && line.contains("GROMACS") {
-            version_string = Some(line.trim().to_string());
-            version_line = Some((idx + 1) as u32);
+        if text.contains("Energies (kJ/mol)") {
+            self.block = Some(EnergyBlock {
+                start: line.number,
+                start_hash: hash.finish(),
+                end: line.number,
+                end_hash: hash.finish(),
+                lines: hash,
+                pairs: Vec::new(),
+                header: None,
+            });
+            return Ok(());
         }
-        if platform_type.is_none() && line.contains("GPU") {
-            platform_type = Some("GPU".to_string());
-            hardware_line = Some((idx + 1) as u32);
-        } else if platform_type.is_none() && line.contains("CPU") {
-            platform_type = Some("CPU".to_string());
-            hardware_line = Some((idx + 1) as u32);
+
+        if text.contains("Finished mdrun") {
+            self.completion = Some((line.number, hash.finish(), ExecutionOutcome::Success));
+        } else if text.contains("Fatal error") {
+            self.completion = Some((line.number, hash.finish(), ExecutionOutcome::CrashDivergent));
         }
+        Ok(())
+    }
 
-        if version_string.is_some() && platform_type.is_some() {
-            break;
+    fn scan_resource(&mut self, line: &Line, hash: LineHasher) -> Result<(), AdapterError> {
+        let resource = &mut self.resource;
+        if resource.emitted {
+            return Ok(());
         }
+        if let Some((_, _, _, running)) = &mut resource.span {
+            running.push(line.raw);
+        }
+
+        let mut found = false;
+        if resource.version.is_none() && line.text.contains("GROMACS") {
+            resource.version = Some(line.text.trim().to_string());
+            found = true;
+        }
+        if resource.platform.is_none() && line.text.contains("GPU") {
+            resource.platform = Some("GPU".to_string());
+            found = true;
+        } else if resource.platform.is_none() && line.text.contains("CPU") {
+            resource.platform = Some("CPU".to_string());
+            found = true;
+        }
+        if found {
+            match &mut resource.span {
+                Some((_, end, span_hash, running)) => {
+                    *end = line.number;
+                    *span_hash = running.finish();
+                }
+                None => resource.span = Some((line.number, line.number, hash.finish(), hash)),
+            }
+        }
+
+        if resource.version.is_some() && resource.platform.is_some() {
+            self.emit_resource()?;
+        }
+        Ok(())
     }
 
-    if version_string.is_some() || platform_type.is_some() {
-        let start = version_line
-            .into_iter()
-            .chain(hardware_line)
-            .min()
-            .unwrap_or(1);
-        let end = version_line
-            .into_iter()
-            .chain(hardware_line)
-            .max()
-            .unwrap_or(start);
+    /// Emit the `ResourceStatus` event, if any, ahead of every other event.
+    fn emit_resource(&mut self) -> Result<(), AdapterError> {
+        if self.resource.emitted {
+            return Ok(());
+        }
+        self.resource.emitted = true;
+        let Some((start, end, hash, _)) = self.resource.span else {
+            return Ok(());
+        };
 
         let resource = TraceEventBuilder::new()
             .layer(Layer::Implementation)
             .kind(EventKind::ResourceStatus {
-                platform_type: platform_type.unwrap_or_else(|| "CPU".to_string()),
-                device_ids: vec![version_string.unwrap_or_else(|| "GROMACS".to_string())],
+                platform_type: self
+                    .resource
+                    .platform
+                    .take()
+                    .unwrap_or_else(|| "CPU".to_string()),
+                device_ids: vec![self
+                    .resource
+                    .version
+                    .take()
+                    .unwrap_or_else(|| "GROMACS".to_string())],
                 memory_allocated: None,
                 memory_peak: None,
                 parallelization: None,
@@ -310,294 +548,264 @@ pub fn parse_log(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, Adap
             .temporal(TemporalCoord {
                 simulation_step: 0,
                 wall_clock_ns: None,
-                logical_sequence,
-            })
-            .provenance(ProvenanceAnchor {
-                source_file: "simulation.log".to_string(),
-                source_location: SourceLocation::LineRange { start, end },
-                raw_hash: 0,
+                logical_sequence: self.logical_sequence,
             })
-            .build();
-        logical_sequence += 1;
-        events.push(resource);
+            .provenance(line_anchor(&self.path, start, end, hash))
+            .try_build(self.ids)?;
+        self.logical_sequence += 1;
+        (self.emit)(resource)
     }
 
-    let mut idx = 0_usize;
-    let mut completion_line: Option<u32> = None;
-    let mut completion_status: Option<ExecutionOutcome> = None;
-
-    while idx < lines.len() {
-        let line = lines[idx];
-
-        if line.trim_start().starts_with("Step") {
-            if let Some(step) = parse_step_from_line(line) {
-                current_step = step;
-            } else if line.contains("Time") && idx + 1 < lines.len() {
-                if let Some(step) = parse_step_from_line(lines[idx + 1]) {
-                    current_step = step;
-                }
-            }
+    fn finish_block(&mut self) -> Result<(), AdapterError> {
+        let Some(block) = self.block.take() else {
+            return Ok(());
+        };
+        if block.pairs.is_empty() {
+            return Ok(());
         }
+        self.emit_resource()?;
 
-        if line.contains("Energies (kJ/mol)") {
-            let mut row_idx = idx + 1;
-            let mut pairs = Vec::<(String, f64)>::new();
-            let mut block_end_line = (idx + 1) as u32;
-
-            while row_idx + 1 < lines.len() {
-                let header_line = lines[row_idx];
-                let value_line = lines[row_idx + 1];
-
-                if header_line.trim().is_empty() {
-                    row_idx += 1;
-                    continue;
-                }
-                if header_line.contains("Energies (kJ/mol)")
-                    || header_line.contains("Step")
-                    || header_line.contains("Finished mdrun")
-                    || header_line.contains("Fatal error")
-                {
-                    break;
-                }
+        let mut total_energy = None;
+        let mut components = Vec::new();
+        let mut numerical_findings = Vec::new();
 
-                if let Some(row_pairs) = parse_energy_row(header_line, value_line) {
-                    block_end_line = (row_idx + 2) as u32;
-                    pairs.extend(row_pairs);
-                    row_idx += 2;
-                } else {
-                    break;
-                }
+        for (name, value) in &block.pairs {
+            if name == "Total Energy" || name == "Total_Energy" {
+                total_energy = Some(*value);
+            } else {
+                components.push((
+                    self.symbols.intern(name),
+                    Value::Known(*value, self.kj_per_mol.clone()),
+                ));
             }
 
-            if !pairs.is_empty() {
-                let mut total_energy = None;
-                let mut components = Vec::new();
-                let mut numerical_findings = Vec::new();
-
-                for (name, value) in &pairs {
-                    if name == "Total Energy" || name == "Total_Energy" {
-                        total_energy = Some(*value);
-                    } else {
-                        components.push((name.clone(), Value::Known(*value, "kJ/mol".to_string())));
-                    }
-
-                    if value.is_nan() {
-                        numerical_findings.push((
-                            NumericalEventType::NaNDetected,
-                            name.clone(),
-                            "NaN detected in energy component".to_string(),
-                        ));
-                    } else if value.is_infinite() {
-                        numerical_findings.push((
-                            NumericalEventType::InfDetected,
-                            name.clone(),
-                            "Inf detected in energy component".to_string(),
-                        ));
-                    }
-                }
-
-                let Some(total) = total_energy else {
-                    let warning_event = TraceEventBuilder::new()
-                        .layer(Layer::Implementation)
-                        .kind(EventKind::NumericalStatus {
-                            event_type: NumericalEventType::ConvergenceFailure,
-                            affected_quantity: "Total Energy".to_string(),
-                            severity: Severity::Warning,
-                            detail: Value::KnownCat(
-                                "Energy block parsed but Total Energy header not found".to_string(),
-                            ),
-                        })
-                        .temporal(TemporalCoord {
-                            simulation_step: current_step,
-                            wall_clock_ns: None,
-                            logical_sequence,
-                        })
-                        .provenance(ProvenanceAnchor {
-                            source_file: "simulation.log".to_string(),
-                            source_location: SourceLocation::LineRange {
-                                start: (idx + 1) as u32,
-                                end: (idx + 1) as u32,
-                            },
-                            raw_hash: 0,
-                        })
-                        .build();
-                    logical_sequence += 1;
-                    events.push(warning_event);
-                    idx = row_idx;
-                    continue;
-                };
-
-                let energy_event = TraceEventBuilder::new()
-                    .layer(Layer::Implementation)
-                    .kind(EventKind::EnergyRecord {
-                        total: Value::Known(total, "kJ/mol".to_string()),
-                        components,
-                    })
-                    .temporal(TemporalCoord {
-                        simulation_step: current_step,
-                        wall_clock_ns: None,
-                        logical_sequence,
-                    })
-                    .provenance(ProvenanceAnchor {
-                        source_file: "simulation.log".to_string(),
-                        source_location: SourceLocation::LineRange {
-                            start: (idx + 1) as u32,
-                            end: block_end_line,
-                        },
-                        raw_hash: 0,
-                    })
-                    .build();
-                logical_sequence += 1;
-
-                events.push(energy_event);
-                for (event_type, affected_quantity, detail) in numerical_findings {
-                    let numerical_event = TraceEventBuilder::new()
-                        .layer(Layer::Implementation)
-                        .kind(EventKind::NumericalStatus {
-                            event_type,
-                            affected_quantity,
-                            severity: Severity::Warning,
-                            detail: Value::KnownCat(detail),
-                        })
-                        .temporal(TemporalCoord {
-                            simulation_step: current_step,
-                            wall_clock_ns: None,
-                            logical_sequence,
-                        })
-                        .provenance(ProvenanceAnchor {
-                            source_file: "simulation.log".to_string(),
-                            source_location: SourceLocation::LineRange {
-                                start: (idx + 1) as u32,
-                                end: block_end_line,
-                            },
-                            raw_hash: 0,
-                        })
-                        .build();
-                    logical_sequence += 1;
-                    events.push(numerical_event);
-                }
+            if value.is_nan() {
+                numerical_findings.push((
+                    NumericalEventType::NaNDetected,
+                    name.clone(),
+                    "NaN detected in energy component".to_string(),
+                ));
+            } else if value.is_infinite() {
+                numerical_findings.push((
+                    NumericalEventType::InfDetected,
+                    name.clone(),
+                    "Inf detected in energy component".to_string(),
+                ));
             }
-
-            idx = row_idx;
-            continue;
         }
 
-        if line.contains("Finished mdrun") {
-            completion_line = Some((idx + 1) as u32);
-            completion_status = Some(ExecutionOutcome::Success);
-        } else if line.contains("Fatal error") {
-            completion_line = Some((idx + 1) as u32);
-            completion_status = Some(ExecutionOutcome::CrashDivergent);
-        }
+        let Some(total) = total_energy else {
+            let warning_event = TraceEventBuilder::new()
+                .layer(Layer::Implementation)
+                .kind(EventKind::NumericalStatus {
+                    event_type: NumericalEventType::ConvergenceFailure,
+                    affected_quantity: "Total Energy".to_string(),
+                    severity: Severity::Warning,
+                    detail: Value::KnownCat(
+                        "Energy block parsed but Total Energy header not found".to_string(),
+                    ),
+                })
+                .temporal(TemporalCoord {
+                    simulation_step: self.current_step,
+                    wall_clock_ns: None,
+                    logical_sequence: self.logical_sequence,
+                })
+                .provenance(line_anchor(
+                    &self.path,
+                    block.start,
+                    block.start,
+                    block.start_hash,
+                ))
+                .try_build(self.ids)?;
+            self.logical_sequence += 1;
+            return (self.emit)(warning_event);
+        };
 
-        idx += 1;
+        let block_anchor = line_anchor(&self.path, block.start, block.end, block.end_hash);
+        let energy_event = TraceEventBuilder::new()
+            .layer(Layer::Implementation)
+            .kind(EventKind::EnergyRecord {
+                total: Value::Known(total, self.kj_per_mol.clone()),
+                components,
+            })
+            .temporal(TemporalCoord {
+                simulation_step: self.current_step,
+                wall_clock_ns: None,
+                logical_sequence: self.logical_sequence,
+            })
+            .provenance(block_anchor.clone())
+            .try_build(self.ids)?;
+        self.logical_sequence += 1;
+        (self.emit)(energy_event)?;
+
+        for (event_type, affected_quantity, detail) in numerical_findings {
+            let numerical_event = TraceEventBuilder::new()
+                .layer(Layer::Implementation)
+                .kind(EventKind::NumericalStatus {
+                    event_type,
+                    affected_quantity,
+                    severity: Severity::Warning,
+                    detail: Value::KnownCat(detail),
+                })
+                .temporal(TemporalCoord {
+                    simulation_step: self.current_step,
+                    wall_clock_ns: None,
+                    logical_sequence: self.logical_sequence,
+                })
+                .provenance(block_anchor.clone())
+                .try_build(self.ids)?;
+            self.logical_sequence += 1;
+            (self.emit)(numerical_event)?;
+        }
+        Ok(())
     }
 
-    let completion_line = completion_line.unwrap_or(lines.len().max(1) as u32);
-    let completion_kind = EventKind::ExecutionStatus {
-        status: completion_status.clone().unwrap_or(ExecutionOutcome::Timeout),
-        framework_error_id: None,
-    };
-
-    let mut completion_builder = TraceEventBuilder::new()
-        .layer(Layer::Implementation)
-        .kind(completion_kind)
-        .temporal(TemporalCoord {
-            simulation_step: current_step,
-            wall_clock_ns: None,
-            logical_sequence,
-        })
-        .provenance(ProvenanceAnchor {
-            source_file: "simulation.log".to_string(),
-            source_location: SourceLocation::LineRange {
-                start: completion_line,
-                end: completion_line,
-            },
-            raw_hash: 0,
-        });
+    fn finish(mut self, line_count: u32) -> Result<(), AdapterError> {
+        self.finish_block()?;
+        self.emit_resource()?;
 
-    if completion_status.is_none() {
-        completion_builder = completion_builder.confidence(ConfidenceMeta {
-            completeness: Completeness::PartiallyInferred {
-                inference_method: "no completion marker in log".to_string(),
-            },
-            field_coverage: 0.5,
-            notes: vec![],
-        });
-    }
+        let (completion_line, completion_hash, completion_status) = match self.completion.take() {
+            Some((line, hash, status)) => (line, hash, Some(status)),
+            None => (line_count.max(1), self.last_line_hash, None),
+        };
+        let completion_kind = EventKind::ExecutionStatus {
+            status: completion_status
+                .clone()
+                .unwrap_or(ExecutionOutcome::Timeout),
+            framework_error_id: None,
+        };
 
-    events.push(completion_builder.build());
-    Ok(events)
+        let mut completion_builder = TraceEventBuilder::new()
+            .layer(Layer::Implementation)
+            .kind(completion_kind)
+            .temporal(TemporalCoord {
+                simulation_step: self.current_step,
+                wall_clock_ns: None,
+                logical_sequence: self.logical_sequence,
+            })
+            .provenance(line_anchor(
+                &self.path,
+                completion_line,
+                completion_line,
+                completion_hash,
+            ));
+
+        if completion_status.is_none() {
+            completion_builder = completion_builder.confidence(ConfidenceMeta {
+                completeness: Completeness::PartiallyInferred {
+                    inference_method: "no completion marker in log".to_string(),
+                },
+                field_coverage: 0.5,
+                notes: vec![],
+            });
+        }
+
+        (self.emit)(completion_builder.try_build(self.ids)?)
+    }
 }
 
 impl DslAdapter for GromacsAdapter {
-    fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError> {
-        let mdp_marker_pos = raw.find(MDP_MARKER);
-        let log_marker_pos = raw.find(LOG_MARKER);
-
-        let mut mdp_content: Option<&str> = None;
-        let mut log_content: Option<&str> = None;
+    fn framework(&self) -> &'static str {
+        "gromacs"
+    }
 
-        if let Some(mdp_pos) = mdp_marker_pos {
-            let mdp_start = mdp_pos + MDP_MARKER.len();
-            let mdp_end = log_marker_pos
-                .filter(|log_pos| *log_pos > mdp_pos)
-                .unwrap_or(raw.len());
-            mdp_content = Some(raw[mdp_start..mdp_end].trim());
+    /// The GROMACS banner, section markers, MDP keys from the
+    /// classification table and `Energies (kJ/mol)` blocks.
+    fn detect(&self, raw: &str) -> Detection {
+        let mut detection = Detection::default();
+        if raw.lines().any(|line| line.contains("GROMACS")) {
+            detection.signal(0.7, "GROMACS banner");
+        }
+        if raw.contains(MDP_MARKER) || raw.contains(LOG_MARKER) {
+            detection.signal(0.5, "MDP/LOG section markers");
+        }
+        let mut keys: Vec<&str> = assignment_keys(raw, &[';'])
+            .filter(|key| {
+                !matches!(
+                    classify_mdp_parameter(key, "").1,
+                    BoundaryClassification::ContextDependent { .. }
+                )
+            })
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        if !keys.is_empty() {
+            detection.signal(
+                key_weight(keys.len()),
+                format!("MDP keys {}", keys.join(", ")),
+            );
+        }
+        if raw.contains("Energies (kJ/mol)") {
+            detection.signal(0.5, "Energies (kJ/mol) block");
         }
+        detection
+    }
 
-        if let Some(log_pos) = log_marker_pos {
-            let log_start = log_pos + LOG_MARKER.len();
-            log_content = Some(raw[log_start..].trim());
+    /// `detect` on the contents, plus the `.mdp` and `.log` extensions.
+    fn detect_file(&self, file: &TraceFile) -> Detection {
+        let mut detection = self.detect(&file.contents);
+        if has_extension(&file.path, "mdp") {
+            detection.signal(0.6, format!("file name {}", file.file_name()));
+        } else if has_extension(&file.path, "log") {
+            detection.signal(0.2, format!("file name {}", file.file_name()));
         }
+        detection
+    }
 
-        if mdp_marker_pos.is_none() && log_marker_pos.is_none() {
+    fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError> {
+        let mut bundle = TraceBundle::from_sections(
+            raw,
+            &[
+                (MDP_MARKER, DEFAULT_MDP_FILE),
+                (LOG_MARKER, DEFAULT_LOG_FILE),
+            ],
+        );
+        if bundle.is_empty() {
             if raw.lines().any(|line| line.contains('=')) {
-                mdp_content = Some(raw);
+                bundle.add(DEFAULT_MDP_FILE, raw);
             } else {
-                log_content = Some(raw);
+                bundle.add(DEFAULT_LOG_FILE, raw);
             }
         }
+        self.parse_bundle(&bundle)
+    }
 
-        let mut mdp_events = if let Some(content) = mdp_content {
-            parse_mdp(content)?
-        } else {
-            Vec::new()
-        };
-        let mdp_event_ids: Vec<EventId> = mdp_events.iter().map(|event| event.id).collect();
+    /// Reads the first `.mdp` and the first `.log` file; other files are
+    /// ignored.
+    fn parse_bundle(&self, bundle: &TraceBundle) -> Result<LayeredEventLog, AdapterError> {
+        self.parse_streams(bundle.stream_sources())
+    }
+}
 
-        let mut log_events = if let Some(content) = log_content {
-            parse_log(content, mdp_events.len() as u64)?
+impl StreamingAdapter for GromacsAdapter {
+    /// The MDP file is read whole; the log is streamed.
+    fn stream(
+        &self,
+        mut sources: Vec<StreamSource<'_>>,
+        sink: &mut dyn EventSink,
+    ) -> Result<(), AdapterError> {
+        let mdp = take_source(&mut sources, |path| has_extension(path, "mdp"));
+        let log = take_source(&mut sources, |path| has_extension(path, "log"));
+        if mdp.is_none() && log.is_none() {
+            return Err(AdapterError::UnsupportedFormat(format!(
+                "no .mdp or .log file among {:?}",
+                source_paths(&sources)
+            )));
+        }
+        let mdp_path = mdp
+            .as_ref()
+            .map_or(DEFAULT_MDP_FILE.to_string(), |source| source.path.clone());
+
+        let mut ids = EventIdAllocator::new();
+        let mut symbols = SymbolTable::new();
+        let mdp_events = if let Some(mut source) = mdp {
+            let mut content = String::new();
+            source.reader.read_to_string(&mut content)?;
+            parse_mdp_file(&mdp_path, &content, &mut ids, &mut symbols)?
         } else {
             Vec::new()
         };
-
-        let mut last_energy_event_id: Option<EventId> = None;
-        for event in &mut log_events {
-            match &event.kind {
-                EventKind::EnergyRecord { .. } => {
-                    event.causal_refs = mdp_event_ids.clone();
-                    last_energy_event_id = Some(event.id);
-                }
-                EventKind::NumericalStatus { .. } => {
-                    if let Some(energy_id) = last_energy_event_id {
-                        event.causal_refs = vec![energy_id];
-                    }
-                }
-                EventKind::ExecutionStatus { .. } => {
-                    if let Some(energy_id) = last_energy_event_id {
-                        event.causal_refs = vec![energy_id];
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        if let Some(summary_event) =
-            convergence::derive_energy_convergence_summary(&log_events, "simulation.log")
-        {
-            log_events.push(summary_event);
-        }
+        let mdp_event_ids: Vec<EventId> = mdp_events.iter().map(|event| event.id).collect();
 
         let mut ref_t_value: Option<Value> = None;
         let mut ref_p_value: Option<Value> = None;
@@ -644,20 +852,52 @@ impl DslAdapter for GromacsAdapter {
             controlled_variables,
             dag_refs: Vec::new(),
             provenance: ProvenanceAnchor {
-                source_file: "input.mdp".to_string(),
+                source_file: mdp_path.as_str().into(),
                 source_location: SourceLocation::ExternalInput,
                 raw_hash: 0,
             },
         };
 
-        let mut builder = LayeredEventLogBuilder::new(experiment_ref, spec);
-        for event in mdp_events.drain(..) {
-            builder = builder.add_event(event);
+        sink.begin(experiment_ref, spec)?;
+        let seq_offset = mdp_events.len() as u64;
+        for event in mdp_events {
+            sink.event(event)?;
         }
-        for event in log_events {
-            builder = builder.add_event(event);
+
+        let log_path = log
+            .as_ref()
+            .map_or(DEFAULT_LOG_FILE.to_string(), |source| source.path.clone());
+        let mut window = ConvergenceWindow::new();
+        if let Some(source) = log {
+            let mut last_energy_event_id: Option<EventId> = None;
+            stream_log_file(
+                &log_path,
+                source.reader,
+                seq_offset,
+                &mut ids,
+                &mut symbols,
+                |mut event| {
+                    match &event.kind {
+                        EventKind::EnergyRecord { .. } => {
+                            event.causal_refs = mdp_event_ids.clone();
+                            last_energy_event_id = Some(event.id);
+                        }
+                        EventKind::NumericalStatus { .. } | EventKind::ExecutionStatus { .. } => {
+                            if let Some(energy_id) = last_energy_event_id {
+                                event.causal_refs = vec![energy_id];
+                            }
+                        }
+                        _ => {}
+                    }
+                    window.record(&event);
+                    sink.event(event)
+                },
+            )?;
         }
 
-        Ok(builder.build())
+        if let Some(summary_event) = window.summary(&log_path, &mut ids)? {
+            sink.event(summary_event)?;
+        }
+        Ok(())
     }
 }
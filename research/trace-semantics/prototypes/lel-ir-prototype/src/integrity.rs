@@ -0,0 +1,342 @@
+//! Structural integrity checks for `LayeredEventLog`.
+//!
+//! `LayeredEventLog::validate` reports every violation in one pass instead of
+//! stopping at the first, so a hand-edited or deserialized log can be
+//! repaired in one go. Consumers such as `CausalOverlay::from_log` skip
+//! dangling refs silently; validate first when the log's origin is untrusted.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::common::{Completeness, ElementId, EventId};
+use crate::lel::{EventIndexes, LayeredEventLog, TraceEvent};
+
+/// One structural problem found by `LayeredEventLog::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityViolation {
+    /// Two or more events share an ID.
+    DuplicateId { id: EventId, positions: Vec<usize> },
+    /// A causal ref names an ID that is not in the log.
+    DanglingCausalRef { event: EventId, target: EventId },
+    /// A causal ref names an event with a later logical sequence.
+    ForwardCausalRef {
+        event: EventId,
+        target: EventId,
+        event_sequence: u64,
+        target_sequence: u64,
+    },
+    /// Events whose causal refs form a cycle, in ref order.
+    CausalCycle { events: Vec<EventId> },
+    /// A secondary index disagrees with `events`.
+    IndexOutOfSync { index: &'static str, key: String },
+    /// `logical_sequence` does not increase from the previous event.
+    NonMonotonicSequence {
+        position: usize,
+        previous: u64,
+        current: u64,
+    },
+    /// `Completeness::Derived` names an element that is not an event ID.
+    MissingDerivedElement { event: EventId, element: ElementId },
+}
+
+impl fmt::Display for IntegrityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityViolation::DuplicateId { id, positions } => {
+                write!(f, "event id {} appears at positions {:?}", id.0, positions)
+            }
+            IntegrityViolation::DanglingCausalRef { event, target } => write!(
+                f,
+                "event {} has causal ref to missing event {}",
+                event.0, target.0
+            ),
+            IntegrityViolation::ForwardCausalRef {
+                event,
+                target,
+                event_sequence,
+                target_sequence,
+            } => write!(
+                f,
+                "event {} (sequence {}) has causal ref to later event {} (sequence {})",
+                event.0, event_sequence, target.0, target_sequence
+            ),
+            IntegrityViolation::CausalCycle { events } => {
+                let ids: Vec<String> = events.iter().map(|id| id.0.to_string()).collect();
+                write!(f, "causal cycle through events {}", ids.join(" -> "))
+            }
+            IntegrityViolation::IndexOutOfSync { index, key } => {
+                write!(f, "index {} out of sync at key {}", index, key)
+            }
+            IntegrityViolation::NonMonotonicSequence {
+                position,
+                previous,
+                current,
+            } => write!(
+                f,
+                "logical_sequence {} at position {} does not follow {}",
+                current, position, previous
+            ),
+            IntegrityViolation::MissingDerivedElement { event, element } => write!(
+                f,
+                "event {} is derived from missing element {}",
+                event.0, element.0
+            ),
+        }
+    }
+}
+
+impl LayeredEventLog {
+    /// Check the log's structural invariants. Returns every violation found;
+    /// an empty list means the log is well-formed.
+    pub fn validate(&self) -> Vec<IntegrityViolation> {
+        let mut violations = Vec::new();
+
+        // First position of each ID; duplicates are reported, not indexed.
+        let mut positions_by_id: HashMap<EventId, Vec<usize>> = HashMap::new();
+        for (position, event) in self.events.iter().enumerate() {
+            positions_by_id.entry(event.id).or_default().push(position);
+        }
+        let mut duplicates: Vec<(&EventId, &Vec<usize>)> = positions_by_id
+            .iter()
+            .filter(|(_, positions)| positions.len() > 1)
+            .collect();
+        duplicates.sort_by_key(|(_, positions)| positions[0]);
+        for (id, positions) in duplicates {
+            violations.push(IntegrityViolation::DuplicateId {
+                id: *id,
+                positions: positions.clone(),
+            });
+        }
+        let first_position = |id: &EventId| positions_by_id.get(id).map(|positions| positions[0]);
+
+        for (position, event) in self.events.iter().enumerate() {
+            if position > 0 {
+                let previous = self.events[position - 1].temporal.logical_sequence;
+                let current = event.temporal.logical_sequence;
+                if current <= previous {
+                    violations.push(IntegrityViolation::NonMonotonicSequence {
+                        position,
+                        previous,
+                        current,
+                    });
+                }
+            }
+
+            for target in &event.causal_refs {
+                match first_position(target) {
+                    None => violations.push(IntegrityViolation::DanglingCausalRef {
+                        event: event.id,
+                        target: *target,
+                    }),
+                    Some(target_position) => {
+                        let target_sequence =
+                            self.events[target_position].temporal.logical_sequence;
+                        if target_sequence > event.temporal.logical_sequence {
+                            violations.push(IntegrityViolation::ForwardCausalRef {
+                                event: event.id,
+                                target: *target,
+                                event_sequence: event.temporal.logical_sequence,
+                                target_sequence,
+                            });
+                        }
+                    }
+                }
+            }
+
+            if let Completeness::Derived { from_elements } = &event.confidence.completeness {
+                for element in from_elements {
+                    if !positions_by_id.contains_key(&EventId(element.0)) {
+                        violations.push(IntegrityViolation::MissingDerivedElement {
+                            event: event.id,
+                            element: *element,
+                        });
+                    }
+                }
+            }
+        }
+
+        violations.extend(self.find_causal_cycles(&first_position));
+        violations.extend(index_violations(&self.indexes, &self.events));
+        violations
+    }
+
+    /// Iterative DFS over causal refs; each back edge yields one cycle.
+    fn find_causal_cycles(
+        &self,
+        first_position: &dyn Fn(&EventId) -> Option<usize>,
+    ) -> Vec<IntegrityViolation> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+
+        let mut cycles = Vec::new();
+        let mut marks = vec![Mark::Unvisited; self.events.len()];
+        for root in 0..self.events.len() {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+            // (position, next causal ref to follow)
+            let mut stack = vec![(root, 0_usize)];
+            marks[root] = Mark::OnStack;
+            while let Some(&mut (position, ref mut next_ref)) = stack.last_mut() {
+                let refs = &self.events[position].causal_refs;
+                if *next_ref == refs.len() {
+                    marks[position] = Mark::Done;
+                    stack.pop();
+                    continue;
+                }
+                let target = refs[*next_ref];
+                *next_ref += 1;
+                let Some(target_position) = first_position(&target) else {
+                    continue;
+                };
+                match marks[target_position] {
+                    Mark::Unvisited => {
+                        marks[target_position] = Mark::OnStack;
+                        stack.push((target_position, 0));
+                    }
+                    Mark::OnStack => {
+                        let start = stack
+                            .iter()
+                            .position(|(on_stack, _)| *on_stack == target_position)
+                            .unwrap_or(0);
+                        cycles.push(IntegrityViolation::CausalCycle {
+                            events: stack[start..]
+                                .iter()
+                                .map(|(on_stack, _)| self.events[*on_stack].id)
+                                .collect(),
+                        });
+                    }
+                    Mark::Done => {}
+                }
+            }
+        }
+        cycles
+    }
+}
+
+/// Compare `indexes` with indexes rebuilt from `events`.
+pub(crate) fn index_violations(
+    indexes: &EventIndexes,
+    events: &[TraceEvent],
+) -> Vec<IntegrityViolation> {
+    let expected = EventIndexes::rebuild(events);
+    if *indexes == expected {
+        return Vec::new();
+    }
+
+    let mut violations = Vec::new();
+    diff_index("by_id", &indexes.by_id, &expected.by_id, &mut violations);
+    diff_index(
+        "by_layer",
+        &indexes.by_layer,
+        &expected.by_layer,
+        &mut violations,
+    );
+    diff_index(
+        "by_kind",
+        &indexes.by_kind,
+        &expected.by_kind,
+        &mut violations,
+    );
+    diff_index(
+        "by_variable",
+        &indexes.by_variable,
+        &expected.by_variable,
+        &mut violations,
+    );
+    diff_index(
+        "by_dag_node",
+        &indexes.by_dag_node,
+        &expected.by_dag_node,
+        &mut violations,
+    );
+    diff_step_index(
+        "by_time_range",
+        &indexes.by_time_range,
+        &expected.by_time_range,
+        &mut violations,
+    );
+    diff_step_index(
+        "by_step",
+        &indexes.by_step,
+        &expected.by_step,
+        &mut violations,
+    );
+    violations
+}
+
+/// Serialized indexes that disagree with the serialized events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexConsistencyError {
+    pub violations: Vec<IntegrityViolation>,
+}
+
+impl fmt::Display for IndexConsistencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details: Vec<String> = self.violations.iter().map(|v| v.to_string()).collect();
+        write!(f, "indexes disagree with events: {}", details.join("; "))
+    }
+}
+
+impl std::error::Error for IndexConsistencyError {}
+
+/// Report every key whose entry differs between `actual` and `expected`.
+fn diff_index<K, V>(
+    index: &'static str,
+    actual: &HashMap<K, V>,
+    expected: &HashMap<K, V>,
+    violations: &mut Vec<IntegrityViolation>,
+) where
+    K: std::hash::Hash + Eq + fmt::Debug,
+    V: PartialEq,
+{
+    let mut keys: Vec<String> = expected
+        .iter()
+        .filter(|(key, value)| actual.get(key) != Some(value))
+        .map(|(key, _)| format!("{:?}", key))
+        .chain(
+            actual
+                .keys()
+                .filter(|key| !expected.contains_key(key))
+                .map(|key| format!("{:?}", key)),
+        )
+        .collect();
+    keys.sort();
+    violations.extend(
+        keys.into_iter()
+            .map(|key| IntegrityViolation::IndexOutOfSync { index, key }),
+    );
+}
+
+/// `diff_index` for the step-keyed ordered indexes.
+fn diff_step_index<V: PartialEq>(
+    index: &'static str,
+    actual: &BTreeMap<u64, V>,
+    expected: &BTreeMap<u64, V>,
+    violations: &mut Vec<IntegrityViolation>,
+) {
+    let mut steps: Vec<u64> = expected
+        .iter()
+        .filter(|(step, value)| actual.get(step) != Some(value))
+        .map(|(step, _)| *step)
+        .chain(
+            actual
+                .keys()
+                .filter(|step| !expected.contains_key(step))
+                .copied(),
+        )
+        .collect();
+    steps.sort_unstable();
+    violations.extend(
+        steps
+            .into_iter()
+            .map(|step| IntegrityViolation::IndexOutOfSync {
+                index,
+                key: step.to_string(),
+            }),
+    );
+}
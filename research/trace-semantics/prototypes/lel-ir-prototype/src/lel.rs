@@ -1,7 +1,8 @@
 use std::collections::{BTreeMap, HashMap};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::fmt;
 
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::common::{
     BoundaryClassification, Completeness, ConfidenceMeta, ContractTerm, ControlledVariable,
@@ -9,13 +10,23 @@ use crate::common::{
     PredictionRecord, ProvenanceAnchor, SourceLocation, SpecElementId, TemporalCoord,
 };
 use crate::event_kinds::EventKind;
+use crate::integrity::{index_violations, IndexConsistencyError};
+use crate::schema::{deserialize_version, LOG_SCHEMA_VERSION};
+use crate::series::TimeSeries;
+use crate::symbol::{Symbol, SymbolTable};
 
 // ============================================================
 // Core LEL Structures (from §2)
 // ============================================================
 
 /// The top-level LEL IR container.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Serialization writes `LOG_SCHEMA_VERSION` alongside the fields.
+/// Deserialization rebuilds `indexes` when the document omits them and
+/// rejects documents whose indexes disagree with `events`, as well as
+/// documents of another schema version (see `schema::log_from_json`).
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(try_from = "SerializedLog")]
 pub struct LayeredEventLog {
     /// The experiment this log belongs to.
     pub experiment_ref: ExperimentRef,
@@ -29,12 +40,115 @@ pub struct LayeredEventLog {
 
     /// Secondary indexes built during construction for R24 queryability.
     pub indexes: EventIndexes,
+
+    /// Distinct names and units shared by `events`. Derived data: not
+    /// serialized, and rebuilt when a log is loaded.
+    #[schemars(skip)]
+    pub symbols: SymbolTable,
+
+    /// Energy and convergence trajectories in columnar form, built from
+    /// `events` like `indexes` but never serialized.
+    #[schemars(skip)]
+    pub series: TimeSeries,
+}
+
+/// Wire form of `LayeredEventLog`, with optional indexes. Also the source
+/// of its JSON Schema.
+#[derive(Deserialize, JsonSchema)]
+struct SerializedLog {
+    #[serde(
+        rename = "schema_version",
+        default,
+        deserialize_with = "deserialize_version"
+    )]
+    _schema_version: Option<u32>,
+    experiment_ref: ExperimentRef,
+    spec: ExperimentSpec,
+    events: Vec<TraceEvent>,
+    #[serde(default)]
+    indexes: Option<EventIndexes>,
+}
+
+impl TryFrom<SerializedLog> for LayeredEventLog {
+    type Error = IndexConsistencyError;
+
+    fn try_from(mut log: SerializedLog) -> Result<Self, Self::Error> {
+        let mut symbols = SymbolTable::new();
+        symbols.intern_events(&mut log.events);
+        let series = TimeSeries::from_events(&log.events);
+        let indexes = match log.indexes {
+            None => EventIndexes::rebuild(&log.events),
+            Some(indexes) => {
+                let violations = index_violations(&indexes, &log.events);
+                if !violations.is_empty() {
+                    return Err(IndexConsistencyError { violations });
+                }
+                indexes
+            }
+        };
+        Ok(LayeredEventLog {
+            experiment_ref: log.experiment_ref,
+            spec: log.spec,
+            events: log.events,
+            indexes,
+            symbols,
+            series,
+        })
+    }
+}
+
+/// Borrowed wire form written by `Serialize`.
+#[derive(Serialize)]
+struct SerializedLogRef<'a> {
+    schema_version: u32,
+    experiment_ref: &'a ExperimentRef,
+    spec: &'a ExperimentSpec,
+    events: &'a [TraceEvent],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indexes: Option<&'a EventIndexes>,
+}
+
+impl<'a> SerializedLogRef<'a> {
+    fn new(log: &'a LayeredEventLog, indexes: Option<&'a EventIndexes>) -> Self {
+        Self {
+            schema_version: LOG_SCHEMA_VERSION,
+            experiment_ref: &log.experiment_ref,
+            spec: &log.spec,
+            events: &log.events,
+            indexes,
+        }
+    }
+}
+
+impl Serialize for LayeredEventLog {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedLogRef::new(self, Some(&self.indexes)).serialize(serializer)
+    }
+}
+
+/// Serializes a log without its indexes; see `LayeredEventLog::without_indexes`.
+pub struct WithoutIndexes<'a> {
+    log: &'a LayeredEventLog,
+}
+
+impl Serialize for WithoutIndexes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedLogRef::new(self.log, None).serialize(serializer)
+    }
+}
+
+impl LayeredEventLog {
+    /// Index-free serialization view. Indexes are derived data and are
+    /// rebuilt from `events` when the document is loaded.
+    pub fn without_indexes(&self) -> WithoutIndexes<'_> {
+        WithoutIndexes { log: self }
+    }
 }
 
 /// Experiment specification as a first-class entity.
 /// Prevents AP1 (specification-implementation conflation) by structurally
 /// separating "what was intended" from "what happened."
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExperimentSpec {
     pub preconditions: Vec<ContractTerm>,
     pub postconditions: Vec<ContractTerm>,
@@ -48,7 +162,7 @@ pub struct ExperimentSpec {
 /// Each trace event has an SSA-like unique ID, a layer tag,
 /// a typed event kind, temporal coordinates, and optional
 /// causal references to prior events.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TraceEvent {
     /// Unique, immutable identifier. Monotonically increasing.
     pub id: EventId,
@@ -71,7 +185,7 @@ pub struct TraceEvent {
 
     /// Optional reference to a DAG node this event relates to (R9, R11).
     /// Preserved for Hybrid upgrade path.
-    pub dag_node_ref: Option<String>,
+    pub dag_node_ref: Option<Symbol>,
 
     /// Optional reference to the spec element this event realizes.
     /// Preserved for Hybrid upgrade path.
@@ -85,15 +199,17 @@ pub struct TraceEvent {
 }
 
 /// Secondary indexes for R24 queryability.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct EventIndexes {
     pub by_id: HashMap<EventId, usize>,
     pub by_layer: HashMap<Layer, Vec<EventId>>,
     pub by_kind: HashMap<EventKindTag, Vec<EventId>>,
     /// simulation_step -> first event at that step.
     pub by_time_range: BTreeMap<u64, EventId>,
-    pub by_variable: HashMap<String, Vec<EventId>>,
-    pub by_dag_node: HashMap<String, Vec<EventId>>,
+    /// simulation_step -> every event at that step, in log order.
+    pub by_step: BTreeMap<u64, Vec<EventId>>,
+    pub by_variable: HashMap<Symbol, Vec<EventId>>,
+    pub by_dag_node: HashMap<Symbol, Vec<EventId>>,
 }
 
 impl EventIndexes {
@@ -104,11 +220,21 @@ impl EventIndexes {
             by_layer: HashMap::new(),
             by_kind: HashMap::new(),
             by_time_range: BTreeMap::new(),
+            by_step: BTreeMap::new(),
             by_variable: HashMap::new(),
             by_dag_node: HashMap::new(),
         }
     }
 
+    /// Indexes for `events`, with positions taken from their order.
+    pub fn rebuild(events: &[TraceEvent]) -> Self {
+        let mut indexes = Self::new();
+        for (position, event) in events.iter().enumerate() {
+            indexes.index_event(event, position);
+        }
+        indexes
+    }
+
     /// Index a single event. Called during log construction.
     pub fn index_event(&mut self, event: &TraceEvent, position: usize) {
         self.by_id.insert(event.id, position);
@@ -126,6 +252,10 @@ impl EventIndexes {
         self.by_time_range
             .entry(event.temporal.simulation_step)
             .or_insert(event.id);
+        self.by_step
+            .entry(event.temporal.simulation_step)
+            .or_default()
+            .push(event.id);
 
         // By variable (for ParameterRecord and ObservableMeasurement)
         match &event.kind {
@@ -161,14 +291,100 @@ impl Default for EventIndexes {
 // Builder Helpers
 // ============================================================
 
-/// Global atomic counter for auto-assigning EventIds.
-static EVENT_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+/// Per-log allocator for EventIds. IDs start at 1 and increase by one, so the
+/// same sequence of builds always yields the same IDs, independent of
+/// anything else running in the process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventIdAllocator {
+    next: u64,
+}
+
+impl EventIdAllocator {
+    pub fn new() -> Self {
+        Self { next: 1 }
+    }
+
+    /// Allocator continuing after the highest ID in `events`.
+    pub fn following(events: &[TraceEvent]) -> Self {
+        let mut allocator = Self::new();
+        for event in events {
+            allocator.observe(event.id);
+        }
+        allocator
+    }
+
+    /// Hand out the next ID.
+    pub fn next_id(&mut self) -> EventId {
+        let id = EventId(self.next);
+        self.next += 1;
+        id
+    }
+
+    /// The ID the next call to `next_id` will return.
+    pub fn peek(&self) -> EventId {
+        EventId(self.next)
+    }
 
-/// Reset the global event ID counter (for test isolation).
-pub fn reset_event_id_counter() {
-    EVENT_ID_COUNTER.store(1, Ordering::SeqCst);
+    /// Make sure future IDs do not collide with `id`.
+    pub fn observe(&mut self, id: EventId) {
+        self.next = self.next.max(id.0 + 1);
+    }
+}
+
+impl Default for EventIdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
+/// A missing field or violated invariant found by `TraceEventBuilder::try_build`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildIssue {
+    MissingLayer,
+    MissingKind,
+    MissingTemporal,
+    /// `ConfidenceMeta::field_coverage` outside 0..=1.
+    FieldCoverageOutOfRange(f32),
+    /// `Completeness::Derived` with no source elements.
+    EmptyDerivation,
+    /// The event lists its own ID among its causal refs.
+    SelfCausalRef(EventId),
+}
+
+impl fmt::Display for BuildIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildIssue::MissingLayer => write!(f, "layer is required"),
+            BuildIssue::MissingKind => write!(f, "kind is required"),
+            BuildIssue::MissingTemporal => write!(f, "temporal is required"),
+            BuildIssue::FieldCoverageOutOfRange(coverage) => {
+                write!(f, "field_coverage {} is outside 0..=1", coverage)
+            }
+            BuildIssue::EmptyDerivation => {
+                write!(f, "Derived completeness has no from_elements")
+            }
+            BuildIssue::SelfCausalRef(id) => {
+                write!(f, "causal_refs contains the event's own id {}", id.0)
+            }
+        }
+    }
+}
+
+/// Error returned by `TraceEventBuilder::try_build`, listing every issue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEventBuildError {
+    pub issues: Vec<BuildIssue>,
+}
+
+impl fmt::Display for TraceEventBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issues: Vec<String> = self.issues.iter().map(ToString::to_string).collect();
+        write!(f, "{}", issues.join("; "))
+    }
+}
+
+impl std::error::Error for TraceEventBuildError {}
+
 /// Fluent builder for constructing TraceEvent instances.
 pub struct TraceEventBuilder {
     layer: Option<Layer>,
@@ -176,7 +392,7 @@ pub struct TraceEventBuilder {
     kind: Option<EventKind>,
     temporal: Option<TemporalCoord>,
     causal_refs: Vec<EventId>,
-    dag_node_ref: Option<String>,
+    dag_node_ref: Option<Symbol>,
     spec_ref: Option<SpecElementId>,
     provenance: Option<ProvenanceAnchor>,
     confidence: Option<ConfidenceMeta>,
@@ -228,8 +444,8 @@ impl TraceEventBuilder {
     }
 
     /// Optional: set DAG node reference (Hybrid upgrade path).
-    pub fn dag_node_ref(mut self, dag_ref: String) -> Self {
-        self.dag_node_ref = Some(dag_ref);
+    pub fn dag_node_ref(mut self, dag_ref: impl Into<Symbol>) -> Self {
+        self.dag_node_ref = Some(dag_ref.into());
         self
     }
 
@@ -251,23 +467,62 @@ impl TraceEventBuilder {
         self
     }
 
-    /// Build the TraceEvent. Panics if required fields (layer, kind, temporal) are missing.
-    pub fn build(self) -> TraceEvent {
-        let id = EventId(EVENT_ID_COUNTER.fetch_add(1, Ordering::SeqCst));
+    /// Build the TraceEvent, taking its ID from `ids`.
+    /// Panics if required fields are missing or invariants are violated;
+    /// use `try_build` on paths that must not abort.
+    pub fn build(self, ids: &mut EventIdAllocator) -> TraceEvent {
+        self.try_build(ids)
+            .unwrap_or_else(|err| panic!("TraceEventBuilder: {}", err))
+    }
 
-        TraceEvent {
+    /// Build the TraceEvent, reporting every missing field and violated
+    /// invariant at once. An ID is only drawn from `ids` on success.
+    pub fn try_build(self, ids: &mut EventIdAllocator) -> Result<TraceEvent, TraceEventBuildError> {
+        let mut issues = Vec::new();
+        if self.layer.is_none() {
+            issues.push(BuildIssue::MissingLayer);
+        }
+        if self.kind.is_none() {
+            issues.push(BuildIssue::MissingKind);
+        }
+        if self.temporal.is_none() {
+            issues.push(BuildIssue::MissingTemporal);
+        }
+        if let Some(confidence) = &self.confidence {
+            if !(0.0..=1.0).contains(&confidence.field_coverage) {
+                issues.push(BuildIssue::FieldCoverageOutOfRange(
+                    confidence.field_coverage,
+                ));
+            }
+            if let Completeness::Derived { from_elements } = &confidence.completeness {
+                if from_elements.is_empty() {
+                    issues.push(BuildIssue::EmptyDerivation);
+                }
+            }
+        }
+        let id = ids.peek();
+        if self.causal_refs.contains(&id) {
+            issues.push(BuildIssue::SelfCausalRef(id));
+        }
+
+        let (Some(layer), Some(kind), Some(temporal), true) =
+            (self.layer, self.kind, self.temporal, issues.is_empty())
+        else {
+            return Err(TraceEventBuildError { issues });
+        };
+        let id = ids.next_id();
+
+        Ok(TraceEvent {
             id,
-            layer: self.layer.expect("TraceEventBuilder: layer is required"),
+            layer,
             boundary: self.boundary,
-            kind: self.kind.expect("TraceEventBuilder: kind is required"),
-            temporal: self
-                .temporal
-                .expect("TraceEventBuilder: temporal is required"),
+            kind,
+            temporal,
             causal_refs: self.causal_refs,
             dag_node_ref: self.dag_node_ref,
             spec_ref: self.spec_ref,
             provenance: self.provenance.unwrap_or_else(|| ProvenanceAnchor {
-                source_file: "synthetic".to_string(),
+                source_file: "synthetic".into(),
                 source_location: SourceLocation::ExternalInput,
                 raw_hash: 0,
             }),
@@ -276,7 +531,7 @@ impl TraceEventBuilder {
                 field_coverage: 1.0,
                 notes: Vec::new(),
             }),
-        }
+        })
     }
 }
 
@@ -287,30 +542,89 @@ impl Default for TraceEventBuilder {
 }
 
 /// Fluent builder for constructing LayeredEventLog instances.
+/// Owns the log's EventId allocator.
 pub struct LayeredEventLogBuilder {
     experiment_ref: ExperimentRef,
     spec: ExperimentSpec,
     events: Vec<TraceEvent>,
     indexes: EventIndexes,
+    ids: EventIdAllocator,
+    symbols: SymbolTable,
+    series: TimeSeries,
 }
 
 impl LayeredEventLogBuilder {
     pub fn new(experiment_ref: ExperimentRef, spec: ExperimentSpec) -> Self {
+        Self::with_ids(experiment_ref, spec, EventIdAllocator::new())
+    }
+
+    /// Start from an allocator that already handed out IDs, e.g. one an
+    /// adapter used while parsing.
+    pub fn with_ids(
+        experiment_ref: ExperimentRef,
+        spec: ExperimentSpec,
+        ids: EventIdAllocator,
+    ) -> Self {
         Self {
             experiment_ref,
             spec,
             events: Vec::new(),
             indexes: EventIndexes::new(),
+            ids,
+            symbols: SymbolTable::new(),
+            series: TimeSeries::new(),
         }
     }
 
-    /// Add an event and update indexes.
-    pub fn add_event(mut self, event: TraceEvent) -> Self {
+    /// The allocator for events destined for this log.
+    pub fn ids(&mut self) -> &mut EventIdAllocator {
+        &mut self.ids
+    }
+
+    /// The log's symbol table, for interning names and units while
+    /// constructing events.
+    pub fn symbols(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// Events added so far, in order.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Add an event and update indexes. The allocator skips past its ID;
+    /// the event's names and units are interned into the symbol table.
+    pub fn push(&mut self, mut event: TraceEvent) -> &mut Self {
+        self.ids.observe(event.id);
+        self.symbols.intern_event(&mut event);
         self.indexes.index_event(&event, self.events.len());
+        self.series.record(&event);
         self.events.push(event);
         self
     }
 
+    /// Add events in order, reserving space for the iterator's lower size
+    /// bound up front.
+    pub fn extend<I>(&mut self, events: I) -> &mut Self
+    where
+        I: IntoIterator<Item = TraceEvent>,
+    {
+        let events = events.into_iter();
+        let additional = events.size_hint().0;
+        self.events.reserve(additional);
+        self.indexes.by_id.reserve(additional);
+        for event in events {
+            self.push(event);
+        }
+        self
+    }
+
+    /// Consuming form of `push`, for chained construction.
+    pub fn add_event(mut self, event: TraceEvent) -> Self {
+        self.push(event);
+        self
+    }
+
     /// Build the LayeredEventLog.
     pub fn build(self) -> LayeredEventLog {
         LayeredEventLog {
@@ -318,6 +632,24 @@ impl LayeredEventLogBuilder {
             spec: self.spec,
             events: self.events,
             indexes: self.indexes,
+            symbols: self.symbols,
+            series: self.series,
+        }
+    }
+}
+
+impl LayeredEventLog {
+    /// Reopen the log for appending. Existing indexes are kept and updated
+    /// incrementally; the allocator continues after the highest event ID.
+    pub fn into_builder(self) -> LayeredEventLogBuilder {
+        LayeredEventLogBuilder {
+            ids: EventIdAllocator::following(&self.events),
+            experiment_ref: self.experiment_ref,
+            spec: self.spec,
+            events: self.events,
+            indexes: self.indexes,
+            symbols: self.symbols,
+            series: self.series,
         }
     }
 }
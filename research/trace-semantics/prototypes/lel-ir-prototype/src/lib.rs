@@ -1,9 +1,26 @@
 pub mod common;
+pub mod units;
 pub mod lel;
 pub mod event_kinds;
 pub mod adapter;
+pub mod binary;
+pub mod bundle;
+pub mod mapped;
 pub mod convergence;
 pub mod overlay;
+pub mod contracts;
+pub mod diff;
+pub mod integrity;
+pub mod merge;
+pub mod provenance;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod query;
+pub mod registry;
+pub mod schema;
+pub mod series;
+pub mod streaming;
+pub mod symbol;
 pub mod gromacs_adapter;
 pub mod vasp_adapter;
 
@@ -0,0 +1,371 @@
+//! Random access to a binary log without decoding it whole.
+//!
+//! `MappedLog` memory-maps a file written by `binary::BinaryLogWriter`,
+//! reads the string table, header and `INDEX` section, and decodes single
+//! events only when they are asked for, so a query or causal traversal over
+//! a week-long trajectory touches only the events it visits. Uncompressed
+//! blocks are decoded in place from the mapping; a compressed block is
+//! inflated on first use and kept until another compressed block is needed.
+//!
+//! Files without an `INDEX` section (binary schema version 1, or written
+//! with `BinaryOptions::index` off) are scanned once on open to build it.
+//! Scanned events are dropped immediately, so memory stays at the size of
+//! the indexes either way.
+//!
+//! `MappedLog::query` runs an `EventQuery` over the indexes, and
+//! `transitive_ancestors` follows `causal_refs`, both decoding only what
+//! they visit. Other `CausalOverlay` analyses need a decoded log: `load`.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+
+use memmap2::Mmap;
+use serde::Deserialize;
+
+use crate::binary::{
+    decompress, extend_strings, read_preamble, BinaryError, Decoder, COMPRESSION_NONE, SECTION_END,
+    SECTION_EVENTS, SECTION_HEADER, SECTION_INDEX, SECTION_STRINGS,
+};
+use crate::common::{EventId, ExperimentRef};
+use crate::lel::{
+    EventIndexes, ExperimentSpec, LayeredEventLog, LayeredEventLogBuilder, TraceEvent,
+};
+
+type Result<T> = std::result::Result<T, BinaryError>;
+
+/// An `EVENTS` section located in the input.
+struct Block {
+    /// Byte range of the stored, possibly compressed, payload.
+    start: usize,
+    end: usize,
+    compression: u8,
+    /// Log position of the block's first event.
+    first_position: usize,
+    /// Offset of each event within the decompressed payload.
+    offsets: Vec<usize>,
+}
+
+/// A binary log opened for on-demand decoding. `B` holds the encoded bytes:
+/// a memory map for `open`, or any byte buffer for `new`.
+pub struct MappedLog<B = Mmap> {
+    bytes: B,
+    strings: Vec<String>,
+    experiment_ref: ExperimentRef,
+    spec: ExperimentSpec,
+    blocks: Vec<Block>,
+    len: usize,
+    indexes: EventIndexes,
+    /// Most recently inflated compressed block, by block number.
+    inflated: RefCell<Option<(usize, Rc<[u8]>)>>,
+    decoded: Cell<usize>,
+}
+
+impl MappedLog<Mmap> {
+    /// Map the file at `path` and read its index.
+    ///
+    /// The file must not be modified while the log is open: the mapping
+    /// would observe the change and decoding could fail or return garbage.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only, and `open` documents that the file
+        // must not change while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Self::new(map)
+    }
+}
+
+impl<B: AsRef<[u8]>> MappedLog<B> {
+    /// Read the header and index of the encoded log in `bytes`.
+    pub fn new(bytes: B) -> Result<Self> {
+        let data = bytes.as_ref();
+        let mut cursor = data;
+        read_preamble(&mut cursor)?;
+        let mut position = data.len() - cursor.len();
+
+        let mut strings = Vec::new();
+        let mut header = None;
+        let mut sections = Vec::new();
+        let mut index = None;
+        loop {
+            let (tag, compression, start, end) = section_at(data, position)?;
+            position = end;
+            let stored = &data[start..end];
+            match tag {
+                SECTION_END => break,
+                SECTION_STRINGS => extend_strings(&mut strings, &decompress(compression, stored)?)?,
+                SECTION_HEADER => {
+                    let payload = decompress(compression, stored)?;
+                    let mut decoder = Decoder::new(&payload, &strings);
+                    let experiment_ref = ExperimentRef::deserialize(&mut decoder)?;
+                    let spec = ExperimentSpec::deserialize(&mut decoder)?;
+                    decoder.finish()?;
+                    header = Some((experiment_ref, spec));
+                }
+                SECTION_EVENTS => sections.push((start, end, compression)),
+                SECTION_INDEX => index = Some((start, end, compression)),
+                other => {
+                    return Err(BinaryError::Corrupt(format!(
+                        "unexpected section {}",
+                        other
+                    )))
+                }
+            }
+        }
+        let (experiment_ref, spec) =
+            header.ok_or_else(|| BinaryError::Corrupt("missing header section".to_string()))?;
+
+        let (blocks, indexes) = match index {
+            Some((start, end, compression)) => read_index(
+                &decompress(compression, &data[start..end])?,
+                &strings,
+                &sections,
+            )?,
+            None => scan_blocks(data, &strings, &sections)?,
+        };
+        let len = blocks
+            .last()
+            .map_or(0, |block| block.first_position + block.offsets.len());
+        // Every event is listed under exactly one kind. `by_id` is not
+        // counted: logs with repeated IDs decode eagerly, so they open here.
+        let indexed: usize = indexes.by_kind.values().map(Vec::len).sum();
+        if indexed != len {
+            return Err(BinaryError::Corrupt(format!(
+                "index lists {} events, blocks hold {}",
+                indexed, len
+            )));
+        }
+
+        Ok(Self {
+            bytes,
+            strings,
+            experiment_ref,
+            spec,
+            blocks,
+            len,
+            indexes,
+            inflated: RefCell::new(None),
+            decoded: Cell::new(0),
+        })
+    }
+
+    pub fn experiment_ref(&self) -> &ExperimentRef {
+        &self.experiment_ref
+    }
+
+    pub fn spec(&self) -> &ExperimentSpec {
+        &self.spec
+    }
+
+    /// Indexes of the whole log, available without decoding any event.
+    pub fn indexes(&self) -> &EventIndexes {
+        &self.indexes
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of events decoded since the log was opened.
+    pub fn decoded_count(&self) -> usize {
+        self.decoded.get()
+    }
+
+    /// Decode the event at log position `position`.
+    pub fn event_at(&self, position: usize) -> Result<Option<TraceEvent>> {
+        if position >= self.len {
+            return Ok(None);
+        }
+        let block_number = self
+            .blocks
+            .partition_point(|block| block.first_position <= position)
+            - 1;
+        let block = &self.blocks[block_number];
+        let offset = block.offsets[position - block.first_position];
+        let stored = &self.bytes.as_ref()[block.start..block.end];
+        let event = if block.compression == COMPRESSION_NONE {
+            self.decode_at(stored, offset)?
+        } else {
+            let payload = self.inflate(block_number, block.compression, stored)?;
+            self.decode_at(&payload, offset)?
+        };
+        Ok(Some(event))
+    }
+
+    /// Decode the event with `id`. With repeated IDs this is the last
+    /// event carrying it, as `EventIndexes::by_id` records.
+    pub fn event(&self, id: EventId) -> Result<Option<TraceEvent>> {
+        match self.indexes.by_id.get(&id) {
+            Some(&position) => self.event_at(position),
+            None => Ok(None),
+        }
+    }
+
+    /// Decode the events with `ids`, in the given order. Unknown IDs are
+    /// skipped.
+    pub fn events(&self, ids: &[EventId]) -> Result<Vec<TraceEvent>> {
+        ids.iter()
+            .filter_map(|id| self.event(*id).transpose())
+            .collect()
+    }
+
+    /// Decode every event in log order, one at a time.
+    pub fn iter(&self) -> impl Iterator<Item = Result<TraceEvent>> + '_ {
+        (0..self.len).filter_map(|position| self.event_at(position).transpose())
+    }
+
+    /// Events reachable from `id` through `causal_refs`, breadth first, as
+    /// `CausalOverlay::transitive_ancestors` orders them. The start event
+    /// is not included; only visited events are decoded.
+    pub fn transitive_ancestors(&self, id: EventId) -> Result<Vec<TraceEvent>> {
+        let Some(start) = self.event(id)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut visited = HashSet::new();
+        let mut queue: VecDeque<EventId> = start.causal_refs.into_iter().collect();
+        let mut ancestors = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            if !visited.insert(current) {
+                continue;
+            }
+            let Some(event) = self.event(current)? else {
+                continue;
+            };
+            queue.extend(
+                event
+                    .causal_refs
+                    .iter()
+                    .filter(|parent| !visited.contains(*parent)),
+            );
+            ancestors.push(event);
+        }
+        Ok(ancestors)
+    }
+
+    /// Decode the whole log.
+    pub fn load(&self) -> Result<LayeredEventLog> {
+        let mut builder =
+            LayeredEventLogBuilder::new(self.experiment_ref.clone(), self.spec.clone());
+        for event in self.iter() {
+            builder.push(event?);
+        }
+        Ok(builder.build())
+    }
+
+    fn decode_at(&self, payload: &[u8], offset: usize) -> Result<TraceEvent> {
+        let input = payload
+            .get(offset..)
+            .ok_or_else(|| BinaryError::Corrupt(format!("event offset {} out of range", offset)))?;
+        let event = TraceEvent::deserialize(&mut Decoder::new(input, &self.strings))?;
+        self.decoded.set(self.decoded.get() + 1);
+        Ok(event)
+    }
+
+    fn inflate(&self, block_number: usize, compression: u8, stored: &[u8]) -> Result<Rc<[u8]>> {
+        let mut inflated = self.inflated.borrow_mut();
+        if let Some((cached, payload)) = &*inflated {
+            if *cached == block_number {
+                return Ok(Rc::clone(payload));
+            }
+        }
+        let payload: Rc<[u8]> = Rc::from(decompress(compression, stored)?.into_owned());
+        *inflated = Some((block_number, Rc::clone(&payload)));
+        Ok(payload)
+    }
+}
+
+/// Tag, compression and payload range of the section at `position`.
+fn section_at(data: &[u8], position: usize) -> Result<(u8, u8, usize, usize)> {
+    let prefix = data
+        .get(position..position + 10)
+        .ok_or_else(|| BinaryError::Corrupt("missing end marker".to_string()))?;
+    let len = u64::from_le_bytes(prefix[2..10].try_into().expect("8-byte slice"));
+    let start = position + 10;
+    let end = usize::try_from(len)
+        .ok()
+        .and_then(|len| start.checked_add(len))
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| {
+            BinaryError::Corrupt(format!(
+                "section {} truncated: needs {} bytes",
+                prefix[0], len
+            ))
+        })?;
+    Ok((prefix[0], prefix[1], start, end))
+}
+
+/// Blocks and indexes from an `INDEX` payload.
+fn read_index(
+    payload: &[u8],
+    strings: &[String],
+    sections: &[(usize, usize, u8)],
+) -> Result<(Vec<Block>, EventIndexes)> {
+    let mut decoder = Decoder::new(payload, strings);
+    let block_count = decoder.read_varint()?;
+    if block_count != sections.len() as u64 {
+        return Err(BinaryError::Corrupt(format!(
+            "index lists {} blocks, file has {}",
+            block_count,
+            sections.len()
+        )));
+    }
+    let mut blocks = Vec::with_capacity(sections.len());
+    let mut first_position = 0;
+    for &(start, end, compression) in sections {
+        let count = decoder.read_varint()?;
+        let offsets = (0..count)
+            .map(|_| Ok(decoder.read_varint()? as usize))
+            .collect::<Result<Vec<_>>>()?;
+        blocks.push(Block {
+            start,
+            end,
+            compression,
+            first_position,
+            offsets,
+        });
+        first_position += count as usize;
+    }
+    let indexes = EventIndexes::deserialize(&mut decoder)?;
+    decoder.finish()?;
+    Ok((blocks, indexes))
+}
+
+/// Blocks and indexes built by decoding every event once.
+fn scan_blocks(
+    data: &[u8],
+    strings: &[String],
+    sections: &[(usize, usize, u8)],
+) -> Result<(Vec<Block>, EventIndexes)> {
+    let mut blocks = Vec::with_capacity(sections.len());
+    let mut indexes = EventIndexes::new();
+    let mut position = 0;
+    for &(start, end, compression) in sections {
+        let payload = decompress(compression, &data[start..end])?;
+        let mut decoder = Decoder::new(&payload, strings);
+        let count = decoder.read_varint()?;
+        let first_position = position;
+        let mut offsets = Vec::new();
+        for _ in 0..count {
+            offsets.push(payload.len() - decoder.remaining());
+            let event = TraceEvent::deserialize(&mut decoder)?;
+            indexes.index_event(&event, position);
+            position += 1;
+        }
+        decoder.finish()?;
+        blocks.push(Block {
+            start,
+            end,
+            compression,
+            first_position,
+            offsets,
+        });
+    }
+    Ok((blocks, indexes))
+}
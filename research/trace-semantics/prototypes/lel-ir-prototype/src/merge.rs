@@ -0,0 +1,222 @@
+//! Merging several logs of one experiment into a single timeline.
+//!
+//! Equilibration and production runs, checkpoint restarts and per-rank
+//! outputs all arrive as separate `LayeredEventLog`s. `merge_logs`
+//! concatenates them in the order given, so callers decide the timeline:
+//! events are not reordered by step or wall clock, since restarts reuse
+//! steps and per-rank clocks need not agree.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::common::{Completeness, ElementId, EventId, ExperimentRef};
+use crate::diff::{pair_spec_sections, ChangeKind, SectionPairs};
+use crate::lel::{EventIdAllocator, EventIndexes, ExperimentSpec, LayeredEventLog};
+use crate::series::TimeSeries;
+use crate::symbol::SymbolTable;
+
+/// Why logs could not be merged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    /// No logs were supplied.
+    NoLogs,
+    /// A log belongs to a different experiment than the first one.
+    ExperimentMismatch {
+        log_index: usize,
+        expected: ExperimentRef,
+        found: ExperimentRef,
+    },
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::NoLogs => write!(f, "no logs to merge"),
+            MergeError::ExperimentMismatch {
+                log_index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "log {} belongs to experiment {} (cycle {}), expected {} (cycle {})",
+                log_index,
+                found.experiment_id,
+                found.cycle_id,
+                expected.experiment_id,
+                expected.cycle_id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Two logs define the same spec element differently. The first definition
+/// is kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpecConflict {
+    /// `ExperimentSpec` field, e.g. "controlled_variables".
+    pub section: &'static str,
+    /// Element ID, or DAG node ID for `dag_refs`.
+    pub key: String,
+    /// Index of the log whose definition was dropped.
+    pub log_index: usize,
+}
+
+impl fmt::Display for SpecConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} element {} in log {} conflicts with an earlier definition",
+            self.section, self.key, self.log_index
+        )
+    }
+}
+
+/// Result of `merge_logs`.
+#[derive(Debug, Clone)]
+pub struct MergedLog {
+    pub log: LayeredEventLog,
+    /// Per input log, the new ID of each event whose ID was changed to
+    /// avoid a collision, keyed by the event's position in that log.
+    pub id_remaps: Vec<HashMap<usize, EventId>>,
+    pub conflicts: Vec<SpecConflict>,
+}
+
+/// Merge logs that share an `ExperimentRef` into one timeline: the events of
+/// each log, in the order the logs are given.
+///
+/// Events keep their IDs unless an earlier event already used them;
+/// colliding IDs are replaced with fresh ones above every input ID. This is
+/// done per occurrence, so an ID repeated within one log also gets a fresh
+/// ID for each repeat. `causal_refs` and `Completeness::Derived` lists are
+/// rewritten within each log, a reference resolving to the latest earlier
+/// event with that ID. `logical_sequence` is renumbered from 1 across the
+/// merged timeline.
+pub fn merge_logs(logs: Vec<LayeredEventLog>) -> Result<MergedLog, MergeError> {
+    let Some(first) = logs.first() else {
+        return Err(MergeError::NoLogs);
+    };
+    let experiment_ref = first.experiment_ref.clone();
+    for (log_index, log) in logs.iter().enumerate().skip(1) {
+        if log.experiment_ref != experiment_ref {
+            return Err(MergeError::ExperimentMismatch {
+                log_index,
+                expected: experiment_ref,
+                found: log.experiment_ref.clone(),
+            });
+        }
+    }
+
+    let mut fresh = EventIdAllocator::new();
+    for log in &logs {
+        for event in &log.events {
+            fresh.observe(event.id);
+        }
+    }
+
+    let mut spec: Option<ExperimentSpec> = None;
+    let mut conflicts = Vec::new();
+    let mut events = Vec::with_capacity(logs.iter().map(|log| log.events.len()).sum());
+    let mut used: HashSet<EventId> = HashSet::new();
+    let mut id_remaps = Vec::with_capacity(logs.len());
+
+    for (log_index, log) in logs.into_iter().enumerate() {
+        match spec.as_mut() {
+            None => spec = Some(log.spec),
+            Some(merged) => reconcile_spec(merged, log.spec, log_index, &mut conflicts),
+        }
+
+        let mut remap = HashMap::new();
+        // Old ID to the ID its latest occurrence so far was given.
+        let mut current: HashMap<EventId, EventId> = HashMap::new();
+        for (position, mut event) in log.events.into_iter().enumerate() {
+            let remapped = |id: EventId| current.get(&id).copied().unwrap_or(id);
+            for target in &mut event.causal_refs {
+                *target = remapped(*target);
+            }
+            if let Completeness::Derived { from_elements } = &mut event.confidence.completeness {
+                for element in from_elements {
+                    *element = ElementId(remapped(EventId(element.0)).0);
+                }
+            }
+
+            let old = event.id;
+            if !used.insert(old) {
+                let replacement = fresh.next_id();
+                used.insert(replacement);
+                remap.insert(position, replacement);
+                event.id = replacement;
+            }
+            current.insert(old, event.id);
+
+            event.temporal.logical_sequence = events.len() as u64 + 1;
+            events.push(event);
+        }
+        id_remaps.push(remap);
+    }
+
+    let mut symbols = SymbolTable::new();
+    symbols.intern_events(&mut events);
+    let indexes = EventIndexes::rebuild(&events);
+    let series = TimeSeries::from_events(&events);
+    Ok(MergedLog {
+        log: LayeredEventLog {
+            experiment_ref,
+            spec: spec.expect("at least one log"),
+            events,
+            indexes,
+            symbols,
+            series,
+        },
+        id_remaps,
+        conflicts,
+    })
+}
+
+/// Union `incoming` into `merged`, keyed by element ID: elements with a new
+/// key are appended, keys defined differently are recorded as conflicts.
+/// Floats compare by bit pattern, so a NaN in both definitions agrees.
+/// Provenance stays that of the first log.
+fn reconcile_spec(
+    merged: &mut ExperimentSpec,
+    incoming: ExperimentSpec,
+    log_index: usize,
+    conflicts: &mut Vec<SpecConflict>,
+) {
+    let sections = pair_spec_sections(merged, &incoming);
+    let mut union = SpecUnion {
+        log_index,
+        conflicts,
+    };
+    union.section(&mut merged.preconditions, sections.preconditions);
+    union.section(&mut merged.postconditions, sections.postconditions);
+    union.section(&mut merged.predictions, sections.predictions);
+    union.section(&mut merged.interventions, sections.interventions);
+    union.section(
+        &mut merged.controlled_variables,
+        sections.controlled_variables,
+    );
+    union.section(&mut merged.dag_refs, sections.dag_refs);
+}
+
+struct SpecUnion<'a> {
+    log_index: usize,
+    conflicts: &'a mut Vec<SpecConflict>,
+}
+
+impl SpecUnion<'_> {
+    fn section<T: Clone>(&mut self, ours: &mut Vec<T>, section: SectionPairs<'_, T>) {
+        for pair in section.pairs {
+            match (pair.change, pair.theirs) {
+                (Some(ChangeKind::Added), Some(element)) => ours.push(element.clone()),
+                (Some(ChangeKind::Modified), _) => self.conflicts.push(SpecConflict {
+                    section: section.name,
+                    key: pair.key,
+                    log_index: self.log_index,
+                }),
+                _ => {}
+            }
+        }
+    }
+}
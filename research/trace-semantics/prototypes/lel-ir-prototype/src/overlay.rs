@@ -2,7 +2,9 @@ use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use crate::common::{ComparisonOutcome, Layer, SpecElementId};
+use crate::common::{
+    ComparisonOutcome, DivergenceMeasure, Layer, SpecElementId, UncertaintySummary, Value,
+};
 use crate::event_kinds::EventKind;
 use crate::lel::LayeredEventLog;
 
@@ -38,6 +40,9 @@ pub struct PredictionComparison {
     pub outcome: ComparisonOutcome,
     pub is_falsified: bool,
     pub dag_node: Option<String>,
+    /// Uncertainty-normalized divergence, when prediction or observation
+    /// (or the recorded divergence) carries an `UncertaintySummary`.
+    pub z_score: Option<f64>,
 }
 
 /// Causal DAG node implicated by a falsified prediction.
@@ -51,6 +56,7 @@ pub struct ImplicatedNode {
 
 impl CausalOverlay {
     /// Construct the overlay in a single O(n) pass over the log events.
+    /// Dangling causal refs are skipped; `LayeredEventLog::validate` reports them.
     pub fn from_log(log: &LayeredEventLog) -> Self {
         let n = log.events.len();
         let mut entities = Vec::with_capacity(n);
@@ -65,14 +71,14 @@ impl CausalOverlay {
 
             if let Some(dag_node) = &event.dag_node_ref {
                 entity_by_dag_node
-                    .entry(dag_node.clone())
+                    .entry(dag_node.to_string())
                     .or_default()
                     .push(event_idx);
             }
 
             entities.push(OverlayEntity {
                 event_idx,
-                dag_node: event.dag_node_ref.clone(),
+                dag_node: event.dag_node_ref.as_deref().map(String::from),
                 causal_parents,
             });
         }
@@ -251,7 +257,7 @@ impl CausalOverlay {
 
             let EventKind::ComparisonResult {
                 prediction_id,
-                observation_id: _,
+                observation_id,
                 result,
             } = &event.kind
             else {
@@ -259,17 +265,65 @@ impl CausalOverlay {
             };
 
             let parsed_prediction_id = prediction_id.parse::<u64>().ok().map(SpecElementId);
-            let variable = parsed_prediction_id
-                .and_then(|id| predictions_by_id.get(&id).map(|prediction| prediction.variable.clone()))
+            let prediction =
+                parsed_prediction_id.and_then(|id| predictions_by_id.get(&id).copied());
+            let variable = prediction
+                .map(|prediction| prediction.variable.clone())
                 .unwrap_or_else(|| "unknown".to_string());
 
+            let observation = log
+                .indexes
+                .by_id
+                .get(observation_id)
+                .map(|&position| &log.events[position].kind);
+            let measured_z_score = match (prediction, observation) {
+                (
+                    Some(prediction),
+                    Some(EventKind::ObservableMeasurement {
+                        value: Value::Known(observed, observed_unit),
+                        uncertainty,
+                        ..
+                    }),
+                ) => match &prediction.predicted_value {
+                    // Compare in the prediction's unit; incompatible units give no z-score.
+                    Value::Known(predicted, predicted_unit) => observed_unit
+                        .conversion_factor(predicted_unit)
+                        .ok()
+                        .and_then(|factor| {
+                            let uncertainty =
+                                uncertainty.as_ref().map(|summary| summary.scaled(factor));
+                            UncertaintySummary::z_score(
+                                observed * factor,
+                                *predicted,
+                                uncertainty.as_ref(),
+                                prediction.uncertainty.as_ref(),
+                            )
+                        }),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let z_score =
+                measured_z_score.or_else(|| match (&result.divergence, &result.uncertainty) {
+                    (Some(DivergenceMeasure::ZScore(z)), _) => Some(*z),
+                    (
+                        Some(DivergenceMeasure::AbsoluteDifference(difference)),
+                        Some(uncertainty),
+                    ) => uncertainty
+                        .standard_error()
+                        .filter(|standard_error| *standard_error > 0.0)
+                        .map(|standard_error| difference / standard_error),
+                    _ => None,
+                });
+
             comparisons.push(PredictionComparison {
                 comparison_event_idx: event_idx,
                 prediction_id: parsed_prediction_id,
                 variable,
                 outcome: result.clone(),
                 is_falsified: !result.agreement,
-                dag_node: event.dag_node_ref.clone(),
+                dag_node: event.dag_node_ref.as_deref().map(String::from),
+                z_score,
             });
         }
 
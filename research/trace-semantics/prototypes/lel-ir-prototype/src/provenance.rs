@@ -0,0 +1,245 @@
+//! R20 source traceability: hashing anchored spans and re-verifying them.
+//!
+//! `raw_hash` is the 64-bit FNV-1a hash of the exact bytes of lines
+//! `start..=end` (1-based) of the source file, with lines joined by `\n`.
+//! Line terminators are excluded so a missing final newline does not change
+//! the hash; a `\r` before `\n` is part of the line and is hashed.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::common::{EventId, ProvenanceAnchor, SourceLocation};
+use crate::lel::LayeredEventLog;
+use crate::symbol::Symbol;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hash.
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// A source file split into lines once, for hashing many anchored spans.
+pub struct SourceText<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> SourceText<'a> {
+    pub fn new(content: &'a str) -> Self {
+        let mut lines: Vec<&str> = content.split('\n').collect();
+        if content.ends_with('\n') {
+            lines.pop();
+        }
+        Self { lines }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Hash of lines `start..=end` (1-based), or `None` if out of range.
+    pub fn hash_lines(&self, start: u32, end: u32) -> Option<u64> {
+        let (start, end) = (start as usize, end as usize);
+        if start == 0 || start > end || end > self.lines.len() {
+            return None;
+        }
+        let mut hasher = LineHasher::new();
+        for line in &self.lines[start - 1..end] {
+            hasher.push(line);
+        }
+        Some(hasher.finish())
+    }
+
+    /// Line-range anchor into `source_file` carrying the span's hash.
+    /// Out-of-range spans get `raw_hash` 0.
+    pub fn anchor(&self, source_file: &Symbol, start: u32, end: u32) -> ProvenanceAnchor {
+        ProvenanceAnchor {
+            source_file: source_file.clone(),
+            source_location: SourceLocation::LineRange { start, end },
+            raw_hash: self.hash_lines(start, end).unwrap_or(0),
+        }
+    }
+}
+
+/// `SourceText::hash_lines` computed one line at a time, for sources read
+/// as a stream. Lines are passed without their `\n`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LineHasher {
+    hash: u64,
+    empty: bool,
+}
+
+impl LineHasher {
+    pub(crate) fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+            empty: true,
+        }
+    }
+
+    /// Hasher holding `line` alone.
+    pub(crate) fn of(line: &str) -> Self {
+        let mut hasher = Self::new();
+        hasher.push(line);
+        hasher
+    }
+
+    pub(crate) fn push(&mut self, line: &str) {
+        if !self.empty {
+            self.hash = (self.hash ^ u64::from(b'\n')).wrapping_mul(FNV_PRIME);
+        }
+        self.empty = false;
+        for byte in line.as_bytes() {
+            self.hash = (self.hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Line-range anchor with a hash computed by a `LineHasher`.
+pub(crate) fn line_anchor(
+    source_file: &Symbol,
+    start: u32,
+    end: u32,
+    raw_hash: u64,
+) -> ProvenanceAnchor {
+    ProvenanceAnchor {
+        source_file: source_file.clone(),
+        source_location: SourceLocation::LineRange { start, end },
+        raw_hash,
+    }
+}
+
+/// Strip the line break that ends a section marker line, keeping the
+/// section's own bytes (including leading whitespace) intact.
+pub(crate) fn section_body(section: &str) -> &str {
+    section
+        .strip_prefix("\r\n")
+        .or_else(|| section.strip_prefix('\n'))
+        .unwrap_or(section)
+}
+
+/// Why an anchor failed verification.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProvenanceIssue {
+    /// No content was supplied for the anchor's source file.
+    MissingSource,
+    /// The line range lies outside the supplied file.
+    OutOfRange { line_count: usize },
+    /// The anchor was never hashed (`raw_hash` 0).
+    NotHashed,
+    /// The span's content changed since the log was produced.
+    HashMismatch { recorded: u64, actual: u64 },
+}
+
+/// An event whose provenance anchor no longer matches its source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvenanceMismatch {
+    pub event: EventId,
+    pub anchor: ProvenanceAnchor,
+    pub issue: ProvenanceIssue,
+}
+
+impl fmt::Display for ProvenanceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "event {} ({} {:?}): ",
+            self.event.0, self.anchor.source_file, self.anchor.source_location
+        )?;
+        match &self.issue {
+            ProvenanceIssue::MissingSource => write!(f, "source file not supplied"),
+            ProvenanceIssue::OutOfRange { line_count } => {
+                write!(f, "line range outside file of {} lines", line_count)
+            }
+            ProvenanceIssue::NotHashed => write!(f, "anchor has no hash"),
+            ProvenanceIssue::HashMismatch { recorded, actual } => write!(
+                f,
+                "content hash {:#018x} does not match recorded {:#018x}",
+                actual, recorded
+            ),
+        }
+    }
+}
+
+/// Re-hash every `LineRange` anchor in `log` against `sources`, keyed by
+/// `ProvenanceAnchor::source_file`. Other location kinds are not checked.
+pub fn verify_provenance(
+    log: &LayeredEventLog,
+    sources: &HashMap<String, String>,
+) -> Vec<ProvenanceMismatch> {
+    let texts: HashMap<&str, SourceText> = sources
+        .iter()
+        .map(|(name, content)| (name.as_str(), SourceText::new(content)))
+        .collect();
+
+    let mut mismatches = Vec::new();
+    for event in &log.events {
+        let anchor = &event.provenance;
+        let SourceLocation::LineRange { start, end } = anchor.source_location else {
+            continue;
+        };
+        let issue = match texts.get(anchor.source_file.as_str()) {
+            None => Some(ProvenanceIssue::MissingSource),
+            Some(text) => match text.hash_lines(start, end) {
+                None => Some(ProvenanceIssue::OutOfRange {
+                    line_count: text.line_count(),
+                }),
+                Some(_) if anchor.raw_hash == 0 => Some(ProvenanceIssue::NotHashed),
+                Some(actual) if actual != anchor.raw_hash => Some(ProvenanceIssue::HashMismatch {
+                    recorded: anchor.raw_hash,
+                    actual,
+                }),
+                Some(_) => None,
+            },
+        };
+        if let Some(issue) = issue {
+            mismatches.push(ProvenanceMismatch {
+                event: event.id,
+                anchor: anchor.clone(),
+                issue,
+            });
+        }
+    }
+    mismatches
+}
+
+/// `verify_provenance` against files read from `dir`, resolving each
+/// anchor's `source_file` relative to it. Unreadable files are reported as
+/// `MissingSource`; other I/O errors are returned.
+pub fn verify_provenance_in_dir(
+    log: &LayeredEventLog,
+    dir: &Path,
+) -> io::Result<Vec<ProvenanceMismatch>> {
+    let names: BTreeSet<&str> = log
+        .events
+        .iter()
+        .filter(|event| {
+            matches!(
+                event.provenance.source_location,
+                SourceLocation::LineRange { .. }
+            )
+        })
+        .map(|event| event.provenance.source_file.as_str())
+        .collect();
+
+    let mut sources = HashMap::new();
+    for name in names {
+        match std::fs::read_to_string(dir.join(name)) {
+            Ok(content) => {
+                sources.insert(name.to_string(), content);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(verify_provenance(log, &sources))
+}
@@ -0,0 +1,586 @@
+//! PyO3 bindings (ADR 001), compiled with the `python` feature.
+//!
+//! Events, overlay results and other IR records cross the boundary as
+//! plain dicts and lists in the JSON layout described by `json_schema()`;
+//! numeric series come back as NumPy arrays.
+
+use std::ops::RangeInclusive;
+
+use numpy::PyArray1;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::adapter::{DslAdapter, MockOpenMmAdapter};
+use crate::bundle::TraceBundle;
+use crate::common::{EventId, EventKindTag, Layer, Severity};
+use crate::convergence::{self, CanonicalConvergence};
+use crate::gromacs_adapter::GromacsAdapter;
+use crate::lel::LayeredEventLog;
+use crate::overlay::{self, PredictionComparison};
+use crate::query::QueryOrder;
+use crate::schema;
+use crate::series::Series;
+use crate::vasp_adapter::VaspAdapter;
+
+fn value_error(err: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+pub(crate) fn json_to_py<'py>(py: Python<'py>, value: &JsonValue) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        JsonValue::Null => py.None().into_bound(py),
+        JsonValue::Bool(flag) => PyBool::new(py, *flag).to_owned().into_any(),
+        JsonValue::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(unsigned), _) => unsigned.into_pyobject(py)?.into_any(),
+            (None, Some(signed)) => signed.into_pyobject(py)?.into_any(),
+            _ => PyFloat::new(py, number.as_f64().unwrap_or(f64::NAN)).into_any(),
+        },
+        JsonValue::String(text) => PyString::new(py, text).into_any(),
+        JsonValue::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| json_to_py(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+        JsonValue::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, field) in fields {
+                dict.set_item(key, json_to_py(py, field)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+pub(crate) fn py_to_json(object: &Bound<'_, PyAny>) -> PyResult<JsonValue> {
+    if object.is_none() {
+        return Ok(JsonValue::Null);
+    }
+    if let Ok(flag) = object.cast::<PyBool>() {
+        return Ok(JsonValue::Bool(flag.is_true()));
+    }
+    if object.is_instance_of::<PyInt>() {
+        if let Ok(unsigned) = object.extract::<u64>() {
+            return Ok(unsigned.into());
+        }
+        return Ok(object.extract::<i64>()?.into());
+    }
+    if let Ok(float) = object.cast::<PyFloat>() {
+        // Non-finite floats take their string form in the JSON layout;
+        // `From<f64>` would turn them into `null`.
+        return Ok(match float.value() {
+            value if value.is_nan() => "NaN".into(),
+            f64::INFINITY => "Infinity".into(),
+            f64::NEG_INFINITY => "-Infinity".into(),
+            value => value.into(),
+        });
+    }
+    if let Ok(text) = object.cast::<PyString>() {
+        return Ok(JsonValue::String(text.to_str()?.to_string()));
+    }
+    if let Ok(dict) = object.cast::<PyDict>() {
+        let mut fields = serde_json::Map::new();
+        for (key, field) in dict.iter() {
+            fields.insert(key.extract::<String>()?, py_to_json(&field)?);
+        }
+        return Ok(JsonValue::Object(fields));
+    }
+    if object.is_instance_of::<PyList>() || object.is_instance_of::<PyTuple>() {
+        return object
+            .try_iter()?
+            .map(|item| py_to_json(&item?))
+            .collect::<PyResult<Vec<_>>>()
+            .map(JsonValue::Array);
+    }
+    Err(value_error(format!(
+        "cannot convert {} to an IR value",
+        object.get_type().name()?
+    )))
+}
+
+pub(crate) fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    json_to_py(py, &serde_json::to_value(value).map_err(value_error)?)
+}
+
+fn to_py_list<'py, T: Serialize>(
+    py: Python<'py>,
+    values: impl IntoIterator<Item = T>,
+) -> PyResult<Bound<'py, PyList>> {
+    let items = values
+        .into_iter()
+        .map(|value| to_py(py, &value))
+        .collect::<PyResult<Vec<_>>>()?;
+    PyList::new(py, items)
+}
+
+pub(crate) fn from_py<T: DeserializeOwned>(object: &Bound<'_, PyAny>) -> PyResult<T> {
+    serde_json::from_value(py_to_json(object)?).map_err(value_error)
+}
+
+/// Parse a unit-variant enum from its Rust name, e.g. `"Theory"`.
+fn parse_name<T: DeserializeOwned>(name: &str) -> PyResult<T> {
+    serde_json::from_value(JsonValue::String(name.to_string())).map_err(value_error)
+}
+
+/// `start..=end` from a Python `(start, end)` pair named `name`.
+fn inclusive_range(name: &str, start: u64, end: u64) -> PyResult<RangeInclusive<u64>> {
+    if start > end {
+        return Err(value_error(format!(
+            "{} start {} is after end {}",
+            name, start, end
+        )));
+    }
+    Ok(start..=end)
+}
+
+/// A parsed trace: `LayeredEventLog` with its index queries.
+#[pyclass(name = "LayeredEventLog", module = "lel_ir_prototype", frozen)]
+pub struct PyLayeredEventLog {
+    log: LayeredEventLog,
+}
+
+impl From<LayeredEventLog> for PyLayeredEventLog {
+    fn from(log: LayeredEventLog) -> Self {
+        Self { log }
+    }
+}
+
+#[pymethods]
+impl PyLayeredEventLog {
+    /// Load a serialized log of any supported schema version.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        schema::log_from_json(json)
+            .map(Self::from)
+            .map_err(value_error)
+    }
+
+    #[pyo3(signature = (include_indexes = true))]
+    fn to_json(&self, include_indexes: bool) -> PyResult<String> {
+        if include_indexes {
+            serde_json::to_string(&self.log)
+        } else {
+            serde_json::to_string(&self.log.without_indexes())
+        }
+        .map_err(value_error)
+    }
+
+    fn __len__(&self) -> usize {
+        self.log.events.len()
+    }
+
+    #[getter]
+    fn experiment_ref<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.log.experiment_ref)
+    }
+
+    #[getter]
+    fn spec<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.log.spec)
+    }
+
+    /// Every event, in log order.
+    fn events<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        to_py_list(py, &self.log.events)
+    }
+
+    /// The event with `id`, or `None`.
+    fn event<'py>(&self, py: Python<'py>, id: u64) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.log
+            .indexes
+            .by_id
+            .get(&EventId(id))
+            .map(|&position| to_py(py, &self.log.events[position]))
+            .transpose()
+    }
+
+    /// Integrity violations, as messages; empty for a well-formed log.
+    fn validate(&self) -> Vec<String> {
+        self.log
+            .validate()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// Events matching every given filter; see `EventQuery`. `steps` and
+    /// `wall_clock` are inclusive `(start, end)` pairs, `order` one of
+    /// `"log"`, `"logical_sequence"`, `"simulation_step"`, `"wall_clock"`.
+    #[pyo3(signature = (
+        *,
+        layer = None,
+        kind = None,
+        variable = None,
+        dag_node = None,
+        steps = None,
+        wall_clock = None,
+        min_severity = None,
+        converged = None,
+        order = "log",
+        descending = false,
+        limit = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn query<'py>(
+        &self,
+        py: Python<'py>,
+        layer: Option<&str>,
+        kind: Option<&str>,
+        variable: Option<&str>,
+        dag_node: Option<&str>,
+        steps: Option<(u64, u64)>,
+        wall_clock: Option<(u64, u64)>,
+        min_severity: Option<&str>,
+        converged: Option<bool>,
+        order: &str,
+        descending: bool,
+        limit: Option<usize>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let mut query = self.log.query();
+        if let Some(layer) = layer {
+            query = query.layer(parse_name::<Layer>(layer)?);
+        }
+        if let Some(kind) = kind {
+            query = query.kind(parse_name::<EventKindTag>(kind)?);
+        }
+        if let Some(variable) = variable {
+            query = query.variable(variable);
+        }
+        if let Some(node) = dag_node {
+            query = query.dag_node(node);
+        }
+        if let Some((start, end)) = steps {
+            query = query.steps(inclusive_range("steps", start, end)?);
+        }
+        if let Some((start, end)) = wall_clock {
+            query = query.wall_clock(inclusive_range("wall_clock", start, end)?);
+        }
+        if let Some(severity) = min_severity {
+            query = query.min_severity(parse_name::<Severity>(severity)?);
+        }
+        if let Some(converged) = converged {
+            query = query.converged(converged);
+        }
+        query = query.order_by(match order {
+            "log" => QueryOrder::Log,
+            "logical_sequence" => QueryOrder::LogicalSequence,
+            "simulation_step" => QueryOrder::SimulationStep,
+            "wall_clock" => QueryOrder::WallClock,
+            other => return Err(value_error(format!("unknown order {:?}", other))),
+        });
+        if descending {
+            query = query.descending();
+        }
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        to_py_list(py, query.events())
+    }
+
+    /// `(steps, values, unit)` for the `EnergyRecord` totals, or for the
+    /// named `component`, converted to the unit of the first record. Empty
+    /// arrays and `None` when there are none.
+    #[pyo3(signature = (component = None))]
+    #[allow(clippy::type_complexity)]
+    fn energy_series<'py>(
+        &self,
+        py: Python<'py>,
+        component: Option<&str>,
+    ) -> PyResult<(
+        Bound<'py, PyArray1<u64>>,
+        Bound<'py, PyArray1<f64>>,
+        Option<String>,
+    )> {
+        let columns = match component {
+            None => Some(self.log.series.energy_total()),
+            Some(name) => self.log.series.energy_component(name),
+        };
+        Ok(match columns {
+            Some(columns) => (
+                PyArray1::from_slice(py, columns.steps()),
+                PyArray1::from_slice(py, columns.values()),
+                columns.unit().map(|unit| unit.to_string()),
+            ),
+            None => (
+                PyArray1::from_slice(py, &[]),
+                PyArray1::from_slice(py, &[]),
+                None,
+            ),
+        })
+    }
+
+    /// `(iterations, values, unit)` for the `ConvergencePoint`s named
+    /// `metric`, converted as in `energy_series`. `metric` may be left out
+    /// when the log has a single metric; with several it is required.
+    #[pyo3(signature = (metric = None))]
+    #[allow(clippy::type_complexity)]
+    fn convergence_series<'py>(
+        &self,
+        py: Python<'py>,
+        metric: Option<&str>,
+    ) -> PyResult<(
+        Bound<'py, PyArray1<u64>>,
+        Bound<'py, PyArray1<f64>>,
+        Option<String>,
+    )> {
+        let columns = match metric {
+            Some(metric) => self.log.series.convergence(metric),
+            None => {
+                let mut metrics = self.log.series.convergence_metrics();
+                match (metrics.next(), metrics.next()) {
+                    (only, None) => only,
+                    _ => {
+                        let names: Vec<&str> = self
+                            .log
+                            .series
+                            .convergence_metrics()
+                            .map(Series::name)
+                            .collect();
+                        return Err(value_error(format!(
+                            "log has several convergence metrics, pass one of {:?}",
+                            names
+                        )));
+                    }
+                }
+            }
+        };
+        Ok(match columns {
+            Some(columns) => (
+                PyArray1::from_slice(py, columns.iterations()),
+                PyArray1::from_slice(py, columns.values()),
+                columns.unit().map(|unit| unit.to_string()),
+            ),
+            None => (
+                PyArray1::from_slice(py, &[]),
+                PyArray1::from_slice(py, &[]),
+                None,
+            ),
+        })
+    }
+}
+
+fn trace_bundle(files: Vec<(String, String)>) -> TraceBundle {
+    let mut bundle = TraceBundle::new();
+    for (path, contents) in files {
+        bundle.add(path, contents);
+    }
+    bundle
+}
+
+/// Parses GROMACS `--- MDP ---` / `--- LOG ---` input.
+#[pyclass(name = "GromacsAdapter", module = "lel_ir_prototype", frozen)]
+pub struct PyGromacsAdapter;
+
+#[pymethods]
+impl PyGromacsAdapter {
+    #[new]
+    fn new() -> Self {
+        Self
+    }
+
+    fn parse_trace(&self, raw: &str) -> PyResult<PyLayeredEventLog> {
+        GromacsAdapter
+            .parse_trace(raw)
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+
+    /// Parse `(path, contents)` pairs as one run; provenance records the paths.
+    fn parse_files(&self, files: Vec<(String, String)>) -> PyResult<PyLayeredEventLog> {
+        GromacsAdapter
+            .parse_bundle(&trace_bundle(files))
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+}
+
+/// Parses VASP `--- INCAR ---` / `--- OSZICAR ---` / `--- OUTCAR ---` input.
+#[pyclass(name = "VaspAdapter", module = "lel_ir_prototype", frozen)]
+pub struct PyVaspAdapter;
+
+#[pymethods]
+impl PyVaspAdapter {
+    #[new]
+    fn new() -> Self {
+        Self
+    }
+
+    fn parse_trace(&self, raw: &str) -> PyResult<PyLayeredEventLog> {
+        VaspAdapter
+            .parse_trace(raw)
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+
+    /// Parse `(path, contents)` pairs as one run; provenance records the paths.
+    fn parse_files(&self, files: Vec<(String, String)>) -> PyResult<PyLayeredEventLog> {
+        VaspAdapter
+            .parse_bundle(&trace_bundle(files))
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+}
+
+/// Parses OpenMM StateDataReporter output.
+#[pyclass(name = "OpenMmAdapter", module = "lel_ir_prototype", frozen)]
+pub struct PyOpenMmAdapter;
+
+#[pymethods]
+impl PyOpenMmAdapter {
+    #[new]
+    fn new() -> Self {
+        Self
+    }
+
+    fn parse_trace(&self, raw: &str) -> PyResult<PyLayeredEventLog> {
+        MockOpenMmAdapter
+            .parse_trace(raw)
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+
+    /// Parse a single `(path, contents)` pair; provenance records the path.
+    fn parse_files(&self, files: Vec<(String, String)>) -> PyResult<PyLayeredEventLog> {
+        MockOpenMmAdapter
+            .parse_bundle(&trace_bundle(files))
+            .map(PyLayeredEventLog::from)
+            .map_err(value_error)
+    }
+}
+
+/// `CausalOverlay` over a log. Methods take the log it was built from.
+#[pyclass(name = "CausalOverlay", module = "lel_ir_prototype", frozen)]
+pub struct PyCausalOverlay {
+    overlay: overlay::CausalOverlay,
+    /// First and last event IDs of the log, to recognise it in `check_log`.
+    log_ends: Option<(EventId, EventId)>,
+}
+
+/// IDs of the first and last events of `log`.
+fn log_ends(log: &LayeredEventLog) -> Option<(EventId, EventId)> {
+    Some((log.events.first()?.id, log.events.last()?.id))
+}
+
+#[pymethods]
+impl PyCausalOverlay {
+    #[staticmethod]
+    fn from_log(log: &PyLayeredEventLog) -> Self {
+        Self {
+            overlay: overlay::CausalOverlay::from_log(&log.log),
+            log_ends: log_ends(&log.log),
+        }
+    }
+
+    fn __len__(&self) -> usize {
+        self.overlay.len()
+    }
+
+    /// Event positions reachable through causal parents of `start`.
+    fn transitive_ancestors(&self, start: usize) -> Vec<usize> {
+        self.overlay.transitive_ancestors(start)
+    }
+
+    fn detect_confounders<'py>(
+        &self,
+        py: Python<'py>,
+        log: &PyLayeredEventLog,
+        observable: &str,
+        intervention: &str,
+    ) -> PyResult<Bound<'py, PyList>> {
+        self.check_log(log)?;
+        to_py_list(
+            py,
+            self.overlay
+                .detect_confounders(&log.log, observable, intervention),
+        )
+    }
+
+    fn compare_predictions<'py>(
+        &self,
+        py: Python<'py>,
+        log: &PyLayeredEventLog,
+    ) -> PyResult<Bound<'py, PyList>> {
+        self.check_log(log)?;
+        to_py_list(py, self.overlay.compare_predictions(&log.log))
+    }
+
+    /// `comparison` is one of the dicts returned by `compare_predictions`.
+    fn implicate_causal_nodes<'py>(
+        &self,
+        py: Python<'py>,
+        log: &PyLayeredEventLog,
+        comparison: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        self.check_log(log)?;
+        let comparison: PredictionComparison = from_py(comparison)?;
+        if comparison.comparison_event_idx >= log.log.events.len() {
+            return Err(value_error("comparison_event_idx is out of range"));
+        }
+        to_py_list(
+            py,
+            self.overlay.implicate_causal_nodes(&log.log, &comparison),
+        )
+    }
+}
+
+impl PyCausalOverlay {
+    /// The Rust methods assume the overlay was built from `log`: same
+    /// length and the same first and last event IDs.
+    fn check_log(&self, log: &PyLayeredEventLog) -> PyResult<()> {
+        if self.overlay.len() == log.log.events.len() && self.log_ends == log_ends(&log.log) {
+            Ok(())
+        } else {
+            Err(value_error("overlay was built from a different log"))
+        }
+    }
+}
+
+fn convergence_to_py<'py>(
+    py: Python<'py>,
+    convergence: &CanonicalConvergence,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("pattern", format!("{:?}", convergence.pattern))?;
+    dict.set_item("confidence", format!("{:?}", convergence.confidence))?;
+    dict.set_item("source_metric", &convergence.source_metric)?;
+    dict.set_item("source_framework", &convergence.source_framework)?;
+    Ok(dict)
+}
+
+/// Classify every `ConvergencePoint` in `log`; one dict per point.
+#[pyfunction]
+fn classify_all_convergence<'py>(
+    py: Python<'py>,
+    log: &PyLayeredEventLog,
+    framework: &str,
+) -> PyResult<Bound<'py, PyList>> {
+    let items = convergence::classify_all_convergence(&log.log, framework)
+        .iter()
+        .map(|convergence| convergence_to_py(py, convergence))
+        .collect::<PyResult<Vec<_>>>()?;
+    PyList::new(py, items)
+}
+
+/// JSON Schema of serialized logs and of the dicts this module returns.
+#[pyfunction]
+fn json_schema(py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+    json_to_py(py, &schema::log_json_schema())
+}
+
+#[pymodule]
+fn lel_ir_prototype(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyLayeredEventLog>()?;
+    module.add_class::<PyGromacsAdapter>()?;
+    module.add_class::<PyVaspAdapter>()?;
+    module.add_class::<PyOpenMmAdapter>()?;
+    module.add_class::<PyCausalOverlay>()?;
+    module.add_function(wrap_pyfunction!(classify_all_convergence, module)?)?;
+    module.add_function(wrap_pyfunction!(json_schema, module)?)?;
+    module.add("LOG_SCHEMA_VERSION", schema::LOG_SCHEMA_VERSION)?;
+    Ok(())
+}
@@ -0,0 +1,379 @@
+//! R24 queryability over `LayeredEventLog`: range queries and `EventQuery`.
+//!
+//! Range results are returned in log order. Step ranges go through
+//! `EventIndexes::by_step`; wall-clock and logical-sequence ranges scan the
+//! event stream, since neither is guaranteed to be present or sorted in a
+//! log that has not been validated.
+//!
+//! `EventQuery` also runs over a `MappedLog`: index filters use
+//! `MappedLog::indexes`, and only the candidates are decoded.
+
+use std::collections::HashSet;
+use std::ops::{Bound, RangeBounds};
+
+use crate::binary::BinaryError;
+use crate::common::{EventId, EventKindTag, Layer, Severity};
+use crate::event_kinds::EventKind;
+use crate::lel::{EventIndexes, LayeredEventLog, TraceEvent};
+use crate::mapped::MappedLog;
+
+impl LayeredEventLog {
+    /// Every event whose `simulation_step` lies in `steps`.
+    pub fn events_in_step_range<R>(&self, steps: R) -> Vec<&TraceEvent>
+    where
+        R: RangeBounds<u64>,
+    {
+        if is_empty_range(&steps) {
+            return Vec::new();
+        }
+        let mut positions: Vec<usize> = self
+            .indexes
+            .by_step
+            .range(steps)
+            .flat_map(|(_, ids)| ids)
+            .filter_map(|id| self.indexes.by_id.get(id).copied())
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+            .into_iter()
+            .filter_map(|position| self.events.get(position))
+            .collect()
+    }
+
+    /// Every event whose `wall_clock_ns` lies in `window`. Events without a
+    /// wall-clock timestamp never match.
+    pub fn events_in_wall_clock_range<R>(&self, window: R) -> Vec<&TraceEvent>
+    where
+        R: RangeBounds<u64>,
+    {
+        self.events
+            .iter()
+            .filter(|event| {
+                event
+                    .temporal
+                    .wall_clock_ns
+                    .is_some_and(|ns| window.contains(&ns))
+            })
+            .collect()
+    }
+
+    /// Every event whose `logical_sequence` lies in `sequence`.
+    pub fn events_in_sequence_range<R>(&self, sequence: R) -> Vec<&TraceEvent>
+    where
+        R: RangeBounds<u64>,
+    {
+        self.events
+            .iter()
+            .filter(|event| sequence.contains(&event.temporal.logical_sequence))
+            .collect()
+    }
+
+    /// Start a query matching every event.
+    pub fn query(&self) -> EventQuery<'_> {
+        EventQuery::new(self)
+    }
+}
+
+impl<B: AsRef<[u8]>> MappedLog<B> {
+    /// Start a query matching every event; see `EventQuery`.
+    pub fn query(&self) -> EventQuery<'_, MappedLog<B>> {
+        EventQuery::new(self)
+    }
+}
+
+impl<'a, L> EventQuery<'a, L> {
+    fn new(log: &'a L) -> Self {
+        EventQuery {
+            log,
+            layer: None,
+            kind: None,
+            variable: None,
+            dag_node: None,
+            steps: (Bound::Unbounded, Bound::Unbounded),
+            wall_clock: None,
+            min_severity: None,
+            converged: None,
+            order: QueryOrder::Log,
+            descending: false,
+            limit: None,
+        }
+    }
+}
+
+/// Whether `range` can contain nothing because its bounds are reversed, or
+/// exclude the same value. `BTreeMap::range` panics on such ranges.
+pub(crate) fn is_empty_range<T: Ord>(range: &impl RangeBounds<T>) -> bool {
+    match (range.start_bound(), range.end_bound()) {
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) if start > end => true,
+        (Bound::Excluded(start), Bound::Excluded(end)) => start == end,
+        _ => false,
+    }
+}
+
+/// Sort key for `EventQuery` results. Ties keep log order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOrder {
+    /// Position in `events` (the default).
+    Log,
+    LogicalSequence,
+    SimulationStep,
+    /// Events without a wall clock sort before all timestamped events.
+    WallClock,
+}
+
+/// Composable filter over a log's indexes, built with `LayeredEventLog::query`
+/// or `MappedLog::query`.
+///
+/// Index filters (layer, kind, variable, DAG node) are intersected starting
+/// from the smallest matching index list; the remaining filters are checked
+/// per candidate event.
+#[derive(Debug, Clone)]
+pub struct EventQuery<'a, L = LayeredEventLog> {
+    log: &'a L,
+    layer: Option<Layer>,
+    kind: Option<EventKindTag>,
+    variable: Option<String>,
+    dag_node: Option<String>,
+    steps: (Bound<u64>, Bound<u64>),
+    wall_clock: Option<(Bound<u64>, Bound<u64>)>,
+    min_severity: Option<Severity>,
+    converged: Option<bool>,
+    order: QueryOrder,
+    descending: bool,
+    limit: Option<usize>,
+}
+
+impl<L> EventQuery<'_, L> {
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+
+    pub fn kind(mut self, kind: EventKindTag) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Events indexed under `name` in `EventIndexes::by_variable`.
+    pub fn variable(mut self, name: &str) -> Self {
+        self.variable = Some(name.to_string());
+        self
+    }
+
+    pub fn dag_node(mut self, node: &str) -> Self {
+        self.dag_node = Some(node.to_string());
+        self
+    }
+
+    /// Restrict to a `simulation_step` range.
+    pub fn steps<R: RangeBounds<u64>>(mut self, steps: R) -> Self {
+        self.steps = (steps.start_bound().cloned(), steps.end_bound().cloned());
+        self
+    }
+
+    /// Restrict to a `wall_clock_ns` range; events without one never match.
+    pub fn wall_clock<R: RangeBounds<u64>>(mut self, window: R) -> Self {
+        self.wall_clock = Some((window.start_bound().cloned(), window.end_bound().cloned()));
+        self
+    }
+
+    /// Keep `ExceptionEvent` and `NumericalStatus` events at or above
+    /// `severity`; other kinds never match.
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Keep `ConvergencePoint` events whose `converged` flag equals
+    /// `converged`; points with no flag and other kinds never match.
+    pub fn converged(mut self, converged: bool) -> Self {
+        self.converged = Some(converged);
+        self
+    }
+
+    pub fn order_by(mut self, order: QueryOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Reverse the result order.
+    pub fn descending(mut self) -> Self {
+        self.descending = true;
+        self
+    }
+
+    /// Return at most `limit` events, applied after ordering.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sorted, deduplicated positions that pass every index filter, out of
+    /// a log of `len` events.
+    fn candidate_positions(&self, indexes: &EventIndexes, len: usize) -> Vec<usize> {
+        let mut lists: Vec<&[EventId]> = Vec::new();
+        if let Some(layer) = &self.layer {
+            lists.push(indexes.by_layer.get(layer).map_or(&[], Vec::as_slice));
+        }
+        if let Some(kind) = &self.kind {
+            lists.push(indexes.by_kind.get(kind).map_or(&[], Vec::as_slice));
+        }
+        if let Some(variable) = &self.variable {
+            lists.push(
+                indexes
+                    .by_variable
+                    .get(variable.as_str())
+                    .map_or(&[], Vec::as_slice),
+            );
+        }
+        if let Some(node) = &self.dag_node {
+            lists.push(
+                indexes
+                    .by_dag_node
+                    .get(node.as_str())
+                    .map_or(&[], Vec::as_slice),
+            );
+        }
+
+        if is_empty_range(&self.steps) {
+            return Vec::new();
+        }
+        let mut positions: Vec<usize> = if lists.is_empty() {
+            if self.steps == (Bound::Unbounded, Bound::Unbounded) {
+                (0..len).collect()
+            } else {
+                indexes
+                    .by_step
+                    .range(self.steps)
+                    .flat_map(|(_, ids)| ids)
+                    .filter_map(|id| indexes.by_id.get(id).copied())
+                    .collect()
+            }
+        } else {
+            lists.sort_by_key(|list| list.len());
+            let others: Vec<HashSet<EventId>> = lists[1..]
+                .iter()
+                .map(|list| list.iter().copied().collect())
+                .collect();
+            lists[0]
+                .iter()
+                .filter(|id| others.iter().all(|set| set.contains(id)))
+                .filter_map(|id| indexes.by_id.get(id).copied())
+                .collect()
+        };
+        positions.sort_unstable();
+        positions.dedup();
+        positions.retain(|&position| position < len);
+        positions
+    }
+
+    /// Order and truncate matching events, given in log order.
+    fn arrange<E>(&self, events: &mut Vec<E>, event: impl Fn(&E) -> &TraceEvent) {
+        match self.order {
+            QueryOrder::Log => {}
+            QueryOrder::LogicalSequence => {
+                events.sort_by_key(|e| event(e).temporal.logical_sequence)
+            }
+            QueryOrder::SimulationStep => events.sort_by_key(|e| event(e).temporal.simulation_step),
+            QueryOrder::WallClock => events.sort_by_key(|e| event(e).temporal.wall_clock_ns),
+        }
+        if self.descending {
+            events.reverse();
+        }
+        if let Some(limit) = self.limit {
+            events.truncate(limit);
+        }
+    }
+
+    /// Filters that need the event itself, including the step range, which
+    /// index lists other than `by_step` do not apply.
+    fn matches_event(&self, event: &TraceEvent) -> bool {
+        self.steps.contains(&event.temporal.simulation_step) && self.matches_payload(event)
+    }
+
+    fn matches_payload(&self, event: &TraceEvent) -> bool {
+        if let Some(window) = &self.wall_clock {
+            if !event
+                .temporal
+                .wall_clock_ns
+                .is_some_and(|ns| window.contains(&ns))
+            {
+                return false;
+            }
+        }
+        if let Some(min_severity) = &self.min_severity {
+            let severity = match &event.kind {
+                EventKind::ExceptionEvent { severity, .. }
+                | EventKind::NumericalStatus { severity, .. } => severity,
+                _ => return false,
+            };
+            if severity < min_severity {
+                return false;
+            }
+        }
+        if let Some(expected) = self.converged {
+            match &event.kind {
+                EventKind::ConvergencePoint { converged, .. } if *converged == Some(expected) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+impl<'a> EventQuery<'a> {
+    /// Matching events in the requested order.
+    pub fn events(&self) -> Vec<&'a TraceEvent> {
+        let log = self.log;
+        let mut events: Vec<&'a TraceEvent> = self
+            .candidate_positions(&log.indexes, log.events.len())
+            .into_iter()
+            .map(|position| &log.events[position])
+            .filter(|event| self.matches_event(event))
+            .collect();
+        self.arrange(&mut events, |event| event);
+        events
+    }
+
+    /// IDs of the matching events in the requested order.
+    pub fn ids(&self) -> Vec<EventId> {
+        self.events().into_iter().map(|event| event.id).collect()
+    }
+
+    /// Number of matching events, after `limit`.
+    pub fn count(&self) -> usize {
+        self.events().len()
+    }
+}
+
+impl<B: AsRef<[u8]>> EventQuery<'_, MappedLog<B>> {
+    /// Matching events in the requested order. Only candidates that pass
+    /// the index filters are decoded.
+    pub fn events(&self) -> Result<Vec<TraceEvent>, BinaryError> {
+        let log = self.log;
+        let mut events = Vec::new();
+        for position in self.candidate_positions(log.indexes(), log.len()) {
+            if let Some(event) = log.event_at(position)? {
+                if self.matches_event(&event) {
+                    events.push(event);
+                }
+            }
+        }
+        self.arrange(&mut events, |event| event);
+        Ok(events)
+    }
+
+    /// IDs of the matching events in the requested order.
+    pub fn ids(&self) -> Result<Vec<EventId>, BinaryError> {
+        Ok(self.events()?.into_iter().map(|event| event.id).collect())
+    }
+
+    /// Number of matching events, after `limit`.
+    pub fn count(&self) -> Result<usize, BinaryError> {
+        Ok(self.events()?.len())
+    }
+}
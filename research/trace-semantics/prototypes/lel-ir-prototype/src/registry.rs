@@ -0,0 +1,225 @@
+//! Framework auto-detection over the registered `DslAdapter`s.
+//!
+//! Ingestion receives run directories with no framework label. Each adapter
+//! scores the input from textual signatures (`DslAdapter::detect`);
+//! `AdapterRegistry::parse_any` hands the input to the best-scoring adapter
+//! and reports which one it chose and why. `parse_any_bundle` does the same
+//! for a directory's files, scoring each by name and contents
+//! (`DslAdapter::detect_file`) and passing the winner only the files it
+//! recognised.
+
+use std::fmt;
+
+use crate::adapter::{AdapterError, Detection, DslAdapter, MockOpenMmAdapter};
+use crate::bundle::TraceBundle;
+use crate::gromacs_adapter::GromacsAdapter;
+use crate::lel::LayeredEventLog;
+use crate::vasp_adapter::VaspAdapter;
+
+/// Confidence below which `parse_any` refuses to guess. In a bundle, files
+/// an adapter scores below it are not passed to that adapter.
+pub const MIN_DETECTION_CONFIDENCE: f64 = 0.5;
+
+/// One adapter's assessment of an input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub framework: &'static str,
+    pub detection: Detection,
+    /// Paths of the bundle files the adapter recognised, in bundle order;
+    /// empty for `detect`.
+    pub files: Vec<String>,
+    /// Registration position of the adapter that made the assessment.
+    adapter: usize,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.framework, self.detection)
+    }
+}
+
+/// A log parsed by `parse_any` or `parse_any_bundle`, with the candidate
+/// that produced it and
+/// the others in decreasing confidence.
+#[derive(Debug)]
+pub struct DetectedLog {
+    pub log: LayeredEventLog,
+    pub chosen: Candidate,
+    pub alternatives: Vec<Candidate>,
+}
+
+impl DetectedLog {
+    pub fn framework(&self) -> &'static str {
+        self.chosen.framework
+    }
+
+    /// Why `chosen` was picked, e.g. "gromacs: confidence 0.96 (GROMACS
+    /// banner; ...); next best vasp: confidence 0.00".
+    pub fn reason(&self) -> String {
+        match self.alternatives.first() {
+            Some(next) => format!("{}; next best {}", self.chosen, next),
+            None => self.chosen.to_string(),
+        }
+    }
+}
+
+/// Ordered set of adapters. Ties go to the adapter registered first.
+pub struct AdapterRegistry {
+    adapters: Vec<Box<dyn DslAdapter + Send + Sync>>,
+}
+
+impl Default for AdapterRegistry {
+    /// The adapters shipped with this build: OpenMM, GROMACS and VASP.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register(MockOpenMmAdapter)
+            .register(GromacsAdapter)
+            .register(VaspAdapter);
+        registry
+    }
+}
+
+impl AdapterRegistry {
+    /// A registry with no adapters.
+    pub fn empty() -> Self {
+        Self {
+            adapters: Vec::new(),
+        }
+    }
+
+    /// Add `adapter` after those already registered.
+    pub fn register(&mut self, adapter: impl DslAdapter + Send + Sync + 'static) -> &mut Self {
+        self.adapters.push(Box::new(adapter));
+        self
+    }
+
+    /// Registered framework names, in registration order.
+    pub fn frameworks(&self) -> Vec<&'static str> {
+        self.adapters
+            .iter()
+            .map(|adapter| adapter.framework())
+            .collect()
+    }
+
+    /// Every adapter's assessment of `raw`, most confident first.
+    pub fn detect(&self, raw: &str) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = self
+            .adapters
+            .iter()
+            .enumerate()
+            .map(|(position, adapter)| Candidate {
+                framework: adapter.framework(),
+                detection: adapter.detect(raw),
+                files: Vec::new(),
+                adapter: position,
+            })
+            .collect();
+        sort_candidates(&mut candidates);
+        candidates
+    }
+
+    /// Every adapter's assessment of the files in `bundle`, most confident
+    /// first. Each adapter claims the files it scores at or above
+    /// `MIN_DETECTION_CONFIDENCE`; its confidence combines theirs, so a
+    /// complete set of run files outscores a lone match.
+    pub fn detect_bundle(&self, bundle: &TraceBundle) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = self
+            .adapters
+            .iter()
+            .enumerate()
+            .map(|(position, adapter)| {
+                let mut detection = Detection::default();
+                let mut files = Vec::new();
+                for file in bundle.files() {
+                    let assessed = adapter.detect_file(file);
+                    if assessed.confidence >= MIN_DETECTION_CONFIDENCE {
+                        detection.signal(
+                            assessed.confidence,
+                            format!("{}: {}", file.file_name(), assessed.signals.join(", ")),
+                        );
+                        files.push(file.path.clone());
+                    }
+                }
+                Candidate {
+                    framework: adapter.framework(),
+                    detection,
+                    files,
+                    adapter: position,
+                }
+            })
+            .collect();
+        sort_candidates(&mut candidates);
+        candidates
+    }
+
+    /// Parse `raw` with the most confident adapter. Fails with
+    /// `UnsupportedFormat` when no adapter reaches
+    /// `MIN_DETECTION_CONFIDENCE`.
+    pub fn parse_any(&self, raw: &str) -> Result<DetectedLog, AdapterError> {
+        let (chosen, alternatives) = choose(self.detect(raw))?;
+        let log = self.adapters[chosen.adapter].parse_trace(raw)?;
+        Ok(DetectedLog {
+            log,
+            chosen,
+            alternatives,
+        })
+    }
+
+    /// Parse the files of `bundle`, e.g. a run directory, with the most
+    /// confident adapter, passing it only the files it recognised. Fails
+    /// like `parse_any`.
+    pub fn parse_any_bundle(&self, bundle: &TraceBundle) -> Result<DetectedLog, AdapterError> {
+        let (chosen, alternatives) = choose(self.detect_bundle(bundle))?;
+        let mut claimed = TraceBundle::new();
+        for file in bundle.files() {
+            if chosen.files.contains(&file.path) {
+                claimed.add(file.path.clone(), file.contents.clone());
+            }
+        }
+        let log = self.adapters[chosen.adapter].parse_bundle(&claimed)?;
+        Ok(DetectedLog {
+            log,
+            chosen,
+            alternatives,
+        })
+    }
+}
+
+/// Most confident first. Stable, so equal confidences keep registration
+/// order.
+fn sort_candidates(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| b.detection.confidence.total_cmp(&a.detection.confidence));
+}
+
+/// Split sorted `candidates` into the winner and the rest, or fail when the
+/// winner is below `MIN_DETECTION_CONFIDENCE`.
+fn choose(mut candidates: Vec<Candidate>) -> Result<(Candidate, Vec<Candidate>), AdapterError> {
+    if candidates
+        .first()
+        .is_some_and(|best| best.detection.confidence >= MIN_DETECTION_CONFIDENCE)
+    {
+        let chosen = candidates.remove(0);
+        return Ok((chosen, candidates));
+    }
+    let assessed: Vec<String> = candidates.iter().map(Candidate::to_string).collect();
+    Err(AdapterError::UnsupportedFormat(format!(
+        "no adapter recognised the input (need confidence {:.2}; {})",
+        MIN_DETECTION_CONFIDENCE,
+        if assessed.is_empty() {
+            "no adapters registered".to_string()
+        } else {
+            assessed.join(", ")
+        }
+    )))
+}
+
+/// `AdapterRegistry::default().parse_any(raw)`.
+pub fn parse_any(raw: &str) -> Result<DetectedLog, AdapterError> {
+    AdapterRegistry::default().parse_any(raw)
+}
+
+/// `AdapterRegistry::default().parse_any_bundle(bundle)`.
+pub fn parse_any_bundle(bundle: &TraceBundle) -> Result<DetectedLog, AdapterError> {
+    AdapterRegistry::default().parse_any_bundle(bundle)
+}
@@ -0,0 +1,221 @@
+//! Schema versioning for serialized `LayeredEventLog` documents.
+//!
+//! Every serialized log carries a top-level `schema_version`. Archived
+//! documents written by older builds are upgraded one version at a time by
+//! a `MigrationRegistry` operating on the JSON tree, then deserialized as
+//! the current layout. Documents without a `schema_version` predate
+//! versioning and are treated as version 0.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+
+use crate::lel::LayeredEventLog;
+
+/// Schema version written by this build.
+///
+/// - 0: unversioned documents; stored indexes may lack `by_step`.
+/// - 1: `schema_version` field; indexes optional and rebuilt when absent.
+/// - 2: non-finite `Value` floats written as strings instead of `null`.
+pub const LOG_SCHEMA_VERSION: u32 = 2;
+
+/// Upgrades a document from one version to the next, in place.
+pub type MigrationFn = fn(&mut JsonValue) -> Result<(), String>;
+
+/// Why a serialized log could not be loaded.
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The document was written by a newer build.
+    NewerVersion {
+        found: u32,
+        supported: u32,
+    },
+    /// No migration is registered from `from` to `from + 1`.
+    MissingMigration {
+        from: u32,
+    },
+    MigrationFailed {
+        from: u32,
+        reason: String,
+    },
+    /// `schema_version` is present but not a non-negative integer, or the
+    /// document is not a JSON object.
+    InvalidVersion(String),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::NewerVersion { found, supported } => write!(
+                f,
+                "log schema version {} is newer than supported version {}; upgrade the library",
+                found, supported
+            ),
+            SchemaError::MissingMigration { from } => {
+                write!(f, "no migration from log schema version {}", from)
+            }
+            SchemaError::MigrationFailed { from, reason } => write!(
+                f,
+                "migration from log schema version {} failed: {}",
+                from, reason
+            ),
+            SchemaError::InvalidVersion(detail) => {
+                write!(f, "invalid log schema version: {}", detail)
+            }
+            SchemaError::Json(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        SchemaError::Json(err)
+    }
+}
+
+/// Migrations keyed by the version they upgrade from.
+#[derive(Debug, Clone)]
+pub struct MigrationRegistry {
+    migrations: BTreeMap<u32, MigrationFn>,
+}
+
+impl Default for MigrationRegistry {
+    /// The migrations shipped with this build.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(0, migrate_v0_drop_indexes);
+        registry.register(1, migrate_v1_non_finite_floats);
+        registry
+    }
+}
+
+impl MigrationRegistry {
+    /// A registry with no migrations.
+    pub fn empty() -> Self {
+        Self {
+            migrations: BTreeMap::new(),
+        }
+    }
+
+    /// Register the migration from `from` to `from + 1`, replacing any
+    /// existing one.
+    pub fn register(&mut self, from: u32, migration: MigrationFn) -> &mut Self {
+        self.migrations.insert(from, migration);
+        self
+    }
+
+    /// Upgrade `document` to `LOG_SCHEMA_VERSION`. Returns the version the
+    /// document had before migration.
+    pub fn upgrade(&self, document: &mut JsonValue) -> Result<u32, SchemaError> {
+        let original = document_version(document)?;
+        if original > LOG_SCHEMA_VERSION {
+            return Err(SchemaError::NewerVersion {
+                found: original,
+                supported: LOG_SCHEMA_VERSION,
+            });
+        }
+        for from in original..LOG_SCHEMA_VERSION {
+            let migration = self
+                .migrations
+                .get(&from)
+                .ok_or(SchemaError::MissingMigration { from })?;
+            migration(document).map_err(|reason| SchemaError::MigrationFailed { from, reason })?;
+            let object = document
+                .as_object_mut()
+                .ok_or_else(|| SchemaError::MigrationFailed {
+                    from,
+                    reason: "migration replaced the document with a non-object".to_string(),
+                })?;
+            object.insert("schema_version".to_string(), JsonValue::from(from + 1));
+        }
+        Ok(original)
+    }
+
+    /// Upgrade and deserialize a document.
+    pub fn load(&self, mut document: JsonValue) -> Result<LayeredEventLog, SchemaError> {
+        self.upgrade(&mut document)?;
+        Ok(serde_json::from_value(document)?)
+    }
+}
+
+/// The `schema_version` of a serialized log; 0 when absent.
+pub fn document_version(document: &JsonValue) -> Result<u32, SchemaError> {
+    let object = document
+        .as_object()
+        .ok_or_else(|| SchemaError::InvalidVersion("document is not an object".to_string()))?;
+    match object.get("schema_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| SchemaError::InvalidVersion(version.to_string())),
+    }
+}
+
+/// JSON Schema (draft 2020-12) of a serialized `LayeredEventLog` at
+/// `LOG_SCHEMA_VERSION`, generated from the Rust types. Every IR type
+/// reachable from the log appears under `$defs`.
+pub fn log_json_schema() -> JsonValue {
+    let mut schema = schemars::schema_for!(LayeredEventLog);
+    schema.insert(
+        "$comment".to_string(),
+        format!("LEL log schema version {}", LOG_SCHEMA_VERSION).into(),
+    );
+    schema.to_value()
+}
+
+/// Load a serialized log of any supported version with the default
+/// migrations.
+pub fn log_from_json(json: &str) -> Result<LayeredEventLog, SchemaError> {
+    MigrationRegistry::default().load(serde_json::from_str(json)?)
+}
+
+/// Version 0 indexes predate `by_step`; drop them so they are rebuilt.
+fn migrate_v0_drop_indexes(document: &mut JsonValue) -> Result<(), String> {
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| "document is not an object".to_string())?;
+    object.remove("indexes");
+    Ok(())
+}
+
+/// Version 1 wrote non-finite `Value` floats as `null`, which version 2
+/// still reads as NaN, so the layout needs no rewrite.
+fn migrate_v1_non_finite_floats(_document: &mut JsonValue) -> Result<(), String> {
+    Ok(())
+}
+
+/// `schema_version` field of the wire form. Direct deserialization accepts
+/// only the current version or none; older versions go through
+/// `MigrationRegistry`.
+pub(crate) fn deserialize_version<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let version = Option::<u32>::deserialize(deserializer)?;
+    match version {
+        Some(found) if found > LOG_SCHEMA_VERSION => {
+            Err(serde::de::Error::custom(SchemaError::NewerVersion {
+                found,
+                supported: LOG_SCHEMA_VERSION,
+            }))
+        }
+        Some(found) if found < LOG_SCHEMA_VERSION => Err(serde::de::Error::custom(format!(
+            "log schema version {} must be migrated; load it with schema::log_from_json",
+            found
+        ))),
+        _ => Ok(version),
+    }
+}
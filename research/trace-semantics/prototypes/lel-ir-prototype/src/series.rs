@@ -0,0 +1,230 @@
+//! Columnar view of the numeric trajectories in a log.
+//!
+//! Energies and convergence metrics are stored one event per step, which
+//! suits provenance but not analysis: "Potential vs step" would otherwise
+//! walk every event and match component names. `TimeSeries` keeps one
+//! `Series` per energy component and convergence metric, each a set of
+//! parallel columns (step, event ID, value) in log order. Like `indexes`, it
+//! is derived from `events`, updated as events are pushed and rebuilt when a
+//! log is loaded.
+
+use std::collections::BTreeMap;
+
+use crate::common::{EventId, Unit, Value};
+use crate::event_kinds::EventKind;
+use crate::lel::TraceEvent;
+use crate::symbol::Symbol;
+
+/// Name of the series holding `EnergyRecord` totals.
+pub const ENERGY_TOTAL: &str = "Total Energy";
+
+/// One quantity sampled over a run. Values are converted to the unit of the
+/// first sample; samples in an incompatible unit, and non-`Known` values,
+/// are left out. `steps`, `event_ids` and `values` have equal length.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    name: Symbol,
+    unit: Option<Unit>,
+    steps: Vec<u64>,
+    event_ids: Vec<EventId>,
+    values: Vec<f64>,
+    /// `ConvergencePoint::iteration` per sample; empty for energy series.
+    iterations: Vec<u64>,
+}
+
+/// A single sample and the event it came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeriesSample {
+    pub step: u64,
+    pub event_id: EventId,
+    pub value: f64,
+}
+
+impl Series {
+    pub fn new(name: impl Into<Symbol>) -> Self {
+        Self {
+            name: name.into(),
+            unit: None,
+            steps: Vec::new(),
+            event_ids: Vec::new(),
+            values: Vec::new(),
+            iterations: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Unit of `values`; `None` until the first sample.
+    pub fn unit(&self) -> Option<&Unit> {
+        self.unit.as_ref()
+    }
+
+    /// `simulation_step` of each sample's event.
+    pub fn steps(&self) -> &[u64] {
+        &self.steps
+    }
+
+    pub fn event_ids(&self) -> &[EventId] {
+        &self.event_ids
+    }
+
+    /// Solver iteration of each sample of a convergence series, parallel to
+    /// `steps`; empty for energy series.
+    pub fn iterations(&self) -> &[u64] {
+        &self.iterations
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<SeriesSample> {
+        Some(SeriesSample {
+            step: *self.steps.get(index)?,
+            event_id: self.event_ids[index],
+            value: self.values[index],
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = SeriesSample> + '_ {
+        (0..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// Position of the sample taken from `event_id`.
+    pub fn position(&self, event_id: EventId) -> Option<usize> {
+        self.event_ids.iter().position(|id| *id == event_id)
+    }
+
+    /// Samples whose step lies in `start..=end`, assuming steps do not
+    /// decrease (true for a single run).
+    pub fn step_range(&self, start: u64, end: u64) -> std::ops::Range<usize> {
+        let from = self.steps.partition_point(|step| *step < start);
+        let to = self.steps.partition_point(|step| *step <= end);
+        from..to.max(from)
+    }
+
+    /// Append `value` if it is `Known` and convertible to the series unit.
+    /// Returns whether a sample was added.
+    pub fn push(&mut self, step: u64, event_id: EventId, value: &Value) -> bool {
+        let Some(converted) = convert_sample(&mut self.unit, value) else {
+            return false;
+        };
+        self.steps.push(step);
+        self.event_ids.push(event_id);
+        self.values.push(converted);
+        true
+    }
+}
+
+/// `value` converted to `unit`, which is set to the value's own unit if
+/// still `None`. `None` for non-`Known` values and incompatible units.
+pub(crate) fn convert_sample(unit: &mut Option<Unit>, value: &Value) -> Option<f64> {
+    let Value::Known(raw, value_unit) = value else {
+        return None;
+    };
+    match unit {
+        None => {
+            *unit = Some(value_unit.clone());
+            Some(*raw)
+        }
+        Some(unit) => value_unit.convert(*raw, unit).ok(),
+    }
+}
+
+/// Energy and convergence series of a log, keyed by component or metric
+/// name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    energy_total: Series,
+    energy_components: BTreeMap<Symbol, Series>,
+    convergence: BTreeMap<Symbol, Series>,
+}
+
+impl Default for TimeSeries {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSeries {
+    pub fn new() -> Self {
+        Self {
+            energy_total: Series::new(ENERGY_TOTAL),
+            energy_components: BTreeMap::new(),
+            convergence: BTreeMap::new(),
+        }
+    }
+
+    /// Series for `events`, in order.
+    pub fn from_events(events: &[TraceEvent]) -> Self {
+        let mut series = Self::new();
+        for event in events {
+            series.record(event);
+        }
+        series
+    }
+
+    /// Add the samples carried by `event`. Called during log construction.
+    pub fn record(&mut self, event: &TraceEvent) {
+        let step = event.temporal.simulation_step;
+        match &event.kind {
+            EventKind::EnergyRecord { total, components } => {
+                self.energy_total.push(step, event.id, total);
+                for (name, value) in components {
+                    self.energy_components
+                        .entry(name.clone())
+                        .or_insert_with(|| Series::new(name.clone()))
+                        .push(step, event.id, value);
+                }
+            }
+            EventKind::ConvergencePoint {
+                iteration,
+                metric_name,
+                metric_value,
+                ..
+            } => {
+                let series = self
+                    .convergence
+                    .entry(metric_name.clone())
+                    .or_insert_with(|| Series::new(metric_name.clone()));
+                if series.push(step, event.id, metric_value) {
+                    series.iterations.push(*iteration);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `EnergyRecord` totals.
+    pub fn energy_total(&self) -> &Series {
+        &self.energy_total
+    }
+
+    pub fn energy_component(&self, name: &str) -> Option<&Series> {
+        self.energy_components.get(name)
+    }
+
+    /// Component series in name order.
+    pub fn energy_components(&self) -> impl Iterator<Item = &Series> {
+        self.energy_components.values()
+    }
+
+    pub fn convergence(&self, metric: &str) -> Option<&Series> {
+        self.convergence.get(metric)
+    }
+
+    /// Convergence metric series in name order.
+    pub fn convergence_metrics(&self) -> impl Iterator<Item = &Series> {
+        self.convergence.values()
+    }
+}
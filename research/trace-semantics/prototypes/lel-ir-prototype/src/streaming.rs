@@ -0,0 +1,258 @@
+//! Line-at-a-time adapter input for logs too large to hold in memory.
+//!
+//! Production `md.log` and OUTCAR files run to gigabytes. A
+//! `StreamingAdapter` reads each `StreamSource` through `BufRead`, one line
+//! at a time into a reused buffer, and hands each event to an `EventSink`
+//! as soon as it is complete. Memory depends on the largest multi-line
+//! construct (one GROMACS energy block, one OUTCAR force table), not on file
+//! size; provenance hashes are computed as lines go by. Parameter files
+//! (MDP, INCAR) are small and read whole. The sink decides whether events
+//! are kept (`LogSink`) or written straight out (`BinarySink`).
+//!
+//! The in-memory parsers (`gromacs_adapter::parse_log`,
+//! `vasp_adapter::parse_outcar`, ...) run the same line parsers over a
+//! string, so both paths produce identical events.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::adapter::{AdapterError, DslAdapter};
+use crate::binary::{BinaryError, BinaryLogWriter, BinaryOptions};
+use crate::common::{ExperimentRef, ProvenanceAnchor};
+use crate::lel::{ExperimentSpec, LayeredEventLog, LayeredEventLogBuilder, TraceEvent};
+use crate::provenance::{line_anchor, LineHasher};
+use crate::symbol::Symbol;
+
+/// A file read incrementally.
+pub struct StreamSource<'a> {
+    /// Path as recorded in `ProvenanceAnchor::source_file`.
+    pub path: String,
+    pub reader: Box<dyn BufRead + 'a>,
+}
+
+impl<'a> StreamSource<'a> {
+    pub fn new(path: impl Into<String>, reader: impl BufRead + 'a) -> Self {
+        Self {
+            path: path.into(),
+            reader: Box::new(reader),
+        }
+    }
+}
+
+impl StreamSource<'static> {
+    /// Open the file at `path` for buffered reading, recording it as given.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Ok(Self::new(
+            path.display().to_string(),
+            BufReader::new(File::open(path)?),
+        ))
+    }
+}
+
+/// Remove and return the first source whose path satisfies `matches`.
+pub(crate) fn take_source<'a>(
+    sources: &mut Vec<StreamSource<'a>>,
+    matches: impl Fn(&str) -> bool,
+) -> Option<StreamSource<'a>> {
+    let position = sources.iter().position(|source| matches(&source.path))?;
+    Some(sources.remove(position))
+}
+
+/// Paths of `sources`, for error messages.
+pub(crate) fn source_paths(sources: &[StreamSource<'_>]) -> Vec<String> {
+    sources.iter().map(|source| source.path.clone()).collect()
+}
+
+/// One line of a streamed source.
+pub(crate) struct Line<'a> {
+    /// 1-based line number.
+    pub number: u32,
+    /// The line without its `\n`, as `SourceText` hashes it.
+    pub raw: &'a str,
+    /// The line as `str::lines` yields it, without a `\r\n` terminator.
+    pub text: &'a str,
+}
+
+impl Line<'_> {
+    /// Hasher holding this line, for spans that start here.
+    pub(crate) fn hash(&self) -> LineHasher {
+        LineHasher::of(self.raw)
+    }
+
+    /// Anchor to this line alone.
+    pub(crate) fn anchor(&self, source_file: &Symbol) -> ProvenanceAnchor {
+        line_anchor(source_file, self.number, self.number, self.hash().finish())
+    }
+}
+
+/// Lines of a reader, read into one reused buffer.
+pub(crate) struct Lines<R> {
+    reader: R,
+    buffer: String,
+    count: u32,
+}
+
+impl<R: BufRead> Lines<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            count: 0,
+        }
+    }
+
+    pub(crate) fn next_line(&mut self) -> Result<Option<Line<'_>>, AdapterError> {
+        self.buffer.clear();
+        if self.reader.read_line(&mut self.buffer)? == 0 {
+            return Ok(None);
+        }
+        self.count += 1;
+        let (raw, text) = match self.buffer.strip_suffix('\n') {
+            Some(raw) => (raw, raw.strip_suffix('\r').unwrap_or(raw)),
+            None => (self.buffer.as_str(), self.buffer.as_str()),
+        };
+        Ok(Some(Line {
+            number: self.count,
+            raw,
+            text,
+        }))
+    }
+
+    /// Lines read so far.
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+/// Receiver of a streamed log.
+pub trait EventSink {
+    /// Called once, before the first event.
+    fn begin(
+        &mut self,
+        experiment_ref: ExperimentRef,
+        spec: ExperimentSpec,
+    ) -> Result<(), AdapterError>;
+
+    /// Called for each event, in log order.
+    fn event(&mut self, event: TraceEvent) -> Result<(), AdapterError>;
+}
+
+/// Collects a streamed log in memory.
+#[derive(Default)]
+pub struct LogSink {
+    builder: Option<LayeredEventLogBuilder>,
+}
+
+impl LogSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The collected log, or `None` if `begin` was never called.
+    pub fn into_log(self) -> Option<LayeredEventLog> {
+        self.builder.map(LayeredEventLogBuilder::build)
+    }
+}
+
+impl EventSink for LogSink {
+    fn begin(
+        &mut self,
+        experiment_ref: ExperimentRef,
+        spec: ExperimentSpec,
+    ) -> Result<(), AdapterError> {
+        self.builder = Some(LayeredEventLogBuilder::new(experiment_ref, spec));
+        Ok(())
+    }
+
+    fn event(&mut self, event: TraceEvent) -> Result<(), AdapterError> {
+        let builder = self.builder.as_mut().ok_or_else(|| {
+            AdapterError::ParseError("event streamed before the log header".to_string())
+        })?;
+        builder.push(event);
+        Ok(())
+    }
+}
+
+/// Writes a streamed log to a `BinaryLogWriter`, one block of
+/// `BinaryOptions::block_size` events at a time. With `index` off, memory
+/// does not grow with the log.
+pub struct BinarySink<W: Write> {
+    writer: BinaryLogWriter<W>,
+    block: Vec<TraceEvent>,
+    block_size: usize,
+}
+
+impl<W: Write> BinarySink<W> {
+    pub fn new(out: W, options: BinaryOptions) -> Result<Self, BinaryError> {
+        Ok(Self {
+            writer: BinaryLogWriter::new(out, options)?,
+            block: Vec::new(),
+            block_size: options.block_size.max(1),
+        })
+    }
+
+    /// Write any buffered events and the end of the log, and return the
+    /// underlying writer.
+    pub fn finish(mut self) -> Result<W, BinaryError> {
+        self.writer.write_events(&self.block)?;
+        self.writer.finish()
+    }
+}
+
+impl<W: Write> EventSink for BinarySink<W> {
+    fn begin(
+        &mut self,
+        experiment_ref: ExperimentRef,
+        spec: ExperimentSpec,
+    ) -> Result<(), AdapterError> {
+        self.writer
+            .write_header(&experiment_ref, &spec)
+            .map_err(sink_error)
+    }
+
+    fn event(&mut self, event: TraceEvent) -> Result<(), AdapterError> {
+        self.block.push(event);
+        if self.block.len() >= self.block_size {
+            self.writer.write_events(&self.block).map_err(sink_error)?;
+            self.block.clear();
+        }
+        Ok(())
+    }
+}
+
+fn sink_error(err: BinaryError) -> AdapterError {
+    match err {
+        BinaryError::Io(err) => AdapterError::Io(err),
+        other => AdapterError::Io(io::Error::other(other.to_string())),
+    }
+}
+
+/// An adapter that can parse its input from readers without holding whole
+/// files in memory.
+pub trait StreamingAdapter: DslAdapter {
+    /// Parse the run in `sources`, passing the log header and then each
+    /// event to `sink` as soon as it is complete. Sources are chosen by path
+    /// as `DslAdapter::parse_bundle` chooses files.
+    fn stream(
+        &self,
+        sources: Vec<StreamSource<'_>>,
+        sink: &mut dyn EventSink,
+    ) -> Result<(), AdapterError>;
+
+    /// `stream` into a `LogSink`.
+    fn parse_streams(
+        &self,
+        sources: Vec<StreamSource<'_>>,
+    ) -> Result<LayeredEventLog, AdapterError> {
+        let mut sink = LogSink::new();
+        self.stream(sources, &mut sink)?;
+        sink.into_log().ok_or_else(|| {
+            AdapterError::ParseError(format!(
+                "{} adapter streamed no log header",
+                self.framework()
+            ))
+        })
+    }
+}
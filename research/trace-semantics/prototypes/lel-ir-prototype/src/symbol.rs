@@ -0,0 +1,303 @@
+//! Interned strings for the names that repeat across events.
+//!
+//! Units, parameter and metric names, energy component names, source files
+//! and DAG node references are drawn from a handful of distinct values but
+//! appear on nearly every event. `Symbol` is a shared handle to one
+//! allocation of such a string; a `SymbolTable` owned by the log (and its
+//! builder) hands out one handle per distinct string. Adapters intern through
+//! one table per run while parsing, so the builder adopts their handles
+//! instead of allocating its own. Symbols serialize as plain strings, so the
+//! JSON and binary layouts are unchanged.
+
+use std::borrow::{Borrow, Cow};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::common::{ProvenanceAnchor, Value};
+use crate::event_kinds::EventKind;
+use crate::lel::TraceEvent;
+use crate::units::Unit;
+
+/// Cheaply clonable, immutable string. Compares, hashes and orders as its
+/// text; reads as `&str` through `Deref`.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// True when both handles share one allocation.
+    pub fn ptr_eq(&self, other: &Symbol) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Self {
+        Symbol(Arc::from(text))
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(text: String) -> Self {
+        Symbol(Arc::from(text))
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(text: &String) -> Self {
+        Symbol::from(text.as_str())
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.0.to_string()
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == &*other.0
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == &*other.0
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        **self == *other.0
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Symbol::from)
+    }
+}
+
+impl JsonSchema for Symbol {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        String::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        String::json_schema(generator)
+    }
+}
+
+/// One `Symbol` per distinct string, plus the parsed `Unit` for each unit
+/// symbol so repeated units are neither reallocated nor reparsed.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashSet<Symbol>,
+    units: HashMap<Symbol, Unit>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The table's handle for `text`, added on first use.
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return symbol.clone();
+        }
+        let symbol = Symbol::from(text);
+        self.symbols.insert(symbol.clone());
+        symbol
+    }
+
+    /// `intern` for an owned symbol; returns the table's handle.
+    pub fn intern_symbol(&mut self, symbol: &Symbol) -> Symbol {
+        if let Some(existing) = self.symbols.get(symbol) {
+            return existing.clone();
+        }
+        self.symbols.insert(symbol.clone());
+        symbol.clone()
+    }
+
+    /// `Unit::parse(text)`, parsed once per distinct symbol.
+    pub fn unit(&mut self, text: &str) -> Unit {
+        if let Some(unit) = self.units.get(text) {
+            return unit.clone();
+        }
+        let symbol = self.intern(text);
+        let unit = Unit::parse_symbol(symbol.clone());
+        self.units.insert(symbol, unit.clone());
+        unit
+    }
+
+    /// `unit`'s entry in this table. A unit new to the table is added as
+    /// is, keeping its handle and parsed dimension.
+    pub fn intern_unit(&mut self, unit: &Unit) -> Unit {
+        if let Some(existing) = self.units.get(unit.as_str()) {
+            return existing.clone();
+        }
+        let symbol = self.intern_symbol(unit.symbol());
+        let unit = unit.with_symbol(symbol.clone());
+        self.units.insert(symbol, unit.clone());
+        unit
+    }
+
+    /// Number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.symbols.contains(text)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.symbols.iter().map(Symbol::as_str)
+    }
+
+    /// Replace the interned fields of `event` with this table's handles, so
+    /// events decoded or built elsewhere share storage with the log.
+    pub fn intern_event(&mut self, event: &mut TraceEvent) {
+        if let Some(node) = &mut event.dag_node_ref {
+            *node = self.intern_symbol(node);
+        }
+        self.intern_provenance(&mut event.provenance);
+        match &mut event.kind {
+            EventKind::ParameterRecord {
+                name,
+                specified_value,
+                actual_value,
+                units,
+                ..
+            } => {
+                *name = self.intern_symbol(name);
+                if let Some(value) = specified_value {
+                    self.intern_value(value);
+                }
+                self.intern_value(actual_value);
+                if let Some(unit) = units {
+                    *unit = self.intern_unit(unit);
+                }
+            }
+            EventKind::ObservableMeasurement {
+                variable_name,
+                value,
+                ..
+            } => {
+                *variable_name = self.intern_symbol(variable_name);
+                self.intern_value(value);
+            }
+            EventKind::ConvergencePoint {
+                metric_name,
+                metric_value,
+                ..
+            } => {
+                *metric_name = self.intern_symbol(metric_name);
+                self.intern_value(metric_value);
+            }
+            EventKind::EnergyRecord { total, components } => {
+                self.intern_value(total);
+                for (name, value) in components {
+                    *name = self.intern_symbol(name);
+                    self.intern_value(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// `intern_event` for each of `events`.
+    pub fn intern_events(&mut self, events: &mut [TraceEvent]) {
+        for event in events {
+            self.intern_event(event);
+        }
+    }
+
+    pub(crate) fn intern_provenance(&mut self, provenance: &mut ProvenanceAnchor) {
+        provenance.source_file = self.intern_symbol(&provenance.source_file);
+    }
+
+    fn intern_value(&mut self, value: &mut Value) {
+        let unit = match value {
+            Value::Known(_, unit) | Value::KnownVec(_, unit) => unit,
+            Value::KnownGrid { value_unit, .. } => value_unit,
+            Value::DataRef { unit, .. } => unit,
+            Value::KnownCat(_) | Value::Havoc { .. } => return,
+        };
+        *unit = self.intern_unit(unit);
+    }
+}
@@ -1,30 +1,47 @@
-use std::sync::Once;
-
-use crate::adapter::{parse_openmm_energy_series, DslAdapter, MockOpenMmAdapter};
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::ops::Bound;
+
+use crate::adapter::{parse_openmm_energy_series, AdapterError, DslAdapter, MockOpenMmAdapter};
+use crate::binary::{
+    decode_log, encode_log, BinaryError, BinaryLogReader, BinaryOptions, Compression,
+    BINARY_SCHEMA_VERSION,
+};
+use crate::bundle::{file_name, TraceBundle};
 use crate::common::*;
+use crate::contracts::evaluate_contracts;
 use crate::convergence::{
     classify_all_convergence, classify_convergence, ConvergenceConfidence, ConvergencePattern,
 };
+use crate::diff::{diff, ChangeKind, KindCountChange, OutcomeChange, SpecChange, StepDelta};
 use crate::event_kinds::EventKind;
-use crate::gromacs_adapter::{
-    classify_mdp_parameter, parse_log, parse_mdp, GromacsAdapter,
-};
+use crate::gromacs_adapter::{classify_mdp_parameter, parse_log, parse_mdp, GromacsAdapter};
+use crate::integrity::IntegrityViolation;
 use crate::lel::*;
+use crate::mapped::MappedLog;
+use crate::merge::{merge_logs, MergeError, SpecConflict};
 use crate::overlay::{CausalOverlay, PredictionComparison};
+use crate::provenance::{
+    fnv1a_64, verify_provenance, verify_provenance_in_dir, ProvenanceIssue, SourceText,
+};
+use crate::query::QueryOrder;
+use crate::registry::{parse_any, parse_any_bundle, AdapterRegistry, MIN_DETECTION_CONFIDENCE};
+use crate::schema::{
+    document_version, log_from_json, log_json_schema, MigrationRegistry, SchemaError,
+    LOG_SCHEMA_VERSION,
+};
+use crate::series::{Series, SeriesSample, TimeSeries, ENERGY_TOTAL};
+use crate::streaming::{BinarySink, EventSink, LogSink, StreamSource, StreamingAdapter};
+use crate::symbol::{Symbol, SymbolTable};
+use crate::units::{Dimension, Quantity, UnitError};
 use crate::vasp_adapter::{
     classify_incar_parameter, parse_incar, parse_oszicar, parse_outcar, VaspAdapter,
 };
 
-/// Helper: initialize the global event ID counter once for the test process.
-fn setup() {
-    static INIT_EVENT_COUNTER: Once = Once::new();
-    INIT_EVENT_COUNTER.call_once(reset_event_id_counter);
-}
-
 /// Helper: create a minimal provenance anchor for tests.
 fn test_provenance() -> ProvenanceAnchor {
     ProvenanceAnchor {
-        source_file: "test.log".to_string(),
+        source_file: "test.log".into(),
         source_location: SourceLocation::LineRange { start: 1, end: 1 },
         raw_hash: 0,
     }
@@ -37,11 +54,15 @@ fn test_spec() -> ExperimentSpec {
             id: SpecElementId(1),
             description: "System must be solvated".to_string(),
             layer: Layer::Theory,
+            target: None,
+            predicate: None,
         }],
         postconditions: vec![ContractTerm {
             id: SpecElementId(2),
             description: "Energy must be finite".to_string(),
             layer: Layer::Implementation,
+            target: None,
+            predicate: None,
         }],
         predictions: Vec::new(),
         interventions: Vec::new(),
@@ -69,7 +90,7 @@ fn test_experiment_ref() -> ExperimentRef {
 
 #[test]
 fn test_event_construction() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let event = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ExecutionStatus {
@@ -81,20 +102,105 @@ fn test_event_construction() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
 
     assert_eq!(event.layer, Layer::Implementation);
     assert_eq!(event.boundary, BoundaryClassification::PrimaryLayer);
     assert!(event.causal_refs.is_empty());
 }
 
+#[test]
+fn test_try_build_reports_all_missing_fields() {
+    let mut ids = EventIdAllocator::new();
+    let err = TraceEventBuilder::new().try_build(&mut ids).unwrap_err();
+    assert_eq!(
+        err.issues,
+        vec![
+            BuildIssue::MissingLayer,
+            BuildIssue::MissingKind,
+            BuildIssue::MissingTemporal
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        "layer is required; kind is required; temporal is required"
+    );
+    // Failed builds do not consume an ID.
+    assert_eq!(ids.peek(), EventId(1));
+}
+
+#[test]
+fn test_try_build_checks_invariants() {
+    let mut ids = EventIdAllocator::new();
+    let err = TraceEventBuilder::new()
+        .layer(Layer::Methodology)
+        .kind(EventKind::ExecutionStatus {
+            status: ExecutionOutcome::Success,
+            framework_error_id: None,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .causal_refs(vec![EventId(1)])
+        .confidence(ConfidenceMeta {
+            completeness: Completeness::Derived {
+                from_elements: vec![],
+            },
+            field_coverage: 1.5,
+            notes: vec![],
+        })
+        .try_build(&mut ids)
+        .unwrap_err();
+
+    assert_eq!(
+        err.issues,
+        vec![
+            BuildIssue::FieldCoverageOutOfRange(1.5),
+            BuildIssue::EmptyDerivation,
+            BuildIssue::SelfCausalRef(EventId(1)),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "TraceEventBuilder: kind is required")]
+fn test_build_panics_on_missing_field() {
+    TraceEventBuilder::new()
+        .layer(Layer::Theory)
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .build(&mut EventIdAllocator::new());
+}
+
+#[test]
+fn test_build_error_converts_to_adapter_error() {
+    let build_error = TraceEventBuilder::new()
+        .try_build(&mut EventIdAllocator::new())
+        .unwrap_err();
+    let adapter_error = AdapterError::from(build_error.clone());
+
+    assert!(matches!(
+        &adapter_error,
+        AdapterError::InvalidEvent(err) if *err == build_error
+    ));
+    assert!(adapter_error
+        .to_string()
+        .starts_with("Invalid event: layer is required"));
+    assert!(std::error::Error::source(&adapter_error).is_some());
+}
+
 #[test]
 fn test_layer_classification() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let theory_event = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "force_field".to_string(),
+            name: "force_field".into(),
             specified_value: None,
             actual_value: Value::KnownCat("amber14".to_string()),
             units: None,
@@ -105,7 +211,7 @@ fn test_layer_classification() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
 
     let impl_event = TraceEventBuilder::new()
         .layer(Layer::Implementation)
@@ -122,7 +228,7 @@ fn test_layer_classification() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
 
     assert_eq!(theory_event.layer, Layer::Theory);
     assert_eq!(impl_event.layer, Layer::Implementation);
@@ -131,7 +237,7 @@ fn test_layer_classification() {
 
 #[test]
 fn test_boundary_classification() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     // DualAnnotated: timestep is primarily Methodology but also Implementation
     let dual_event = TraceEventBuilder::new()
         .layer(Layer::Methodology)
@@ -140,10 +246,10 @@ fn test_boundary_classification() {
             rationale: "Timestep affects numerical stability".to_string(),
         })
         .kind(EventKind::ParameterRecord {
-            name: "dt".to_string(),
+            name: "dt".into(),
             specified_value: None,
-            actual_value: Value::Known(0.002, "ps".to_string()),
-            units: Some("ps".to_string()),
+            actual_value: Value::Known(0.002, "ps".into()),
+            units: Some("ps".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -151,7 +257,7 @@ fn test_boundary_classification() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
 
     match &dual_event.boundary {
         BoundaryClassification::DualAnnotated {
@@ -172,7 +278,7 @@ fn test_boundary_classification() {
             context_note: "ALGO is Theory-adjacent for pathological systems".to_string(),
         })
         .kind(EventKind::ParameterRecord {
-            name: "ALGO".to_string(),
+            name: "ALGO".into(),
             specified_value: None,
             actual_value: Value::KnownCat("Normal".to_string()),
             units: None,
@@ -183,7 +289,7 @@ fn test_boundary_classification() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
 
     match &ctx_event.boundary {
         BoundaryClassification::ContextDependent {
@@ -199,13 +305,13 @@ fn test_boundary_classification() {
 
 #[test]
 fn test_log_append_and_query_by_layer() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "force_field".to_string(),
+                    name: "force_field".into(),
                     specified_value: None,
                     actual_value: Value::KnownCat("amber14".to_string()),
                     units: None,
@@ -216,7 +322,7 @@ fn test_log_append_and_query_by_layer() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -230,7 +336,7 @@ fn test_log_append_and_query_by_layer() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -248,7 +354,7 @@ fn test_log_append_and_query_by_layer() {
                     wall_clock_ns: None,
                     logical_sequence: 3,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -265,16 +371,16 @@ fn test_log_append_and_query_by_layer() {
 
 #[test]
 fn test_by_id_index_populated() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "alpha".to_string(),
+                    name: "alpha".into(),
                     specified_value: None,
-                    actual_value: Value::Known(1.0, "nm".to_string()),
-                    units: Some("nm".to_string()),
+                    actual_value: Value::Known(1.0, "nm".into()),
+                    units: Some("nm".into()),
                     observation_mode: ObservationMode::Observational,
                 })
                 .temporal(TemporalCoord {
@@ -282,7 +388,7 @@ fn test_by_id_index_populated() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -296,7 +402,7 @@ fn test_by_id_index_populated() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -308,13 +414,13 @@ fn test_by_id_index_populated() {
 
 #[test]
 fn test_by_id_index_correct_positions() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "force_field".to_string(),
+                    name: "force_field".into(),
                     specified_value: None,
                     actual_value: Value::KnownCat("amber14".to_string()),
                     units: None,
@@ -325,7 +431,7 @@ fn test_by_id_index_correct_positions() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -343,13 +449,13 @@ fn test_by_id_index_correct_positions() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Implementation)
                 .kind(EventKind::EnergyRecord {
-                    total: Value::Known(-100.0, "kJ/mol".to_string()),
+                    total: Value::Known(-100.0, "kJ/mol".into()),
                     components: vec![],
                 })
                 .temporal(TemporalCoord {
@@ -357,7 +463,7 @@ fn test_by_id_index_correct_positions() {
                     wall_clock_ns: None,
                     logical_sequence: 3,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -373,7 +479,6 @@ fn test_by_id_index_correct_positions() {
 
 #[test]
 fn test_by_id_serde_roundtrip() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let original = adapter.parse_trace("").unwrap();
 
@@ -389,7 +494,6 @@ fn test_by_id_serde_roundtrip() {
 
 #[test]
 fn test_overlay_empty_log() {
-    setup();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec()).build();
     let overlay = CausalOverlay::from_log(&log);
 
@@ -400,16 +504,16 @@ fn test_overlay_empty_log() {
 
 #[test]
 fn test_overlay_one_to_one_mapping() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "a".to_string(),
+                    name: "a".into(),
                     specified_value: None,
-                    actual_value: Value::Known(1.0, "nm".to_string()),
-                    units: Some("nm".to_string()),
+                    actual_value: Value::Known(1.0, "nm".into()),
+                    units: Some("nm".into()),
                     observation_mode: ObservationMode::Observational,
                 })
                 .temporal(TemporalCoord {
@@ -417,7 +521,7 @@ fn test_overlay_one_to_one_mapping() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -431,7 +535,7 @@ fn test_overlay_one_to_one_mapping() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -444,7 +548,7 @@ fn test_overlay_one_to_one_mapping() {
 
 #[test]
 fn test_overlay_dag_node_index() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
@@ -459,7 +563,7 @@ fn test_overlay_dag_node_index() {
                     logical_sequence: 1,
                 })
                 .dag_node_ref("node_x".to_string())
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -474,16 +578,16 @@ fn test_overlay_dag_node_index() {
                     logical_sequence: 2,
                 })
                 .dag_node_ref("node_x".to_string())
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "alpha".to_string(),
+                    name: "alpha".into(),
                     specified_value: None,
-                    actual_value: Value::Known(2.0, "nm".to_string()),
-                    units: Some("nm".to_string()),
+                    actual_value: Value::Known(2.0, "nm".into()),
+                    units: Some("nm".into()),
                     observation_mode: ObservationMode::Observational,
                 })
                 .temporal(TemporalCoord {
@@ -492,7 +596,7 @@ fn test_overlay_dag_node_index() {
                     logical_sequence: 3,
                 })
                 .dag_node_ref("node_y".to_string())
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -503,14 +607,14 @@ fn test_overlay_dag_node_index() {
 
 #[test]
 fn test_overlay_causal_parents_resolution() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let event1 = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "p0".to_string(),
+            name: "p0".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "nm".to_string()),
-            units: Some("nm".to_string()),
+            actual_value: Value::Known(1.0, "nm".into()),
+            units: Some("nm".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -518,7 +622,7 @@ fn test_overlay_causal_parents_resolution() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let event1_id = event1.id;
 
     let event2 = TraceEventBuilder::new()
@@ -533,7 +637,7 @@ fn test_overlay_causal_parents_resolution() {
             logical_sequence: 2,
         })
         .causal_refs(vec![event1_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(event1)
@@ -547,14 +651,14 @@ fn test_overlay_causal_parents_resolution() {
 
 #[test]
 fn test_overlay_dangling_ref_skipped() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let parent = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "k".to_string(),
+            name: "k".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "nm".to_string()),
-            units: Some("nm".to_string()),
+            actual_value: Value::Known(1.0, "nm".into()),
+            units: Some("nm".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -562,7 +666,7 @@ fn test_overlay_dangling_ref_skipped() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let parent_id = parent.id;
 
     let child = TraceEventBuilder::new()
@@ -577,7 +681,7 @@ fn test_overlay_dangling_ref_skipped() {
             logical_sequence: 2,
         })
         .causal_refs(vec![parent_id, EventId(999_999)])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(parent)
@@ -590,14 +694,14 @@ fn test_overlay_dangling_ref_skipped() {
 
 #[test]
 fn test_overlay_linear_chain_ancestors() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let e0 = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "e0".to_string(),
+            name: "e0".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "nm".to_string()),
-            units: Some("nm".to_string()),
+            actual_value: Value::Known(1.0, "nm".into()),
+            units: Some("nm".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -605,7 +709,7 @@ fn test_overlay_linear_chain_ancestors() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let e0_id = e0.id;
 
     let e1 = TraceEventBuilder::new()
@@ -620,7 +724,7 @@ fn test_overlay_linear_chain_ancestors() {
             logical_sequence: 2,
         })
         .causal_refs(vec![e0_id])
-        .build();
+        .build(&mut ids);
     let e1_id = e1.id;
 
     let e2 = TraceEventBuilder::new()
@@ -635,7 +739,7 @@ fn test_overlay_linear_chain_ancestors() {
             logical_sequence: 3,
         })
         .causal_refs(vec![e1_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(e0)
@@ -649,14 +753,14 @@ fn test_overlay_linear_chain_ancestors() {
 
 #[test]
 fn test_overlay_diamond_ancestors() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let root = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "root".to_string(),
+            name: "root".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "nm".to_string()),
-            units: Some("nm".to_string()),
+            actual_value: Value::Known(1.0, "nm".into()),
+            units: Some("nm".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -664,7 +768,7 @@ fn test_overlay_diamond_ancestors() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let root_id = root.id;
 
     let left = TraceEventBuilder::new()
@@ -679,7 +783,7 @@ fn test_overlay_diamond_ancestors() {
             logical_sequence: 2,
         })
         .causal_refs(vec![root_id])
-        .build();
+        .build(&mut ids);
     let left_id = left.id;
 
     let right = TraceEventBuilder::new()
@@ -694,7 +798,7 @@ fn test_overlay_diamond_ancestors() {
             logical_sequence: 3,
         })
         .causal_refs(vec![root_id])
-        .build();
+        .build(&mut ids);
     let right_id = right.id;
 
     let sink = TraceEventBuilder::new()
@@ -709,7 +813,7 @@ fn test_overlay_diamond_ancestors() {
             logical_sequence: 4,
         })
         .causal_refs(vec![left_id, right_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(root)
@@ -726,16 +830,16 @@ fn test_overlay_diamond_ancestors() {
 
 #[test]
 fn test_overlay_serde_roundtrip() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Theory)
                 .kind(EventKind::ParameterRecord {
-                    name: "a".to_string(),
+                    name: "a".into(),
                     specified_value: None,
-                    actual_value: Value::Known(1.0, "nm".to_string()),
-                    units: Some("nm".to_string()),
+                    actual_value: Value::Known(1.0, "nm".into()),
+                    units: Some("nm".into()),
                     observation_mode: ObservationMode::Observational,
                 })
                 .temporal(TemporalCoord {
@@ -744,7 +848,7 @@ fn test_overlay_serde_roundtrip() {
                     logical_sequence: 1,
                 })
                 .dag_node_ref("node_a".to_string())
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -758,7 +862,7 @@ fn test_overlay_serde_roundtrip() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
     let original = CausalOverlay::from_log(&log);
@@ -777,21 +881,21 @@ fn test_overlay_serde_roundtrip() {
 
 #[test]
 fn test_detect_confounders_all_controlled() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let mut spec = test_spec();
     spec.controlled_variables = vec![ControlledVariable {
         id: SpecElementId(99),
         parameter: "conf".to_string(),
-        held_value: Value::Known(1.0, "arb".to_string()),
+        held_value: Value::Known(1.0, "arb".into()),
     }];
 
     let conf = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "conf".to_string(),
+            name: "conf".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -800,16 +904,16 @@ fn test_detect_confounders_all_controlled() {
             logical_sequence: 1,
         })
         .dag_node_ref("conf".to_string())
-        .build();
+        .build(&mut ids);
     let conf_id = conf.id;
 
     let intervention = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(2.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -819,14 +923,14 @@ fn test_detect_confounders_all_controlled() {
         })
         .causal_refs(vec![conf_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(10.0, "unit".to_string()),
+            value: Value::Known(10.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -838,7 +942,7 @@ fn test_detect_confounders_all_controlled() {
         })
         .causal_refs(vec![conf_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(conf)
@@ -853,16 +957,16 @@ fn test_detect_confounders_all_controlled() {
 
 #[test]
 fn test_detect_confounders_uncontrolled_detected() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let conf = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "conf".to_string(),
+            name: "conf".into(),
             specified_value: None,
-            actual_value: Value::Known(5.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(5.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -871,16 +975,16 @@ fn test_detect_confounders_uncontrolled_detected() {
             logical_sequence: 1,
         })
         .dag_node_ref("conf".to_string())
-        .build();
+        .build(&mut ids);
     let conf_id = conf.id;
 
     let intervention = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(2.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -890,14 +994,14 @@ fn test_detect_confounders_uncontrolled_detected() {
         })
         .causal_refs(vec![conf_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(12.0, "unit".to_string()),
+            value: Value::Known(12.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -909,7 +1013,7 @@ fn test_detect_confounders_uncontrolled_detected() {
         })
         .causal_refs(vec![conf_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(conf)
@@ -927,16 +1031,16 @@ fn test_detect_confounders_uncontrolled_detected() {
 
 #[test]
 fn test_detect_confounders_intervention_excluded() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let intervention_root = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(1.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -945,16 +1049,16 @@ fn test_detect_confounders_intervention_excluded() {
             logical_sequence: 1,
         })
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
     let intervention_root_id = intervention_root.id;
 
     let intervention_child = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(2.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -964,15 +1068,15 @@ fn test_detect_confounders_intervention_excluded() {
         })
         .causal_refs(vec![intervention_root_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
     let intervention_child_id = intervention_child.id;
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(8.0, "unit".to_string()),
+            value: Value::Known(8.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -984,7 +1088,7 @@ fn test_detect_confounders_intervention_excluded() {
         })
         .causal_refs(vec![intervention_child_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(intervention_root)
@@ -999,16 +1103,16 @@ fn test_detect_confounders_intervention_excluded() {
 
 #[test]
 fn test_detect_confounders_no_common_ancestors() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let left_root = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "left_root".to_string(),
+            name: "left_root".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1017,16 +1121,16 @@ fn test_detect_confounders_no_common_ancestors() {
             logical_sequence: 1,
         })
         .dag_node_ref("left_root".to_string())
-        .build();
+        .build(&mut ids);
     let left_root_id = left_root.id;
 
     let right_root = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "right_root".to_string(),
+            name: "right_root".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -1035,16 +1139,16 @@ fn test_detect_confounders_no_common_ancestors() {
             logical_sequence: 2,
         })
         .dag_node_ref("right_root".to_string())
-        .build();
+        .build(&mut ids);
     let right_root_id = right_root.id;
 
     let intervention = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(3.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(3.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -1054,14 +1158,14 @@ fn test_detect_confounders_no_common_ancestors() {
         })
         .causal_refs(vec![right_root_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(9.0, "unit".to_string()),
+            value: Value::Known(9.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1073,7 +1177,7 @@ fn test_detect_confounders_no_common_ancestors() {
         })
         .causal_refs(vec![left_root_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(left_root)
@@ -1089,15 +1193,15 @@ fn test_detect_confounders_no_common_ancestors() {
 
 #[test]
 fn test_detect_confounders_unknown_variable() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let event = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(1.0, "unit".to_string()),
+            value: Value::Known(1.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1107,7 +1211,7 @@ fn test_detect_confounders_unknown_variable() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(event)
@@ -1124,16 +1228,16 @@ fn test_detect_confounders_unknown_variable() {
 
 #[test]
 fn test_detect_confounders_multiple_confounders() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let conf_a = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "conf_a".to_string(),
+            name: "conf_a".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1142,16 +1246,16 @@ fn test_detect_confounders_multiple_confounders() {
             logical_sequence: 1,
         })
         .dag_node_ref("conf_a".to_string())
-        .build();
+        .build(&mut ids);
     let conf_a_id = conf_a.id;
 
     let conf_b = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "conf_b".to_string(),
+            name: "conf_b".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1160,16 +1264,16 @@ fn test_detect_confounders_multiple_confounders() {
             logical_sequence: 2,
         })
         .dag_node_ref("conf_b".to_string())
-        .build();
+        .build(&mut ids);
     let conf_b_id = conf_b.id;
 
     let intervention = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(4.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(4.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -1179,14 +1283,14 @@ fn test_detect_confounders_multiple_confounders() {
         })
         .causal_refs(vec![conf_a_id, conf_b_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(7.0, "unit".to_string()),
+            value: Value::Known(7.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1198,7 +1302,7 @@ fn test_detect_confounders_multiple_confounders() {
         })
         .causal_refs(vec![conf_a_id, conf_b_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(conf_a)
@@ -1216,16 +1320,16 @@ fn test_detect_confounders_multiple_confounders() {
 
 #[test]
 fn test_detect_confounders_transitive_chain() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec();
 
     let root = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "root".to_string(),
+            name: "root".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1233,16 +1337,16 @@ fn test_detect_confounders_transitive_chain() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let root_id = root.id;
 
     let mid = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "mid".to_string(),
+            name: "mid".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1252,16 +1356,16 @@ fn test_detect_confounders_transitive_chain() {
         })
         .causal_refs(vec![root_id])
         .dag_node_ref("mid".to_string())
-        .build();
+        .build(&mut ids);
     let mid_id = mid.id;
 
     let intervention = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dose".to_string(),
+            name: "dose".into(),
             specified_value: None,
-            actual_value: Value::Known(5.0, "mg".to_string()),
-            units: Some("mg".to_string()),
+            actual_value: Value::Known(5.0, "mg".into()),
+            units: Some("mg".into()),
             observation_mode: ObservationMode::Interventional,
         })
         .temporal(TemporalCoord {
@@ -1271,14 +1375,14 @@ fn test_detect_confounders_transitive_chain() {
         })
         .causal_refs(vec![mid_id])
         .dag_node_ref("dose".to_string())
-        .build();
+        .build(&mut ids);
 
     let observable = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "outcome".to_string(),
+            variable_name: "outcome".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(6.0, "unit".to_string()),
+            value: Value::Known(6.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1290,7 +1394,7 @@ fn test_detect_confounders_transitive_chain() {
         })
         .causal_refs(vec![mid_id])
         .dag_node_ref("outcome".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(root)
@@ -1308,7 +1412,6 @@ fn test_detect_confounders_transitive_chain() {
 
 #[test]
 fn test_compare_predictions_empty_log() {
-    setup();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec()).build();
     let overlay = CausalOverlay::from_log(&log);
 
@@ -1317,12 +1420,13 @@ fn test_compare_predictions_empty_log() {
 
 #[test]
 fn test_compare_predictions_no_comparison_events() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![PredictionRecord {
         id: SpecElementId(1),
         variable: "outcome".to_string(),
-        predicted_value: Value::Known(10.0, "unit".to_string()),
+        predicted_value: Value::Known(10.0, "unit".into()),
         tolerance: Some(0.5),
+        uncertainty: None,
     }]);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
@@ -1330,9 +1434,9 @@ fn test_compare_predictions_no_comparison_events() {
             TraceEventBuilder::new()
                 .layer(Layer::Implementation)
                 .kind(EventKind::ObservableMeasurement {
-                    variable_name: "outcome".to_string(),
+                    variable_name: "outcome".into(),
                     measurement_method: "synthetic".to_string(),
-                    value: Value::Known(10.1, "unit".to_string()),
+                    value: Value::Known(10.1, "unit".into()),
                     uncertainty: None,
                     conditions: "test".to_string(),
                     observation_mode: ObservationMode::Observational,
@@ -1342,7 +1446,7 @@ fn test_compare_predictions_no_comparison_events() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
     let overlay = CausalOverlay::from_log(&log);
@@ -1352,20 +1456,21 @@ fn test_compare_predictions_no_comparison_events() {
 
 #[test]
 fn test_compare_predictions_matched_agreement() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![PredictionRecord {
         id: SpecElementId(101),
         variable: "energy".to_string(),
-        predicted_value: Value::Known(-100.0, "kJ/mol".to_string()),
+        predicted_value: Value::Known(-100.0, "kJ/mol".into()),
         tolerance: Some(5.0),
+        uncertainty: None,
     }]);
 
     let observation = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "energy".to_string(),
+            variable_name: "energy".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(-98.0, "kJ/mol".to_string()),
+            value: Value::Known(-98.0, "kJ/mol".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1375,7 +1480,7 @@ fn test_compare_predictions_matched_agreement() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let observation_id = observation.id;
 
     let comparison = TraceEventBuilder::new()
@@ -1387,6 +1492,7 @@ fn test_compare_predictions_matched_agreement() {
                 agreement: true,
                 divergence: None,
                 detail: "within tolerance".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1394,7 +1500,7 @@ fn test_compare_predictions_matched_agreement() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(observation)
@@ -1413,20 +1519,21 @@ fn test_compare_predictions_matched_agreement() {
 
 #[test]
 fn test_compare_predictions_matched_falsified() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![PredictionRecord {
         id: SpecElementId(202),
         variable: "rdf_peak".to_string(),
-        predicted_value: Value::Known(1.5, "arb".to_string()),
+        predicted_value: Value::Known(1.5, "arb".into()),
         tolerance: Some(0.1),
+        uncertainty: None,
     }]);
 
     let observation = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "rdf_peak".to_string(),
+            variable_name: "rdf_peak".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(2.2, "arb".to_string()),
+            value: Value::Known(2.2, "arb".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1436,7 +1543,7 @@ fn test_compare_predictions_matched_falsified() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let observation_id = observation.id;
 
     let comparison = TraceEventBuilder::new()
@@ -1448,6 +1555,7 @@ fn test_compare_predictions_matched_falsified() {
                 agreement: false,
                 divergence: Some(DivergenceMeasure::AbsoluteDifference(0.7)),
                 detail: "outside tolerance".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1455,7 +1563,7 @@ fn test_compare_predictions_matched_falsified() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(observation)
@@ -1477,20 +1585,21 @@ fn test_compare_predictions_matched_falsified() {
 
 #[test]
 fn test_compare_predictions_unresolvable_prediction_id() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![PredictionRecord {
         id: SpecElementId(303),
         variable: "pressure".to_string(),
-        predicted_value: Value::Known(1.0, "bar".to_string()),
+        predicted_value: Value::Known(1.0, "bar".into()),
         tolerance: Some(0.05),
+        uncertainty: None,
     }]);
 
     let observation = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "pressure".to_string(),
+            variable_name: "pressure".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(1.2, "bar".to_string()),
+            value: Value::Known(1.2, "bar".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1500,7 +1609,7 @@ fn test_compare_predictions_unresolvable_prediction_id() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let observation_id = observation.id;
 
     let comparison = TraceEventBuilder::new()
@@ -1512,6 +1621,7 @@ fn test_compare_predictions_unresolvable_prediction_id() {
                 agreement: false,
                 divergence: Some(DivergenceMeasure::AbsoluteDifference(0.2)),
                 detail: "malformed prediction id".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1519,7 +1629,7 @@ fn test_compare_predictions_unresolvable_prediction_id() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(observation)
@@ -1535,28 +1645,30 @@ fn test_compare_predictions_unresolvable_prediction_id() {
 
 #[test]
 fn test_compare_predictions_multiple_predictions() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![
         PredictionRecord {
             id: SpecElementId(1),
             variable: "var_a".to_string(),
-            predicted_value: Value::Known(10.0, "unit".to_string()),
+            predicted_value: Value::Known(10.0, "unit".into()),
             tolerance: Some(1.0),
+            uncertainty: None,
         },
         PredictionRecord {
             id: SpecElementId(2),
             variable: "var_b".to_string(),
-            predicted_value: Value::Known(20.0, "unit".to_string()),
+            predicted_value: Value::Known(20.0, "unit".into()),
             tolerance: Some(1.0),
+            uncertainty: None,
         },
     ]);
 
     let observation_a = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "var_a".to_string(),
+            variable_name: "var_a".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(10.2, "unit".to_string()),
+            value: Value::Known(10.2, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1566,15 +1678,15 @@ fn test_compare_predictions_multiple_predictions() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let observation_a_id = observation_a.id;
 
     let observation_b = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "var_b".to_string(),
+            variable_name: "var_b".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(22.0, "unit".to_string()),
+            value: Value::Known(22.0, "unit".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1584,7 +1696,7 @@ fn test_compare_predictions_multiple_predictions() {
             wall_clock_ns: None,
             logical_sequence: 2,
         })
-        .build();
+        .build(&mut ids);
     let observation_b_id = observation_b.id;
 
     let comparison_a = TraceEventBuilder::new()
@@ -1596,6 +1708,7 @@ fn test_compare_predictions_multiple_predictions() {
                 agreement: true,
                 divergence: None,
                 detail: "prediction A matched".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1603,7 +1716,7 @@ fn test_compare_predictions_multiple_predictions() {
             wall_clock_ns: None,
             logical_sequence: 3,
         })
-        .build();
+        .build(&mut ids);
 
     let comparison_b = TraceEventBuilder::new()
         .layer(Layer::Theory)
@@ -1614,6 +1727,7 @@ fn test_compare_predictions_multiple_predictions() {
                 agreement: false,
                 divergence: Some(DivergenceMeasure::AbsoluteDifference(2.0)),
                 detail: "prediction B falsified".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1621,7 +1735,7 @@ fn test_compare_predictions_multiple_predictions() {
             wall_clock_ns: None,
             logical_sequence: 4,
         })
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(observation_a)
@@ -1643,20 +1757,21 @@ fn test_compare_predictions_multiple_predictions() {
 
 #[test]
 fn test_compare_predictions_with_dag_node_ref() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let spec = test_spec_with_predictions(vec![PredictionRecord {
         id: SpecElementId(7),
         variable: "temperature".to_string(),
-        predicted_value: Value::Known(300.0, "K".to_string()),
+        predicted_value: Value::Known(300.0, "K".into()),
         tolerance: Some(1.0),
+        uncertainty: None,
     }]);
 
     let observation = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ObservableMeasurement {
-            variable_name: "temperature".to_string(),
+            variable_name: "temperature".into(),
             measurement_method: "synthetic".to_string(),
-            value: Value::Known(300.5, "K".to_string()),
+            value: Value::Known(300.5, "K".into()),
             uncertainty: None,
             conditions: "test".to_string(),
             observation_mode: ObservationMode::Observational,
@@ -1666,7 +1781,7 @@ fn test_compare_predictions_with_dag_node_ref() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let observation_id = observation.id;
 
     let comparison = TraceEventBuilder::new()
@@ -1678,6 +1793,7 @@ fn test_compare_predictions_with_dag_node_ref() {
                 agreement: true,
                 divergence: None,
                 detail: "matched".to_string(),
+                uncertainty: None,
             },
         })
         .temporal(TemporalCoord {
@@ -1686,7 +1802,7 @@ fn test_compare_predictions_with_dag_node_ref() {
             logical_sequence: 2,
         })
         .dag_node_ref("dag.compare.temperature".to_string())
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
         .add_event(observation)
@@ -1702,9 +1818,307 @@ fn test_compare_predictions_with_dag_node_ref() {
     );
 }
 
+#[test]
+fn test_uncertainty_summary_from_samples() {
+    let summary = UncertaintySummary::from_samples(&[1.0, 2.0, 3.0, 4.0], "mean/sem");
+    let PointUncertainty::Summary {
+        estimate,
+        standard_error,
+        interval,
+        sample_size,
+        method_ref,
+    } = &summary.point
+    else {
+        panic!("Expected Summary, got {:?}", summary.point);
+    };
+
+    assert!((estimate - 2.5).abs() < 1e-12);
+    // Sample variance 5/3, SEM sqrt(5/12).
+    let expected_se = (5.0_f64 / 12.0).sqrt();
+    assert!((standard_error.unwrap() - expected_se).abs() < 1e-12);
+    let interval = interval.as_ref().unwrap();
+    assert!((interval.level - 0.95).abs() < f64::EPSILON);
+    assert!(interval.lower < 2.5 && interval.upper > 2.5);
+    assert_eq!(*sample_size, Some(4));
+    assert_eq!(method_ref, "mean/sem");
+    assert!(matches!(
+        summary.distribution,
+        Some(DistributionPayload::Samples(ref samples)) if samples.len() == 4
+    ));
+
+    let single = UncertaintySummary::from_samples(&[1.0], "mean/sem");
+    assert_eq!(
+        single.point,
+        PointUncertainty::NoUncertainty {
+            reason: UncertaintyUnavailableReason::InsufficientSamples
+        }
+    );
+    assert_eq!(single.standard_error(), None);
+}
+
+#[test]
+fn test_uncertainty_standard_error_from_interval() {
+    let summary = UncertaintySummary {
+        point: PointUncertainty::Summary {
+            estimate: 10.0,
+            standard_error: None,
+            interval: Some(IntervalEstimate {
+                lower: 10.0 - 1.959_963_984_540_054,
+                upper: 10.0 + 1.959_963_984_540_054,
+                level: 0.95,
+            }),
+            sample_size: None,
+            method_ref: "reported CI".to_string(),
+        },
+        distribution: None,
+    };
+    assert!((summary.standard_error().unwrap() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_uncertainty_z_score_and_bayes_factor() {
+    let observation = UncertaintySummary {
+        point: PointUncertainty::Summary {
+            estimate: -98.0,
+            standard_error: Some(3.0),
+            interval: None,
+            sample_size: Some(100),
+            method_ref: "block averaging".to_string(),
+        },
+        distribution: None,
+    };
+    let prediction = UncertaintySummary {
+        point: PointUncertainty::Summary {
+            estimate: -100.0,
+            standard_error: Some(4.0),
+            interval: None,
+            sample_size: None,
+            method_ref: "model ensemble".to_string(),
+        },
+        distribution: None,
+    };
+
+    // Combined SE = 5.
+    let z = UncertaintySummary::z_score(-98.0, -100.0, Some(&observation), Some(&prediction));
+    assert!((z.unwrap() - 0.4).abs() < 1e-12);
+    assert_eq!(UncertaintySummary::z_score(-98.0, -100.0, None, None), None);
+
+    // Observation at the prediction favours H0; a far observation favours H1.
+    let near = UncertaintySummary::gaussian_bayes_factor(-100.0, -100.0, &observation, &prediction)
+        .unwrap();
+    let far = UncertaintySummary::gaussian_bayes_factor(-70.0, -100.0, &observation, &prediction)
+        .unwrap();
+    assert!((near - 5.0 / 3.0).abs() < 1e-12);
+    assert!(far < 1.0);
+
+    let unavailable = UncertaintySummary::unavailable(UncertaintyUnavailableReason::NotComputed);
+    assert_eq!(
+        UncertaintySummary::gaussian_bayes_factor(-98.0, -100.0, &unavailable, &prediction),
+        None
+    );
+}
+
+#[test]
+fn test_compare_predictions_z_score_from_uncertainty() {
+    let mut ids = EventIdAllocator::new();
+    let spec = test_spec_with_predictions(vec![PredictionRecord {
+        id: SpecElementId(7),
+        variable: "energy".to_string(),
+        predicted_value: Value::Known(-100.0, "kJ/mol".into()),
+        tolerance: None,
+        uncertainty: Some(UncertaintySummary::from_samples(
+            &[-101.0, -99.0],
+            "ensemble",
+        )),
+    }]);
+
+    let observation = TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ObservableMeasurement {
+            variable_name: "energy".into(),
+            measurement_method: "synthetic".to_string(),
+            value: Value::Known(-96.0, "kJ/mol".into()),
+            uncertainty: Some(UncertaintySummary::from_samples(&[-97.0, -95.0], "blocks")),
+            conditions: "test".to_string(),
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .build(&mut ids);
+    let observation_id = observation.id;
+
+    let comparison = TraceEventBuilder::new()
+        .layer(Layer::Theory)
+        .kind(EventKind::ComparisonResult {
+            prediction_id: "7".to_string(),
+            observation_id,
+            result: ComparisonOutcome {
+                agreement: false,
+                divergence: Some(DivergenceMeasure::AbsoluteDifference(4.0)),
+                detail: "synthetic".to_string(),
+                uncertainty: None,
+            },
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 1,
+            wall_clock_ns: None,
+            logical_sequence: 2,
+        })
+        .build(&mut ids);
+
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
+        .add_event(observation)
+        .add_event(comparison)
+        .build();
+    let comparisons = CausalOverlay::from_log(&log).compare_predictions(&log);
+
+    // Each side has SEM 1.0, so the combined SE is sqrt(2).
+    let z_score = comparisons[0].z_score.expect("z-score from uncertainty");
+    assert!((z_score - 4.0 / 2.0_f64.sqrt()).abs() < 1e-12);
+}
+
+#[test]
+fn test_compare_predictions_z_score_from_divergence_uncertainty() {
+    let mut ids = EventIdAllocator::new();
+    let comparison = TraceEventBuilder::new()
+        .layer(Layer::Theory)
+        .kind(EventKind::ComparisonResult {
+            prediction_id: "unparseable".to_string(),
+            observation_id: EventId(0),
+            result: ComparisonOutcome {
+                agreement: false,
+                divergence: Some(DivergenceMeasure::AbsoluteDifference(3.0)),
+                detail: "synthetic".to_string(),
+                uncertainty: Some(UncertaintySummary {
+                    point: PointUncertainty::Summary {
+                        estimate: 3.0,
+                        standard_error: Some(1.5),
+                        interval: None,
+                        sample_size: None,
+                        method_ref: "bootstrap".to_string(),
+                    },
+                    distribution: None,
+                }),
+            },
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .build(&mut ids);
+
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
+        .add_event(comparison)
+        .build();
+    let comparisons = CausalOverlay::from_log(&log).compare_predictions(&log);
+    assert_eq!(comparisons[0].z_score, Some(2.0));
+}
+
+#[test]
+fn test_uncertainty_summary_serde_roundtrip() {
+    let summary = UncertaintySummary {
+        point: PointUncertainty::Summary {
+            estimate: 0.5,
+            standard_error: Some(0.1),
+            interval: Some(IntervalEstimate {
+                lower: 0.3,
+                upper: 0.7,
+                level: 0.95,
+            }),
+            sample_size: Some(40),
+            method_ref: "MCSE".to_string(),
+        },
+        distribution: Some(DistributionPayload::Histogram {
+            bin_edges: vec![0.0, 0.5, 1.0],
+            counts: vec![18, 22],
+        }),
+    };
+    let json = serde_json::to_string(&summary).expect("Serialization must succeed");
+    let restored: UncertaintySummary =
+        serde_json::from_str(&json).expect("Deserialization must succeed");
+    assert_eq!(restored, summary);
+
+    // Records serialized before the uncertainty field existed still load.
+    let legacy_prediction =
+        r#"{"id":1,"variable":"x","predicted_value":{"KnownCat":"a"},"tolerance":null}"#;
+    let prediction: PredictionRecord =
+        serde_json::from_str(legacy_prediction).expect("Legacy prediction must deserialize");
+    assert_eq!(prediction.uncertainty, None);
+}
+
+#[test]
+fn test_compare_predictions_z_score_converts_units() {
+    let mut ids = EventIdAllocator::new();
+    let spec = test_spec_with_predictions(vec![PredictionRecord {
+        id: SpecElementId(8),
+        variable: "energy".to_string(),
+        predicted_value: Value::Known(-100.0, "kJ/mol".into()),
+        tolerance: None,
+        uncertainty: None,
+    }]);
+
+    // -24.0 kcal/mol = -100.416 kJ/mol, SE 0.1 kcal/mol = 0.4184 kJ/mol.
+    let observation = TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ObservableMeasurement {
+            variable_name: "energy".into(),
+            measurement_method: "synthetic".to_string(),
+            value: Value::Known(-24.0, "kcal/mol".into()),
+            uncertainty: Some(UncertaintySummary {
+                point: PointUncertainty::Summary {
+                    estimate: -24.0,
+                    standard_error: Some(0.1),
+                    interval: None,
+                    sample_size: None,
+                    method_ref: "blocks".to_string(),
+                },
+                distribution: None,
+            }),
+            conditions: "test".to_string(),
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .build(&mut ids);
+    let observation_id = observation.id;
+    let comparison = TraceEventBuilder::new()
+        .layer(Layer::Theory)
+        .kind(EventKind::ComparisonResult {
+            prediction_id: "8".to_string(),
+            observation_id,
+            result: ComparisonOutcome {
+                agreement: true,
+                divergence: None,
+                detail: "synthetic".to_string(),
+                uncertainty: None,
+            },
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 1,
+            wall_clock_ns: None,
+            logical_sequence: 2,
+        })
+        .build(&mut ids);
+
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
+        .add_event(observation)
+        .add_event(comparison)
+        .build();
+    let comparisons = CausalOverlay::from_log(&log).compare_predictions(&log);
+    let z_score = comparisons[0].z_score.expect("z-score in prediction units");
+    assert!((z_score - (-0.416 / 0.4184)).abs() < 1e-9);
+}
+
 #[test]
 fn test_implicate_no_ancestors() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
@@ -1718,7 +2132,7 @@ fn test_implicate_no_ancestors() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
     let overlay = CausalOverlay::from_log(&log);
@@ -1730,9 +2144,11 @@ fn test_implicate_no_ancestors() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -1741,14 +2157,14 @@ fn test_implicate_no_ancestors() {
 
 #[test]
 fn test_implicate_theory_layer() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let theory_ancestor = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "theory".to_string(),
+            name: "theory".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1757,7 +2173,7 @@ fn test_implicate_theory_layer() {
             logical_sequence: 1,
         })
         .dag_node_ref("theory_ancestor".to_string())
-        .build();
+        .build(&mut ids);
     let theory_ancestor_id = theory_ancestor.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -1772,7 +2188,7 @@ fn test_implicate_theory_layer() {
             logical_sequence: 2,
         })
         .causal_refs(vec![theory_ancestor_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(theory_ancestor)
@@ -1787,9 +2203,11 @@ fn test_implicate_theory_layer() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -1802,7 +2220,7 @@ fn test_implicate_theory_layer() {
 
 #[test]
 fn test_implicate_implementation_layer() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let impl_ancestor = TraceEventBuilder::new()
         .layer(Layer::Implementation)
         .kind(EventKind::ResourceStatus {
@@ -1819,7 +2237,7 @@ fn test_implicate_implementation_layer() {
             logical_sequence: 1,
         })
         .dag_node_ref("impl_ancestor".to_string())
-        .build();
+        .build(&mut ids);
     let impl_ancestor_id = impl_ancestor.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -1834,7 +2252,7 @@ fn test_implicate_implementation_layer() {
             logical_sequence: 2,
         })
         .causal_refs(vec![impl_ancestor_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(impl_ancestor)
@@ -1849,9 +2267,11 @@ fn test_implicate_implementation_layer() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -1863,14 +2283,14 @@ fn test_implicate_implementation_layer() {
 
 #[test]
 fn test_implicate_methodology_layer() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let methodology_ancestor = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "method".to_string(),
+            name: "method".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1879,7 +2299,7 @@ fn test_implicate_methodology_layer() {
             logical_sequence: 1,
         })
         .dag_node_ref("methodology_ancestor".to_string())
-        .build();
+        .build(&mut ids);
     let methodology_ancestor_id = methodology_ancestor.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -1894,7 +2314,7 @@ fn test_implicate_methodology_layer() {
             logical_sequence: 2,
         })
         .causal_refs(vec![methodology_ancestor_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(methodology_ancestor)
@@ -1909,9 +2329,11 @@ fn test_implicate_methodology_layer() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -1923,14 +2345,14 @@ fn test_implicate_methodology_layer() {
 
 #[test]
 fn test_implicate_mixed_layers() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let methodology_ancestor = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "method".to_string(),
+            name: "method".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1939,16 +2361,16 @@ fn test_implicate_mixed_layers() {
             logical_sequence: 1,
         })
         .dag_node_ref("method_node".to_string())
-        .build();
+        .build(&mut ids);
     let methodology_ancestor_id = methodology_ancestor.id;
 
     let theory_ancestor = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "theory".to_string(),
+            name: "theory".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -1957,7 +2379,7 @@ fn test_implicate_mixed_layers() {
             logical_sequence: 2,
         })
         .dag_node_ref("theory_node".to_string())
-        .build();
+        .build(&mut ids);
     let theory_ancestor_id = theory_ancestor.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -1972,7 +2394,7 @@ fn test_implicate_mixed_layers() {
             logical_sequence: 3,
         })
         .causal_refs(vec![methodology_ancestor_id, theory_ancestor_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(methodology_ancestor)
@@ -1988,9 +2410,11 @@ fn test_implicate_mixed_layers() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -2003,14 +2427,14 @@ fn test_implicate_mixed_layers() {
 
 #[test]
 fn test_implicate_depth_ordering() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let far = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "far".to_string(),
+            name: "far".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -2019,16 +2443,16 @@ fn test_implicate_depth_ordering() {
             logical_sequence: 1,
         })
         .dag_node_ref("far_node".to_string())
-        .build();
+        .build(&mut ids);
     let far_id = far.id;
 
     let near = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "near".to_string(),
+            name: "near".into(),
             specified_value: None,
-            actual_value: Value::Known(2.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(2.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -2038,7 +2462,7 @@ fn test_implicate_depth_ordering() {
         })
         .causal_refs(vec![far_id])
         .dag_node_ref("near_node".to_string())
-        .build();
+        .build(&mut ids);
     let near_id = near.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -2053,7 +2477,7 @@ fn test_implicate_depth_ordering() {
             logical_sequence: 3,
         })
         .causal_refs(vec![near_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(far)
@@ -2069,9 +2493,11 @@ fn test_implicate_depth_ordering() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -2084,14 +2510,14 @@ fn test_implicate_depth_ordering() {
 
 #[test]
 fn test_implicate_ancestor_without_dag_node() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let ancestor_without_dag = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "unnamed".to_string(),
+            name: "unnamed".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -2099,7 +2525,7 @@ fn test_implicate_ancestor_without_dag_node() {
             wall_clock_ns: None,
             logical_sequence: 1,
         })
-        .build();
+        .build(&mut ids);
     let ancestor_without_dag_id = ancestor_without_dag.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -2114,7 +2540,7 @@ fn test_implicate_ancestor_without_dag_node() {
             logical_sequence: 2,
         })
         .causal_refs(vec![ancestor_without_dag_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(ancestor_without_dag)
@@ -2129,9 +2555,11 @@ fn test_implicate_ancestor_without_dag_node() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -2140,14 +2568,14 @@ fn test_implicate_ancestor_without_dag_node() {
 
 #[test]
 fn test_implicate_multiple_events_same_dag_node() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let far_shared = TraceEventBuilder::new()
         .layer(Layer::Theory)
         .kind(EventKind::ParameterRecord {
-            name: "shared_far".to_string(),
+            name: "shared_far".into(),
             specified_value: None,
-            actual_value: Value::Known(1.0, "arb".to_string()),
-            units: Some("arb".to_string()),
+            actual_value: Value::Known(1.0, "arb".into()),
+            units: Some("arb".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -2156,7 +2584,7 @@ fn test_implicate_multiple_events_same_dag_node() {
             logical_sequence: 1,
         })
         .dag_node_ref("shared_node".to_string())
-        .build();
+        .build(&mut ids);
     let far_shared_id = far_shared.id;
 
     let near_shared = TraceEventBuilder::new()
@@ -2176,7 +2604,7 @@ fn test_implicate_multiple_events_same_dag_node() {
         })
         .causal_refs(vec![far_shared_id])
         .dag_node_ref("shared_node".to_string())
-        .build();
+        .build(&mut ids);
     let near_shared_id = near_shared.id;
 
     let comparison_event = TraceEventBuilder::new()
@@ -2191,7 +2619,7 @@ fn test_implicate_multiple_events_same_dag_node() {
             logical_sequence: 3,
         })
         .causal_refs(vec![near_shared_id])
-        .build();
+        .build(&mut ids);
 
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(far_shared)
@@ -2207,9 +2635,11 @@ fn test_implicate_multiple_events_same_dag_node() {
             agreement: false,
             divergence: None,
             detail: "synthetic".to_string(),
+            uncertainty: None,
         },
         is_falsified: true,
         dag_node: None,
+        z_score: None,
     };
 
     let implicated = overlay.implicate_causal_nodes(&log, &comparison);
@@ -2224,7 +2654,7 @@ fn test_implicate_multiple_events_same_dag_node() {
 
 #[test]
 fn test_query_by_event_kind() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
@@ -2238,7 +2668,7 @@ fn test_query_by_event_kind() {
                     wall_clock_ns: None,
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -2256,13 +2686,13 @@ fn test_query_by_event_kind() {
                     wall_clock_ns: None,
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Implementation)
                 .kind(EventKind::EnergyRecord {
-                    total: Value::Known(-100.0, "kJ/mol".to_string()),
+                    total: Value::Known(-100.0, "kJ/mol".into()),
                     components: vec![],
                 })
                 .temporal(TemporalCoord {
@@ -2270,7 +2700,7 @@ fn test_query_by_event_kind() {
                     wall_clock_ns: None,
                     logical_sequence: 3,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -2291,7 +2721,7 @@ fn test_query_by_event_kind() {
 
 #[test]
 fn test_temporal_ordering() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec())
         .add_event(
             TraceEventBuilder::new()
@@ -2305,13 +2735,13 @@ fn test_temporal_ordering() {
                     wall_clock_ns: Some(0),
                     logical_sequence: 1,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
                 .layer(Layer::Implementation)
                 .kind(EventKind::EnergyRecord {
-                    total: Value::Known(-100.0, "kJ/mol".to_string()),
+                    total: Value::Known(-100.0, "kJ/mol".into()),
                     components: vec![],
                 })
                 .temporal(TemporalCoord {
@@ -2319,7 +2749,7 @@ fn test_temporal_ordering() {
                     wall_clock_ns: Some(500_000),
                     logical_sequence: 2,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .add_event(
             TraceEventBuilder::new()
@@ -2333,7 +2763,7 @@ fn test_temporal_ordering() {
                     wall_clock_ns: Some(5_000_000),
                     logical_sequence: 3,
                 })
-                .build(),
+                .build(&mut ids),
         )
         .build();
 
@@ -2350,9 +2780,273 @@ fn test_temporal_ordering() {
     }
 }
 
+/// Helper: energy records at (step, wall clock) points followed by a crash.
+fn range_test_log(ids: &mut EventIdAllocator) -> LayeredEventLog {
+    let points = [
+        (0, Some(0)),
+        (0, None),
+        (100, Some(1_000)),
+        (200, Some(2_000)),
+        (300, Some(3_000)),
+    ];
+    let mut builder = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    for (sequence, (step, wall_clock_ns)) in points.into_iter().enumerate() {
+        let event = TraceEventBuilder::new()
+            .layer(Layer::Implementation)
+            .kind(EventKind::EnergyRecord {
+                total: Value::Known(-100.0, "kJ/mol".into()),
+                components: vec![],
+            })
+            .temporal(TemporalCoord {
+                simulation_step: step,
+                wall_clock_ns,
+                logical_sequence: sequence as u64 + 1,
+            })
+            .build(ids);
+        builder.push(event);
+    }
+    let crash = TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ExecutionStatus {
+            status: ExecutionOutcome::CrashDivergent,
+            framework_error_id: None,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 300,
+            wall_clock_ns: Some(3_500),
+            logical_sequence: 6,
+        })
+        .build(ids);
+    builder.push(crash);
+    builder.build()
+}
+
+fn event_ids(events: &[&TraceEvent]) -> Vec<u64> {
+    events.iter().map(|event| event.id.0).collect()
+}
+
+#[test]
+fn test_by_step_index_keeps_every_event() {
+    let mut ids = EventIdAllocator::new();
+    let log = range_test_log(&mut ids);
+    assert_eq!(log.indexes.by_time_range.get(&0), Some(&EventId(1)));
+    assert_eq!(log.indexes.by_step[&0], vec![EventId(1), EventId(2)]);
+    assert_eq!(log.indexes.by_step[&300], vec![EventId(5), EventId(6)]);
+}
+
+#[test]
+fn test_events_in_step_range() {
+    let mut ids = EventIdAllocator::new();
+    let log = range_test_log(&mut ids);
+    assert_eq!(
+        event_ids(&log.events_in_step_range(100..=300)),
+        vec![3, 4, 5, 6]
+    );
+    assert_eq!(event_ids(&log.events_in_step_range(0..200)), vec![1, 2, 3]);
+    assert_eq!(event_ids(&log.events_in_step_range(..)).len(), 6);
+    assert!(log.events_in_step_range(301..).is_empty());
+}
+
+#[test]
+fn test_events_in_reversed_step_range_is_empty() {
+    let mut ids = EventIdAllocator::new();
+    let log = range_test_log(&mut ids);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 300..100;
+    assert!(log.events_in_step_range(reversed).is_empty());
+    assert!(log
+        .events_in_step_range((Bound::Excluded(100), Bound::Excluded(100)))
+        .is_empty());
+    assert_eq!(
+        event_ids(&log.events_in_step_range((Bound::Excluded(0), Bound::Included(100)))),
+        vec![3]
+    );
+}
+
+#[test]
+fn test_events_in_wall_clock_range_before_crash() {
+    let mut ids = EventIdAllocator::new();
+    let log = range_test_log(&mut ids);
+    let crash_ns = log.events.last().unwrap().temporal.wall_clock_ns.unwrap();
+
+    let window = log.events_in_wall_clock_range(crash_ns - 2_000..=crash_ns);
+    assert_eq!(event_ids(&window), vec![4, 5, 6]);
+    // Events without a wall clock never match, even for an unbounded range.
+    assert_eq!(
+        event_ids(&log.events_in_wall_clock_range(..)),
+        vec![1, 3, 4, 5, 6]
+    );
+}
+
+#[test]
+fn test_events_in_sequence_range() {
+    let mut ids = EventIdAllocator::new();
+    let log = range_test_log(&mut ids);
+    assert_eq!(event_ids(&log.events_in_sequence_range(2..4)), vec![2, 3]);
+    assert_eq!(event_ids(&log.events_in_sequence_range(5..)), vec![5, 6]);
+}
+
+/// Helper: mixed-layer log for `EventQuery` tests.
+fn query_test_log(ids: &mut EventIdAllocator) -> LayeredEventLog {
+    let numerical = |severity: Severity| EventKind::NumericalStatus {
+        event_type: NumericalEventType::NaNDetected,
+        affected_quantity: "Total Energy".to_string(),
+        severity,
+        detail: Value::KnownCat("test".to_string()),
+    };
+    let convergence = |converged: bool| EventKind::ConvergencePoint {
+        iteration: 0,
+        metric_name: "energy_drift".into(),
+        metric_value: Value::Known(0.01, "relative".into()),
+        converged: Some(converged),
+        uncertainty: None,
+    };
+    let specs: Vec<(Layer, EventKind, u64, Option<&str>)> = vec![
+        (
+            Layer::Methodology,
+            EventKind::ParameterRecord {
+                name: "dt".into(),
+                specified_value: None,
+                actual_value: Value::Known(0.002, "ps".into()),
+                units: None,
+                observation_mode: ObservationMode::Observational,
+            },
+            0,
+            Some("dt"),
+        ),
+        (
+            Layer::Implementation,
+            numerical(Severity::Warning),
+            500,
+            Some("dt"),
+        ),
+        (
+            Layer::Implementation,
+            numerical(Severity::Error),
+            1500,
+            Some("dt"),
+        ),
+        (
+            Layer::Implementation,
+            numerical(Severity::Critical),
+            2000,
+            None,
+        ),
+        (Layer::Methodology, convergence(true), 1000, None),
+        (Layer::Methodology, convergence(false), 2000, None),
+        (
+            Layer::Implementation,
+            numerical(Severity::Warning),
+            2500,
+            Some("dt"),
+        ),
+    ];
+
+    let mut builder = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    for (sequence, (layer, kind, step, dag_node)) in specs.into_iter().enumerate() {
+        let mut event = TraceEventBuilder::new()
+            .layer(layer)
+            .kind(kind)
+            .temporal(TemporalCoord {
+                simulation_step: step,
+                wall_clock_ns: Some(step * 10),
+                logical_sequence: sequence as u64 + 1,
+            });
+        if let Some(node) = dag_node {
+            event = event.dag_node_ref(node.to_string());
+        }
+        builder.push(event.build(ids));
+    }
+    builder.build()
+}
+
+#[test]
+fn test_query_intersects_indexes() {
+    let mut ids = EventIdAllocator::new();
+    let log = query_test_log(&mut ids);
+
+    let ids = log
+        .query()
+        .layer(Layer::Implementation)
+        .kind(EventKindTag::NumericalStatus)
+        .dag_node("dt")
+        .steps(1001..)
+        .ids();
+    assert_eq!(ids, vec![EventId(3), EventId(7)]);
+
+    assert_eq!(log.query().variable("dt").ids(), vec![EventId(1)]);
+    assert_eq!(
+        log.query()
+            .variable("dt")
+            .layer(Layer::Implementation)
+            .count(),
+        0
+    );
+    assert_eq!(log.query().dag_node("missing").count(), 0);
+    assert_eq!(log.query().count(), log.events.len());
+    assert_eq!(log.query().steps(1000..=2000).ids().len(), 4);
+    assert_eq!(log.query().wall_clock(..=10_000).ids().len(), 3);
+}
+
+#[test]
+#[allow(clippy::reversed_empty_ranges)]
+fn test_query_reversed_step_range_matches_nothing() {
+    let mut ids = EventIdAllocator::new();
+    let log = query_test_log(&mut ids);
+
+    assert_eq!(log.query().steps(7..3).count(), 0);
+    assert_eq!(
+        log.query()
+            .layer(Layer::Implementation)
+            .steps(2000..=1000)
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_query_payload_predicates() {
+    let mut ids = EventIdAllocator::new();
+    let log = query_test_log(&mut ids);
+
+    assert_eq!(
+        log.query().min_severity(Severity::Error).ids(),
+        vec![EventId(3), EventId(4)]
+    );
+    assert_eq!(log.query().converged(true).ids(), vec![EventId(5)]);
+    assert_eq!(
+        log.query().converged(false).steps(..=2000).ids(),
+        vec![EventId(6)]
+    );
+}
+
+#[test]
+fn test_query_ordering_and_limit() {
+    let mut ids = EventIdAllocator::new();
+    let log = query_test_log(&mut ids);
+
+    let latest = log
+        .query()
+        .kind(EventKindTag::NumericalStatus)
+        .descending()
+        .limit(2)
+        .ids();
+    assert_eq!(latest, vec![EventId(7), EventId(4)]);
+
+    // Stable sort: events 4 and 6 share step 2000 and keep log order.
+    let by_step = log
+        .query()
+        .steps(1000..)
+        .order_by(QueryOrder::SimulationStep)
+        .ids();
+    assert_eq!(
+        by_step,
+        vec![EventId(5), EventId(3), EventId(4), EventId(6), EventId(7)]
+    );
+}
+
 #[test]
 fn test_havoc_value() {
-    setup();
     let havoc = Value::Havoc {
         expected_type: ValueType::Scalar,
         reason: HavocReason::NotLogged,
@@ -2394,6 +3088,205 @@ fn test_havoc_value() {
     }
 }
 
+#[test]
+fn test_unit_parse_dimensions() {
+    assert_eq!(Unit::parse("kJ/mol").dimension(), Some(Dimension::ENERGY));
+    assert_eq!(Unit::parse("eV").dimension(), Some(Dimension::ENERGY));
+    assert_eq!(Unit::parse("nm").dimension(), Some(Dimension::LENGTH));
+    assert_eq!(Unit::parse("ps").dimension(), Some(Dimension::TIME));
+    assert_eq!(Unit::parse("K").dimension(), Some(Dimension::TEMPERATURE));
+    assert_eq!(Unit::parse("bar").dimension(), Some(Dimension::PRESSURE));
+    assert_eq!(Unit::parse("kJ/mol/nm").dimension(), Some(Dimension::FORCE));
+    assert_eq!(Unit::parse("eV/Angst").dimension(), Some(Dimension::FORCE));
+    assert_eq!(
+        Unit::parse("kJ mol^-1 nm^-1").dimension(),
+        Some(Dimension::FORCE)
+    );
+    assert_eq!(
+        Unit::parse("1/ps").dimension(),
+        Some(Dimension::TIME.powi(-1))
+    );
+    assert_eq!(
+        Unit::parse("eV/(Angst*Angst)").dimension(),
+        Some(Dimension::new(1, 0, -2, 0, 0))
+    );
+    assert_eq!(
+        Unit::parse("relative").dimension(),
+        Some(Dimension::DIMENSIONLESS)
+    );
+    assert_eq!(Unit::parse("").dimension(), Some(Dimension::DIMENSIONLESS));
+
+    let opaque = Unit::parse("MB");
+    assert!(!opaque.is_known());
+    assert_eq!(opaque.as_str(), "MB");
+    assert_eq!(Dimension::new(0, 3, 0, 0, 0).to_string(), "L^3");
+}
+
+#[test]
+fn test_unit_energy_conversions() {
+    let kj_mol = Unit::parse("kJ/mol");
+    let kcal_mol = Unit::parse("kcal/mol");
+    let ev = Unit::parse("eV");
+
+    assert!((kcal_mol.convert(1.0, &kj_mol).unwrap() - 4.184).abs() < 1e-12);
+    assert!((ev.convert(1.0, &kj_mol).unwrap() - 96.485_332_12).abs() < 1e-6);
+    assert!((ev.convert(1.0, &kcal_mol).unwrap() - 23.060_547_83).abs() < 1e-6);
+    assert!((Unit::parse("Ha").convert(1.0, &ev).unwrap() - 27.211_386_245_988).abs() < 1e-9);
+
+    // Pressure and energy density share a dimension: 1 kJ/mol/nm^3 = 16.6054 bar.
+    let bar = Unit::parse("kJ/mol/nm^3")
+        .convert(1.0, &Unit::parse("bar"))
+        .unwrap();
+    assert!((bar - 16.605_390_7).abs() < 1e-6);
+    let force = Unit::parse("eV/Angst")
+        .convert(1.0, &Unit::parse("kJ/mol/nm"))
+        .unwrap();
+    assert!((force - 964.853_321_2).abs() < 1e-4);
+    assert!((Unit::parse("fs").convert(2.0, &Unit::parse("ps")).unwrap() - 0.002).abs() < 1e-15);
+}
+
+#[test]
+fn test_unit_conversion_errors() {
+    let kj_mol = Unit::parse("kJ/mol");
+    assert!(matches!(
+        kj_mol.conversion_factor(&Unit::parse("nm")),
+        Err(UnitError::Incompatible { .. })
+    ));
+    assert_eq!(
+        Unit::parse("MB").conversion_factor(&kj_mol),
+        Err(UnitError::UnknownUnit("MB".to_string()))
+    );
+    // Opaque symbols still match themselves.
+    assert_eq!(
+        Unit::parse("MB").conversion_factor(&Unit::parse("MB")),
+        Ok(1.0)
+    );
+
+    let message = kj_mol
+        .conversion_factor(&Unit::parse("K"))
+        .unwrap_err()
+        .to_string();
+    assert!(message.contains("energy") && message.contains("temperature"));
+
+    // Exponents past i8 make the unit opaque rather than overflowing.
+    assert!(!Unit::parse("m^100*m^100").is_known());
+    assert!(!Unit::parse("m^-100/m^100").is_known());
+    let huge = Unit::parse("m^100");
+    assert!(!huge.checked_mul(&huge).unwrap().is_known());
+    assert_eq!(
+        Dimension::LENGTH
+            .powi(100)
+            .checked_mul(Dimension::LENGTH.powi(100)),
+        None
+    );
+}
+
+#[test]
+fn test_quantity_checked_arithmetic() {
+    let a = Quantity::new(1.0, "kcal/mol");
+    let b = Quantity::new(4.184, "kJ/mol");
+
+    let sum = a.checked_add(&b).unwrap();
+    assert_eq!(sum.unit, "kcal/mol");
+    assert!((sum.value - 2.0).abs() < 1e-12);
+    assert!(a.checked_sub(&b).unwrap().value.abs() < 1e-12);
+    assert!(a.checked_add(&Quantity::new(1.0, "nm")).is_err());
+
+    let force = Quantity::new(10.0, "kJ/mol")
+        .checked_div(&Quantity::new(2.0, "nm"))
+        .unwrap();
+    assert_eq!(force.unit, "kJ/mol/nm");
+    assert_eq!(force.unit.dimension(), Some(Dimension::FORCE));
+    assert!((force.value - 5.0).abs() < 1e-12);
+    let energy = force.checked_mul(&Quantity::new(2.0, "nm")).unwrap();
+    assert_eq!(energy.unit.dimension(), Some(Dimension::ENERGY));
+
+    let per_energy = Quantity::new(1.0, "")
+        .checked_div(&Quantity::new(1.0, "kJ/mol"))
+        .unwrap();
+    assert_eq!(per_energy.unit, "1/(kJ/mol)");
+    assert_eq!(
+        Unit::parse(per_energy.unit.as_str()).dimension(),
+        per_energy.unit.dimension()
+    );
+
+    assert_eq!(
+        Quantity::new(1.0, "eV").checked_cmp(&Quantity::new(90.0, "kJ/mol")),
+        Ok(Some(std::cmp::Ordering::Greater))
+    );
+}
+
+#[test]
+fn test_value_convert_to() {
+    let value = Value::KnownVec(vec![1.0, -2.0], "eV".into());
+    let converted = value.convert_to(&"kJ/mol".into()).unwrap();
+    match converted {
+        Value::KnownVec(values, unit) => {
+            assert_eq!(unit, "kJ/mol");
+            assert!((values[1] + 2.0 * 96.485_332_12).abs() < 1e-6);
+        }
+        other => panic!("Expected KnownVec, got {:?}", other),
+    }
+    assert!(matches!(
+        Value::KnownCat("x".to_string()).convert_to(&"eV".into()),
+        Err(UnitError::NotNumeric(_))
+    ));
+    assert_eq!(
+        Value::Known(2.0, "ps".into()).quantity(),
+        Some(Quantity::new(2.0, "ps"))
+    );
+}
+
+#[test]
+fn test_unit_serializes_as_symbol() {
+    let value = Value::Known(-1.5, "kcal/mol".into());
+    let json = serde_json::to_string(&value).expect("Serialization must succeed");
+    assert_eq!(json, r#"{"Known":[-1.5,"kcal/mol"]}"#);
+    let restored: Value = serde_json::from_str(&json).expect("Deserialization must succeed");
+    match restored {
+        Value::Known(_, unit) => assert_eq!(unit.dimension(), Some(Dimension::ENERGY)),
+        other => panic!("Expected Known, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_known_grid_and_data_ref_serde_roundtrip() {
+    let dos = Value::KnownGrid {
+        axes: vec![GridAxis {
+            label: "energy".to_string(),
+            coordinates: vec![-1.0, 0.0, 1.0],
+            unit: "eV".into(),
+        }],
+        values: vec![0.1, 2.5, 0.3],
+        value_unit: "states/eV".into(),
+    };
+    let chgcar = Value::DataRef {
+        path: "CHGCAR".to_string(),
+        data_type: "charge_density".to_string(),
+        shape: vec![224, 224, 224],
+        unit: "e".into(),
+        checksum: Some(0x1234_5678),
+    };
+    let missing_grid = Value::Havoc {
+        expected_type: ValueType::Grid,
+        reason: HavocReason::NotLogged,
+    };
+    let missing_ref = Value::Havoc {
+        expected_type: ValueType::DataRef,
+        reason: HavocReason::CrashStateGap,
+    };
+
+    assert_eq!(dos.shape(), Some(vec![3]));
+    assert_eq!(chgcar.shape(), Some(vec![224, 224, 224]));
+    assert_eq!(missing_grid.shape(), None);
+
+    for value in [dos, chgcar, missing_grid, missing_ref] {
+        let json = serde_json::to_string(&value).expect("Serialization must succeed");
+        let restored: Value = serde_json::from_str(&json).expect("Deserialization must succeed");
+        assert_eq!(restored, value);
+    }
+}
+
 const OPENMM_STABLE_ENERGY_SERIES: &str = r#"
 0 -45023.7000
 1000 -45023.9000
@@ -2527,7 +3420,7 @@ fn assert_openmm_csv_variant(
 }
 
 fn parse_gromacs_log_energy_pairs(log_content: &str) -> Vec<(u64, f64)> {
-    parse_log(log_content, 0)
+    parse_log(log_content, 0, &mut EventIdAllocator::new())
         .unwrap()
         .into_iter()
         .filter_map(|event| match event.kind {
@@ -2565,7 +3458,7 @@ fn assert_gromacs_log_variant(
 }
 
 fn assert_gromacs_log_parses_energy_count(log: &str, count: usize) {
-    let energy_count = parse_log(log, 0)
+    let energy_count = parse_log(log, 0, &mut EventIdAllocator::new())
         .unwrap()
         .into_iter()
         .filter(|event| matches!(event.kind, EventKind::EnergyRecord { .. }))
@@ -2575,7 +3468,6 @@ fn assert_gromacs_log_parses_energy_count(log: &str, count: usize) {
 
 #[test]
 fn test_mock_adapter() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace("").unwrap();
 
@@ -2614,7 +3506,6 @@ fn test_mock_adapter() {
 
 #[test]
 fn test_mock_adapter_derives_convergence_summary_for_stable_series() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_STABLE_ENERGY_SERIES).unwrap();
 
@@ -2637,7 +3528,6 @@ fn test_mock_adapter_derives_convergence_summary_for_stable_series() {
 
 #[test]
 fn test_mock_adapter_derives_oscillation_summary_for_non_converging_series() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_OSCILLATING_ENERGY_SERIES).unwrap();
 
@@ -2660,7 +3550,6 @@ fn test_mock_adapter_derives_oscillation_summary_for_non_converging_series() {
 
 #[test]
 fn test_mock_adapter_no_convergence_summary_below_min_window() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_SHORT_ENERGY_SERIES).unwrap();
 
@@ -2672,7 +3561,6 @@ fn test_mock_adapter_no_convergence_summary_below_min_window() {
 
 #[test]
 fn test_mock_adapter_convergence_summary_provenance_refs() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_STABLE_ENERGY_SERIES).unwrap();
 
@@ -2711,7 +3599,6 @@ fn test_mock_adapter_convergence_summary_provenance_refs() {
 
 #[test]
 fn test_mock_adapter_parses_openmm_statedatareporter_csv_pairs() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_CSV_STABLE).unwrap();
 
@@ -2738,7 +3625,6 @@ fn test_mock_adapter_parses_openmm_statedatareporter_csv_pairs() {
 
 #[test]
 fn test_mock_adapter_csv_derives_convergence_summary_for_stable_series() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_CSV_STABLE).unwrap();
 
@@ -2761,7 +3647,6 @@ fn test_mock_adapter_csv_derives_convergence_summary_for_stable_series() {
 
 #[test]
 fn test_mock_adapter_csv_derives_oscillation_summary_for_non_converging_series() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_CSV_OSCILLATING).unwrap();
 
@@ -2784,7 +3669,6 @@ fn test_mock_adapter_csv_derives_oscillation_summary_for_non_converging_series()
 
 #[test]
 fn test_mock_adapter_whitespace_parser_backward_compat() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_STABLE_ENERGY_SERIES).unwrap();
 
@@ -2809,7 +3693,6 @@ fn test_mock_adapter_whitespace_parser_backward_compat() {
 
 #[test]
 fn test_openmm_csv_divergent_fixture_emits_nan_status() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace(OPENMM_CSV_DIVERGENT_NAN).unwrap();
 
@@ -2826,7 +3709,6 @@ fn test_openmm_csv_divergent_fixture_emits_nan_status() {
 
 #[test]
 fn test_openmm_csv_variant_reordered_columns() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_REORDERED_EXTRA_COLUMNS,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2836,7 +3718,6 @@ fn test_openmm_csv_variant_reordered_columns() {
 
 #[test]
 fn test_openmm_csv_variant_optional_extra_columns() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_REORDERED_EXTRA_COLUMNS,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2846,7 +3727,6 @@ fn test_openmm_csv_variant_optional_extra_columns() {
 
 #[test]
 fn test_openmm_csv_variant_minimal_columns() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_MINIMAL_COLUMNS,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2856,7 +3736,6 @@ fn test_openmm_csv_variant_minimal_columns() {
 
 #[test]
 fn test_openmm_csv_variant_kcal_units() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_SOURCE_DERIVED_CSV_KCAL_UNITS,
         &OPENMM_EXPECTED_ZERO_SERIES_4,
@@ -2866,7 +3745,6 @@ fn test_openmm_csv_variant_kcal_units() {
 
 #[test]
 fn test_openmm_csv_variant_quoted_header() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_DEFAULT_KJ,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2876,7 +3754,6 @@ fn test_openmm_csv_variant_quoted_header() {
 
 #[test]
 fn test_openmm_csv_variant_unquoted_header() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_SOURCE_DERIVED_CSV_UNQUOTED_HEADER,
         &OPENMM_EXPECTED_ZERO_SERIES_4,
@@ -2886,7 +3763,6 @@ fn test_openmm_csv_variant_unquoted_header() {
 
 #[test]
 fn test_openmm_csv_variant_empty_trailing_columns() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_SOURCE_DERIVED_CSV_EMPTY_TRAILING_COLUMNS,
         &OPENMM_EXPECTED_ZERO_SERIES_4,
@@ -2896,7 +3772,6 @@ fn test_openmm_csv_variant_empty_trailing_columns() {
 
 #[test]
 fn test_openmm_csv_variant_windows_crlf() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_WINDOWS_CRLF,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2906,7 +3781,6 @@ fn test_openmm_csv_variant_windows_crlf() {
 
 #[test]
 fn test_openmm_csv_variant_bom_prefix() {
-    setup();
     assert_openmm_csv_variant(
         OPENMM_REAL_CSV_BOM_PREFIX,
         &OPENMM_EXPECTED_REAL_ZERO_SERIES_5,
@@ -2916,7 +3790,6 @@ fn test_openmm_csv_variant_bom_prefix() {
 
 #[test]
 fn test_hybrid_upgrade_fields_present() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace("").unwrap();
 
@@ -2941,7 +3814,6 @@ fn test_hybrid_upgrade_fields_present() {
 
 #[test]
 fn test_spec_separation() {
-    setup();
     let adapter = MockOpenMmAdapter;
     let log = adapter.parse_trace("").unwrap();
 
@@ -2966,73 +3838,1789 @@ fn test_spec_separation() {
     );
 }
 
-#[test]
-fn test_serde_roundtrip() {
-    setup();
-    let adapter = MockOpenMmAdapter;
-    let original = adapter.parse_trace("").unwrap();
-
-    // Serialize to JSON
-    let json = serde_json::to_string_pretty(&original).expect("Serialization must succeed");
-
-    // Verify JSON is non-empty and contains expected fields
-    assert!(json.contains("openmm-mock-001"));
-    assert!(json.contains("force_field"));
-    assert!(json.contains("Theory"));
-    assert!(json.contains("Implementation"));
+/// Helper: parameter record event for contract evaluation tests.
+fn test_parameter_event(
+    name: &str,
+    value: Value,
+    logical_sequence: u64,
+    ids: &mut EventIdAllocator,
+) -> TraceEvent {
+    TraceEventBuilder::new()
+        .layer(Layer::Methodology)
+        .kind(EventKind::ParameterRecord {
+            name: name.into(),
+            specified_value: None,
+            actual_value: value,
+            units: None,
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence,
+        })
+        .provenance(test_provenance())
+        .build(ids)
+}
 
-    // Deserialize back
-    let restored: LayeredEventLog =
-        serde_json::from_str(&json).expect("Deserialization must succeed");
+/// Helper: observable measurement event for contract evaluation tests.
+fn test_observable_event(
+    name: &str,
+    value: Value,
+    step: u64,
+    logical_sequence: u64,
+    ids: &mut EventIdAllocator,
+) -> TraceEvent {
+    TraceEventBuilder::new()
+        .layer(Layer::Methodology)
+        .kind(EventKind::ObservableMeasurement {
+            variable_name: name.into(),
+            measurement_method: "test".to_string(),
+            value,
+            uncertainty: None,
+            conditions: String::new(),
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: step,
+            wall_clock_ns: None,
+            logical_sequence,
+        })
+        .provenance(test_provenance())
+        .build(ids)
+}
+
+fn contract_term(
+    id: u64,
+    layer: Layer,
+    target: Option<Value>,
+    predicate: Option<ContractPredicate>,
+) -> ContractTerm {
+    ContractTerm {
+        id: SpecElementId(id),
+        description: format!("term {}", id),
+        layer,
+        target,
+        predicate,
+    }
+}
 
-    // Verify structural equality
-    assert_eq!(original.events.len(), restored.events.len());
-    assert_eq!(
-        original.experiment_ref.experiment_id,
-        restored.experiment_ref.experiment_id
+#[test]
+fn test_evaluate_contracts_pre_and_postconditions() {
+    let mut ids = EventIdAllocator::new();
+    let dt = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 1, &mut ids);
+    let nsteps = test_parameter_event(
+        "nsteps",
+        Value::Known(500000.0, Unit::dimensionless()),
+        2,
+        &mut ids,
     );
-    assert_eq!(original.experiment_ref.cycle_id, restored.experiment_ref.cycle_id);
+    let density_a = test_observable_event(
+        "density",
+        Value::Known(1.01, "g/cm3".into()),
+        100,
+        3,
+        &mut ids,
+    );
+    let density_b = test_observable_event(
+        "density",
+        Value::Known(0.99, "g/cm3".into()),
+        200,
+        4,
+        &mut ids,
+    );
+    let (dt_id, nsteps_id) = (dt.id, nsteps.id);
+    let density_ids = vec![density_a.id, density_b.id];
 
-    // Verify event-level equality
-    for (orig, rest) in original.events.iter().zip(restored.events.iter()) {
-        assert_eq!(orig.id, rest.id);
-        assert_eq!(orig.layer, rest.layer);
-        assert_eq!(orig.temporal, rest.temporal);
-        assert_eq!(orig.dag_node_ref, rest.dag_node_ref);
-        assert_eq!(orig.spec_ref, rest.spec_ref);
-        assert_eq!(orig.causal_refs, rest.causal_refs);
+    let mut spec = test_spec();
+    spec.preconditions = vec![
+        contract_term(
+            10,
+            Layer::Methodology,
+            Some(Value::Known(0.002, "ps".into())),
+            Some(ContractPredicate::ParameterEquals {
+                parameter: "dt".to_string(),
+            }),
+        ),
+        contract_term(
+            11,
+            Layer::Methodology,
+            Some(Value::Known(1000.0, Unit::dimensionless())),
+            Some(ContractPredicate::Compare {
+                variable: "nsteps".to_string(),
+                op: ComparisonOp::Ge,
+            }),
+        ),
+    ];
+    spec.postconditions = vec![contract_term(
+        12,
+        Layer::Theory,
+        Some(Value::Known(1.0, "g/cm3".into())),
+        Some(ContractPredicate::WithinTolerance {
+            observable: "density".to_string(),
+            tolerance: 0.05,
+        }),
+    )];
+
+    let mut builder = LayeredEventLogBuilder::new(test_experiment_ref(), spec);
+    for event in [dt, nsteps, density_a, density_b] {
+        builder = builder.add_event(event);
     }
-}
+    let log = builder.build();
 
-const GROMACS_MDP_SAMPLE: &str = r#"
-; GROMACS mdp file
-integrator = md
-dt = 0.002 ; ps
-nsteps = 500000
-coulombtype = PME
-rcoulomb = 1.0
-ref_t = 300
-ref_p = 1.0
-tcoupl = V-rescale
-tau_t = 0.1
-pcoupl = Parrinello-Rahman
-nstlog = 1000
-nstxout = 1000
-"#;
+    let evaluation = evaluate_contracts(&log).unwrap();
+    assert!(evaluation.unchecked.is_empty());
+    assert_eq!(evaluation.validation_events.len(), 3);
 
-const GROMACS_LOG_SAMPLE: &str = r#"
-             :-) GROMACS - gmx mdrun, 2023.3 (-:
+    let statuses: Vec<(&str, &MatchStatus, &Vec<EventId>, Option<SpecElementId>)> = evaluation
+        .validation_events
+        .iter()
+        .map(|event| match &event.kind {
+            EventKind::ValidationResult {
+                parameter_name,
+                match_status,
+                ..
+            } => (
+                parameter_name.as_str(),
+                match_status,
+                &event.causal_refs,
+                event.spec_ref,
+            ),
+            other => panic!("Expected ValidationResult, got {:?}", other),
+        })
+        .collect();
 
-Using 1 GPU
+    assert_eq!(statuses[0].0, "dt");
+    assert_eq!(statuses[0].1, &MatchStatus::Exact);
+    assert_eq!(statuses[0].2, &vec![dt_id]);
+    assert_eq!(statuses[0].3, Some(SpecElementId(10)));
 
-   Step           Time
-      0        0.00000
+    assert_eq!(statuses[1].0, "nsteps");
+    assert!(matches!(statuses[1].1, MatchStatus::WithinTolerance { .. }));
+    assert_eq!(statuses[1].2, &vec![nsteps_id]);
 
-Energies (kJ/mol)
-   Bond          Angle    Proper Dih.          LJ-14     Coulomb-14
-1234.56       2345.67        345.678       456.789       567.890
-   LJ (SR)   Coulomb (SR)   Coul. recip.      Potential    Kinetic En.
--12345.6      -54321.0        1234.56      -45678.9       12345.6
+    assert_eq!(statuses[2].0, "density");
+    match statuses[2].1 {
+        MatchStatus::WithinTolerance { deviation } => assert!((deviation - 0.01).abs() < 1e-9),
+        other => panic!("Expected WithinTolerance, got {:?}", other),
+    }
+    assert_eq!(statuses[2].2, &density_ids);
+
+    let postcondition_event = &evaluation.validation_events[2];
+    assert_eq!(postcondition_event.layer, Layer::Theory);
+    assert_eq!(postcondition_event.temporal.simulation_step, 200);
+    assert_eq!(postcondition_event.temporal.logical_sequence, 7);
+    assert!(matches!(
+        postcondition_event.confidence.completeness,
+        Completeness::Derived { .. }
+    ));
+}
+
+#[test]
+fn test_evaluate_contracts_detects_violation() {
+    let mut ids = EventIdAllocator::new();
+    let dt = test_parameter_event("dt", Value::Known(0.004, "ps".into()), 1, &mut ids);
+    let integrator =
+        test_parameter_event("integrator", Value::KnownCat("sd".to_string()), 2, &mut ids);
+
+    let mut spec = test_spec();
+    spec.preconditions = vec![
+        contract_term(
+            20,
+            Layer::Methodology,
+            Some(Value::Known(0.002, "ps".into())),
+            Some(ContractPredicate::Compare {
+                variable: "dt".to_string(),
+                op: ComparisonOp::Le,
+            }),
+        ),
+        contract_term(
+            21,
+            Layer::Methodology,
+            Some(Value::KnownCat("md".to_string())),
+            Some(ContractPredicate::ParameterEquals {
+                parameter: "integrator".to_string(),
+            }),
+        ),
+        contract_term(
+            22,
+            Layer::Methodology,
+            None,
+            Some(ContractPredicate::InRange {
+                variable: "dt".to_string(),
+                min: 0.0005,
+                max: 0.002,
+                unit: Some("ps".into()),
+            }),
+        ),
+    ];
+    spec.postconditions = Vec::new();
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
+        .add_event(dt)
+        .add_event(integrator)
+        .build();
+
+    let evaluation = evaluate_contracts(&log).unwrap();
+    let statuses: Vec<MatchStatus> = evaluation
+        .validation_events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::ValidationResult { match_status, .. } => Some(match_status.clone()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(statuses.len(), 3);
+    match &statuses[0] {
+        MatchStatus::Mismatch { deviation } => assert!((deviation - 0.002).abs() < 1e-12),
+        other => panic!("Expected Mismatch, got {:?}", other),
+    }
+    assert_eq!(statuses[1], MatchStatus::Mismatch { deviation: 1.0 });
+    match &statuses[2] {
+        MatchStatus::Mismatch { deviation } => assert!((deviation - 0.002).abs() < 1e-12),
+        other => panic!("Expected Mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_evaluate_contracts_reports_unchecked_terms() {
+    let mut ids = EventIdAllocator::new();
+    let dt = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 1, &mut ids);
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), {
+        let mut spec = test_spec();
+        spec.postconditions.push(contract_term(
+            30,
+            Layer::Theory,
+            Some(Value::Known(300.0, "K".into())),
+            Some(ContractPredicate::WithinTolerance {
+                observable: "temperature".to_string(),
+                tolerance: 5.0,
+            }),
+        ));
+        spec.postconditions.push(contract_term(
+            31,
+            Layer::Methodology,
+            Some(Value::Known(0.002, "nm".into())),
+            Some(ContractPredicate::ParameterEquals {
+                parameter: "dt".to_string(),
+            }),
+        ));
+        spec
+    })
+    .add_event(dt)
+    .build();
+
+    let evaluation = evaluate_contracts(&log).unwrap();
+    assert!(evaluation.validation_events.is_empty());
+
+    let unchecked_ids: Vec<SpecElementId> = evaluation
+        .unchecked
+        .iter()
+        .map(|unchecked| unchecked.term_id)
+        .collect();
+    // Description-only terms from test_spec, missing variable, unit mismatch.
+    assert_eq!(
+        unchecked_ids,
+        vec![
+            SpecElementId(1),
+            SpecElementId(2),
+            SpecElementId(30),
+            SpecElementId(31)
+        ]
+    );
+    assert!(evaluation.unchecked[2].reason.contains("temperature"));
+    assert!(evaluation.unchecked[3].reason.contains("unit mismatch"));
+}
+
+#[test]
+fn test_evaluate_contracts_converts_units() {
+    let mut ids = EventIdAllocator::new();
+    let dt = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 1, &mut ids);
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), {
+        let mut spec = test_spec();
+        spec.preconditions.push(contract_term(
+            40,
+            Layer::Methodology,
+            Some(Value::Known(2.5, "fs".into())),
+            Some(ContractPredicate::Compare {
+                variable: "dt".to_string(),
+                op: ComparisonOp::Le,
+            }),
+        ));
+        spec
+    })
+    .add_event(dt)
+    .build();
+
+    let evaluation = evaluate_contracts(&log).unwrap();
+    assert_eq!(evaluation.validation_events.len(), 1);
+    // Validation events continue the log's IDs.
+    assert_eq!(evaluation.validation_events[0].id, EventId(2));
+    match &evaluation.validation_events[0].kind {
+        EventKind::ValidationResult {
+            match_status: MatchStatus::WithinTolerance { deviation },
+            ..
+        } => assert!((deviation - 0.5).abs() < 1e-9),
+        other => panic!("Expected WithinTolerance in fs, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_evaluate_contracts_range_units_and_non_finite_values() {
+    let mut ids = EventIdAllocator::new();
+    let energy = test_observable_event(
+        "energy",
+        Value::Known(-10.0, "kcal/mol".into()),
+        0,
+        1,
+        &mut ids,
+    );
+    let drift = test_observable_event(
+        "drift",
+        Value::Known(f64::NAN, "kJ/mol".into()),
+        0,
+        2,
+        &mut ids,
+    );
+    let barrier =
+        test_parameter_event("barrier", Value::Known(1.0, "kcal/mol".into()), 3, &mut ids);
+    let mut spec = test_spec();
+    spec.preconditions = vec![
+        contract_term(
+            50,
+            Layer::Theory,
+            None,
+            Some(ContractPredicate::InRange {
+                variable: "energy".to_string(),
+                min: -50.0,
+                max: -40.0,
+                unit: Some("kJ/mol".into()),
+            }),
+        ),
+        contract_term(
+            51,
+            Layer::Theory,
+            None,
+            Some(ContractPredicate::InRange {
+                variable: "drift".to_string(),
+                min: -1.0,
+                max: 1.0,
+                unit: Some("kJ/mol".into()),
+            }),
+        ),
+        contract_term(
+            52,
+            Layer::Theory,
+            Some(Value::Known(1.0, "kJ/mol".into())),
+            Some(ContractPredicate::Compare {
+                variable: "drift".to_string(),
+                op: ComparisonOp::Le,
+            }),
+        ),
+        contract_term(
+            53,
+            Layer::Theory,
+            Some(Value::Known(4.184, "kJ/mol".into())),
+            Some(ContractPredicate::ParameterEquals {
+                parameter: "barrier".to_string(),
+            }),
+        ),
+    ];
+    spec.postconditions = Vec::new();
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
+        .add_event(energy)
+        .add_event(drift)
+        .add_event(barrier)
+        .build();
+
+    let evaluation = evaluate_contracts(&log).unwrap();
+    assert!(evaluation.unchecked.is_empty());
+    let statuses: Vec<MatchStatus> = evaluation
+        .validation_events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::ValidationResult { match_status, .. } => Some(match_status.clone()),
+            _ => None,
+        })
+        .collect();
+    // -10 kcal/mol is -41.84 kJ/mol, inside the bounds.
+    assert_eq!(statuses[0], MatchStatus::Exact);
+    for status in &statuses[1..3] {
+        match status {
+            MatchStatus::Mismatch { deviation } => assert!(deviation.is_nan()),
+            other => panic!("Expected NaN Mismatch, got {:?}", other),
+        }
+    }
+    assert_eq!(statuses[3], MatchStatus::Exact);
+}
+
+#[test]
+fn test_evaluate_contracts_compare_tolerates_conversion_rounding() {
+    let mut ids = EventIdAllocator::new();
+    let barrier =
+        test_parameter_event("barrier", Value::Known(0.1, "kcal/mol".into()), 1, &mut ids);
+    let expected = Value::Known(0.4184, "kJ/mol".into());
+    assert_ne!(
+        Unit::parse("kcal/mol")
+            .convert(0.1, &"kJ/mol".into())
+            .unwrap(),
+        0.4184,
+        "fixture must exercise conversion rounding"
+    );
+    let mut spec = test_spec();
+    spec.preconditions = [
+        ComparisonOp::Eq,
+        ComparisonOp::Le,
+        ComparisonOp::Ge,
+        ComparisonOp::Ne,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(idx, op)| {
+        contract_term(
+            60 + idx as u64,
+            Layer::Theory,
+            Some(expected.clone()),
+            Some(ContractPredicate::Compare {
+                variable: "barrier".to_string(),
+                op,
+            }),
+        )
+    })
+    .collect();
+    spec.postconditions = Vec::new();
+    let log = LayeredEventLogBuilder::new(test_experiment_ref(), spec)
+        .add_event(barrier)
+        .build();
+
+    let statuses: Vec<MatchStatus> = evaluate_contracts(&log)
+        .unwrap()
+        .validation_events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::ValidationResult { match_status, .. } => Some(match_status.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            MatchStatus::Exact,
+            MatchStatus::Exact,
+            MatchStatus::Exact,
+            MatchStatus::Mismatch { deviation: 0.0 },
+        ]
+    );
+}
+
+#[test]
+fn test_contract_term_deserializes_without_predicate_fields() {
+    let json = r#"{"id":7,"description":"legacy","layer":"Theory"}"#;
+    let term: ContractTerm = serde_json::from_str(json).expect("Legacy term must deserialize");
+    assert_eq!(term.target, None);
+    assert_eq!(term.predicate, None);
+}
+
+#[test]
+fn test_event_id_allocator_sequence() {
+    let mut ids = EventIdAllocator::new();
+    assert_eq!(ids.peek(), EventId(1));
+    assert_eq!(ids.next_id(), EventId(1));
+    assert_eq!(ids.next_id(), EventId(2));
+
+    ids.observe(EventId(10));
+    assert_eq!(ids.next_id(), EventId(11));
+    ids.observe(EventId(3));
+    assert_eq!(ids.next_id(), EventId(12));
+}
+
+#[test]
+fn test_event_id_allocator_following_log() {
+    let log = MockOpenMmAdapter.parse_trace("").unwrap();
+    let max_id = log.events.iter().map(|event| event.id.0).max().unwrap();
+    assert_eq!(
+        EventIdAllocator::following(&log.events).peek(),
+        EventId(max_id + 1)
+    );
+    assert_eq!(EventIdAllocator::following(&[]).peek(), EventId(1));
+}
+
+#[test]
+fn test_log_builder_owns_allocator() {
+    let mut builder = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    let first = TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ExecutionStatus {
+            status: ExecutionOutcome::Success,
+            framework_error_id: None,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: 1,
+        })
+        .build(builder.ids());
+    builder = builder.add_event(first);
+
+    // Events built elsewhere push the builder's allocator past their IDs.
+    let mut external = EventIdAllocator::new();
+    external.observe(EventId(41));
+    let imported = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 2, &mut external);
+    builder = builder.add_event(imported);
+    assert_eq!(builder.ids().peek(), EventId(43));
+
+    let log = builder.build();
+    let ids: Vec<EventId> = log.events.iter().map(|event| event.id).collect();
+    assert_eq!(ids, vec![EventId(1), EventId(42)]);
+}
+
+#[test]
+fn test_log_builder_push_and_extend_match_add_event() {
+    let events = |ids: &mut EventIdAllocator| {
+        vec![
+            test_parameter_event("dt", Value::Known(0.002, "ps".into()), 1, ids),
+            test_observable_event("temperature", Value::Known(300.0, "K".into()), 10, 2, ids),
+            test_parameter_event("nsteps", Value::Known(500.0, "".into()), 3, ids),
+        ]
+    };
+
+    let mut chained = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    for event in events(chained.ids()) {
+        chained = chained.add_event(event);
+    }
+    let chained = chained.build();
+
+    let mut pushed = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    let mut batch = events(pushed.ids());
+    let last = batch.pop().unwrap();
+    pushed.extend(batch).push(last);
+    assert_eq!(pushed.events().len(), 3);
+    let pushed = pushed.build();
+
+    assert_eq!(
+        serde_json::to_string(&pushed.events).unwrap(),
+        serde_json::to_string(&chained.events).unwrap()
+    );
+    assert_eq!(pushed.indexes.by_id, chained.indexes.by_id);
+    assert_eq!(pushed.indexes.by_variable, chained.indexes.by_variable);
+    assert_eq!(pushed.validate(), vec![]);
+}
+
+#[test]
+fn test_reopened_log_appends_with_indexes_in_sync() {
+    let mut builder = LayeredEventLogBuilder::new(test_experiment_ref(), test_spec());
+    let first = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 1, builder.ids());
+    let second = test_observable_event(
+        "temperature",
+        Value::Known(300.0, "K".into()),
+        10,
+        2,
+        builder.ids(),
+    );
+    builder.extend([first, second]);
+    let log = builder.build();
+
+    let mut reopened = log.into_builder();
+    assert_eq!(reopened.ids().peek(), EventId(3));
+    let derived = test_observable_event(
+        "temperature",
+        Value::Known(301.0, "K".into()),
+        20,
+        3,
+        reopened.ids(),
+    );
+    reopened.push(derived);
+    let log = reopened.build();
+
+    assert_eq!(log.validate(), vec![]);
+    assert_eq!(log.indexes.by_id.get(&EventId(3)), Some(&2));
+    assert_eq!(
+        log.indexes.by_variable["temperature"],
+        vec![EventId(2), EventId(3)]
+    );
+    assert_eq!(log.indexes.by_time_range.get(&20), Some(&EventId(3)));
+}
+
+#[test]
+fn test_adapter_event_ids_are_reproducible() {
+    let raw = format!(
+        "--- MDP ---\n{}\n--- LOG ---\n{}",
+        GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE
+    );
+    let first = serde_json::to_string(&GromacsAdapter.parse_trace(&raw).unwrap().events).unwrap();
+    // Unrelated ID allocation in between must not shift the next parse.
+    MockOpenMmAdapter
+        .parse_trace(OPENMM_STABLE_ENERGY_SERIES)
+        .unwrap();
+    let second = serde_json::to_string(&GromacsAdapter.parse_trace(&raw).unwrap().events).unwrap();
+    assert_eq!(first, second);
+
+    let log = VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
+    let ids: Vec<u64> = log.events.iter().map(|event| event.id.0).collect();
+    assert_eq!(ids, (1..=log.events.len() as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_adapters_run_concurrently_with_identical_ids() {
+    let sequential: Vec<Vec<EventId>> = (0..4)
+        .map(|_| {
+            VaspAdapter
+                .parse_trace(VASP_COMBINED_SAMPLE)
+                .unwrap()
+                .events
+                .iter()
+                .map(|event| event.id)
+                .collect()
+        })
+        .collect();
+
+    let concurrent: Vec<Vec<EventId>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    VaspAdapter
+                        .parse_trace(VASP_COMBINED_SAMPLE)
+                        .unwrap()
+                        .events
+                        .iter()
+                        .map(|event| event.id)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    assert_eq!(concurrent, sequential);
+}
+
+/// Helper: a three-event chain 1 <- 2 <- 3 with valid indexes.
+fn integrity_test_log(ids: &mut EventIdAllocator) -> LayeredEventLog {
+    let mut events: Vec<TraceEvent> = Vec::new();
+    for sequence in 1..=3 {
+        let causal_refs = events
+            .last()
+            .map(|event| vec![event.id])
+            .unwrap_or_default();
+        events.push(
+            TraceEventBuilder::new()
+                .layer(Layer::Implementation)
+                .kind(EventKind::ExecutionStatus {
+                    status: ExecutionOutcome::Success,
+                    framework_error_id: None,
+                })
+                .temporal(TemporalCoord {
+                    simulation_step: sequence,
+                    wall_clock_ns: None,
+                    logical_sequence: sequence,
+                })
+                .causal_refs(causal_refs)
+                .build(ids),
+        );
+    }
+    test_log_from_events(events)
+}
+
+#[test]
+fn test_validate_accepts_adapter_logs() {
+    let raw = format!(
+        "--- MDP ---\n{}\n--- LOG ---\n{}",
+        GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE
+    );
+    let logs = [
+        MockOpenMmAdapter
+            .parse_trace(OPENMM_CSV_DIVERGENT_NAN)
+            .unwrap(),
+        GromacsAdapter.parse_trace(&raw).unwrap(),
+        VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap(),
+    ];
+    for log in &logs {
+        assert_eq!(log.validate(), vec![]);
+    }
+}
+
+#[test]
+fn test_validate_reports_duplicate_ids_and_dangling_refs() {
+    let mut ids = EventIdAllocator::new();
+    let mut log = integrity_test_log(&mut ids);
+    log.events[2].id = EventId(1);
+    log.events[1].causal_refs = vec![EventId(99)];
+    log.indexes = EventIndexes::new();
+    for (position, event) in log.events.iter().enumerate() {
+        log.indexes.index_event(event, position);
+    }
+
+    let violations = log.validate();
+    assert!(violations.contains(&IntegrityViolation::DuplicateId {
+        id: EventId(1),
+        positions: vec![0, 2],
+    }));
+    assert!(violations.contains(&IntegrityViolation::DanglingCausalRef {
+        event: EventId(2),
+        target: EventId(99),
+    }));
+}
+
+#[test]
+fn test_validate_reports_forward_refs_and_sequence_order() {
+    let mut ids = EventIdAllocator::new();
+    let mut log = integrity_test_log(&mut ids);
+    log.events[0].causal_refs = vec![EventId(2)];
+    log.events[2].temporal.logical_sequence = 2;
+
+    let violations = log.validate();
+    assert!(violations.contains(&IntegrityViolation::ForwardCausalRef {
+        event: EventId(1),
+        target: EventId(2),
+        event_sequence: 1,
+        target_sequence: 2,
+    }));
+    assert!(
+        violations.contains(&IntegrityViolation::NonMonotonicSequence {
+            position: 2,
+            previous: 2,
+            current: 2,
+        })
+    );
+    // 1 -> 2 -> 1 is also a cycle.
+    assert!(violations.contains(&IntegrityViolation::CausalCycle {
+        events: vec![EventId(1), EventId(2)],
+    }));
+}
+
+#[test]
+fn test_validate_reports_causal_cycle_once() {
+    let mut ids = EventIdAllocator::new();
+    let mut log = integrity_test_log(&mut ids);
+    log.events[0].causal_refs = vec![EventId(3)];
+
+    let cycles: Vec<IntegrityViolation> = log
+        .validate()
+        .into_iter()
+        .filter(|violation| matches!(violation, IntegrityViolation::CausalCycle { .. }))
+        .collect();
+    assert_eq!(
+        cycles,
+        vec![IntegrityViolation::CausalCycle {
+            events: vec![EventId(1), EventId(3), EventId(2)],
+        }]
+    );
+    assert_eq!(
+        cycles[0].to_string(),
+        "causal cycle through events 1 -> 3 -> 2"
+    );
+}
+
+#[test]
+fn test_validate_reports_index_desync() {
+    let mut ids = EventIdAllocator::new();
+    let mut edited = integrity_test_log(&mut ids);
+    edited.events.pop();
+
+    let violations = edited.validate();
+    assert!(violations.contains(&IntegrityViolation::IndexOutOfSync {
+        index: "by_id",
+        key: "EventId(3)".to_string(),
+    }));
+    assert!(violations.contains(&IntegrityViolation::IndexOutOfSync {
+        index: "by_time_range",
+        key: "3".to_string(),
+    }));
+    assert!(violations.contains(&IntegrityViolation::IndexOutOfSync {
+        index: "by_kind",
+        key: "ExecutionStatus".to_string(),
+    }));
+}
+
+fn gromacs_run(mdp: &str, log: &str) -> LayeredEventLog {
+    GromacsAdapter
+        .parse_trace(&format!("--- MDP ---\n{}--- LOG ---\n{}", mdp, log))
+        .unwrap()
+}
+
+#[test]
+fn test_merge_logs_remaps_colliding_ids() {
+    let equilibration = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let production = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let (first_len, second_len) = (equilibration.events.len(), production.events.len());
+    let max_input_id = equilibration
+        .events
+        .iter()
+        .chain(&production.events)
+        .map(|event| event.id.0)
+        .max()
+        .unwrap();
+    let energy_position = production
+        .events
+        .iter()
+        .position(|event| matches!(event.kind, EventKind::EnergyRecord { .. }))
+        .unwrap();
+    let production_energy = production.events[energy_position].clone();
+    let production_positions: HashMap<EventId, usize> = production
+        .events
+        .iter()
+        .enumerate()
+        .map(|(position, event)| (event.id, position))
+        .collect();
+
+    let merged = merge_logs(vec![equilibration, production]).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert!(merged.id_remaps[0].is_empty());
+    assert_eq!(merged.id_remaps[1].len(), second_len);
+    assert!(merged.id_remaps[1].values().all(|id| id.0 > max_input_id));
+
+    let log = &merged.log;
+    assert_eq!(log.events.len(), first_len + second_len);
+    assert_eq!(log.validate(), vec![]);
+    let sequences: Vec<u64> = log
+        .events
+        .iter()
+        .map(|e| e.temporal.logical_sequence)
+        .collect();
+    assert_eq!(sequences, (1..=log.events.len() as u64).collect::<Vec<_>>());
+
+    // Causal refs of the production energy record follow its remapped MDP events.
+    let energy = &log.events[log.indexes.by_id[&merged.id_remaps[1][&energy_position]]];
+    let expected_refs: Vec<EventId> = production_energy
+        .causal_refs
+        .iter()
+        .map(|id| merged.id_remaps[1][&production_positions[id]])
+        .collect();
+    assert_eq!(energy.causal_refs, expected_refs);
+}
+
+#[test]
+fn test_merge_logs_remaps_each_occurrence_of_a_repeated_id() {
+    let mut log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    // Give the second event the first event's ID, as a faulty writer might,
+    // and point the third event at it.
+    let repeated = log.events[0].id;
+    log.events[1].id = repeated;
+    log.events[2].causal_refs = vec![repeated];
+    let (len, max_input_id) = (
+        log.events.len(),
+        log.events.iter().map(|event| event.id.0).max().unwrap(),
+    );
+
+    let merged = merge_logs(vec![log]).unwrap();
+    let replacement = merged.id_remaps[0][&1];
+    assert_eq!(merged.id_remaps[0].len(), 1);
+    assert!(replacement.0 > max_input_id);
+
+    let events = &merged.log.events;
+    assert_eq!(events.len(), len);
+    assert_eq!(events[0].id, repeated);
+    assert_eq!(events[1].id, replacement);
+    // The reference resolves to the latest earlier event with that ID.
+    assert_eq!(events[2].causal_refs, vec![replacement]);
+    assert_eq!(merged.log.indexes.by_id.len(), len);
+}
+
+#[test]
+fn test_merge_logs_concatenates_in_input_order() {
+    let production = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let equilibration = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let production_steps: Vec<u64> = production
+        .events
+        .iter()
+        .map(|event| event.temporal.simulation_step)
+        .collect();
+    let first_len = production.events.len();
+
+    // Logs are not interleaved by step: the caller's order is the timeline.
+    let merged = merge_logs(vec![production, equilibration]).unwrap();
+    let steps: Vec<u64> = merged.log.events[..first_len]
+        .iter()
+        .map(|event| event.temporal.simulation_step)
+        .collect();
+    assert_eq!(steps, production_steps);
+    assert!(merged.log.events[first_len..]
+        .iter()
+        .all(|event| event.temporal.logical_sequence > first_len as u64));
+}
+
+#[test]
+fn test_merge_logs_reports_spec_conflicts() {
+    let first = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let mut second = gromacs_run(
+        &GROMACS_MDP_SAMPLE.replace("ref_t = 300", "ref_t = 310"),
+        GROMACS_LOG_SAMPLE,
+    );
+    second.spec.dag_refs.push(DagReference {
+        node_id: "dt".to_string(),
+        edge_ids: vec![],
+    });
+
+    let merged = merge_logs(vec![first, second]).unwrap();
+    assert_eq!(
+        merged.conflicts,
+        vec![SpecConflict {
+            section: "controlled_variables",
+            key: "1".to_string(),
+            log_index: 1,
+        }]
+    );
+    let spec = &merged.log.spec;
+    assert_eq!(spec.controlled_variables.len(), 2);
+    assert_eq!(
+        spec.controlled_variables[0].held_value,
+        Value::Known(300.0, "K".into())
+    );
+    assert_eq!(spec.dag_refs.len(), 1);
+
+    // The same NaN definition in both logs is not a conflict.
+    let nan_mdp = GROMACS_MDP_SAMPLE.replace("ref_t = 300", "ref_t = nan");
+    let merged = merge_logs(vec![
+        gromacs_run(&nan_mdp, GROMACS_LOG_SAMPLE),
+        gromacs_run(&nan_mdp, GROMACS_LOG_SAMPLE),
+    ])
+    .unwrap();
+    assert!(merged.conflicts.is_empty(), "{:?}", merged.conflicts);
+    assert_eq!(merged.log.spec.controlled_variables.len(), 2);
+}
+
+#[test]
+fn test_merge_logs_rejects_other_experiments() {
+    assert_eq!(merge_logs(vec![]).unwrap_err(), MergeError::NoLogs);
+
+    let first = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let mut second = first.clone();
+    second.experiment_ref.cycle_id = 1;
+    let err = merge_logs(vec![first, second]).unwrap_err();
+    assert!(matches!(
+        err,
+        MergeError::ExperimentMismatch { log_index: 1, .. }
+    ));
+}
+
+#[test]
+fn test_diff_identical_logs_is_empty() {
+    let log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let result = diff(&log, &log.clone());
+    assert!(result.is_empty(), "{:?}", result);
+    assert_eq!(result.energy.points.len(), 4);
+    assert_eq!(result.energy.unit, "kJ/mol");
+
+    // NaN parameters and spec values match themselves.
+    let nan_mdp = GROMACS_MDP_SAMPLE.replace("ref_t = 300", "ref_t = nan");
+    let log = gromacs_run(&nan_mdp, GROMACS_LOG_STABLE_SERIES);
+    assert!(log.spec.controlled_variables[0]
+        .held_value
+        .quantity()
+        .is_some_and(|quantity| quantity.value.is_nan()));
+    let result = diff(&log, &log.clone());
+    assert!(result.is_empty(), "{:?}", result);
+
+    // Nested spec floats compare by bit pattern too.
+    let mut log = log;
+    log.spec.predictions.push(PredictionRecord {
+        id: SpecElementId(50),
+        variable: "temperature".to_string(),
+        predicted_value: Value::Known(f64::NAN, "K".into()),
+        tolerance: Some(f64::NAN),
+        uncertainty: Some(UncertaintySummary::from_samples(&[1.0, f64::NAN], "mean")),
+    });
+    let mut other = log.clone();
+    assert!(diff(&log, &other).is_empty());
+    other.spec.predictions[0].tolerance = Some(0.5);
+    assert_eq!(
+        diff(&log, &other).spec_changes,
+        vec![SpecChange {
+            section: "predictions",
+            key: "50".to_string(),
+            kind: ChangeKind::Modified,
+        }]
+    );
+}
+
+#[test]
+fn test_diff_reports_parameter_spec_and_trajectory_changes() {
+    let before = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let intervened_mdp = GROMACS_MDP_SAMPLE
+        .replace("dt = 0.002", "dt = 0.004")
+        .replace("ref_p = 1.0\n", "");
+    let after = gromacs_run(&intervened_mdp, GROMACS_LOG_DIVERGENT_NAN_SERIES);
+
+    let result = diff(&before, &after);
+    let parameters: Vec<(&str, ChangeKind)> = result
+        .parameter_changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.kind))
+        .collect();
+    assert_eq!(
+        parameters,
+        vec![("dt", ChangeKind::Modified), ("ref_p", ChangeKind::Removed)]
+    );
+    assert_eq!(
+        result.parameter_changes[0].after.as_ref().unwrap().actual,
+        Value::Known(0.004, "ps".into())
+    );
+    assert_eq!(
+        result.spec_changes,
+        vec![SpecChange {
+            section: "controlled_variables",
+            key: "2".to_string(),
+            kind: ChangeKind::Removed,
+        }]
+    );
+    assert!(result.kind_changes.contains(&KindCountChange {
+        kind: EventKindTag::NumericalStatus,
+        before: 0,
+        after: 1,
+    }));
+
+    assert_eq!(result.energy.first_divergent_step, Some(100));
+    assert_eq!(
+        result.energy.points[1],
+        StepDelta {
+            step: 100,
+            iteration: None,
+            before: Some(-1000.05),
+            after: Some(-999.9),
+        }
+    );
+    assert!(result.outcome_changes.is_empty());
+}
+
+#[test]
+fn test_diff_reports_outcome_changes() {
+    let before = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let after = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_FATAL_ERROR);
+    assert_eq!(
+        diff(&before, &after).outcome_changes,
+        vec![OutcomeChange {
+            index: 0,
+            before: Some(ExecutionOutcome::Success),
+            after: Some(ExecutionOutcome::CrashDivergent),
+        }]
+    );
+}
+
+#[test]
+fn test_diff_compares_energies_across_units() {
+    let before = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let mut after = before.clone();
+    for event in &mut after.events {
+        if let EventKind::EnergyRecord { total, .. } = &mut event.kind {
+            *total = total.convert_to(&"kcal/mol".into()).unwrap();
+        }
+    }
+
+    let result = diff(&before, &after);
+    assert_eq!(result.energy.first_divergent_step, None);
+    assert_eq!(result.energy.unit, "kJ/mol");
+}
+
+#[test]
+fn test_diff_aligns_scf_iterations_and_energy_components() {
+    let before = VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
+    let changed = VASP_COMBINED_SAMPLE
+        .replace(
+            "RMM:   2   -0.200E+00   -0.100E+00",
+            "RMM:   2   -0.200E+00   -0.900E+00",
+        )
+        .replace("E0= -.11410000E+03", "E0= -.11420000E+03");
+    let after = VaspAdapter.parse_trace(&changed).unwrap();
+    assert!(diff(&before, &before.clone()).is_empty());
+
+    let result = diff(&before, &after);
+    let (metric, de) = &result.convergence[0];
+    assert_eq!(metric, "dE");
+    // Each SCF iteration is its own point, not just the last of the step.
+    let keys: Vec<(u64, Option<u64>)> = de
+        .points
+        .iter()
+        .map(|point| (point.step, point.iteration))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (0, Some(1)),
+            (0, Some(2)),
+            (0, Some(3)),
+            (1, Some(1)),
+            (1, Some(2)),
+            (1, Some(3)),
+        ]
+    );
+    let divergent: Vec<&StepDelta> = de.points.iter().filter(|p| p.diverges()).collect();
+    assert_eq!(divergent.len(), 1);
+    assert_eq!(divergent[0].iteration, Some(2));
+    assert_eq!(de.first_divergent_step, Some(1));
+
+    let components: Vec<(&str, Option<u64>)> = result
+        .energy_components
+        .iter()
+        .map(|(name, trajectory)| (name.as_str(), trajectory.first_divergent_step))
+        .collect();
+    assert!(components.contains(&("E0", Some(2))), "{:?}", components);
+    assert_eq!(result.energy.first_divergent_step, None);
+}
+
+/// Helper: an observed parameter record whose actual value is `actual`.
+fn parameter_event(
+    ids: &mut EventIdAllocator,
+    sequence: u64,
+    name: &str,
+    actual: Value,
+) -> TraceEvent {
+    TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ParameterRecord {
+            name: name.into(),
+            specified_value: None,
+            actual_value: actual,
+            units: None,
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence: sequence,
+        })
+        .build(ids)
+}
+
+#[test]
+fn test_binary_roundtrip_adapter_logs() {
+    let raw = format!(
+        "--- MDP ---\n{}\n--- LOG ---\n{}",
+        GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE
+    );
+    let logs = [
+        MockOpenMmAdapter
+            .parse_trace(OPENMM_CSV_DIVERGENT_NAN)
+            .unwrap(),
+        GromacsAdapter.parse_trace(&raw).unwrap(),
+        VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap(),
+    ];
+    for log in &logs {
+        let bytes = encode_log(log, BinaryOptions::default()).unwrap();
+        let restored = decode_log(&bytes).unwrap();
+        assert_eq!(restored.experiment_ref, log.experiment_ref);
+        assert_eq!(
+            serde_json::to_string(&restored.spec).unwrap(),
+            serde_json::to_string(&log.spec).unwrap()
+        );
+        assert_eq!(restored.indexes, log.indexes);
+        assert_eq!(restored.events.len(), log.events.len());
+        // Byte-identical re-encoding covers NaN payloads that JSON cannot.
+        assert_eq!(
+            encode_log(&restored, BinaryOptions::default()).unwrap(),
+            bytes
+        );
+    }
+}
+
+#[test]
+fn test_binary_preserves_havoc_and_non_finite_floats() {
+    let mut ids = EventIdAllocator::new();
+    let payload_nan = f64::from_bits(0x7ff8_0000_dead_beef);
+    let events = vec![
+        parameter_event(&mut ids, 1, "nan", Value::Known(payload_nan, "K".into())),
+        parameter_event(
+            &mut ids,
+            2,
+            "inf",
+            Value::KnownVec(vec![f64::INFINITY, f64::NEG_INFINITY, -0.0], "nm".into()),
+        ),
+        parameter_event(
+            &mut ids,
+            3,
+            "missing",
+            Value::Havoc {
+                expected_type: ValueType::Scalar,
+                reason: HavocReason::TemporalGap {
+                    last_known_step: 400,
+                    gap_steps: 100,
+                },
+            },
+        ),
+    ];
+    let log = test_log_from_events(events);
+    let restored = decode_log(&encode_log(&log, BinaryOptions::default()).unwrap()).unwrap();
+
+    let actual = |event: &TraceEvent| match &event.kind {
+        EventKind::ParameterRecord { actual_value, .. } => actual_value.clone(),
+        other => panic!("unexpected kind {:?}", other),
+    };
+    match actual(&restored.events[0]) {
+        Value::Known(value, _) => assert_eq!(value.to_bits(), payload_nan.to_bits()),
+        other => panic!("unexpected value {:?}", other),
+    }
+    match actual(&restored.events[1]) {
+        Value::KnownVec(values, _) => {
+            let bits: Vec<u64> = values.iter().map(|value| value.to_bits()).collect();
+            assert_eq!(
+                bits,
+                vec![
+                    f64::INFINITY.to_bits(),
+                    f64::NEG_INFINITY.to_bits(),
+                    (-0.0_f64).to_bits()
+                ]
+            );
+        }
+        other => panic!("unexpected value {:?}", other),
+    }
+    assert_eq!(actual(&restored.events[2]), actual(&log.events[2]));
+}
+
+/// Helper: whether any `EnergyRecord` in `log` carries a NaN total or component.
+fn has_nan_energy(log: &LayeredEventLog) -> bool {
+    log.events.iter().any(|event| match &event.kind {
+        EventKind::EnergyRecord {
+            total, components, ..
+        } => std::iter::once(total)
+            .chain(components.iter().map(|(_, value)| value))
+            .any(|value| matches!(value, Value::Known(v, _) if v.is_nan())),
+        _ => false,
+    })
+}
+
+#[test]
+fn test_divergent_fixtures_roundtrip_json_and_binary() {
+    let logs = [
+        MockOpenMmAdapter
+            .parse_trace(OPENMM_CSV_DIVERGENT_NAN)
+            .unwrap(),
+        gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_DIVERGENT_NAN_SERIES),
+        VaspAdapter
+            .parse_trace(VASP_COMBINED_DIVERGENT_SAMPLE)
+            .unwrap(),
+    ];
+    assert!(has_nan_energy(&logs[0]));
+    assert!(has_nan_energy(&logs[1]));
+    for log in &logs {
+        let json = serde_json::to_string(&log.without_indexes()).unwrap();
+        let restored: LayeredEventLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&restored.without_indexes()).unwrap(),
+            json
+        );
+        assert_eq!(has_nan_energy(&restored), has_nan_energy(log));
+
+        let binary = decode_log(&encode_log(log, BinaryOptions::default()).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_string(&binary.without_indexes()).unwrap(),
+            json
+        );
+    }
+}
+
+#[test]
+fn test_value_non_finite_json_representation() {
+    let value = Value::KnownVec(
+        vec![f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1.5],
+        "nm".into(),
+    );
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(
+        json,
+        r#"{"KnownVec":[["NaN","Infinity","-Infinity",1.5],"nm"]}"#
+    );
+    match serde_json::from_str::<Value>(&json).unwrap() {
+        Value::KnownVec(values, _) => {
+            assert!(values[0].is_nan());
+            assert_eq!(values[1..], [f64::INFINITY, f64::NEG_INFINITY, 1.5]);
+        }
+        other => panic!("unexpected value {:?}", other),
+    }
+
+    // serde_json wrote every non-finite float as null before.
+    match serde_json::from_str::<Value>(r#"{"Known":[null,"K"]}"#).unwrap() {
+        Value::Known(value, _) => assert!(value.is_nan()),
+        other => panic!("unexpected value {:?}", other),
+    }
+    assert!(serde_json::from_str::<Value>(r#"{"Known":["nan","K"]}"#).is_err());
+}
+
+#[test]
+fn test_binary_compression_is_optional_and_smaller_than_json() {
+    let log = gromacs_split_log();
+    let plain = encode_log(&log, BinaryOptions::default()).unwrap();
+    let compressed = encode_log(
+        &log,
+        BinaryOptions {
+            compression: Compression::Deflate,
+            ..BinaryOptions::default()
+        },
+    )
+    .unwrap();
+    let json = serde_json::to_vec(&log.without_indexes()).unwrap();
+
+    assert!(plain.len() < json.len());
+    assert!(compressed.len() <= plain.len());
+    assert_eq!(
+        serde_json::to_string(&decode_log(&compressed).unwrap().events).unwrap(),
+        serde_json::to_string(&log.events).unwrap()
+    );
+}
+
+#[test]
+fn test_binary_reader_streams_blocks_and_reports_truncation() {
+    let log = gromacs_split_log();
+    assert!(log.events.len() > 4);
+    let bytes = encode_log(
+        &log,
+        BinaryOptions {
+            block_size: 2,
+            ..BinaryOptions::default()
+        },
+    )
+    .unwrap();
+
+    let streamed: Vec<EventId> = BinaryLogReader::new(bytes.as_slice())
+        .unwrap()
+        .map(|event| event.unwrap().id)
+        .collect();
+    assert_eq!(
+        streamed,
+        log.events.iter().map(|event| event.id).collect::<Vec<_>>()
+    );
+
+    let truncated = &bytes[..bytes.len() - 20];
+    let mut reader = BinaryLogReader::new(truncated).unwrap();
+    assert_eq!(reader.experiment_ref(), &log.experiment_ref);
+    assert_eq!(reader.next().unwrap().unwrap().id, log.events[0].id);
+    assert_eq!(reader.next().unwrap().unwrap().id, log.events[1].id);
+    let results: Vec<_> = reader.collect();
+    assert!(matches!(results.last(), Some(Err(BinaryError::Corrupt(_)))));
+}
+
+#[test]
+fn test_binary_rejects_bad_magic_and_newer_versions() {
+    let log = gromacs_split_log();
+    let mut bytes = encode_log(&log, BinaryOptions::default()).unwrap();
+
+    let mut not_binary = bytes.clone();
+    not_binary[0] = b'{';
+    assert!(matches!(
+        decode_log(&not_binary),
+        Err(BinaryError::BadMagic(_))
+    ));
+
+    bytes[4..6].copy_from_slice(&(BINARY_SCHEMA_VERSION + 1).to_le_bytes());
+    match decode_log(&bytes) {
+        Err(BinaryError::UnsupportedVersion { found, supported }) => {
+            assert_eq!(found, BINARY_SCHEMA_VERSION + 1);
+            assert_eq!(supported, BINARY_SCHEMA_VERSION);
+        }
+        other => panic!("expected UnsupportedVersion, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_event_indexes_rebuild_matches_construction() {
+    let mut ids = EventIdAllocator::new();
+    let log = integrity_test_log(&mut ids);
+    assert_eq!(EventIndexes::rebuild(&log.events), log.indexes);
+
+    let gromacs = gromacs_split_log();
+    assert_eq!(EventIndexes::rebuild(&gromacs.events), gromacs.indexes);
+}
+
+#[test]
+fn test_serialize_without_indexes_rebuilds_on_load() {
+    let log = gromacs_split_log();
+    let full = serde_json::to_string(&log).unwrap();
+    let compact = serde_json::to_string(&log.without_indexes()).unwrap();
+    assert!(compact.len() < full.len());
+
+    let value: serde_json::Value = serde_json::from_str(&compact).unwrap();
+    assert!(value.get("indexes").is_none());
+
+    let restored: LayeredEventLog = serde_json::from_str(&compact).unwrap();
+    assert_eq!(restored.indexes, log.indexes);
+    assert_eq!(restored.validate(), vec![]);
+}
+
+#[test]
+fn test_deserialize_rejects_inconsistent_indexes() {
+    let mut ids = EventIdAllocator::new();
+    let log = integrity_test_log(&mut ids);
+    let mut json: serde_json::Value = serde_json::to_value(&log).unwrap();
+    json["events"].as_array_mut().unwrap().pop();
+
+    let err = serde_json::from_value::<LayeredEventLog>(json).unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.starts_with("indexes disagree with events"),
+        "{}",
+        message
+    );
+    assert!(message.contains("index by_id out of sync at key EventId(3)"));
+}
+
+#[test]
+fn test_serialized_logs_carry_schema_version() {
+    let log = gromacs_split_log();
+    for json in [
+        serde_json::to_value(&log).unwrap(),
+        serde_json::to_value(log.without_indexes()).unwrap(),
+    ] {
+        assert_eq!(document_version(&json).unwrap(), LOG_SCHEMA_VERSION);
+    }
+}
+
+#[test]
+fn test_unversioned_log_is_migrated() {
+    let log = gromacs_split_log();
+    let mut legacy = serde_json::to_value(&log).unwrap();
+    let object = legacy.as_object_mut().unwrap();
+    object.remove("schema_version");
+    object["indexes"].as_object_mut().unwrap().remove("by_step");
+    let legacy = serde_json::to_string(&legacy).unwrap();
+
+    assert!(serde_json::from_str::<LayeredEventLog>(&legacy).is_err());
+    let restored = log_from_json(&legacy).unwrap();
+    assert_eq!(restored.indexes, log.indexes);
+    assert_eq!(restored.events.len(), log.events.len());
+}
+
+#[test]
+fn test_newer_schema_version_is_rejected() {
+    let log = gromacs_split_log();
+    let mut json = serde_json::to_value(&log).unwrap();
+    json["schema_version"] = (LOG_SCHEMA_VERSION + 1).into();
+
+    let err = serde_json::from_value::<LayeredEventLog>(json.clone()).unwrap_err();
+    assert!(err.to_string().contains("is newer than supported"));
+    match log_from_json(&json.to_string()) {
+        Err(SchemaError::NewerVersion { found, supported }) => {
+            assert_eq!(found, LOG_SCHEMA_VERSION + 1);
+            assert_eq!(supported, LOG_SCHEMA_VERSION);
+        }
+        other => panic!("expected NewerVersion, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_migration_registry_applies_registered_steps() {
+    let log = gromacs_split_log();
+    let mut document = serde_json::to_value(&log).unwrap();
+    let object = document.as_object_mut().unwrap();
+    object.remove("schema_version");
+    let experiment_ref = object.remove("experiment_ref").unwrap();
+    object.insert("experiment".to_string(), experiment_ref);
+
+    assert!(matches!(
+        MigrationRegistry::empty().upgrade(&mut document.clone()),
+        Err(SchemaError::MissingMigration { from: 0 })
+    ));
+
+    let mut registry = MigrationRegistry::default();
+    registry.register(0, |document| {
+        let object = document.as_object_mut().ok_or("not an object")?;
+        let experiment = object.remove("experiment").ok_or("missing experiment")?;
+        object.insert("experiment_ref".to_string(), experiment);
+        Ok(())
+    });
+    let restored = registry.load(document).unwrap();
+    assert_eq!(restored.experiment_ref, log.experiment_ref);
+}
+
+/// Helper: a log for every on-disk fixture plus the divergent inline samples.
+fn fixture_logs() -> Vec<LayeredEventLog> {
+    let mut logs: Vec<LayeredEventLog> = [
+        OPENMM_REAL_CSV_DEFAULT_KJ,
+        OPENMM_REAL_CSV_REORDERED_EXTRA_COLUMNS,
+        OPENMM_REAL_CSV_MINIMAL_COLUMNS,
+        OPENMM_REAL_CSV_WINDOWS_CRLF,
+        OPENMM_REAL_CSV_BOM_PREFIX,
+        OPENMM_CSV_DIVERGENT_NAN,
+    ]
+    .into_iter()
+    .map(|csv| MockOpenMmAdapter.parse_trace(csv).unwrap())
+    .collect();
+    for log in [
+        GROMACS_FILE_NVT_MD_LOG,
+        GROMACS_FILE_NPT_EQUILIBRATION_LOG,
+        GROMACS_FILE_ENERGY_MINIMIZATION_LOG,
+        GROMACS_LOG_DIVERGENT_NAN_SERIES,
+    ] {
+        logs.push(gromacs_run(GROMACS_MDP_SAMPLE, log));
+    }
+    for raw in [
+        VASP_FILE_CONVERGED_RELAXATION,
+        VASP_FILE_NONCONVERGED_SCF,
+        VASP_FILE_MIXED_SCF_DAV_RMM,
+        VASP_FILE_T1_HONEYCOMB_PT52,
+        VASP_FILE_T1_LARGE_APPROX,
+        VASP_FILE_T1_SIGMA_PT56_SUBSTRATE,
+        VASP_COMBINED_DIVERGENT_SAMPLE,
+    ] {
+        logs.push(VaspAdapter.parse_trace(raw).unwrap());
+    }
+    logs
+}
+
+#[test]
+fn test_json_schema_covers_ir_types() {
+    let schema = log_json_schema();
+    let defs = schema["$defs"].as_object().unwrap();
+    for name in [
+        "TraceEvent",
+        "EventKind",
+        "Value",
+        "ExperimentSpec",
+        "EventIndexes",
+        "ConfidenceMeta",
+        "HavocReason",
+        "Severity",
+        "LosslessF64",
+    ] {
+        assert!(defs.contains_key(name), "missing definition {}", name);
+    }
+    assert!(schema["properties"]["schema_version"].is_object());
+}
+
+#[test]
+fn test_fixture_logs_validate_against_json_schema() {
+    let validator = jsonschema::validator_for(&log_json_schema()).unwrap();
+    for log in fixture_logs() {
+        for document in [
+            serde_json::to_value(&log).unwrap(),
+            serde_json::to_value(log.without_indexes()).unwrap(),
+        ] {
+            let errors: Vec<String> = validator
+                .iter_errors(&document)
+                .map(|error| format!("{} at {}", error, error.instance_path()))
+                .collect();
+            assert!(
+                errors.is_empty(),
+                "{} fails schema: {:?}",
+                log.experiment_ref.experiment_id,
+                errors
+            );
+        }
+    }
+
+    let mut invalid = serde_json::to_value(gromacs_split_log()).unwrap();
+    invalid["events"][0]["layer"] = "Hardware".into();
+    assert!(!validator.is_valid(&invalid));
+}
+
+#[test]
+fn test_validate_reports_missing_derived_elements() {
+    let mut ids = EventIdAllocator::new();
+    let mut log = integrity_test_log(&mut ids);
+    log.events[2].confidence.completeness = Completeness::Derived {
+        from_elements: vec![ElementId(1), ElementId(7)],
+    };
+
+    assert_eq!(
+        log.validate(),
+        vec![IntegrityViolation::MissingDerivedElement {
+            event: EventId(3),
+            element: ElementId(7),
+        }]
+    );
+}
+
+fn gromacs_split_sources() -> (String, String) {
+    let mdp = GROMACS_MDP_SAMPLE.trim_start_matches('\n').to_string();
+    let log = GROMACS_LOG_SAMPLE.trim_start_matches('\n').to_string();
+    (mdp, log)
+}
+
+fn gromacs_split_log() -> LayeredEventLog {
+    let (mdp, log) = gromacs_split_sources();
+    GromacsAdapter
+        .parse_trace(&format!("--- MDP ---\n{}--- LOG ---\n{}", mdp, log))
+        .unwrap()
+}
+
+#[test]
+fn test_source_text_hashes_exact_line_bytes() {
+    let source = SourceText::new("alpha\r\n  beta\ngamma");
+    assert_eq!(source.line_count(), 3);
+    assert_eq!(source.hash_lines(1, 1), Some(fnv1a_64(b"alpha\r")));
+    assert_eq!(source.hash_lines(2, 3), Some(fnv1a_64(b"  beta\ngamma")));
+    assert_eq!(source.hash_lines(0, 1), None);
+    assert_eq!(source.hash_lines(3, 4), None);
+    assert_eq!(source.hash_lines(2, 1), None);
+
+    // A trailing newline does not add a line or change the hash.
+    let terminated = SourceText::new("alpha\r\n  beta\ngamma\n");
+    assert_eq!(terminated.line_count(), 3);
+    assert_eq!(terminated.hash_lines(3, 3), source.hash_lines(3, 3));
+}
+
+#[test]
+fn test_adapters_hash_anchored_lines() {
+    let mdp = "; comment\nintegrator = md\n\n dt = 0.002 ; ps\n";
+    let events = parse_mdp(mdp, &mut EventIdAllocator::new()).unwrap();
+    assert_eq!(events[1].provenance.raw_hash, fnv1a_64(b" dt = 0.002 ; ps"));
+
+    let vasp = VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
+    let gromacs = gromacs_split_log();
+    for event in vasp.events.iter().chain(&gromacs.events) {
+        if let SourceLocation::LineRange { .. } = event.provenance.source_location {
+            assert_ne!(
+                event.provenance.raw_hash, 0,
+                "unhashed anchor: {:?}",
+                event.kind
+            );
+        }
+    }
+}
+
+#[test]
+fn test_verify_provenance_accepts_original_sources() {
+    let (mdp, log_text) = gromacs_split_sources();
+    let log = gromacs_split_log();
+    let sources = HashMap::from([
+        ("input.mdp".to_string(), mdp),
+        ("simulation.log".to_string(), log_text),
+    ]);
+    assert_eq!(verify_provenance(&log, &sources), vec![]);
+}
+
+#[test]
+fn test_verify_provenance_reports_mismatches() {
+    let (mdp, log_text) = gromacs_split_sources();
+    let mut log = gromacs_split_log();
+
+    // Edit the dt line and truncate the log before the completion marker.
+    let edited_mdp = mdp.replace("dt = 0.002", "dt = 0.004");
+    let truncated_log = log_text
+        .lines()
+        .take_while(|line| !line.contains("Finished mdrun"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let sources = HashMap::from([
+        ("input.mdp".to_string(), edited_mdp),
+        ("simulation.log".to_string(), truncated_log),
+    ]);
+
+    let dt_id = log
+        .events
+        .iter()
+        .find(|event| event.dag_node_ref.as_deref() == Some("dt"))
+        .unwrap()
+        .id;
+    let completion_id = log
+        .events
+        .iter()
+        .find(|event| matches!(event.kind, EventKind::ExecutionStatus { .. }))
+        .unwrap()
+        .id;
+    let integrator = log
+        .events
+        .iter_mut()
+        .find(|event| event.dag_node_ref.as_deref() == Some("integrator"))
+        .unwrap();
+    integrator.provenance.raw_hash = 0;
+    let integrator_id = integrator.id;
+
+    let mismatches = verify_provenance(&log, &sources);
+    let issues: Vec<(EventId, &ProvenanceIssue)> = mismatches
+        .iter()
+        .map(|mismatch| (mismatch.event, &mismatch.issue))
+        .collect();
+    assert_eq!(issues.len(), 3, "{:?}", issues);
+    assert_eq!(issues[0], (integrator_id, &ProvenanceIssue::NotHashed));
+    assert_eq!(issues[1].0, dt_id);
+    assert!(matches!(issues[1].1, ProvenanceIssue::HashMismatch { .. }));
+    assert_eq!(issues[2].0, completion_id);
+    assert!(matches!(issues[2].1, ProvenanceIssue::OutOfRange { .. }));
+
+    let missing = verify_provenance(&log, &HashMap::new());
+    assert!(missing
+        .iter()
+        .all(|mismatch| mismatch.issue == ProvenanceIssue::MissingSource));
+    assert!(!missing.is_empty());
+}
+
+#[test]
+fn test_verify_provenance_in_dir_reads_source_files() {
+    let (mdp, log_text) = gromacs_split_sources();
+    let log = gromacs_split_log();
+    let dir = std::env::temp_dir().join(format!("lel-provenance-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("input.mdp"), mdp).unwrap();
+
+    let mismatches = verify_provenance_in_dir(&log, &dir).unwrap();
+    assert!(!mismatches.is_empty());
+    assert!(mismatches.iter().all(|mismatch| {
+        mismatch.anchor.source_file == "simulation.log"
+            && mismatch.issue == ProvenanceIssue::MissingSource
+    }));
+
+    std::fs::write(dir.join("simulation.log"), log_text).unwrap();
+    assert_eq!(verify_provenance_in_dir(&log, &dir).unwrap(), vec![]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    let adapter = MockOpenMmAdapter;
+    let original = adapter.parse_trace("").unwrap();
+
+    // Serialize to JSON
+    let json = serde_json::to_string_pretty(&original).expect("Serialization must succeed");
+
+    // Verify JSON is non-empty and contains expected fields
+    assert!(json.contains("openmm-mock-001"));
+    assert!(json.contains("force_field"));
+    assert!(json.contains("Theory"));
+    assert!(json.contains("Implementation"));
+
+    // Deserialize back
+    let restored: LayeredEventLog =
+        serde_json::from_str(&json).expect("Deserialization must succeed");
+
+    // Verify structural equality
+    assert_eq!(original.events.len(), restored.events.len());
+    assert_eq!(
+        original.experiment_ref.experiment_id,
+        restored.experiment_ref.experiment_id
+    );
+    assert_eq!(original.experiment_ref.cycle_id, restored.experiment_ref.cycle_id);
+
+    // Verify event-level equality
+    for (orig, rest) in original.events.iter().zip(restored.events.iter()) {
+        assert_eq!(orig.id, rest.id);
+        assert_eq!(orig.layer, rest.layer);
+        assert_eq!(orig.temporal, rest.temporal);
+        assert_eq!(orig.dag_node_ref, rest.dag_node_ref);
+        assert_eq!(orig.spec_ref, rest.spec_ref);
+        assert_eq!(orig.causal_refs, rest.causal_refs);
+    }
+}
+
+const GROMACS_MDP_SAMPLE: &str = r#"
+; GROMACS mdp file
+integrator = md
+dt = 0.002 ; ps
+nsteps = 500000
+coulombtype = PME
+rcoulomb = 1.0
+ref_t = 300
+ref_p = 1.0
+tcoupl = V-rescale
+tau_t = 0.1
+pcoupl = Parrinello-Rahman
+nstlog = 1000
+nstxout = 1000
+"#;
+
+const GROMACS_LOG_SAMPLE: &str = r#"
+             :-) GROMACS - gmx mdrun, 2023.3 (-:
+
+Using 1 GPU
+
+   Step           Time
+      0        0.00000
+
+Energies (kJ/mol)
+   Bond          Angle    Proper Dih.          LJ-14     Coulomb-14
+1234.56       2345.67        345.678       456.789       567.890
+   LJ (SR)   Coulomb (SR)   Coul. recip.      Potential    Kinetic En.
+-12345.6      -54321.0        1234.56      -45678.9       12345.6
    Total Energy   Pressure (bar)
 -33333.3            1.013
 
@@ -3402,7 +5990,6 @@ Finished mdrun on rank 0
 
 #[test]
 fn test_classify_theory_params() {
-    setup();
     let (layer, boundary, units) = classify_mdp_parameter("coulombtype", "PME");
     assert_eq!(layer, Layer::Theory);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -3421,7 +6008,6 @@ fn test_classify_theory_params() {
 
 #[test]
 fn test_classify_methodology_params() {
-    setup();
     let (layer, boundary, units) = classify_mdp_parameter("integrator", "md");
     assert_eq!(layer, Layer::Methodology);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -3439,7 +6025,6 @@ fn test_classify_methodology_params() {
 
 #[test]
 fn test_classify_implementation_params() {
-    setup();
     let (layer, boundary, units) = classify_mdp_parameter("nstlog", "1000");
     assert_eq!(layer, Layer::Implementation);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -3453,7 +6038,6 @@ fn test_classify_implementation_params() {
 
 #[test]
 fn test_classify_dual_annotated() {
-    setup();
     let (_, boundary, _) = classify_mdp_parameter("dt", "0.002");
     match boundary {
         BoundaryClassification::DualAnnotated {
@@ -3481,7 +6065,6 @@ fn test_classify_dual_annotated() {
 
 #[test]
 fn test_classify_unknown_param() {
-    setup();
     let (layer, boundary, units) = classify_mdp_parameter("mystery_param", "42");
     assert_eq!(layer, Layer::Implementation);
     assert_eq!(units, None);
@@ -3499,7 +6082,7 @@ fn test_classify_unknown_param() {
 
 #[test]
 fn test_parse_mdp_basic() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let mdp = r#"
 integrator = md
 dt = 0.002
@@ -3507,13 +6090,13 @@ nsteps = 500000
 coulombtype = PME
 ref_t = 300
 "#;
-    let events = parse_mdp(mdp).unwrap();
+    let events = parse_mdp(mdp, &mut ids).unwrap();
     assert_eq!(events.len(), 5);
 
     let names: Vec<String> = events
         .iter()
         .map(|event| match &event.kind {
-            EventKind::ParameterRecord { name, .. } => name.clone(),
+            EventKind::ParameterRecord { name, .. } => name.to_string(),
             other => panic!("Expected ParameterRecord, got {:?}", other),
         })
         .collect();
@@ -3524,7 +6107,7 @@ ref_t = 300
 
     match &events[1].kind {
         EventKind::ParameterRecord { actual_value, .. } => {
-            assert_eq!(actual_value, &Value::Known(0.002, "ps".to_string()));
+            assert_eq!(actual_value, &Value::Known(0.002, "ps".into()));
         }
         other => panic!("Expected ParameterRecord for dt, got {:?}", other),
     }
@@ -3532,14 +6115,14 @@ ref_t = 300
 
 #[test]
 fn test_parse_mdp_comments_stripped() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let mdp = "dt = 0.002 ; ps\nintegrator = md ; leapfrog\n";
-    let events = parse_mdp(mdp).unwrap();
+    let events = parse_mdp(mdp, &mut ids).unwrap();
     assert_eq!(events.len(), 2);
 
     match &events[0].kind {
         EventKind::ParameterRecord { actual_value, .. } => {
-            assert_eq!(actual_value, &Value::Known(0.002, "ps".to_string()));
+            assert_eq!(actual_value, &Value::Known(0.002, "ps".into()));
         }
         other => panic!("Expected ParameterRecord for dt, got {:?}", other),
     }
@@ -3554,15 +6137,15 @@ fn test_parse_mdp_comments_stripped() {
 
 #[test]
 fn test_parse_mdp_empty() {
-    setup();
-    let events = parse_mdp("").unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_mdp("", &mut ids).unwrap();
     assert!(events.is_empty());
 }
 
 #[test]
 fn test_parse_mdp_layer_distribution() {
-    setup();
-    let events = parse_mdp(GROMACS_MDP_SAMPLE).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_mdp(GROMACS_MDP_SAMPLE, &mut ids).unwrap();
     assert!(!events.is_empty());
 
     let layers: std::collections::HashSet<Layer> =
@@ -3574,9 +6157,9 @@ fn test_parse_mdp_layer_distribution() {
 
 #[test]
 fn test_parse_mdp_provenance_lines() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let mdp = "; comment\nintegrator = md\n\n dt = 0.002 ; ps\n; another comment\nnstlog = 1000\n";
-    let events = parse_mdp(mdp).unwrap();
+    let events = parse_mdp(mdp, &mut ids).unwrap();
     assert_eq!(events.len(), 3);
 
     let line_starts: Vec<u32> = events
@@ -3595,7 +6178,7 @@ fn test_parse_mdp_provenance_lines() {
 
 #[test]
 fn test_parse_energy_block() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let log = r#"
    Step           Time
       0        0.00000
@@ -3606,7 +6189,7 @@ Energies (kJ/mol)
 3.0
 Finished mdrun on rank 0
 "#;
-    let events = parse_log(log, 0).unwrap();
+    let events = parse_log(log, 0, &mut ids).unwrap();
     let energy = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -3615,15 +6198,15 @@ Finished mdrun on rank 0
         })
         .expect("Expected at least one EnergyRecord");
 
-    assert_eq!(energy.0, &Value::Known(3.0, "kJ/mol".to_string()));
+    assert_eq!(energy.0, &Value::Known(3.0, "kJ/mol".into()));
     assert!(energy.1.iter().any(|(name, _)| name == "Bond"));
     assert!(energy.1.iter().any(|(name, _)| name == "Angle"));
 }
 
 #[test]
 fn test_parse_log_header() {
-    setup();
-    let events = parse_log(GROMACS_LOG_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_log(GROMACS_LOG_SAMPLE, 0, &mut ids).unwrap();
     let resource = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -3642,8 +6225,8 @@ fn test_parse_log_header() {
 
 #[test]
 fn test_parse_log_energy_record() {
-    setup();
-    let events = parse_log(GROMACS_LOG_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_log(GROMACS_LOG_SAMPLE, 0, &mut ids).unwrap();
     let energy = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -3665,12 +6248,11 @@ fn test_parse_log_energy_record() {
 
 #[test]
 fn test_parse_log_nan_detection() {
-    setup();
-    let events = parse_log(GROMACS_LOG_NAN, 0).unwrap();
-    assert!(events.iter().any(|event| matches!(
-        event.kind,
-        EventKind::EnergyRecord { .. }
-    )));
+    let mut ids = EventIdAllocator::new();
+    let events = parse_log(GROMACS_LOG_NAN, 0, &mut ids).unwrap();
+    assert!(events
+        .iter()
+        .any(|event| matches!(event.kind, EventKind::EnergyRecord { .. })));
     assert!(events.iter().any(|event| matches!(
         event.kind,
         EventKind::NumericalStatus {
@@ -3682,8 +6264,8 @@ fn test_parse_log_nan_detection() {
 
 #[test]
 fn test_parse_log_success() {
-    setup();
-    let events = parse_log(GROMACS_LOG_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_log(GROMACS_LOG_SAMPLE, 0, &mut ids).unwrap();
     assert!(events.iter().any(|event| matches!(
         event.kind,
         EventKind::ExecutionStatus {
@@ -3695,8 +6277,8 @@ fn test_parse_log_success() {
 
 #[test]
 fn test_parse_log_fatal_error() {
-    setup();
-    let events = crate::gromacs_adapter::parse_log(GROMACS_LOG_FATAL_ERROR, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = crate::gromacs_adapter::parse_log(GROMACS_LOG_FATAL_ERROR, 0, &mut ids).unwrap();
     let last = events.last().expect("Expected at least one event");
     match &last.kind {
         EventKind::ExecutionStatus {
@@ -3709,8 +6291,8 @@ fn test_parse_log_fatal_error() {
 
 #[test]
 fn test_parse_log_truncated() {
-    setup();
-    let events = parse_log(GROMACS_LOG_TRUNCATED, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_log(GROMACS_LOG_TRUNCATED, 0, &mut ids).unwrap();
     let timeout_event = events
         .iter()
         .find(|event| {
@@ -3737,7 +6319,7 @@ fn test_parse_log_truncated() {
 /// GROMACS 2023.x documented block shape and parser normalization table conventions.
 #[test]
 fn test_gromacs_log_variant_standard_nvt_md() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let expected_pairs = [
         (0, -10000.0000),
         (100, -10000.5000),
@@ -3750,7 +6332,7 @@ fn test_gromacs_log_variant_standard_nvt_md() {
         ConvergencePattern::Converged,
     );
 
-    let components = parse_log(GROMACS_FILE_NVT_MD_LOG, 0)
+    let components = parse_log(GROMACS_FILE_NVT_MD_LOG, 0, &mut ids)
         .unwrap()
         .into_iter()
         .find_map(|event| match event.kind {
@@ -3784,7 +6366,7 @@ fn test_gromacs_log_variant_standard_nvt_md() {
 /// hyphenated single-token headers consistent with GROMACS 2023.x log formatting.
 #[test]
 fn test_gromacs_log_variant_npt_equilibration() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let expected_pairs = [
         (0, -9000.0000),
         (100, -9000.4500),
@@ -3797,7 +6379,7 @@ fn test_gromacs_log_variant_npt_equilibration() {
         ConvergencePattern::Converged,
     );
 
-    let events = parse_log(GROMACS_FILE_NPT_EQUILIBRATION_LOG, 0).unwrap();
+    let events = parse_log(GROMACS_FILE_NPT_EQUILIBRATION_LOG, 0, &mut ids).unwrap();
     let components = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -3822,7 +6404,7 @@ fn test_gromacs_log_variant_npt_equilibration() {
 /// steep minimization blocks where Potential is present and Total Energy is absent.
 #[test]
 fn test_gromacs_log_variant_energy_minimization() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     assert_gromacs_log_parses_energy_count(GROMACS_FILE_ENERGY_MINIMIZATION_LOG, 0);
     assert_gromacs_log_variant(
         GROMACS_FILE_ENERGY_MINIMIZATION_LOG,
@@ -3830,7 +6412,7 @@ fn test_gromacs_log_variant_energy_minimization() {
         ConvergencePattern::InsufficientData,
     );
 
-    let warning_count = parse_log(GROMACS_FILE_ENERGY_MINIMIZATION_LOG, 0)
+    let warning_count = parse_log(GROMACS_FILE_ENERGY_MINIMIZATION_LOG, 0, &mut ids)
         .unwrap()
         .into_iter()
         .filter(|event| {
@@ -3851,7 +6433,6 @@ fn test_gromacs_log_variant_energy_minimization() {
 /// canonical GROMACS 2023.x energy block structure.
 #[test]
 fn test_gromacs_log_variant_compact_block() {
-    setup();
     let expected_pairs = [
         (0, -10000.0000),
         (100, -10000.5000),
@@ -3869,7 +6450,7 @@ fn test_gromacs_log_variant_compact_block() {
 /// GROMACS 2023.x block formatting to stress header/value alignment.
 #[test]
 fn test_gromacs_log_variant_wide_block() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     assert_gromacs_log_parses_energy_count(GROMACS_LOG_WIDE_BLOCK, 1);
     assert_gromacs_log_variant(
         GROMACS_LOG_WIDE_BLOCK,
@@ -3877,7 +6458,7 @@ fn test_gromacs_log_variant_wide_block() {
         ConvergencePattern::InsufficientData,
     );
 
-    let components = parse_log(GROMACS_LOG_WIDE_BLOCK, 0)
+    let components = parse_log(GROMACS_LOG_WIDE_BLOCK, 0, &mut ids)
         .unwrap()
         .into_iter()
         .find_map(|event| match event.kind {
@@ -3911,7 +6492,6 @@ fn test_gromacs_log_variant_wide_block() {
 /// numeric formatting and verify f64 parsing behavior.
 #[test]
 fn test_gromacs_log_variant_scientific_notation() {
-    setup();
     let expected_pairs = [
         (0, -1.234560e+05),
         (100, -1.234570e+05),
@@ -3929,7 +6509,6 @@ fn test_gromacs_log_variant_scientific_notation() {
 /// when EOF occurs after headers but before value rows.
 #[test]
 fn test_gromacs_log_variant_truncated_mid_block() {
-    setup();
     assert_gromacs_log_parses_energy_count(GROMACS_LOG_TRUNCATED_MID_BLOCK, 1);
     assert_gromacs_log_variant(
         GROMACS_LOG_TRUNCATED_MID_BLOCK,
@@ -3942,7 +6521,6 @@ fn test_gromacs_log_variant_truncated_mid_block() {
 /// GROMACS headers to verify non-space whitespace tokenization.
 #[test]
 fn test_gromacs_log_variant_tab_whitespace() {
-    setup();
     let expected_pairs = [
         (0, -10000.0000),
         (100, -10000.5000),
@@ -3960,7 +6538,6 @@ fn test_gromacs_log_variant_tab_whitespace() {
 /// GROMACS block formatting to verify 9+ decimal-place parsing.
 #[test]
 fn test_gromacs_log_variant_double_precision() {
-    setup();
     let expected_pairs = [
         (0, -12345.123456789),
         (100, -12345.123956789),
@@ -3978,7 +6555,7 @@ fn test_gromacs_log_variant_double_precision() {
 /// energy block format; documents current prototype limitation for EM convergence.
 #[test]
 fn test_gromacs_log_variant_em_no_total_energy() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     assert_gromacs_log_parses_energy_count(GROMACS_LOG_EM_NO_TOTAL_ENERGY, 0);
     assert_gromacs_log_variant(
         GROMACS_LOG_EM_NO_TOTAL_ENERGY,
@@ -3986,7 +6563,7 @@ fn test_gromacs_log_variant_em_no_total_energy() {
         ConvergencePattern::InsufficientData,
     );
 
-    let warning_count = parse_log(GROMACS_LOG_EM_NO_TOTAL_ENERGY, 0)
+    let warning_count = parse_log(GROMACS_LOG_EM_NO_TOTAL_ENERGY, 0, &mut ids)
         .unwrap()
         .into_iter()
         .filter(|event| {
@@ -4005,7 +6582,6 @@ fn test_gromacs_log_variant_em_no_total_energy() {
 
 #[test]
 fn test_gromacs_adapter_combined() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4028,7 +6604,6 @@ fn test_gromacs_adapter_combined() {
 
 #[test]
 fn test_gromacs_adapter_derives_convergence_summary_for_stable_series() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4055,7 +6630,6 @@ fn test_gromacs_adapter_derives_convergence_summary_for_stable_series() {
 
 #[test]
 fn test_gromacs_adapter_derives_oscillation_summary_for_non_converging_series() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4082,7 +6656,6 @@ fn test_gromacs_adapter_derives_oscillation_summary_for_non_converging_series()
 
 #[test]
 fn test_gromacs_adapter_no_convergence_summary_below_min_window() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4098,7 +6671,6 @@ fn test_gromacs_adapter_no_convergence_summary_below_min_window() {
 
 #[test]
 fn test_gromacs_adapter_convergence_summary_provenance_refs() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4141,7 +6713,6 @@ fn test_gromacs_adapter_convergence_summary_provenance_refs() {
 
 #[test]
 fn test_gromacs_adapter_mdp_only() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!("--- MDP ---\n{}", GROMACS_MDP_SAMPLE);
     let log = adapter.parse_trace(&raw).unwrap();
@@ -4156,7 +6727,6 @@ fn test_gromacs_adapter_mdp_only() {
 
 #[test]
 fn test_gromacs_adapter_controlled_vars() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!("--- MDP ---\n{}", GROMACS_MDP_SAMPLE);
     let log = adapter.parse_trace(&raw).unwrap();
@@ -4174,7 +6744,6 @@ fn test_gromacs_adapter_controlled_vars() {
 
 #[test]
 fn test_gromacs_overlay_construction() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4188,7 +6757,6 @@ fn test_gromacs_overlay_construction() {
 
 #[test]
 fn test_gromacs_overlay_layer_span() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4203,7 +6771,6 @@ fn test_gromacs_overlay_layer_span() {
 
 #[test]
 fn test_gromacs_e2e_confounder_detection() {
-    setup();
     let adapter = GromacsAdapter;
     let raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -4439,7 +7006,7 @@ fn test_vasp_rebuild_log(log: &LayeredEventLog) -> LayeredEventLog {
 }
 
 fn parse_vasp_oszicar_energy_pairs(oszicar: &str) -> Vec<(u64, f64)> {
-    parse_oszicar(oszicar, 0)
+    parse_oszicar(oszicar, 0, &mut EventIdAllocator::new())
         .unwrap()
         .into_iter()
         .filter_map(|event| match event.kind {
@@ -4453,7 +7020,7 @@ fn parse_vasp_oszicar_energy_pairs(oszicar: &str) -> Vec<(u64, f64)> {
 }
 
 fn parse_vasp_oszicar_convergence_pairs(oszicar: &str) -> Vec<(u64, f64)> {
-    parse_oszicar(oszicar, 0)
+    parse_oszicar(oszicar, 0, &mut EventIdAllocator::new())
         .unwrap()
         .into_iter()
         .filter_map(|event| match event.kind {
@@ -4533,7 +7100,6 @@ fn assert_vasp_execution_status(combined: &str, expected: ExecutionOutcome) {
 
 #[test]
 fn test_vasp_classify_theory_params() {
-    setup();
     let (layer, boundary, units) = classify_incar_parameter("GGA", "PE");
     assert_eq!(layer, Layer::Theory);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -4547,7 +7113,6 @@ fn test_vasp_classify_theory_params() {
 
 #[test]
 fn test_vasp_classify_methodology_params() {
-    setup();
     let (layer, boundary, units) = classify_incar_parameter("IBRION", "2");
     assert_eq!(layer, Layer::Methodology);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -4566,7 +7131,6 @@ fn test_vasp_classify_methodology_params() {
 
 #[test]
 fn test_vasp_classify_implementation_params() {
-    setup();
     let (layer, boundary, units) = classify_incar_parameter("NCORE", "4");
     assert_eq!(layer, Layer::Implementation);
     assert_eq!(boundary, BoundaryClassification::PrimaryLayer);
@@ -4580,8 +7144,6 @@ fn test_vasp_classify_implementation_params() {
 
 #[test]
 fn test_vasp_classify_dual_annotated() {
-    setup();
-
     let (_, boundary, _) = classify_incar_parameter("ENCUT", "520");
     match boundary {
         BoundaryClassification::DualAnnotated {
@@ -4657,7 +7219,6 @@ fn test_vasp_classify_dual_annotated() {
 
 #[test]
 fn test_vasp_classify_unknown_param() {
-    setup();
     let (layer, boundary, units) = classify_incar_parameter("MYSTERY", "42");
     assert_eq!(layer, Layer::Implementation);
     assert_eq!(units, None);
@@ -4676,8 +7237,8 @@ fn test_vasp_classify_unknown_param() {
 
 #[test]
 fn test_vasp_parse_incar_basic() {
-    setup();
-    let events = parse_incar(VASP_INCAR_SAMPLE).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_incar(VASP_INCAR_SAMPLE, &mut ids).unwrap();
     assert_eq!(events.len(), 13);
     assert!(events
         .iter()
@@ -4686,7 +7247,7 @@ fn test_vasp_parse_incar_basic() {
     let names: Vec<String> = events
         .iter()
         .map(|event| match &event.kind {
-            EventKind::ParameterRecord { name, .. } => name.clone(),
+            EventKind::ParameterRecord { name, .. } => name.to_string(),
             other => panic!("Expected ParameterRecord, got {:?}", other),
         })
         .collect();
@@ -4713,8 +7274,8 @@ fn test_vasp_parse_incar_basic() {
 
 #[test]
 fn test_vasp_parse_incar_comments_stripped() {
-    setup();
-    let events = parse_incar(VASP_INCAR_SAMPLE).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_incar(VASP_INCAR_SAMPLE, &mut ids).unwrap();
 
     let encut = events
         .iter()
@@ -4727,7 +7288,7 @@ fn test_vasp_parse_incar_comments_stripped() {
         .expect("Expected ENCUT");
     match &encut.kind {
         EventKind::ParameterRecord { actual_value, .. } => {
-            assert_eq!(actual_value, &Value::Known(520.0, "eV".to_string()));
+            assert_eq!(actual_value, &Value::Known(520.0, "eV".into()));
         }
         other => panic!("Expected ParameterRecord for ENCUT, got {:?}", other),
     }
@@ -4743,7 +7304,7 @@ fn test_vasp_parse_incar_comments_stripped() {
         .expect("Expected SIGMA");
     match &sigma.kind {
         EventKind::ParameterRecord { actual_value, .. } => {
-            assert_eq!(actual_value, &Value::Known(0.05, "eV".to_string()));
+            assert_eq!(actual_value, &Value::Known(0.05, "eV".into()));
         }
         other => panic!("Expected ParameterRecord for SIGMA, got {:?}", other),
     }
@@ -4751,17 +7312,16 @@ fn test_vasp_parse_incar_comments_stripped() {
 
 #[test]
 fn test_vasp_parse_incar_empty() {
-    setup();
-    let events = parse_incar("").unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_incar("", &mut ids).unwrap();
     assert!(events.is_empty());
 }
 
 #[test]
 fn test_vasp_parse_incar_layer_distribution() {
-    setup();
-    let events = parse_incar(VASP_INCAR_SAMPLE).unwrap();
-    let layers: std::collections::HashSet<Layer> =
-        events.iter().map(|event| event.layer).collect();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_incar(VASP_INCAR_SAMPLE, &mut ids).unwrap();
+    let layers: std::collections::HashSet<Layer> = events.iter().map(|event| event.layer).collect();
 
     assert!(layers.contains(&Layer::Theory));
     assert!(layers.contains(&Layer::Methodology));
@@ -4770,8 +7330,8 @@ fn test_vasp_parse_incar_layer_distribution() {
 
 #[test]
 fn test_vasp_parse_oszicar_convergence_points() {
-    setup();
-    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0, &mut ids).unwrap();
     let convergence_count = events
         .iter()
         .filter(|event| matches!(event.kind, EventKind::ConvergencePoint { .. }))
@@ -4781,8 +7341,8 @@ fn test_vasp_parse_oszicar_convergence_points() {
 
 #[test]
 fn test_vasp_parse_oszicar_energy_records() {
-    setup();
-    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0, &mut ids).unwrap();
     let totals: Vec<f64> = events
         .iter()
         .filter_map(|event| match &event.kind {
@@ -4801,8 +7361,8 @@ fn test_vasp_parse_oszicar_energy_records() {
 
 #[test]
 fn test_vasp_parse_oszicar_convergence_flagged() {
-    setup();
-    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0, &mut ids).unwrap();
     let converged_count = events
         .iter()
         .filter(|event| {
@@ -4818,15 +7378,31 @@ fn test_vasp_parse_oszicar_convergence_flagged() {
     assert_eq!(converged_count, 2);
 }
 
+#[test]
+fn test_vasp_parse_oszicar_explicit_no_uncertainty() {
+    let mut ids = EventIdAllocator::new();
+    let events = parse_oszicar(VASP_OSZICAR_SAMPLE, 0, &mut ids).unwrap();
+    for event in &events {
+        if let EventKind::ConvergencePoint { uncertainty, .. } = &event.kind {
+            assert_eq!(
+                uncertainty,
+                &Some(UncertaintySummary::unavailable(
+                    UncertaintyUnavailableReason::AdapterCannotObserve
+                ))
+            );
+        }
+    }
+}
+
 #[test]
 fn test_vasp_parse_oszicar_single_step() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let sample = r#"
 DAV:   1    0.100E+02    0.100E+02   -0.500E+00   200   0.200E+02
 DAV:   2   -0.200E+01   -0.100E+01   -0.100E+00   220   0.120E+02
    1 F= -.10000000E+02 E0= -.99900000E+01  d E =-.10000000E-01
 "#;
-    let events = parse_oszicar(sample, 0).unwrap();
+    let events = parse_oszicar(sample, 0, &mut ids).unwrap();
 
     let convergence_count = events
         .iter()
@@ -4843,8 +7419,8 @@ DAV:   2   -0.200E+01   -0.100E+01   -0.100E+00   220   0.120E+02
 
 #[test]
 fn test_vasp_parse_outcar_resource_status() {
-    setup();
-    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0, &mut ids).unwrap();
     let resource = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -4865,8 +7441,8 @@ fn test_vasp_parse_outcar_resource_status() {
 
 #[test]
 fn test_vasp_parse_outcar_energy_and_forces() {
-    setup();
-    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0, &mut ids).unwrap();
 
     assert!(events
         .iter()
@@ -4882,8 +7458,8 @@ fn test_vasp_parse_outcar_energy_and_forces() {
 
 #[test]
 fn test_vasp_parse_outcar_success() {
-    setup();
-    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_SAMPLE, 0, &mut ids).unwrap();
     assert!(events.iter().any(|event| matches!(
         event.kind,
         EventKind::ExecutionStatus {
@@ -4895,8 +7471,8 @@ fn test_vasp_parse_outcar_success() {
 
 #[test]
 fn test_vasp_parse_outcar_truncated() {
-    setup();
-    let events = parse_outcar(VASP_OUTCAR_TRUNCATED, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_TRUNCATED, 0, &mut ids).unwrap();
     let timeout = events
         .iter()
         .find(|event| {
@@ -4908,20 +7484,136 @@ fn test_vasp_parse_outcar_truncated() {
                 }
             )
         })
-        .expect("Expected timeout event");
+        .expect("Expected timeout event");
+
+    match &timeout.confidence.completeness {
+        Completeness::PartiallyInferred { inference_method } => {
+            assert_eq!(inference_method, "no completion marker in OUTCAR");
+        }
+        other => panic!("Expected PartiallyInferred, got {:?}", other),
+    }
+    assert!((timeout.confidence.field_coverage - 0.5).abs() < f32::EPSILON);
+}
+
+const VASP_OUTCAR_FORCES_AND_CHGCAR: &str = r#"
+vasp.6.4.2 18Apr23 complex
+   dimension x,y,z NGXF=    48 NGYF=   48 NGZF=   60
+   LCHARG       =      T    write CHGCAR
+   LVTOT        =      F    write LOCPOT, total local potential
+free  energy   TOTEN  =      -114.50000000 eV
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.100000     -0.200000      0.300000
+      1.38500      2.39889      0.00000        -0.100000      0.200000     -0.300000
+ -----------------------------------------------------------------------------------
+    total drift:                                0.000000      0.000000      0.000000
+General timing and accounting
+"#;
+
+#[test]
+fn test_vasp_parse_outcar_force_grid() {
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_FORCES_AND_CHGCAR, 0, &mut ids).unwrap();
+    let force_event = events
+        .iter()
+        .find(|event| {
+            matches!(
+                &event.kind,
+                EventKind::ObservableMeasurement { variable_name, .. }
+                    if variable_name == "total_force"
+            )
+        })
+        .expect("Expected total_force measurement");
+
+    let EventKind::ObservableMeasurement { value, .. } = &force_event.kind else {
+        unreachable!();
+    };
+    match value {
+        Value::KnownGrid {
+            axes,
+            values,
+            value_unit,
+        } => {
+            assert_eq!(axes.len(), 2);
+            assert_eq!(axes[0].label, "ion");
+            assert_eq!(axes[0].coordinates, vec![1.0, 2.0]);
+            assert_eq!(value_unit, "eV/Angst");
+            assert_eq!(values, &vec![0.1, -0.2, 0.3, -0.1, 0.2, -0.3]);
+        }
+        other => panic!("Expected KnownGrid, got {:?}", other),
+    }
+    assert_eq!(value.shape(), Some(vec![2, 3]));
+    assert_eq!(
+        force_event.provenance.source_location,
+        SourceLocation::LineRange { start: 7, end: 10 }
+    );
+}
+
+#[test]
+fn test_vasp_parse_outcar_volumetric_data_ref() {
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_FORCES_AND_CHGCAR, 0, &mut ids).unwrap();
+    let data_refs: Vec<&Value> = events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::ObservableMeasurement {
+                value: value @ Value::DataRef { .. },
+                ..
+            } => Some(value),
+            _ => None,
+        })
+        .collect();
+
+    // LCHARG = T writes CHGCAR; LVTOT = F writes nothing.
+    assert_eq!(data_refs.len(), 1);
+    assert_eq!(
+        data_refs[0],
+        &Value::DataRef {
+            path: "CHGCAR".to_string(),
+            data_type: "charge_density".to_string(),
+            shape: vec![48, 48, 60],
+            unit: "e".into(),
+            checksum: None,
+        }
+    );
+}
+
+#[test]
+fn test_vasp_t1_force_grid_matches_ion_count() {
+    let log = VaspAdapter
+        .parse_trace(VASP_FILE_T1_HONEYCOMB_PT52)
+        .unwrap();
+    let force_values = log
+        .events
+        .iter()
+        .find_map(|event| match &event.kind {
+            EventKind::ObservableMeasurement {
+                variable_name,
+                value,
+                ..
+            } if variable_name == "total_force" => Some(value),
+            _ => None,
+        })
+        .expect("Expected total_force measurement");
 
-    match &timeout.confidence.completeness {
-        Completeness::PartiallyInferred { inference_method } => {
-            assert_eq!(inference_method, "no completion marker in OUTCAR");
+    let table_rows = VASP_FILE_T1_HONEYCOMB_PT52
+        .lines()
+        .skip_while(|line| !line.contains("TOTAL-FORCE"))
+        .skip(2)
+        .take_while(|line| !line.trim_start().starts_with("---"))
+        .count();
+    assert_eq!(force_values.shape(), Some(vec![table_rows, 3]));
+    assert!(!log.events.iter().any(|event| matches!(
+        event.kind,
+        EventKind::ObservableMeasurement {
+            value: Value::DataRef { .. },
+            ..
         }
-        other => panic!("Expected PartiallyInferred, got {:?}", other),
-    }
-    assert!((timeout.confidence.field_coverage - 0.5).abs() < f32::EPSILON);
+    )));
 }
 
 #[test]
 fn test_vasp_adapter_combined() {
-    setup();
     let adapter = VaspAdapter;
     let log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
 
@@ -4953,7 +7645,6 @@ fn test_vasp_adapter_combined() {
 
 #[test]
 fn test_vasp_adapter_incar_only() {
-    setup();
     let adapter = VaspAdapter;
     let log = adapter.parse_trace(VASP_INCAR_SAMPLE).unwrap();
 
@@ -4966,7 +7657,6 @@ fn test_vasp_adapter_incar_only() {
 
 #[test]
 fn test_vasp_adapter_controlled_vars_empty() {
-    setup();
     let adapter = VaspAdapter;
     let log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
     assert!(log.spec.controlled_variables.is_empty());
@@ -4974,7 +7664,6 @@ fn test_vasp_adapter_controlled_vars_empty() {
 
 #[test]
 fn test_vasp_overlay_construction() {
-    setup();
     let adapter = VaspAdapter;
     let log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
     let overlay = CausalOverlay::from_log(&log);
@@ -4983,7 +7672,6 @@ fn test_vasp_overlay_construction() {
 
 #[test]
 fn test_vasp_overlay_layer_span() {
-    setup();
     let adapter = VaspAdapter;
     let log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
     assert!(log.indexes.by_layer.contains_key(&Layer::Theory));
@@ -4993,7 +7681,6 @@ fn test_vasp_overlay_layer_span() {
 
 #[test]
 fn test_vasp_hidden_confounder_litmus() {
-    setup();
     let adapter = VaspAdapter;
     let mut log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
 
@@ -5030,7 +7717,6 @@ fn test_vasp_hidden_confounder_litmus() {
 
 #[test]
 fn test_vasp_hidden_confounder_controlled_excluded() {
-    setup();
     let adapter = VaspAdapter;
     let mut log = adapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap();
 
@@ -5069,8 +7755,8 @@ fn test_vasp_hidden_confounder_controlled_excluded() {
 
 #[test]
 fn test_vasp_adapter_error_execution() {
-    setup();
-    let events = parse_outcar(VASP_OUTCAR_ERROR, 0).unwrap();
+    let mut ids = EventIdAllocator::new();
+    let events = parse_outcar(VASP_OUTCAR_ERROR, 0, &mut ids).unwrap();
     let execution = events
         .iter()
         .find_map(|event| match &event.kind {
@@ -5084,7 +7770,7 @@ fn test_vasp_adapter_error_execution() {
 
 #[test]
 fn test_vasp_variant_converged_relaxation() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let oszicar = VASP_FILE_CONVERGED_RELAXATION
         .split("--- OSZICAR ---")
         .nth(1)
@@ -5094,14 +7780,14 @@ fn test_vasp_variant_converged_relaxation() {
     let parsed_pairs = parse_vasp_oszicar_energy_pairs(oszicar);
     assert_eq!(parsed_pairs, expected_pairs);
 
-    let oszicar_events = parse_oszicar(oszicar, 0).unwrap();
+    let oszicar_events = parse_oszicar(oszicar, 0, &mut ids).unwrap();
     let component_names: Vec<Vec<String>> = oszicar_events
         .iter()
         .filter_map(|event| match &event.kind {
             EventKind::EnergyRecord { components, .. } => Some(
                 components
                     .iter()
-                    .map(|(name, _)| name.clone())
+                    .map(|(name, _)| name.to_string())
                     .collect::<Vec<String>>(),
             ),
             _ => None,
@@ -5125,7 +7811,7 @@ fn test_vasp_variant_converged_relaxation() {
 
 #[test]
 fn test_vasp_variant_nonconverged_scf() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let oszicar = VASP_FILE_NONCONVERGED_SCF
         .split("--- OSZICAR ---")
         .nth(1)
@@ -5136,7 +7822,7 @@ fn test_vasp_variant_nonconverged_scf() {
     assert!(energy_pairs.is_empty());
 
     let convergence_pairs = parse_vasp_oszicar_convergence_pairs(oszicar);
-    let expected_pairs = vec![(1, 50.0), (2, 20.0), (3, 10.0), (4, 5.0), (5, 2.0)];
+    let expected_pairs = [(1, 50.0), (2, 20.0), (3, 10.0), (4, 5.0), (5, 2.0)];
     assert_eq!(convergence_pairs.len(), expected_pairs.len());
     for ((actual_iteration, actual_value), (expected_iteration, expected_value)) in
         convergence_pairs.iter().zip(expected_pairs.iter())
@@ -5145,7 +7831,7 @@ fn test_vasp_variant_nonconverged_scf() {
         assert!((*actual_value - *expected_value).abs() < 1e-6);
     }
 
-    let oszicar_events = parse_oszicar(oszicar, 0).unwrap();
+    let oszicar_events = parse_oszicar(oszicar, 0, &mut ids).unwrap();
     let unconverged_count = oszicar_events
         .iter()
         .filter(|event| {
@@ -5170,7 +7856,6 @@ fn test_vasp_variant_nonconverged_scf() {
 
 #[test]
 fn test_vasp_variant_mixed_scf_dav_rmm() {
-    setup();
     let oszicar = VASP_FILE_MIXED_SCF_DAV_RMM
         .split("--- OSZICAR ---")
         .nth(1)
@@ -5182,7 +7867,7 @@ fn test_vasp_variant_mixed_scf_dav_rmm() {
     assert_eq!(parsed_energy_pairs, expected_energy_pairs);
 
     let convergence_pairs = parse_vasp_oszicar_convergence_pairs(oszicar);
-    let expected_convergence_pairs = vec![
+    let expected_convergence_pairs = [
         (1, 6.0),
         (2, 3.0),
         (3, 2.0),
@@ -5210,7 +7895,6 @@ fn test_vasp_variant_mixed_scf_dav_rmm() {
 
 #[test]
 fn test_vasp_variant_error_edddav() {
-    setup();
     assert_vasp_variant(
         VASP_VARIANT_ERROR_EDDDAV,
         &[(1, -100.0)],
@@ -5224,7 +7908,6 @@ fn test_vasp_variant_error_edddav() {
 
 #[test]
 fn test_vasp_variant_large_encut_scinotation() {
-    setup();
     assert_vasp_variant(
         VASP_VARIANT_LARGE_ENCUT_SCINOTATION,
         &[(1, -200.0)],
@@ -5251,7 +7934,7 @@ fn test_vasp_variant_large_encut_scinotation() {
         .expect("Expected ENCUT parameter");
     match &encut.kind {
         EventKind::ParameterRecord { actual_value, .. } => {
-            assert_eq!(actual_value, &Value::Known(1200.0, "eV".to_string()));
+            assert_eq!(actual_value, &Value::Known(1200.0, "eV".into()));
         }
         other => panic!("Expected ParameterRecord for ENCUT, got {:?}", other),
     }
@@ -5259,7 +7942,6 @@ fn test_vasp_variant_large_encut_scinotation() {
 
 #[test]
 fn test_vasp_variant_static_calc() {
-    setup();
     assert_vasp_variant(
         VASP_VARIANT_STATIC_CALC,
         &[(1, -50.0)],
@@ -5270,7 +7952,6 @@ fn test_vasp_variant_static_calc() {
 
 #[test]
 fn test_vasp_variant_v5_version() {
-    setup();
     assert_vasp_variant(
         VASP_VARIANT_V5_VERSION,
         &[(1, -75.0)],
@@ -5291,7 +7972,6 @@ fn test_vasp_variant_v5_version() {
 
 #[test]
 fn test_vasp_variant_truncated_outcar() {
-    setup();
     let combined = format!(
         "--- INCAR ---\n{}\n--- OSZICAR ---\n{}\n--- OUTCAR ---\n{}",
         VASP_INCAR_SAMPLE, VASP_OSZICAR_SAMPLE, VASP_OUTCAR_TRUNCATED
@@ -5323,7 +8003,6 @@ fn test_vasp_variant_truncated_outcar() {
 
 #[test]
 fn test_vasp_variant_error_very_bad_news() {
-    setup();
     let combined = format!(
         "--- INCAR ---\n{}\n--- OSZICAR ---\n{}\n--- OUTCAR ---\n{}",
         VASP_INCAR_SAMPLE, VASP_OSZICAR_SAMPLE, VASP_OUTCAR_ERROR
@@ -5338,7 +8017,6 @@ fn test_vasp_variant_error_very_bad_news() {
 
 #[test]
 fn test_vasp_variant_energy_count_cross_source() {
-    setup();
     assert_vasp_parses_energy_count(VASP_FILE_CONVERGED_RELAXATION, 4);
     assert_vasp_parses_energy_count(VASP_FILE_NONCONVERGED_SCF, 1);
     assert_vasp_parses_energy_count(VASP_FILE_MIXED_SCF_DAV_RMM, 3);
@@ -5346,7 +8024,6 @@ fn test_vasp_variant_energy_count_cross_source() {
 
 #[test]
 fn test_vasp_t1_honeycomb_pt52() {
-    setup();
     assert_vasp_variant(
         VASP_FILE_T1_HONEYCOMB_PT52,
         &[(1, -957.02531)],
@@ -5358,7 +8035,6 @@ fn test_vasp_t1_honeycomb_pt52() {
 
 #[test]
 fn test_vasp_t1_large_approx() {
-    setup();
     assert_vasp_variant(
         VASP_FILE_T1_LARGE_APPROX,
         &[(1, -3279.8853)],
@@ -5370,7 +8046,6 @@ fn test_vasp_t1_large_approx() {
 
 #[test]
 fn test_vasp_t1_sigma_pt56_substrate() {
-    setup();
     assert_vasp_variant(
         VASP_FILE_T1_SIGMA_PT56_SUBSTRATE,
         &[(1, -561.68546)],
@@ -5388,14 +8063,16 @@ fn test_convergence_event(
     converged: Option<bool>,
     completeness: Completeness,
     logical_sequence: u64,
+    ids: &mut EventIdAllocator,
 ) -> TraceEvent {
     TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ConvergencePoint {
             iteration: logical_sequence,
-            metric_name: metric_name.to_string(),
-            metric_value: Value::Known(1.0, "relative".to_string()),
+            metric_name: metric_name.into(),
+            metric_value: Value::Known(1.0, "relative".into()),
             converged,
+            uncertainty: None,
         })
         .temporal(TemporalCoord {
             simulation_step: logical_sequence,
@@ -5408,7 +8085,7 @@ fn test_convergence_event(
             field_coverage: 1.0,
             notes: vec![],
         })
-        .build()
+        .build(ids)
 }
 
 fn test_log_from_events(events: Vec<TraceEvent>) -> LayeredEventLog {
@@ -5421,7 +8098,7 @@ fn test_log_from_events(events: Vec<TraceEvent>) -> LayeredEventLog {
 
 #[test]
 fn test_classify_convergence_converged_derived() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence = test_convergence_event(
         "derived_convergence_rel_delta_max",
         Some(true),
@@ -5429,6 +8106,7 @@ fn test_classify_convergence_converged_derived() {
             from_elements: vec![ElementId(1)],
         },
         1,
+        &mut ids,
     );
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
@@ -5440,7 +8118,7 @@ fn test_classify_convergence_converged_derived() {
 
 #[test]
 fn test_classify_convergence_oscillating_derived() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence = test_convergence_event(
         "derived_oscillation_rel_delta_mean",
         Some(false),
@@ -5448,6 +8126,7 @@ fn test_classify_convergence_oscillating_derived() {
             from_elements: vec![ElementId(2)],
         },
         2,
+        &mut ids,
     );
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
@@ -5459,7 +8138,7 @@ fn test_classify_convergence_oscillating_derived() {
 
 #[test]
 fn test_classify_convergence_stalled_derived() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence = test_convergence_event(
         "derived_stall_rel_delta_mean",
         Some(false),
@@ -5467,6 +8146,7 @@ fn test_classify_convergence_stalled_derived() {
             from_elements: vec![ElementId(3)],
         },
         3,
+        &mut ids,
     );
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
@@ -5478,9 +8158,9 @@ fn test_classify_convergence_stalled_derived() {
 
 #[test]
 fn test_classify_convergence_vasp_de_converged_direct() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence =
-        test_convergence_event("dE", Some(true), Completeness::FullyObserved, 4);
+        test_convergence_event("dE", Some(true), Completeness::FullyObserved, 4, &mut ids);
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
 
@@ -5491,8 +8171,8 @@ fn test_classify_convergence_vasp_de_converged_direct() {
 
 #[test]
 fn test_classify_convergence_vasp_de_insufficient_direct() {
-    setup();
-    let convergence = test_convergence_event("dE", None, Completeness::FullyObserved, 5);
+    let mut ids = EventIdAllocator::new();
+    let convergence = test_convergence_event("dE", None, Completeness::FullyObserved, 5, &mut ids);
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
 
@@ -5503,7 +8183,7 @@ fn test_classify_convergence_vasp_de_insufficient_direct() {
 
 #[test]
 fn test_classify_convergence_divergent_override_priority() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence = test_convergence_event(
         "derived_convergence_rel_delta_max",
         Some(true),
@@ -5511,6 +8191,7 @@ fn test_classify_convergence_divergent_override_priority() {
             from_elements: vec![ElementId(6)],
         },
         6,
+        &mut ids,
     );
     let numerical = TraceEventBuilder::new()
         .layer(Layer::Implementation)
@@ -5531,7 +8212,7 @@ fn test_classify_convergence_divergent_override_priority() {
             field_coverage: 1.0,
             notes: vec![],
         })
-        .build();
+        .build(&mut ids);
     let log = test_log_from_events(vec![convergence, numerical]);
     let event = log
         .events
@@ -5546,12 +8227,13 @@ fn test_classify_convergence_divergent_override_priority() {
 
 #[test]
 fn test_classify_convergence_unknown_metric_absent() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence = test_convergence_event(
         "unknown_metric",
         Some(false),
         Completeness::FullyObserved,
         8,
+        &mut ids,
     );
     let log = test_log_from_events(vec![convergence]);
     let event = log.events.first().expect("Expected ConvergencePoint");
@@ -5563,7 +8245,7 @@ fn test_classify_convergence_unknown_metric_absent() {
 
 #[test]
 fn test_classify_all_convergence_filters_convergence_points() {
-    setup();
+    let mut ids = EventIdAllocator::new();
     let convergence_one = test_convergence_event(
         "derived_convergence_rel_delta_max",
         Some(true),
@@ -5571,14 +8253,15 @@ fn test_classify_all_convergence_filters_convergence_points() {
             from_elements: vec![ElementId(9)],
         },
         9,
+        &mut ids,
     );
     let parameter = TraceEventBuilder::new()
         .layer(Layer::Methodology)
         .kind(EventKind::ParameterRecord {
-            name: "dt".to_string(),
+            name: "dt".into(),
             specified_value: None,
-            actual_value: Value::Known(0.002, "ps".to_string()),
-            units: Some("ps".to_string()),
+            actual_value: Value::Known(0.002, "ps".into()),
+            units: Some("ps".into()),
             observation_mode: ObservationMode::Observational,
         })
         .temporal(TemporalCoord {
@@ -5592,7 +8275,7 @@ fn test_classify_all_convergence_filters_convergence_points() {
             field_coverage: 1.0,
             notes: vec![],
         })
-        .build();
+        .build(&mut ids);
     let convergence_two = test_convergence_event(
         "derived_stall_rel_delta_mean",
         Some(false),
@@ -5600,6 +8283,7 @@ fn test_classify_all_convergence_filters_convergence_points() {
             from_elements: vec![ElementId(10)],
         },
         11,
+        &mut ids,
     );
     let log = test_log_from_events(vec![convergence_one, parameter, convergence_two]);
 
@@ -5622,7 +8306,6 @@ fn first_pattern_or_insufficient(
 
 #[test]
 fn test_equivalence_scenario_a_steady_state_converged() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5654,7 +8337,6 @@ fn test_equivalence_scenario_a_steady_state_converged() {
 
 #[test]
 fn test_equivalence_scenario_b_oscillating() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5679,7 +8361,6 @@ fn test_equivalence_scenario_b_oscillating() {
 
 #[test]
 fn test_equivalence_scenario_c_stalled() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5704,7 +8385,6 @@ fn test_equivalence_scenario_c_stalled() {
 
 #[test]
 fn test_equivalence_scenario_d_divergent_nan() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5738,7 +8418,6 @@ fn test_equivalence_scenario_d_divergent_nan() {
 
 #[test]
 fn test_equivalence_scenario_e_insufficient_data() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5772,7 +8451,6 @@ fn test_equivalence_scenario_e_insufficient_data() {
 
 #[test]
 fn test_equivalence_scenario_f_threshold_boundary() {
-    setup();
     let gromacs_adapter = GromacsAdapter;
     let gromacs_raw = format!(
         "--- MDP ---\n{}\n--- LOG ---\n{}",
@@ -5792,3 +8470,808 @@ fn test_equivalence_scenario_f_threshold_boundary() {
         ConvergencePattern::Converged
     );
 }
+
+#[test]
+fn test_symbol_table_interns_each_string_once() {
+    let mut symbols = SymbolTable::new();
+    let first = symbols.intern("Potential");
+    let second = symbols.intern(&String::from("Potential"));
+    assert!(first.ptr_eq(&second));
+    assert_eq!(first, "Potential");
+    assert_eq!(symbols.len(), 1);
+
+    let unit = symbols.unit("kJ/mol");
+    let again = symbols.unit("kJ/mol");
+    assert!(unit.symbol().ptr_eq(again.symbol()));
+    assert!(unit.symbol().ptr_eq(&symbols.intern("kJ/mol")));
+    assert_eq!(unit.dimension(), Some(Dimension::ENERGY));
+    assert_eq!(symbols.len(), 2);
+
+    let outside = Symbol::from("Kinetic En.");
+    assert!(outside.ptr_eq(&symbols.intern_symbol(&outside)));
+    assert!(outside.ptr_eq(&symbols.intern("Kinetic En.")));
+}
+
+/// Every energy component name and unit in `log`, in event order.
+fn energy_symbols(log: &LayeredEventLog) -> Vec<(Symbol, Symbol)> {
+    log.events
+        .iter()
+        .filter_map(|event| match &event.kind {
+            EventKind::EnergyRecord { components, .. } => Some(components),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|(name, value)| match value {
+            Value::Known(_, unit) => Some((name.clone(), unit.symbol().clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_gromacs_log_shares_interned_symbols() {
+    let start = GROMACS_LOG_SAMPLE.find("   Step").unwrap();
+    let end = GROMACS_LOG_SAMPLE.find("Finished").unwrap();
+    let second_step = GROMACS_LOG_SAMPLE[start..end].replace("      0        0.00000", "    100");
+    let log = gromacs_run(
+        GROMACS_MDP_SAMPLE,
+        &GROMACS_LOG_SAMPLE.replace("Finished", &format!("{}Finished", second_step)),
+    );
+    let components = energy_symbols(&log);
+    let (first, second) = components.split_at(components.len() / 2);
+    assert!(!first.is_empty());
+    for ((first_name, first_unit), (second_name, second_unit)) in first.iter().zip(second) {
+        assert!(first_name.ptr_eq(second_name));
+        assert!(first_unit.ptr_eq(second_unit));
+    }
+
+    let mut symbols = log.symbols.clone();
+    for (name, unit) in &components {
+        assert!(name.ptr_eq(&symbols.intern(name)));
+        assert!(unit.ptr_eq(&symbols.intern(unit)));
+    }
+    for event in &log.events {
+        let source_file = &event.provenance.source_file;
+        assert!(source_file.ptr_eq(&symbols.intern(source_file)));
+    }
+    assert_eq!(symbols.len(), log.symbols.len());
+}
+
+/// Names, units and source files of `event` held as symbols.
+fn event_symbols(event: &TraceEvent) -> Vec<Symbol> {
+    fn unit_of(value: &Value) -> Option<Symbol> {
+        match value {
+            Value::Known(_, unit) | Value::KnownVec(_, unit) => Some(unit.symbol().clone()),
+            Value::KnownGrid { value_unit, .. } => Some(value_unit.symbol().clone()),
+            Value::DataRef { unit, .. } => Some(unit.symbol().clone()),
+            Value::KnownCat(_) | Value::Havoc { .. } => None,
+        }
+    }
+
+    let mut symbols = vec![event.provenance.source_file.clone()];
+    symbols.extend(event.dag_node_ref.clone());
+    match &event.kind {
+        EventKind::ParameterRecord {
+            name,
+            actual_value,
+            units,
+            ..
+        } => {
+            symbols.push(name.clone());
+            symbols.extend(unit_of(actual_value));
+            symbols.extend(units.as_ref().map(|unit| unit.symbol().clone()));
+        }
+        EventKind::ObservableMeasurement {
+            variable_name,
+            value,
+            ..
+        } => {
+            symbols.push(variable_name.clone());
+            symbols.extend(unit_of(value));
+        }
+        EventKind::ConvergencePoint {
+            metric_name,
+            metric_value,
+            ..
+        } => {
+            symbols.push(metric_name.clone());
+            symbols.extend(unit_of(metric_value));
+        }
+        EventKind::EnergyRecord { total, components } => {
+            symbols.extend(unit_of(total));
+            for (name, value) in components {
+                symbols.push(name.clone());
+                symbols.extend(unit_of(value));
+            }
+        }
+        _ => {}
+    }
+    symbols
+}
+
+#[test]
+fn test_adapter_logs_share_the_log_symbol_handles() {
+    for log in [
+        VaspAdapter.parse_trace(VASP_COMBINED_SAMPLE).unwrap(),
+        MockOpenMmAdapter
+            .parse_trace(OPENMM_REAL_CSV_DEFAULT_KJ)
+            .unwrap(),
+        gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE),
+    ] {
+        let mut symbols = log.symbols.clone();
+        for event in &log.events {
+            for symbol in event_symbols(event) {
+                assert!(symbol.ptr_eq(&symbols.intern(&symbol)), "{symbol}");
+            }
+            if let EventKind::ParameterRecord {
+                actual_value: Value::Known(_, unit),
+                units: Some(units),
+                ..
+            } = &event.kind
+            {
+                assert!(units.symbol().ptr_eq(unit.symbol()));
+            }
+        }
+        assert_eq!(symbols.len(), log.symbols.len());
+    }
+
+    // The parsers intern as they go, so equal strings already share one
+    // handle before a log builder sees the events.
+    let mut ids = EventIdAllocator::new();
+    for events in [
+        parse_incar(VASP_INCAR_SAMPLE, &mut ids).unwrap(),
+        parse_oszicar(VASP_OSZICAR_SAMPLE, 0, &mut ids).unwrap(),
+        parse_outcar(VASP_OUTCAR_FORCES_AND_CHGCAR, 0, &mut ids).unwrap(),
+        parse_mdp(GROMACS_MDP_SAMPLE, &mut ids).unwrap(),
+    ] {
+        let symbols: Vec<Symbol> = events.iter().flat_map(event_symbols).collect();
+        for symbol in &symbols {
+            let first = symbols.iter().find(|other| *other == symbol).unwrap();
+            assert!(symbol.ptr_eq(first), "{symbol}");
+        }
+    }
+}
+
+#[test]
+fn test_loaded_logs_are_interned_and_serialize_unchanged() {
+    let log = gromacs_split_log();
+    let json = serde_json::to_string(&log.without_indexes()).unwrap();
+    let binary = encode_log(&log, BinaryOptions::default()).unwrap();
+
+    for restored in [
+        serde_json::from_str::<LayeredEventLog>(&json).unwrap(),
+        decode_log(&binary).unwrap(),
+    ] {
+        assert_eq!(
+            serde_json::to_string(&restored.without_indexes()).unwrap(),
+            json
+        );
+        let mut symbols = restored.symbols.clone();
+        for (name, unit) in energy_symbols(&restored) {
+            assert!(name.ptr_eq(&symbols.intern(&name)));
+            assert!(unit.ptr_eq(&symbols.intern(&unit)));
+        }
+        assert_eq!(symbols.len(), restored.symbols.len());
+        assert_eq!(restored.query().variable("dt").count(), 1);
+    }
+}
+
+#[test]
+fn test_time_series_columns_follow_energy_records() {
+    let log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let totals = log.series.energy_total();
+    assert_eq!(totals.name(), ENERGY_TOTAL);
+    assert_eq!(totals.steps(), &[0, 100, 200, 300]);
+    assert_eq!(totals.values(), &[-1000.0, -1000.05, -1000.08, -1000.09]);
+    assert_eq!(totals.unit().map(|unit| unit.as_str()), Some("kJ/mol"));
+    for sample in totals.iter() {
+        let event = &log.events[log.indexes.by_id[&sample.event_id]];
+        assert!(matches!(event.kind, EventKind::EnergyRecord { .. }));
+        assert_eq!(event.temporal.simulation_step, sample.step);
+    }
+    assert_eq!(totals.step_range(100, 200), 1..3);
+    assert_eq!(totals.position(totals.event_ids()[2]), Some(2));
+
+    let derived = log
+        .series
+        .convergence("derived_convergence_rel_delta_max")
+        .unwrap();
+    assert_eq!(derived.len(), 1);
+    assert_eq!(derived.steps(), &[300]);
+
+    let single = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    let potential = single.series.energy_component("Potential").unwrap();
+    assert_eq!(potential.values(), &[-45678.9]);
+    assert!(single.series.energy_component("Total Energy").is_none());
+    assert_eq!(
+        single.series.energy_components().count(),
+        single
+            .events
+            .iter()
+            .find_map(|event| match &event.kind {
+                EventKind::EnergyRecord { components, .. } => Some(components.len()),
+                _ => None,
+            })
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_series_converts_to_first_unit_and_skips_the_rest() {
+    let mut series = Series::new("Potential");
+    assert!(series.unit().is_none());
+    assert!(series.push(0, EventId(1), &Value::Known(4.184, "kJ/mol".into())));
+    assert!(series.push(1, EventId(2), &Value::Known(1.0, "kcal/mol".into())));
+    assert!(!series.push(2, EventId(3), &Value::Known(300.0, "K".into())));
+    assert!(!series.push(3, EventId(4), &Value::KnownCat("n/a".to_string())));
+    assert_eq!(series.len(), 2);
+    assert_eq!(series.event_ids(), &[EventId(1), EventId(2)]);
+    assert!((series.values()[1] - 4.184).abs() < 1e-12);
+    assert_eq!(
+        series.get(1),
+        Some(SeriesSample {
+            step: 1,
+            event_id: EventId(2),
+            value: series.values()[1],
+        })
+    );
+    assert_eq!(series.get(2), None);
+}
+
+#[test]
+fn test_time_series_rebuilt_on_load_and_extended_on_push() {
+    let log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let json = serde_json::to_string(&log.without_indexes()).unwrap();
+    let restored: LayeredEventLog = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.series, log.series);
+    assert_eq!(
+        decode_log(&encode_log(&log, BinaryOptions::default()).unwrap())
+            .unwrap()
+            .series,
+        log.series
+    );
+    assert_eq!(TimeSeries::from_events(&log.events), log.series);
+
+    let mut builder = log.into_builder();
+    let event = TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::EnergyRecord {
+            total: Value::Known(-1.0, "kcal/mol".into()),
+            components: vec![],
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 400,
+            wall_clock_ns: None,
+            logical_sequence: 100,
+        })
+        .build(builder.ids());
+    let id = event.id;
+    builder.push(event);
+    let extended = builder.build();
+    let totals = extended.series.energy_total();
+    assert_eq!(totals.len(), 5);
+    assert_eq!(totals.event_ids().last(), Some(&id));
+    assert!((totals.values()[4] + 4.184).abs() < 1e-12);
+}
+
+/// IDs of the events at overlay positions `positions`.
+fn ids_at(log: &LayeredEventLog, positions: &[usize]) -> Vec<EventId> {
+    positions.iter().map(|&idx| log.events[idx].id).collect()
+}
+
+#[test]
+fn test_mapped_log_decodes_only_touched_events() {
+    let log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let summary = log
+        .events
+        .iter()
+        .position(|event| !event.causal_refs.is_empty())
+        .unwrap();
+    let expected = ids_at(
+        &log,
+        &CausalOverlay::from_log(&log).transitive_ancestors(summary),
+    );
+    assert!(!expected.is_empty());
+
+    for options in [
+        BinaryOptions {
+            block_size: 3,
+            ..BinaryOptions::default()
+        },
+        BinaryOptions {
+            compression: Compression::Deflate,
+            block_size: 3,
+            ..BinaryOptions::default()
+        },
+        BinaryOptions {
+            index: false,
+            block_size: 3,
+            ..BinaryOptions::default()
+        },
+    ] {
+        let mapped = MappedLog::new(encode_log(&log, options).unwrap()).unwrap();
+        assert_eq!(mapped.len(), log.events.len());
+        assert_eq!(mapped.indexes(), &log.indexes);
+        assert_eq!(mapped.experiment_ref(), &log.experiment_ref);
+        assert_eq!(mapped.decoded_count(), 0);
+
+        let event = mapped.event(log.events[summary].id).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            serde_json::to_string(&log.events[summary]).unwrap()
+        );
+        let ancestors = mapped.transitive_ancestors(log.events[summary].id).unwrap();
+        assert_eq!(
+            ancestors.iter().map(|event| event.id).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(mapped.decoded_count(), 2 + expected.len());
+        assert!(mapped.event(EventId(u64::MAX)).unwrap().is_none());
+        assert!(mapped.event_at(log.events.len()).unwrap().is_none());
+
+        let loaded = mapped.load().unwrap();
+        assert_eq!(
+            serde_json::to_string(&loaded.without_indexes()).unwrap(),
+            serde_json::to_string(&log.without_indexes()).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_mapped_log_query_decodes_only_candidates() {
+    let log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_STABLE_SERIES);
+    let mapped = MappedLog::new(encode_log(&log, BinaryOptions::default()).unwrap()).unwrap();
+
+    let expected = log
+        .query()
+        .kind(EventKindTag::EnergyRecord)
+        .steps(100..)
+        .descending()
+        .ids();
+    assert!(!expected.is_empty());
+    let query = mapped.query().kind(EventKindTag::EnergyRecord).steps(100..);
+    assert_eq!(query.descending().ids().unwrap(), expected);
+    let energies = log.indexes.by_kind[&EventKindTag::EnergyRecord].len();
+    assert_eq!(mapped.decoded_count(), energies);
+
+    let unfiltered = mapped.query().order_by(QueryOrder::SimulationStep);
+    assert_eq!(
+        unfiltered.ids().unwrap(),
+        log.query().order_by(QueryOrder::SimulationStep).ids()
+    );
+}
+
+#[test]
+fn test_mapped_log_accepts_repeated_ids_like_decode_log() {
+    let mut log = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_LOG_SAMPLE);
+    log.events[1].id = log.events[0].id;
+    log.indexes = EventIndexes::rebuild(&log.events);
+    let bytes = encode_log(&log, BinaryOptions::default()).unwrap();
+
+    let eager = decode_log(&bytes).unwrap();
+    let mapped = MappedLog::new(&bytes[..]).unwrap();
+    assert_eq!(mapped.len(), eager.events.len());
+    assert_eq!(mapped.indexes(), &eager.indexes);
+    let streamed: Vec<EventId> = mapped.iter().map(|event| event.unwrap().id).collect();
+    assert_eq!(
+        streamed,
+        eager
+            .events
+            .iter()
+            .map(|event| event.id)
+            .collect::<Vec<_>>()
+    );
+    // By ID, the last event carrying it is returned, as in `by_id`.
+    let repeated = mapped.event(log.events[0].id).unwrap().unwrap();
+    assert_eq!(
+        serde_json::to_string(&repeated).unwrap(),
+        serde_json::to_string(&log.events[1]).unwrap()
+    );
+}
+
+#[test]
+fn test_mapped_log_opens_file_and_rejects_truncation() {
+    let log = gromacs_split_log();
+    let bytes = encode_log(&log, BinaryOptions::default()).unwrap();
+    let path = std::env::temp_dir().join(format!("lel-mapped-{}.lelb", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+
+    let mapped = MappedLog::open(&path).unwrap();
+    let kinds = &mapped.indexes().by_kind[&EventKindTag::EnergyRecord];
+    let energies = mapped.events(kinds).unwrap();
+    assert_eq!(energies.len(), kinds.len());
+    assert!(energies
+        .iter()
+        .all(|event| matches!(event.kind, EventKind::EnergyRecord { .. })));
+    assert_eq!(mapped.decoded_count(), kinds.len());
+    let streamed: Vec<EventId> = mapped.iter().map(|event| event.unwrap().id).collect();
+    assert_eq!(
+        streamed,
+        log.events.iter().map(|event| event.id).collect::<Vec<_>>()
+    );
+    drop(mapped);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        MappedLog::new(&bytes[..bytes.len() - 20]),
+        Err(BinaryError::Corrupt(_))
+    ));
+    assert!(matches!(
+        MappedLog::new(&b"JSON{}"[..]),
+        Err(BinaryError::BadMagic(_))
+    ));
+}
+
+#[test]
+fn test_registry_detects_framework_from_signatures() {
+    let registry = AdapterRegistry::default();
+    assert_eq!(registry.frameworks(), vec!["openmm", "gromacs", "vasp"]);
+
+    let gromacs = format!(
+        "--- MDP ---\n{}--- LOG ---\n{}",
+        GROMACS_MDP_SAMPLE, GROMACS_FILE_NVT_MD_LOG
+    );
+    let inputs = [
+        (OPENMM_REAL_CSV_DEFAULT_KJ, "openmm"),
+        (OPENMM_CSV_STABLE, "openmm"),
+        (gromacs.as_str(), "gromacs"),
+        (VASP_COMBINED_SAMPLE, "vasp"),
+        (VASP_FILE_CONVERGED_RELAXATION, "vasp"),
+    ];
+    for (raw, framework) in inputs {
+        let candidates = registry.detect(raw);
+        assert_eq!(candidates[0].framework, framework);
+        assert!(candidates[0].detection.confidence >= MIN_DETECTION_CONFIDENCE);
+        assert!(candidates[1].detection.confidence < candidates[0].detection.confidence);
+
+        let detected = parse_any(raw).unwrap();
+        assert_eq!(detected.framework(), framework);
+        assert!(!detected.log.events.is_empty());
+        assert_eq!(detected.alternatives.len(), 2);
+        assert!(detected
+            .reason()
+            .starts_with(&format!("{}: confidence", framework)));
+        assert!(detected.reason().contains("next best"));
+    }
+}
+
+#[test]
+fn test_registry_rejects_unrecognised_input() {
+    let err = parse_any("hello world\nnothing to see here\n").unwrap_err();
+    let AdapterError::UnsupportedFormat(message) = err else {
+        panic!("expected UnsupportedFormat, got {:?}", err);
+    };
+    assert!(message.contains("openmm: confidence 0.00"));
+    assert!(message.contains("vasp: confidence 0.00"));
+
+    let mut registry = AdapterRegistry::empty();
+    assert!(matches!(
+        registry.parse_any(VASP_COMBINED_SAMPLE),
+        Err(AdapterError::UnsupportedFormat(_))
+    ));
+    registry.register(VaspAdapter);
+    let detected = registry.parse_any(VASP_COMBINED_SAMPLE).unwrap();
+    assert_eq!(detected.framework(), "vasp");
+    assert!(detected.alternatives.is_empty());
+    assert!(detected.reason().contains("version line vasp.6.4.2"));
+}
+
+/// Shares VASP's framework name but recognises and parses nothing.
+struct ShadowVaspAdapter;
+
+impl DslAdapter for ShadowVaspAdapter {
+    fn parse_trace(&self, _raw: &str) -> Result<LayeredEventLog, AdapterError> {
+        Err(AdapterError::ParseError("shadow adapter".to_string()))
+    }
+
+    fn framework(&self) -> &'static str {
+        "vasp"
+    }
+}
+
+#[test]
+fn test_registry_parses_with_the_adapter_that_won_detection() {
+    let mut registry = AdapterRegistry::empty();
+    registry.register(ShadowVaspAdapter).register(VaspAdapter);
+    let detected = registry.parse_any(VASP_COMBINED_SAMPLE).unwrap();
+    assert!(!detected.log.events.is_empty());
+    assert_eq!(detected.alternatives[0].detection.confidence, 0.0);
+}
+
+#[test]
+fn test_registry_detects_and_parses_a_mixed_run_directory() {
+    let dir = std::env::temp_dir().join(format!("lel-mixed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, contents) in [
+        ("INCAR", VASP_INCAR_SAMPLE),
+        ("OSZICAR", VASP_OSZICAR_SAMPLE),
+        ("OUTCAR", VASP_OUTCAR_SAMPLE),
+        ("notes.txt", "relaxation restarted twice\n"),
+        ("job.log", "job 4127 finished\n"),
+        ("energies.csv", OPENMM_REAL_CSV_DEFAULT_KJ),
+    ] {
+        std::fs::write(dir.join(name), contents).unwrap();
+    }
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    let bundle = TraceBundle::from_paths(&paths).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let detected = parse_any_bundle(&bundle).unwrap();
+    assert_eq!(detected.framework(), "vasp");
+    let claimed: Vec<&str> = detected
+        .chosen
+        .files
+        .iter()
+        .map(|path| file_name(path))
+        .collect();
+    assert_eq!(claimed, vec!["INCAR", "OSZICAR", "OUTCAR"]);
+    let openmm = detected
+        .alternatives
+        .iter()
+        .find(|candidate| candidate.framework == "openmm")
+        .unwrap();
+    assert_eq!(openmm.files.len(), 1);
+    assert!(openmm.files[0].ends_with("energies.csv"));
+
+    let mut vasp_files = TraceBundle::new();
+    for file in bundle.files() {
+        if detected.chosen.files.contains(&file.path) {
+            vasp_files.add(file.path.clone(), file.contents.clone());
+        }
+    }
+    let expected = VaspAdapter.parse_bundle(&vasp_files).unwrap();
+    assert_eq!(
+        serde_json::to_string(&detected.log.without_indexes()).unwrap(),
+        serde_json::to_string(&expected.without_indexes()).unwrap()
+    );
+
+    // A GROMACS run next to unrelated files.
+    let mut gromacs = TraceBundle::new();
+    gromacs
+        .add("nvt/README", "see lab notebook\n")
+        .add("nvt/grompp.mdp", GROMACS_MDP_SAMPLE)
+        .add("nvt/md.log", GROMACS_FILE_NVT_MD_LOG);
+    let detected = parse_any_bundle(&gromacs).unwrap();
+    assert_eq!(detected.framework(), "gromacs");
+    assert_eq!(detected.chosen.files, vec!["nvt/grompp.mdp", "nvt/md.log"]);
+    assert!(!detected.log.events.is_empty());
+
+    let mut unrelated = TraceBundle::new();
+    unrelated.add("notes.txt", "nothing");
+    assert!(matches!(
+        parse_any_bundle(&unrelated),
+        Err(AdapterError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn test_gromacs_bundle_records_real_file_paths() {
+    let mut bundle = TraceBundle::new();
+    bundle
+        .add("runs/nvt/grompp.mdp", GROMACS_MDP_SAMPLE)
+        .add("runs/nvt/topol.top", "[ system ]\nwater\n")
+        .add("runs/nvt/md.log", GROMACS_FILE_NVT_MD_LOG);
+    let log = GromacsAdapter.parse_bundle(&bundle).unwrap();
+    let concatenated = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_FILE_NVT_MD_LOG);
+
+    assert_eq!(log.events.len(), concatenated.events.len());
+    for (event, expected) in log.events.iter().zip(&concatenated.events) {
+        assert_eq!(
+            serde_json::to_string(&event.kind).unwrap(),
+            serde_json::to_string(&expected.kind).unwrap()
+        );
+        let path = match expected.provenance.source_file.as_str() {
+            "input.mdp" => "runs/nvt/grompp.mdp",
+            "simulation.log" => "runs/nvt/md.log",
+            other => panic!("unexpected source file {}", other),
+        };
+        assert_eq!(event.provenance.source_file, path);
+        assert_eq!(event.provenance.raw_hash, expected.provenance.raw_hash);
+    }
+    assert_eq!(log.spec.provenance.source_file, "runs/nvt/grompp.mdp");
+    assert_eq!(verify_provenance(&log, &bundle.sources()), vec![]);
+
+    let mut unrelated = TraceBundle::new();
+    unrelated.add("notes.txt", "nothing");
+    assert!(matches!(
+        GromacsAdapter.parse_bundle(&unrelated),
+        Err(AdapterError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn test_vasp_bundle_reads_files_from_disk() {
+    let sections = TraceBundle::from_sections(
+        VASP_COMBINED_SAMPLE,
+        &[
+            ("--- INCAR ---", "INCAR"),
+            ("--- OSZICAR ---", "OSZICAR"),
+            ("--- OUTCAR ---", "OUTCAR"),
+        ],
+    );
+    assert_eq!(sections.len(), 3);
+    let dir = std::env::temp_dir().join(format!("lel-bundle-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let paths: Vec<_> = sections
+        .files()
+        .iter()
+        .map(|file| {
+            let path = dir.join(&file.path);
+            std::fs::write(&path, &file.contents).unwrap();
+            path
+        })
+        .collect();
+
+    let bundle = TraceBundle::from_paths(&paths).unwrap();
+    let incar = bundle.file_named("INCAR").unwrap();
+    assert_eq!(incar.path, paths[0].display().to_string());
+    let log = VaspAdapter.parse_bundle(&bundle).unwrap();
+    assert_eq!(
+        log.events.len(),
+        VaspAdapter
+            .parse_trace(VASP_COMBINED_SAMPLE)
+            .unwrap()
+            .events
+            .len()
+    );
+    assert!(log.events.iter().all(|event| event
+        .provenance
+        .source_file
+        .starts_with(&*dir.display().to_string())));
+    assert_eq!(log.spec.provenance.source_file, incar.path.as_str());
+    assert_eq!(verify_provenance_in_dir(&log, &dir).unwrap(), vec![]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut single = TraceBundle::new();
+    single
+        .add_reader("state.csv", OPENMM_REAL_CSV_DEFAULT_KJ.as_bytes())
+        .unwrap();
+    let openmm = MockOpenMmAdapter.parse_bundle(&single).unwrap();
+    assert!(openmm
+        .events
+        .iter()
+        .all(|event| event.provenance.source_file == "state.csv"));
+    assert_eq!(verify_provenance(&openmm, &single.sources()), vec![]);
+    single.add("extra.csv", OPENMM_CSV_STABLE);
+    assert!(matches!(
+        MockOpenMmAdapter.parse_bundle(&single),
+        Err(AdapterError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn test_streaming_adapters_split_blocks_across_buffer_boundaries() {
+    // Seven-byte buffers split every energy block and force table line.
+    let mdp = GROMACS_MDP_SAMPLE.replace('\n', "\r\n");
+    let md_log = GROMACS_FILE_NVT_MD_LOG.replace('\n', "\r\n");
+    let mut bundle = TraceBundle::new();
+    bundle
+        .add("nvt/grompp.mdp", mdp.as_str())
+        .add("nvt/md.log", md_log.as_str());
+    let streamed = GromacsAdapter
+        .parse_streams(vec![
+            StreamSource::new(
+                "nvt/grompp.mdp",
+                BufReader::with_capacity(7, mdp.as_bytes()),
+            ),
+            StreamSource::new("nvt/md.log", BufReader::with_capacity(7, md_log.as_bytes())),
+        ])
+        .unwrap();
+    let expected = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_FILE_NVT_MD_LOG);
+    assert_eq!(streamed.events.len(), expected.events.len());
+    for (event, expected) in streamed.events.iter().zip(&expected.events) {
+        assert_eq!(
+            serde_json::to_string(&event.kind).unwrap(),
+            serde_json::to_string(&expected.kind).unwrap()
+        );
+    }
+    assert_eq!(verify_provenance(&streamed, &bundle.sources()), vec![]);
+
+    let bundle = TraceBundle::from_sections(
+        VASP_COMBINED_SAMPLE,
+        &[
+            ("--- INCAR ---", "INCAR"),
+            ("--- OSZICAR ---", "OSZICAR"),
+            ("--- OUTCAR ---", "OUTCAR"),
+        ],
+    );
+    let sources = bundle
+        .files()
+        .iter()
+        .map(|file| {
+            StreamSource::new(
+                file.path.as_str(),
+                BufReader::with_capacity(7, file.contents.as_bytes()),
+            )
+        })
+        .collect();
+    let streamed = VaspAdapter.parse_streams(sources).unwrap();
+    assert_eq!(
+        serde_json::to_string(&streamed.events).unwrap(),
+        serde_json::to_string(&VaspAdapter.parse_bundle(&bundle).unwrap().events).unwrap()
+    );
+    assert_eq!(verify_provenance(&streamed, &bundle.sources()), vec![]);
+
+    assert!(matches!(
+        VaspAdapter.parse_streams(vec![StreamSource::new("notes.txt", "nothing".as_bytes())]),
+        Err(AdapterError::UnsupportedFormat(_))
+    ));
+}
+
+#[test]
+fn test_streaming_into_binary_sink_writes_blocks_as_it_goes() {
+    let mut sink = LogSink::new();
+    let mut ids = EventIdAllocator::new();
+    let early = test_parameter_event("dt", Value::Known(0.002, "ps".into()), 0, &mut ids);
+    assert!(matches!(
+        sink.event(early),
+        Err(AdapterError::ParseError(_))
+    ));
+
+    let options = BinaryOptions {
+        block_size: 3,
+        index: false,
+        ..BinaryOptions::default()
+    };
+    let mut sink = BinarySink::new(Vec::new(), options).unwrap();
+    let mut bundle = TraceBundle::new();
+    bundle
+        .add("input.mdp", GROMACS_MDP_SAMPLE)
+        .add("simulation.log", GROMACS_FILE_NVT_MD_LOG);
+    GromacsAdapter
+        .stream(bundle.stream_sources(), &mut sink)
+        .unwrap();
+    let bytes = sink.finish().unwrap();
+    let decoded = decode_log(&bytes).unwrap();
+    let expected = gromacs_run(GROMACS_MDP_SAMPLE, GROMACS_FILE_NVT_MD_LOG);
+    assert_eq!(
+        serde_json::to_string(&decoded.events).unwrap(),
+        serde_json::to_string(&expected.events).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_string(&decoded.spec).unwrap(),
+        serde_json::to_string(&expected.spec).unwrap()
+    );
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn test_python_conversion_roundtrips_non_finite_values() {
+    use pyo3::prelude::*;
+
+    use crate::python::{from_py, py_to_json, to_py};
+
+    Python::initialize();
+    Python::attach(|py| {
+        let values = vec![
+            Value::Known(f64::NAN, "K".into()),
+            Value::Known(f64::INFINITY, "eV".into()),
+            Value::KnownVec(vec![f64::NEG_INFINITY, 1.5], "nm".into()),
+        ];
+        let object = to_py(py, &values).unwrap();
+        let restored: Vec<Value> = from_py(&object).unwrap();
+        assert!(restored.bitwise_eq(&values), "{:?}", restored);
+
+        // Python floats built on the Python side cross the same way.
+        let floats = py
+            .eval(
+                c"[float('nan'), float('inf'), -float('inf'), 2.5]",
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            py_to_json(&floats).unwrap(),
+            serde_json::json!(["NaN", "Infinity", "-Infinity", 2.5])
+        );
+        let known = py
+            .eval(c"{'Known': [float('inf'), 'K']}", None, None)
+            .unwrap();
+        let value: Value = from_py(&known).unwrap();
+        assert!(value.bitwise_eq(&Value::Known(f64::INFINITY, "K".into())));
+    });
+}
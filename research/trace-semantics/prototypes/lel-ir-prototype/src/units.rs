@@ -0,0 +1,604 @@
+//! Dimensional units for `Value` payloads.
+//!
+//! A `Unit` keeps the symbol the framework wrote ("kJ/mol", "eV", "bar") and,
+//! when the symbol is recognised, its dimension and SI scale factor. Unknown
+//! symbols stay opaque: they round-trip unchanged and only compare or convert
+//! against the identical symbol.
+//!
+//! Molar quantities are treated per particle: "mol" is a dimensionless count
+//! of N_A particles, so "kJ/mol", "kcal/mol" and "eV" are all energies and
+//! convert into each other (1 eV = 96.485 kJ/mol).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Div, Mul};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::symbol::Symbol;
+
+/// Exponents over the base dimensions used by simulation outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Dimension {
+    pub mass: i8,
+    pub length: i8,
+    pub time: i8,
+    pub temperature: i8,
+    pub charge: i8,
+}
+
+impl Dimension {
+    pub const DIMENSIONLESS: Dimension = Dimension::new(0, 0, 0, 0, 0);
+    pub const MASS: Dimension = Dimension::new(1, 0, 0, 0, 0);
+    pub const LENGTH: Dimension = Dimension::new(0, 1, 0, 0, 0);
+    pub const TIME: Dimension = Dimension::new(0, 0, 1, 0, 0);
+    pub const TEMPERATURE: Dimension = Dimension::new(0, 0, 0, 1, 0);
+    pub const CHARGE: Dimension = Dimension::new(0, 0, 0, 0, 1);
+    /// M L^2 T^-2.
+    pub const ENERGY: Dimension = Dimension::new(1, 2, -2, 0, 0);
+    /// M L T^-2.
+    pub const FORCE: Dimension = Dimension::new(1, 1, -2, 0, 0);
+    /// M L^-1 T^-2.
+    pub const PRESSURE: Dimension = Dimension::new(1, -1, -2, 0, 0);
+
+    pub const fn new(mass: i8, length: i8, time: i8, temperature: i8, charge: i8) -> Self {
+        Self {
+            mass,
+            length,
+            time,
+            temperature,
+            charge,
+        }
+    }
+
+    /// Panics if an exponent overflows `i8`; see `checked_powi`.
+    pub fn powi(self, exponent: i8) -> Dimension {
+        self.checked_powi(exponent)
+            .expect("dimension exponent overflow")
+    }
+
+    /// `self` raised to `exponent`, or `None` if an exponent overflows.
+    pub fn checked_powi(self, exponent: i8) -> Option<Dimension> {
+        Some(Dimension::new(
+            self.mass.checked_mul(exponent)?,
+            self.length.checked_mul(exponent)?,
+            self.time.checked_mul(exponent)?,
+            self.temperature.checked_mul(exponent)?,
+            self.charge.checked_mul(exponent)?,
+        ))
+    }
+
+    /// Product dimension, or `None` if an exponent overflows.
+    pub fn checked_mul(self, other: Dimension) -> Option<Dimension> {
+        Some(Dimension::new(
+            self.mass.checked_add(other.mass)?,
+            self.length.checked_add(other.length)?,
+            self.time.checked_add(other.time)?,
+            self.temperature.checked_add(other.temperature)?,
+            self.charge.checked_add(other.charge)?,
+        ))
+    }
+
+    /// Quotient dimension, or `None` if an exponent overflows.
+    pub fn checked_div(self, other: Dimension) -> Option<Dimension> {
+        self.checked_mul(other.checked_powi(-1)?)
+    }
+
+    /// Common name of the dimension, if it has one.
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match *self {
+            Dimension::DIMENSIONLESS => "dimensionless",
+            Dimension::MASS => "mass",
+            Dimension::LENGTH => "length",
+            Dimension::TIME => "time",
+            Dimension::TEMPERATURE => "temperature",
+            Dimension::CHARGE => "charge",
+            Dimension::ENERGY => "energy",
+            Dimension::FORCE => "force",
+            Dimension::PRESSURE => "pressure",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Panics if an exponent overflows `i8`; see `Dimension::checked_mul`.
+impl Mul for Dimension {
+    type Output = Dimension;
+
+    fn mul(self, other: Dimension) -> Dimension {
+        self.checked_mul(other)
+            .expect("dimension exponent overflow")
+    }
+}
+
+/// Panics if an exponent overflows `i8`; see `Dimension::checked_div`.
+impl Div for Dimension {
+    type Output = Dimension;
+
+    fn div(self, other: Dimension) -> Dimension {
+        self.checked_div(other)
+            .expect("dimension exponent overflow")
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name() {
+            return write!(f, "{}", name);
+        }
+        let parts: Vec<String> = [
+            ("M", self.mass),
+            ("L", self.length),
+            ("T", self.time),
+            ("Θ", self.temperature),
+            ("Q", self.charge),
+        ]
+        .iter()
+        .filter(|(_, exponent)| *exponent != 0)
+        .map(|(symbol, exponent)| format!("{}^{}", symbol, exponent))
+        .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+const AVOGADRO: f64 = 6.022_140_76e23;
+const ELEMENTARY_CHARGE: f64 = 1.602_176_634e-19;
+
+/// Recognised unit atoms: (symbol, dimension, SI scale).
+const UNIT_ATOMS: &[(&str, Dimension, f64)] = &[
+    // Energy
+    ("J", Dimension::ENERGY, 1.0),
+    ("kJ", Dimension::ENERGY, 1.0e3),
+    ("cal", Dimension::ENERGY, 4.184),
+    ("kcal", Dimension::ENERGY, 4.184e3),
+    ("eV", Dimension::ENERGY, ELEMENTARY_CHARGE),
+    ("meV", Dimension::ENERGY, ELEMENTARY_CHARGE * 1.0e-3),
+    ("Ha", Dimension::ENERGY, 4.359_744_722_207_1e-18),
+    ("Hartree", Dimension::ENERGY, 4.359_744_722_207_1e-18),
+    ("Ry", Dimension::ENERGY, 2.179_872_361_103_5e-18),
+    // Length
+    ("m", Dimension::LENGTH, 1.0),
+    ("cm", Dimension::LENGTH, 1.0e-2),
+    ("nm", Dimension::LENGTH, 1.0e-9),
+    ("pm", Dimension::LENGTH, 1.0e-12),
+    ("Angstrom", Dimension::LENGTH, 1.0e-10),
+    ("Angst", Dimension::LENGTH, 1.0e-10),
+    ("Å", Dimension::LENGTH, 1.0e-10),
+    ("Bohr", Dimension::LENGTH, 5.291_772_109_03e-11),
+    ("bohr", Dimension::LENGTH, 5.291_772_109_03e-11),
+    // Time
+    ("s", Dimension::TIME, 1.0),
+    ("ms", Dimension::TIME, 1.0e-3),
+    ("us", Dimension::TIME, 1.0e-6),
+    ("ns", Dimension::TIME, 1.0e-9),
+    ("ps", Dimension::TIME, 1.0e-12),
+    ("fs", Dimension::TIME, 1.0e-15),
+    // Temperature
+    ("K", Dimension::TEMPERATURE, 1.0),
+    // Pressure
+    ("Pa", Dimension::PRESSURE, 1.0),
+    ("kPa", Dimension::PRESSURE, 1.0e3),
+    ("MPa", Dimension::PRESSURE, 1.0e6),
+    ("GPa", Dimension::PRESSURE, 1.0e9),
+    ("bar", Dimension::PRESSURE, 1.0e5),
+    ("kbar", Dimension::PRESSURE, 1.0e8),
+    ("atm", Dimension::PRESSURE, 101_325.0),
+    // Force
+    ("N", Dimension::FORCE, 1.0),
+    ("pN", Dimension::FORCE, 1.0e-12),
+    // Mass
+    ("kg", Dimension::MASS, 1.0),
+    ("g", Dimension::MASS, 1.0e-3),
+    ("amu", Dimension::MASS, 1.660_539_066_60e-27),
+    ("Da", Dimension::MASS, 1.660_539_066_60e-27),
+    // Charge
+    ("C", Dimension::CHARGE, 1.0),
+    ("e", Dimension::CHARGE, ELEMENTARY_CHARGE),
+    // Amount, as a particle count
+    ("mol", Dimension::DIMENSIONLESS, AVOGADRO),
+];
+
+/// Symbols that denote a pure number.
+const DIMENSIONLESS_SYMBOLS: &[&str] = &["", "1", "relative", "dimensionless"];
+
+/// Errors from unit conversion and unit-checked arithmetic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitError {
+    /// The symbol was not recognised, so it only matches itself.
+    UnknownUnit(String),
+    /// Both units are known but have different dimensions.
+    Incompatible { from: Unit, to: Unit },
+    /// The value has no numeric payload to convert, e.g. a categorical value.
+    NotNumeric(String),
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitError::UnknownUnit(symbol) => write!(f, "Unknown unit: '{}'", symbol),
+            UnitError::Incompatible { from, to } => write!(
+                f,
+                "Incompatible units: '{}' ({}) cannot be converted to '{}' ({})",
+                from,
+                from.describe_dimension(),
+                to,
+                to.describe_dimension()
+            ),
+            UnitError::NotNumeric(value) => write!(f, "Not a numeric value: {}", value),
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// Physical unit: the symbol as written plus its parsed dimension and scale.
+/// Serializes as the plain symbol string.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "String", into = "String")]
+pub struct Unit {
+    symbol: Symbol,
+    /// `None` for symbols that could not be parsed.
+    dimension: Option<Dimension>,
+    /// Multiplier converting one of this unit into SI (per-particle) units.
+    scale: f64,
+}
+
+impl Unit {
+    /// Parse a unit symbol. Never fails: unrecognised symbols become opaque.
+    pub fn parse(symbol: &str) -> Unit {
+        Unit::parse_symbol(Symbol::from(symbol))
+    }
+
+    /// `parse` for an already interned symbol; see `SymbolTable::unit`.
+    pub(crate) fn parse_symbol(symbol: Symbol) -> Unit {
+        let trimmed = symbol.trim();
+        let parsed = if DIMENSIONLESS_SYMBOLS.contains(&trimmed) {
+            Some((Dimension::DIMENSIONLESS, 1.0))
+        } else {
+            UnitParser::new(trimmed).parse()
+        };
+        Unit {
+            symbol,
+            dimension: parsed.map(|(dimension, _)| dimension),
+            scale: parsed.map_or(1.0, |(_, scale)| scale),
+        }
+    }
+
+    /// This unit under another handle for the same symbol.
+    pub(crate) fn with_symbol(&self, symbol: Symbol) -> Unit {
+        Unit {
+            symbol,
+            dimension: self.dimension,
+            scale: self.scale,
+        }
+    }
+
+    pub fn dimensionless() -> Unit {
+        Unit::parse("")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.symbol
+    }
+
+    /// The symbol as written, shared with the log's `SymbolTable`.
+    pub fn symbol(&self) -> &Symbol {
+        &self.symbol
+    }
+
+    pub fn dimension(&self) -> Option<Dimension> {
+        self.dimension
+    }
+
+    /// True when the symbol was recognised.
+    pub fn is_known(&self) -> bool {
+        self.dimension.is_some()
+    }
+
+    /// True when values in `self` can be converted to `other`.
+    pub fn is_compatible(&self, other: &Unit) -> bool {
+        self.conversion_factor(other).is_ok()
+    }
+
+    /// Factor `f` such that `x self == x * f other`.
+    pub fn conversion_factor(&self, to: &Unit) -> Result<f64, UnitError> {
+        if self.symbol == to.symbol {
+            return Ok(1.0);
+        }
+        match (self.dimension, to.dimension) {
+            (Some(from_dimension), Some(to_dimension)) if from_dimension == to_dimension => {
+                Ok(self.scale / to.scale)
+            }
+            (Some(_), Some(_)) => Err(UnitError::Incompatible {
+                from: self.clone(),
+                to: to.clone(),
+            }),
+            (None, _) => Err(UnitError::UnknownUnit(self.symbol.to_string())),
+            (_, None) => Err(UnitError::UnknownUnit(to.symbol.to_string())),
+        }
+    }
+
+    pub fn convert(&self, value: f64, to: &Unit) -> Result<f64, UnitError> {
+        Ok(value * self.conversion_factor(to)?)
+    }
+
+    /// Product unit, e.g. "kJ/mol" * "nm" = "kJ/mol*nm".
+    pub fn checked_mul(&self, other: &Unit) -> Result<Unit, UnitError> {
+        self.combine(other, "*", Dimension::checked_mul, |a, b| a * b)
+    }
+
+    /// Quotient unit, e.g. "kJ/mol" / "nm" = "kJ/mol/nm".
+    pub fn checked_div(&self, other: &Unit) -> Result<Unit, UnitError> {
+        self.combine(other, "/", Dimension::checked_div, |a, b| a / b)
+    }
+
+    fn combine(
+        &self,
+        other: &Unit,
+        operator: &str,
+        dimension_op: fn(Dimension, Dimension) -> Option<Dimension>,
+        scale_op: fn(f64, f64) -> f64,
+    ) -> Result<Unit, UnitError> {
+        let lhs = self
+            .dimension
+            .ok_or_else(|| UnitError::UnknownUnit(self.symbol.to_string()))?;
+        let rhs = other
+            .dimension
+            .ok_or_else(|| UnitError::UnknownUnit(other.symbol.to_string()))?;
+        let rhs_symbol = if other.symbol.contains(['*', '/', ' ']) {
+            format!("({})", other.symbol)
+        } else {
+            other.symbol.to_string()
+        };
+        let lhs_symbol = if self.symbol.trim().is_empty() {
+            "1"
+        } else {
+            self.symbol.as_str()
+        };
+        Ok(Unit {
+            symbol: format!("{}{}{}", lhs_symbol, operator, rhs_symbol).into(),
+            // An exponent overflow leaves the product an unknown unit.
+            dimension: dimension_op(lhs, rhs),
+            scale: scale_op(self.scale, other.scale),
+        })
+    }
+
+    fn describe_dimension(&self) -> String {
+        self.dimension
+            .map_or_else(|| "unknown".to_string(), |dimension| dimension.to_string())
+    }
+}
+
+impl Default for Unit {
+    fn default() -> Self {
+        Unit::dimensionless()
+    }
+}
+
+/// Units are identified by their symbol; dimension and scale derive from it.
+impl PartialEq for Unit {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol
+    }
+}
+
+impl Eq for Unit {}
+
+impl Hash for Unit {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.symbol.hash(state);
+    }
+}
+
+impl PartialEq<str> for Unit {
+    fn eq(&self, other: &str) -> bool {
+        self.symbol == other
+    }
+}
+
+impl PartialEq<&str> for Unit {
+    fn eq(&self, other: &&str) -> bool {
+        self.symbol == *other
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
+
+impl From<&str> for Unit {
+    fn from(symbol: &str) -> Self {
+        Unit::parse(symbol)
+    }
+}
+
+impl From<String> for Unit {
+    fn from(symbol: String) -> Self {
+        Unit::parse_symbol(Symbol::from(symbol))
+    }
+}
+
+impl From<Unit> for String {
+    fn from(unit: Unit) -> Self {
+        unit.symbol.into()
+    }
+}
+
+/// Recursive-descent parser for symbols such as "kJ/mol/nm", "nm^3",
+/// "kJ mol^-1" and "eV/(Angst*ps)". A `/` divides by the next factor only.
+struct UnitParser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> UnitParser<'a> {
+    fn new(symbol: &'a str) -> Self {
+        Self { rest: symbol }
+    }
+
+    fn parse(mut self) -> Option<(Dimension, f64)> {
+        let result = self.product()?;
+        self.rest.trim_start().is_empty().then_some(result)
+    }
+
+    fn product(&mut self) -> Option<(Dimension, f64)> {
+        let (mut dimension, mut scale) = self.factor()?;
+        loop {
+            self.rest = self.rest.trim_start();
+            let divide = match self.rest.chars().next() {
+                Some('/') => true,
+                Some('*') | Some('.') | Some('·') => false,
+                Some(c) if c.is_alphabetic() || c == '(' => {
+                    // Juxtaposition, e.g. "kJ mol^-1".
+                    let (next_dimension, next_scale) = self.factor()?;
+                    dimension = dimension.checked_mul(next_dimension)?;
+                    scale *= next_scale;
+                    continue;
+                }
+                _ => return Some((dimension, scale)),
+            };
+            self.advance_char();
+            let (next_dimension, next_scale) = self.factor()?;
+            if divide {
+                dimension = dimension.checked_div(next_dimension)?;
+                scale /= next_scale;
+            } else {
+                dimension = dimension.checked_mul(next_dimension)?;
+                scale *= next_scale;
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<(Dimension, f64)> {
+        self.rest = self.rest.trim_start();
+        let (dimension, scale) = if self.rest.starts_with('(') {
+            self.advance_char();
+            let inner = self.product()?;
+            self.rest = self.rest.trim_start();
+            if !self.rest.starts_with(')') {
+                return None;
+            }
+            self.advance_char();
+            inner
+        } else {
+            let end = self
+                .rest
+                .find(|c: char| !(c.is_alphabetic() || c == '_'))
+                .unwrap_or(self.rest.len());
+            let (atom, rest) = self.rest.split_at(end);
+            self.rest = rest;
+            if atom.is_empty() {
+                // Leading "1" as in "1/ps".
+                if let Some(rest) = self.rest.strip_prefix('1') {
+                    self.rest = rest;
+                    return Some((Dimension::DIMENSIONLESS, 1.0));
+                }
+                return None;
+            }
+            UNIT_ATOMS
+                .iter()
+                .find(|(symbol, _, _)| *symbol == atom)
+                .map(|(_, dimension, scale)| (*dimension, *scale))?
+        };
+
+        let exponent = self.exponent()?;
+        Some((
+            dimension.checked_powi(exponent)?,
+            scale.powi(exponent as i32),
+        ))
+    }
+
+    fn exponent(&mut self) -> Option<i8> {
+        let Some(rest) = self.rest.strip_prefix('^') else {
+            return Some(1);
+        };
+        let end = rest
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(rest.len(), |(i, _)| i);
+        self.rest = &rest[end..];
+        rest[..end].parse().ok()
+    }
+
+    fn advance_char(&mut self) {
+        let mut chars = self.rest.chars();
+        chars.next();
+        self.rest = chars.as_str();
+    }
+}
+
+/// A number with a unit, supporting unit-checked arithmetic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: impl Into<Unit>) -> Self {
+        Self {
+            value,
+            unit: unit.into(),
+        }
+    }
+
+    pub fn convert_to(&self, unit: &Unit) -> Result<Quantity, UnitError> {
+        Ok(Quantity {
+            value: self.unit.convert(self.value, unit)?,
+            unit: unit.clone(),
+        })
+    }
+
+    /// Sum in `self`'s unit.
+    pub fn checked_add(&self, other: &Quantity) -> Result<Quantity, UnitError> {
+        let rhs = other.unit.convert(other.value, &self.unit)?;
+        Ok(Quantity {
+            value: self.value + rhs,
+            unit: self.unit.clone(),
+        })
+    }
+
+    /// Difference in `self`'s unit.
+    pub fn checked_sub(&self, other: &Quantity) -> Result<Quantity, UnitError> {
+        let rhs = other.unit.convert(other.value, &self.unit)?;
+        Ok(Quantity {
+            value: self.value - rhs,
+            unit: self.unit.clone(),
+        })
+    }
+
+    pub fn checked_mul(&self, other: &Quantity) -> Result<Quantity, UnitError> {
+        Ok(Quantity {
+            value: self.value * other.value,
+            unit: self.unit.checked_mul(&other.unit)?,
+        })
+    }
+
+    pub fn checked_div(&self, other: &Quantity) -> Result<Quantity, UnitError> {
+        Ok(Quantity {
+            value: self.value / other.value,
+            unit: self.unit.checked_div(&other.unit)?,
+        })
+    }
+
+    /// Order two quantities after converting `other` into `self`'s unit.
+    /// `Ok(None)` when either value is NaN.
+    pub fn checked_cmp(&self, other: &Quantity) -> Result<Option<Ordering>, UnitError> {
+        let rhs = other.unit.convert(other.value, &self.unit)?;
+        Ok(self.value.partial_cmp(&rhs))
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unit.as_str().is_empty() {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.unit)
+        }
+    }
+}
@@ -1,13 +1,24 @@
-use crate::adapter::{AdapterError, DslAdapter};
+use std::io::{BufRead, Read};
+
+use crate::adapter::{assignment_keys, key_weight, AdapterError, Detection, DslAdapter};
+use crate::bundle::{file_name, TraceBundle, TraceFile};
 use crate::common::*;
 use crate::event_kinds::EventKind;
 use crate::lel::*;
+use crate::provenance::{line_anchor, LineHasher, SourceText};
+use crate::streaming::{
+    source_paths, take_source, EventSink, Lines, StreamSource, StreamingAdapter,
+};
+use crate::symbol::{Symbol, SymbolTable};
 
 pub struct VaspAdapter;
 
 const INCAR_MARKER: &str = "--- INCAR ---";
 const OSZICAR_MARKER: &str = "--- OSZICAR ---";
 const OUTCAR_MARKER: &str = "--- OUTCAR ---";
+const INCAR_FILE: &str = "INCAR";
+const OSZICAR_FILE: &str = "OSZICAR";
+const OUTCAR_FILE: &str = "OUTCAR";
 
 pub fn classify_incar_parameter(
     key: &str,
@@ -96,7 +107,23 @@ pub fn classify_incar_parameter(
     }
 }
 
-pub fn parse_incar(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
+pub fn parse_incar(
+    content: &str,
+    ids: &mut EventIdAllocator,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    parse_incar_file(INCAR_FILE, content, ids, &mut SymbolTable::new())
+}
+
+/// `parse_incar`, anchoring provenance to `path` and interning names and
+/// units into `symbols`.
+pub fn parse_incar_file(
+    path: &str,
+    content: &str,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    let source = SourceText::new(content);
+    let path = symbols.intern(path);
     let mut events = Vec::new();
     let mut logical_sequence = 1_u64;
 
@@ -115,9 +142,11 @@ pub fn parse_incar(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
         let key = raw_key.trim().to_ascii_uppercase();
         let value = raw_value.split(['!', '#']).next().unwrap_or("").trim();
         let (layer, boundary, unit) = classify_incar_parameter(&key, value);
+        let name = symbols.intern(&key);
+        let unit = unit.map(|symbol| symbols.unit(symbol));
 
         let parsed_value = match value.parse::<f64>() {
-            Ok(numeric) => Value::Known(numeric, unit.unwrap_or("").to_string()),
+            Ok(numeric) => Value::Known(numeric, unit.clone().unwrap_or_else(|| symbols.unit(""))),
             Err(_) => Value::KnownCat(value.to_string()),
         };
 
@@ -125,10 +154,10 @@ pub fn parse_incar(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
             .layer(layer)
             .boundary(boundary)
             .kind(EventKind::ParameterRecord {
-                name: key.clone(),
+                name: name.clone(),
                 specified_value: None,
                 actual_value: parsed_value,
-                units: unit.map(|u| u.to_string()),
+                units: unit,
                 observation_mode: ObservationMode::Observational,
             })
             .temporal(TemporalCoord {
@@ -136,16 +165,9 @@ pub fn parse_incar(content: &str) -> Result<Vec<TraceEvent>, AdapterError> {
                 wall_clock_ns: None,
                 logical_sequence,
             })
-            .provenance(ProvenanceAnchor {
-                source_file: "INCAR".to_string(),
-                source_location: SourceLocation::LineRange {
-                    start: line_num,
-                    end: line_num,
-                },
-                raw_hash: 0,
-            })
-            .dag_node_ref(key)
-            .build();
+            .provenance(source.anchor(&path, line_num, line_num))
+            .dag_node_ref(name)
+            .try_build(ids)?;
 
         logical_sequence += 1;
         events.push(event);
@@ -172,14 +194,67 @@ fn extract_vasp_version(line: &str) -> Option<String> {
         })
 }
 
-pub fn parse_oszicar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, AdapterError> {
+pub fn parse_oszicar(
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    parse_oszicar_file(
+        OSZICAR_FILE,
+        content,
+        seq_offset,
+        ids,
+        &mut SymbolTable::new(),
+    )
+}
+
+/// `parse_oszicar`, anchoring provenance to `path` and interning names and
+/// units into `symbols`.
+pub fn parse_oszicar_file(
+    path: &str,
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<TraceEvent>, AdapterError> {
     let mut events = Vec::new();
+    stream_oszicar_file(
+        path,
+        content.as_bytes(),
+        seq_offset,
+        ids,
+        symbols,
+        |event| {
+            events.push(event);
+            Ok(())
+        },
+    )?;
+    Ok(events)
+}
+
+/// `parse_oszicar_file` over a reader, passing each event to `emit` as soon
+/// as it is complete. The latest `ConvergencePoint` is held until the next
+/// `F=` line can mark it converged.
+pub fn stream_oszicar_file(
+    path: &str,
+    reader: impl BufRead,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+    mut emit: impl FnMut(TraceEvent) -> Result<(), AdapterError>,
+) -> Result<(), AdapterError> {
+    // Every line repeats the same names and unit.
+    let path = symbols.intern(path);
+    let electron_volt = symbols.unit("eV");
+    let delta_e_name = symbols.intern("dE");
+    let e0_name = symbols.intern("E0");
     let mut logical_sequence = seq_offset + 1;
     let mut current_ionic_step = 0_u64;
+    let mut held_convergence: Option<TraceEvent> = None;
+    let mut lines = Lines::new(reader);
 
-    for (idx, raw_line) in content.lines().enumerate() {
-        let line_num = (idx + 1) as u32;
-        let line = raw_line.trim();
+    while let Some(raw_line) = lines.next_line()? {
+        let line = raw_line.text.trim();
 
         if line.starts_with("DAV:") || line.starts_with("RMM:") {
             let tokens: Vec<&str> = line.split_whitespace().collect();
@@ -191,27 +266,25 @@ pub fn parse_oszicar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>,
                     .layer(Layer::Methodology)
                     .kind(EventKind::ConvergencePoint {
                         iteration,
-                        metric_name: "dE".to_string(),
-                        metric_value: Value::Known(delta_e, "eV".to_string()),
+                        metric_name: delta_e_name.clone(),
+                        metric_value: Value::Known(delta_e, electron_volt.clone()),
                         converged: None,
+                        uncertainty: Some(UncertaintySummary::unavailable(
+                            UncertaintyUnavailableReason::AdapterCannotObserve,
+                        )),
                     })
                     .temporal(TemporalCoord {
                         simulation_step: current_ionic_step,
                         wall_clock_ns: None,
                         logical_sequence,
                     })
-                    .provenance(ProvenanceAnchor {
-                        source_file: "OSZICAR".to_string(),
-                        source_location: SourceLocation::LineRange {
-                            start: line_num,
-                            end: line_num,
-                        },
-                        raw_hash: 0,
-                    })
-                    .build();
+                    .provenance(raw_line.anchor(&path))
+                    .try_build(ids)?;
 
                 logical_sequence += 1;
-                events.push(event);
+                if let Some(prior) = held_convergence.replace(event) {
+                    emit(prior)?;
+                }
             }
             continue;
         }
@@ -230,25 +303,31 @@ pub fn parse_oszicar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>,
                 .or_else(|| parse_value_after_marker(line, "dE ="));
 
             if let Some(total_energy) = total_energy {
-                for prior in events.iter_mut().rev() {
+                if let Some(mut prior) = held_convergence.take() {
                     if let EventKind::ConvergencePoint { converged, .. } = &mut prior.kind {
                         *converged = Some(true);
-                        break;
                     }
+                    emit(prior)?;
                 }
 
                 let mut components = Vec::new();
                 if let Some(e0_energy) = e0_energy {
-                    components.push(("E0".to_string(), Value::Known(e0_energy, "eV".to_string())));
+                    components.push((
+                        e0_name.clone(),
+                        Value::Known(e0_energy, electron_volt.clone()),
+                    ));
                 }
                 if let Some(delta_e) = delta_e {
-                    components.push(("dE".to_string(), Value::Known(delta_e, "eV".to_string())));
+                    components.push((
+                        delta_e_name.clone(),
+                        Value::Known(delta_e, electron_volt.clone()),
+                    ));
                 }
 
                 let event = TraceEventBuilder::new()
                     .layer(Layer::Implementation)
                     .kind(EventKind::EnergyRecord {
-                        total: Value::Known(total_energy, "eV".to_string()),
+                        total: Value::Known(total_energy, electron_volt.clone()),
                         components,
                     })
                     .temporal(TemporalCoord {
@@ -256,35 +335,328 @@ pub fn parse_oszicar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>,
                         wall_clock_ns: None,
                         logical_sequence,
                     })
-                    .provenance(ProvenanceAnchor {
-                        source_file: "OSZICAR".to_string(),
-                        source_location: SourceLocation::LineRange {
-                            start: line_num,
-                            end: line_num,
-                        },
-                        raw_hash: 0,
-                    })
-                    .build();
+                    .provenance(raw_line.anchor(&path))
+                    .try_build(ids)?;
 
                 logical_sequence += 1;
-                events.push(event);
+                emit(event)?;
             }
         }
     }
 
+    if let Some(prior) = held_convergence {
+        emit(prior)?;
+    }
+    Ok(())
+}
+
+/// OUTCAR echo flags that cause VASP to write a volumetric file:
+/// (INCAR tag, file name, data type, value unit).
+const VOLUMETRIC_OUTPUTS: [(&str, &str, &str, &str); 2] = [
+    ("LCHARG", "CHGCAR", "charge_density", "e"),
+    ("LVTOT", "LOCPOT", "local_potential", "eV"),
+];
+
+/// Rows of an OUTCAR `POSITION  TOTAL-FORCE` table collected so far.
+struct ForceTable {
+    start_line: u32,
+    end_line: u32,
+    /// Running hash from `start_line`, and the hash of
+    /// `start_line..=end_line`.
+    lines: LineHasher,
+    hash: u64,
+    forces: Vec<[f64; 3]>,
+    saw_separator: bool,
+}
+
+fn parse_force_row(line: &str) -> Option<[f64; 3]> {
+    let values: Vec<f64> = line
+        .split_whitespace()
+        .map(|token| token.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    if values.len() != 6 {
+        return None;
+    }
+    Some([values[3], values[4], values[5]])
+}
+
+/// Parse the fine FFT grid from `dimension x,y,z NGXF=   224 NGYF=  224 NGZF=  224`.
+fn parse_fine_grid_shape(line: &str) -> Option<Vec<usize>> {
+    let shape: Vec<usize> = ["NGXF=", "NGYF=", "NGZF="]
+        .iter()
+        .map(|marker| {
+            line.split(marker)
+                .nth(1)?
+                .split_whitespace()
+                .next()?
+                .parse::<usize>()
+                .ok()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(shape)
+}
+
+/// Match an OUTCAR flag echo such as `LCHARG       =      T    write CHGCAR`.
+fn volumetric_output_for_line(line: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let (raw_key, raw_value) = line.split_once('=')?;
+    let key = raw_key.trim();
+    let enabled = raw_value
+        .split_whitespace()
+        .next()
+        .is_some_and(|flag| matches!(flag, "T" | ".TRUE." | ".TRUE"));
+    if !enabled {
+        return None;
+    }
+    VOLUMETRIC_OUTPUTS
+        .iter()
+        .find(|(tag, ..)| *tag == key)
+        .map(|(_, file, data_type, unit)| (*file, *data_type, *unit))
+}
+
+fn force_grid_event(
+    table: ForceTable,
+    logical_sequence: u64,
+    path: &Symbol,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<TraceEvent, AdapterError> {
+    let ion_count = table.forces.len();
+    let dimensionless = symbols.unit("");
+    TraceEventBuilder::new()
+        .layer(Layer::Implementation)
+        .kind(EventKind::ObservableMeasurement {
+            variable_name: symbols.intern("total_force"),
+            measurement_method: "OUTCAR TOTAL-FORCE table".to_string(),
+            value: Value::KnownGrid {
+                axes: vec![
+                    GridAxis {
+                        label: "ion".to_string(),
+                        coordinates: (1..=ion_count).map(|ion| ion as f64).collect(),
+                        unit: dimensionless.clone(),
+                    },
+                    GridAxis {
+                        label: "cartesian_component".to_string(),
+                        coordinates: vec![0.0, 1.0, 2.0],
+                        unit: dimensionless,
+                    },
+                ],
+                values: table.forces.into_iter().flatten().collect(),
+                value_unit: symbols.unit("eV/Angst"),
+            },
+            uncertainty: None,
+            conditions: format!("{} ions", ion_count),
+            observation_mode: ObservationMode::Observational,
+        })
+        .temporal(TemporalCoord {
+            simulation_step: 0,
+            wall_clock_ns: None,
+            logical_sequence,
+        })
+        .provenance(line_anchor(
+            path,
+            table.start_line,
+            table.end_line,
+            table.hash,
+        ))
+        .try_build(ids)
+        .map_err(AdapterError::from)
+}
+
+pub fn parse_outcar(
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    parse_outcar_file(
+        OUTCAR_FILE,
+        content,
+        seq_offset,
+        ids,
+        &mut SymbolTable::new(),
+    )
+}
+
+/// `parse_outcar`, anchoring provenance to `path` and interning names and
+/// units into `symbols`.
+pub fn parse_outcar_file(
+    path: &str,
+    content: &str,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+) -> Result<Vec<TraceEvent>, AdapterError> {
+    let mut events = Vec::new();
+    stream_outcar_file(
+        path,
+        content.as_bytes(),
+        seq_offset,
+        ids,
+        symbols,
+        |event| {
+            events.push(event);
+            Ok(())
+        },
+    )?;
     Ok(events)
 }
 
-pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, AdapterError> {
-    let mut events: Vec<TraceEvent> = Vec::new();
+/// Lines after the version line within which a `running on N total cores`
+/// line still fills in the `ResourceStatus` parallelization. Events in that
+/// window are held back until the line is seen or the window closes.
+const RESOURCE_LOOKAHEAD_LINES: u32 = 100;
+
+/// Passes events to `emit`, or holds them behind a `ResourceStatus` event
+/// whose parallelization may still be filled in.
+struct Outbox<E> {
+    emit: E,
+    /// The held events, `ResourceStatus` first, and the last line of the
+    /// lookahead window.
+    held: Option<(Vec<TraceEvent>, u32)>,
+}
+
+impl<E> Outbox<E>
+where
+    E: FnMut(TraceEvent) -> Result<(), AdapterError>,
+{
+    fn send(&mut self, event: TraceEvent) -> Result<(), AdapterError> {
+        match &mut self.held {
+            Some((events, _)) => {
+                events.push(event);
+                Ok(())
+            }
+            None => (self.emit)(event),
+        }
+    }
+
+    fn hold(&mut self, resource: TraceEvent, until_line: u32) -> Result<(), AdapterError> {
+        self.release()?;
+        self.held = Some((vec![resource], until_line));
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<(), AdapterError> {
+        for event in self.held.take().map_or_else(Vec::new, |(events, _)| events) {
+            (self.emit)(event)?;
+        }
+        Ok(())
+    }
+
+    /// Release held events once `line_num` is past the lookahead window.
+    fn advance(&mut self, line_num: u32) -> Result<(), AdapterError> {
+        match self.held {
+            Some((_, until_line)) if line_num > until_line => self.release(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the held `ResourceStatus` parallelization and release it. Does
+    /// nothing once the window has closed.
+    fn set_parallelization(&mut self, value: String) -> Result<(), AdapterError> {
+        if let Some((events, _)) = &mut self.held {
+            if let EventKind::ResourceStatus {
+                parallelization, ..
+            } = &mut events[0].kind
+            {
+                *parallelization = Some(value);
+            }
+        }
+        self.release()
+    }
+}
+
+/// `parse_outcar_file` over a reader, passing each event to `emit` as soon
+/// as it is complete. Holds at most one force table, plus the events within
+/// `RESOURCE_LOOKAHEAD_LINES` of the version line.
+pub fn stream_outcar_file(
+    path: &str,
+    reader: impl BufRead,
+    seq_offset: u64,
+    ids: &mut EventIdAllocator,
+    symbols: &mut SymbolTable,
+    emit: impl FnMut(TraceEvent) -> Result<(), AdapterError>,
+) -> Result<(), AdapterError> {
+    let path = symbols.intern(path);
+    let electron_volt = symbols.unit("eV");
+    let mut out = Outbox { emit, held: None };
     let mut logical_sequence = seq_offset + 1;
-    let mut resource_event_idx: Option<usize> = None;
+    let mut saw_resource = false;
     let mut pending_parallelization: Option<String> = None;
     let mut saw_terminal_status = false;
+    let mut fine_grid_shape: Vec<usize> = Vec::new();
+    let mut force_table: Option<ForceTable> = None;
+    let mut last_line_hash = LineHasher::new().finish();
+    let mut lines = Lines::new(reader);
 
-    for (idx, raw_line) in content.lines().enumerate() {
-        let line_num = (idx + 1) as u32;
-        let line = raw_line.trim();
+    while let Some(raw_line) = lines.next_line()? {
+        let line_num = raw_line.number;
+        let line = raw_line.text.trim();
+        last_line_hash = raw_line.hash().finish();
+
+        out.advance(line_num)?;
+
+        if let Some(table) = force_table.as_mut() {
+            if line.starts_with("---") && !table.saw_separator {
+                table.saw_separator = true;
+                table.lines.push(raw_line.raw);
+                continue;
+            }
+            if let Some(force) = parse_force_row(line) {
+                table.forces.push(force);
+                table.lines.push(raw_line.raw);
+                table.end_line = line_num;
+                table.hash = table.lines.finish();
+                continue;
+            }
+            if let Some(table) = force_table.take() {
+                if !table.forces.is_empty() {
+                    out.send(force_grid_event(
+                        table,
+                        logical_sequence,
+                        &path,
+                        ids,
+                        symbols,
+                    )?)?;
+                    logical_sequence += 1;
+                }
+            }
+        }
+
+        if fine_grid_shape.is_empty() && line.contains("NGXF=") {
+            if let Some(shape) = parse_fine_grid_shape(line) {
+                fine_grid_shape = shape;
+            }
+        }
+
+        if let Some((file, data_type, unit)) = volumetric_output_for_line(line) {
+            let event = TraceEventBuilder::new()
+                .layer(Layer::Implementation)
+                .kind(EventKind::ObservableMeasurement {
+                    variable_name: symbols.intern(data_type),
+                    measurement_method: format!("{} volumetric output", file),
+                    value: Value::DataRef {
+                        path: file.to_string(),
+                        data_type: data_type.to_string(),
+                        shape: fine_grid_shape.clone(),
+                        unit: symbols.unit(unit),
+                        checksum: None,
+                    },
+                    uncertainty: None,
+                    conditions: "written at end of run".to_string(),
+                    observation_mode: ObservationMode::Observational,
+                })
+                .temporal(TemporalCoord {
+                    simulation_step: 0,
+                    wall_clock_ns: None,
+                    logical_sequence,
+                })
+                .provenance(raw_line.anchor(&path))
+                .try_build(ids)?;
+
+            logical_sequence += 1;
+            out.send(event)?;
+            continue;
+        }
 
         if line.contains("running on") && line.contains("total cores") {
             if let Some(core_count) = line
@@ -292,12 +664,8 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                 .find_map(|token| token.parse::<u64>().ok())
             {
                 let parallelization_value = format!("{} cores", core_count);
-                if let Some(resource_idx) = resource_event_idx {
-                    if let EventKind::ResourceStatus { parallelization, .. } =
-                        &mut events[resource_idx].kind
-                    {
-                        *parallelization = Some(parallelization_value.clone());
-                    }
+                if saw_resource {
+                    out.set_parallelization(parallelization_value)?;
                 } else {
                     pending_parallelization = Some(parallelization_value);
                 }
@@ -305,7 +673,7 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
             continue;
         }
 
-        if resource_event_idx.is_none() && (line.contains("vasp.") || line.contains("VASP")) {
+        if !saw_resource && (line.contains("vasp.") || line.contains("VASP")) {
             let version = extract_vasp_version(line).unwrap_or_else(|| "VASP".to_string());
             let event = TraceEventBuilder::new()
                 .layer(Layer::Implementation)
@@ -322,19 +690,12 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                     wall_clock_ns: None,
                     logical_sequence,
                 })
-                .provenance(ProvenanceAnchor {
-                    source_file: "OUTCAR".to_string(),
-                    source_location: SourceLocation::LineRange {
-                        start: line_num,
-                        end: line_num,
-                    },
-                    raw_hash: 0,
-                })
-                .build();
+                .provenance(raw_line.anchor(&path))
+                .try_build(ids)?;
 
             logical_sequence += 1;
-            events.push(event);
-            resource_event_idx = Some(events.len() - 1);
+            saw_resource = true;
+            out.hold(event, line_num + RESOURCE_LOOKAHEAD_LINES)?;
             continue;
         }
 
@@ -343,7 +704,7 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                 let event = TraceEventBuilder::new()
                     .layer(Layer::Implementation)
                     .kind(EventKind::EnergyRecord {
-                        total: Value::Known(total_energy, "eV".to_string()),
+                        total: Value::Known(total_energy, electron_volt.clone()),
                         components: Vec::new(),
                     })
                     .temporal(TemporalCoord {
@@ -351,18 +712,11 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                         wall_clock_ns: None,
                         logical_sequence,
                     })
-                    .provenance(ProvenanceAnchor {
-                        source_file: "OUTCAR".to_string(),
-                        source_location: SourceLocation::LineRange {
-                            start: line_num,
-                            end: line_num,
-                        },
-                        raw_hash: 0,
-                    })
-                    .build();
+                    .provenance(raw_line.anchor(&path))
+                    .try_build(ids)?;
 
                 logical_sequence += 1;
-                events.push(event);
+                out.send(event)?;
             }
             continue;
         }
@@ -379,18 +733,20 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                     wall_clock_ns: None,
                     logical_sequence,
                 })
-                .provenance(ProvenanceAnchor {
-                    source_file: "OUTCAR".to_string(),
-                    source_location: SourceLocation::LineRange {
-                        start: line_num,
-                        end: line_num,
-                    },
-                    raw_hash: 0,
-                })
-                .build();
+                .provenance(raw_line.anchor(&path))
+                .try_build(ids)?;
 
             logical_sequence += 1;
-            events.push(event);
+            out.send(event)?;
+            let lines = raw_line.hash();
+            force_table = Some(ForceTable {
+                start_line: line_num,
+                end_line: line_num,
+                lines,
+                hash: lines.finish(),
+                forces: Vec::new(),
+                saw_separator: false,
+            });
             continue;
         }
 
@@ -406,19 +762,12 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                     wall_clock_ns: None,
                     logical_sequence,
                 })
-                .provenance(ProvenanceAnchor {
-                    source_file: "OUTCAR".to_string(),
-                    source_location: SourceLocation::LineRange {
-                        start: line_num,
-                        end: line_num,
-                    },
-                    raw_hash: 0,
-                })
-                .build();
+                .provenance(raw_line.anchor(&path))
+                .try_build(ids)?;
 
             saw_terminal_status = true;
             logical_sequence += 1;
-            events.push(event);
+            out.send(event)?;
             continue;
         }
 
@@ -434,24 +783,31 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                     wall_clock_ns: None,
                     logical_sequence,
                 })
-                .provenance(ProvenanceAnchor {
-                    source_file: "OUTCAR".to_string(),
-                    source_location: SourceLocation::LineRange {
-                        start: line_num,
-                        end: line_num,
-                    },
-                    raw_hash: 0,
-                })
-                .build();
+                .provenance(raw_line.anchor(&path))
+                .try_build(ids)?;
 
             saw_terminal_status = true;
             logical_sequence += 1;
-            events.push(event);
+            out.send(event)?;
         }
     }
 
+    if let Some(table) = force_table.take() {
+        if !table.forces.is_empty() {
+            out.send(force_grid_event(
+                table,
+                logical_sequence,
+                &path,
+                ids,
+                symbols,
+            )?)?;
+            logical_sequence += 1;
+        }
+    }
+    out.release()?;
+
     if !saw_terminal_status {
-        let timeout_line = content.lines().count().max(1) as u32;
+        let timeout_line = lines.count().max(1);
         let event = TraceEventBuilder::new()
             .layer(Layer::Implementation)
             .kind(EventKind::ExecutionStatus {
@@ -463,14 +819,12 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                 wall_clock_ns: None,
                 logical_sequence,
             })
-            .provenance(ProvenanceAnchor {
-                source_file: "OUTCAR".to_string(),
-                source_location: SourceLocation::LineRange {
-                    start: timeout_line,
-                    end: timeout_line,
-                },
-                raw_hash: 0,
-            })
+            .provenance(line_anchor(
+                &path,
+                timeout_line,
+                timeout_line,
+                last_line_hash,
+            ))
             .confidence(ConfidenceMeta {
                 completeness: Completeness::PartiallyInferred {
                     inference_method: "no completion marker in OUTCAR".to_string(),
@@ -478,109 +832,113 @@ pub fn parse_outcar(content: &str, seq_offset: u64) -> Result<Vec<TraceEvent>, A
                 field_coverage: 0.5,
                 notes: vec![],
             })
-            .build();
-        events.push(event);
+            .try_build(ids)?;
+        out.send(event)?;
     }
 
-    Ok(events)
+    Ok(())
 }
 
 impl DslAdapter for VaspAdapter {
-    fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError> {
-        let mut marker_positions = Vec::new();
-        if let Some(position) = raw.find(INCAR_MARKER) {
-            marker_positions.push((position, INCAR_MARKER));
+    fn framework(&self) -> &'static str {
+        "vasp"
+    }
+
+    /// `vasp.` version lines, section markers, INCAR tags from the
+    /// classification table, OSZICAR iteration lines and OUTCAR energies.
+    fn detect(&self, raw: &str) -> Detection {
+        let mut detection = Detection::default();
+        if let Some(version) = raw
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("vasp."))
+        {
+            let version = version.split_whitespace().next().unwrap_or(version);
+            detection.signal(0.7, format!("version line {}", version));
+        }
+        if [INCAR_MARKER, OSZICAR_MARKER, OUTCAR_MARKER]
+            .iter()
+            .any(|marker| raw.contains(marker))
+        {
+            detection.signal(0.5, "INCAR/OSZICAR/OUTCAR section markers");
         }
-        if let Some(position) = raw.find(OSZICAR_MARKER) {
-            marker_positions.push((position, OSZICAR_MARKER));
+        let mut tags: Vec<&str> = assignment_keys(raw, &['!', '#'])
+            .filter(|key| {
+                !matches!(
+                    classify_incar_parameter(key, "").1,
+                    BoundaryClassification::ContextDependent { .. }
+                )
+            })
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        if !tags.is_empty() {
+            detection.signal(
+                key_weight(tags.len()),
+                format!("INCAR tags {}", tags.join(", ")),
+            );
+        }
+        if raw.lines().map(str::trim_start).any(|line| {
+            line.starts_with("DAV:") || line.starts_with("RMM:") || line.contains(" E0=")
+        }) {
+            detection.signal(0.5, "OSZICAR iteration lines");
         }
-        if let Some(position) = raw.find(OUTCAR_MARKER) {
-            marker_positions.push((position, OUTCAR_MARKER));
+        if raw.contains("TOTEN") {
+            detection.signal(0.3, "OUTCAR TOTEN energy");
         }
-        marker_positions.sort_by_key(|(position, _)| *position);
+        detection
+    }
 
-        let mut incar_content: Option<&str> = None;
-        let mut oszicar_content: Option<&str> = None;
-        let mut outcar_content: Option<&str> = None;
+    /// `detect` on the contents, plus the INCAR, OSZICAR and OUTCAR names.
+    fn detect_file(&self, file: &TraceFile) -> Detection {
+        let mut detection = self.detect(&file.contents);
+        if [INCAR_FILE, OSZICAR_FILE, OUTCAR_FILE].contains(&file.file_name()) {
+            detection.signal(0.6, format!("file name {}", file.file_name()));
+        }
+        detection
+    }
 
-        if marker_positions.is_empty() {
+    fn parse_trace(&self, raw: &str) -> Result<LayeredEventLog, AdapterError> {
+        let mut bundle = TraceBundle::from_sections(
+            raw,
+            &[
+                (INCAR_MARKER, INCAR_FILE),
+                (OSZICAR_MARKER, OSZICAR_FILE),
+                (OUTCAR_MARKER, OUTCAR_FILE),
+            ],
+        );
+        if bundle.is_empty() {
             if raw.lines().any(|line| line.contains('=')) {
-                incar_content = Some(raw);
+                bundle.add(INCAR_FILE, raw);
             } else {
-                outcar_content = Some(raw);
-            }
-        } else {
-            for (idx, (position, marker)) in marker_positions.iter().enumerate() {
-                let start = *position + marker.len();
-                let end = marker_positions
-                    .get(idx + 1)
-                    .map(|(next_position, _)| *next_position)
-                    .unwrap_or(raw.len());
-                let section = raw[start..end].trim();
-
-                match *marker {
-                    INCAR_MARKER => incar_content = Some(section),
-                    OSZICAR_MARKER => oszicar_content = Some(section),
-                    OUTCAR_MARKER => outcar_content = Some(section),
-                    _ => {}
-                }
+                bundle.add(OUTCAR_FILE, raw);
             }
         }
+        self.parse_bundle(&bundle)
+    }
 
-        let mut incar_events = if let Some(content) = incar_content {
-            parse_incar(content)?
-        } else {
-            Vec::new()
-        };
-        let incar_event_ids: Vec<EventId> = incar_events.iter().map(|event| event.id).collect();
-
-        let mut oszicar_events = if let Some(content) = oszicar_content {
-            parse_oszicar(content, incar_events.len() as u64)?
-        } else {
-            Vec::new()
-        };
-
-        let mut outcar_events = if let Some(content) = outcar_content {
-            parse_outcar(content, (incar_events.len() + oszicar_events.len()) as u64)?
-        } else {
-            Vec::new()
-        };
-
-        let mut last_convergence_id: Option<EventId> = None;
-        let mut last_energy_event_id: Option<EventId> = None;
-
-        for event in &mut oszicar_events {
-            match &event.kind {
-                EventKind::ConvergencePoint { .. } => {
-                    event.causal_refs = incar_event_ids.clone();
-                    last_convergence_id = Some(event.id);
-                }
-                EventKind::EnergyRecord { .. } => {
-                    if let Some(convergence_id) = last_convergence_id {
-                        event.causal_refs = vec![convergence_id];
-                    }
-                    last_energy_event_id = Some(event.id);
-                }
-                _ => {}
-            }
-        }
+    /// Reads the files named INCAR, OSZICAR and OUTCAR, in any directory;
+    /// other files are ignored.
+    fn parse_bundle(&self, bundle: &TraceBundle) -> Result<LayeredEventLog, AdapterError> {
+        self.parse_streams(bundle.stream_sources())
+    }
+}
 
-        for event in &mut outcar_events {
-            match &event.kind {
-                EventKind::EnergyRecord { .. } => {
-                    event.causal_refs = incar_event_ids.clone();
-                    last_energy_event_id = Some(event.id);
-                }
-                EventKind::StateSnapshot { .. } => {
-                    event.causal_refs = incar_event_ids.clone();
-                }
-                EventKind::ExecutionStatus { .. } => {
-                    if let Some(energy_event_id) = last_energy_event_id {
-                        event.causal_refs = vec![energy_event_id];
-                    }
-                }
-                _ => {}
-            }
+impl StreamingAdapter for VaspAdapter {
+    /// INCAR is read whole; OSZICAR and OUTCAR are streamed.
+    fn stream(
+        &self,
+        mut sources: Vec<StreamSource<'_>>,
+        sink: &mut dyn EventSink,
+    ) -> Result<(), AdapterError> {
+        let incar = take_source(&mut sources, |path| file_name(path) == INCAR_FILE);
+        let oszicar = take_source(&mut sources, |path| file_name(path) == OSZICAR_FILE);
+        let outcar = take_source(&mut sources, |path| file_name(path) == OUTCAR_FILE);
+        if incar.is_none() && oszicar.is_none() && outcar.is_none() {
+            return Err(AdapterError::UnsupportedFormat(format!(
+                "no INCAR, OSZICAR or OUTCAR among {:?}",
+                source_paths(&sources)
+            )));
         }
 
         let experiment_ref = ExperimentRef {
@@ -597,23 +955,91 @@ impl DslAdapter for VaspAdapter {
             controlled_variables: Vec::new(),
             dag_refs: Vec::new(),
             provenance: ProvenanceAnchor {
-                source_file: "INCAR".to_string(),
+                source_file: incar
+                    .as_ref()
+                    .map_or(INCAR_FILE, |source| source.path.as_str())
+                    .into(),
                 source_location: SourceLocation::ExternalInput,
                 raw_hash: 0,
             },
         };
+        sink.begin(experiment_ref, spec)?;
 
-        let mut builder = LayeredEventLogBuilder::new(experiment_ref, spec);
-        for event in incar_events.drain(..) {
-            builder = builder.add_event(event);
-        }
-        for event in oszicar_events.drain(..) {
-            builder = builder.add_event(event);
+        let mut ids = EventIdAllocator::new();
+        let mut symbols = SymbolTable::new();
+        let incar_events = if let Some(mut source) = incar {
+            let mut content = String::new();
+            source.reader.read_to_string(&mut content)?;
+            parse_incar_file(&source.path, &content, &mut ids, &mut symbols)?
+        } else {
+            Vec::new()
+        };
+        let incar_event_ids: Vec<EventId> = incar_events.iter().map(|event| event.id).collect();
+        let mut seq_offset = incar_events.len() as u64;
+        for event in incar_events {
+            sink.event(event)?;
         }
-        for event in outcar_events.drain(..) {
-            builder = builder.add_event(event);
+
+        let mut last_convergence_id: Option<EventId> = None;
+        let mut last_energy_event_id: Option<EventId> = None;
+
+        if let Some(source) = oszicar {
+            let mut oszicar_count = 0;
+            stream_oszicar_file(
+                &source.path,
+                source.reader,
+                seq_offset,
+                &mut ids,
+                &mut symbols,
+                |mut event| {
+                    match &event.kind {
+                        EventKind::ConvergencePoint { .. } => {
+                            event.causal_refs = incar_event_ids.clone();
+                            last_convergence_id = Some(event.id);
+                        }
+                        EventKind::EnergyRecord { .. } => {
+                            if let Some(convergence_id) = last_convergence_id {
+                                event.causal_refs = vec![convergence_id];
+                            }
+                            last_energy_event_id = Some(event.id);
+                        }
+                        _ => {}
+                    }
+                    oszicar_count += 1;
+                    sink.event(event)
+                },
+            )?;
+            seq_offset += oszicar_count;
         }
 
-        Ok(builder.build())
+        if let Some(source) = outcar {
+            stream_outcar_file(
+                &source.path,
+                source.reader,
+                seq_offset,
+                &mut ids,
+                &mut symbols,
+                |mut event| {
+                    match &event.kind {
+                        EventKind::EnergyRecord { .. } => {
+                            event.causal_refs = incar_event_ids.clone();
+                            last_energy_event_id = Some(event.id);
+                        }
+                        EventKind::StateSnapshot { .. }
+                        | EventKind::ObservableMeasurement { .. } => {
+                            event.causal_refs = incar_event_ids.clone();
+                        }
+                        EventKind::ExecutionStatus { .. } => {
+                            if let Some(energy_event_id) = last_energy_event_id {
+                                event.causal_refs = vec![energy_event_id];
+                            }
+                        }
+                        _ => {}
+                    }
+                    sink.event(event)
+                },
+            )?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,109 @@
+"""Smoke tests for the PyO3 bindings.
+
+Build the extension into the active environment first:
+
+    maturin develop
+    pytest
+"""
+
+from pathlib import Path
+
+import numpy as np
+import pytest
+
+import lel_ir_prototype as lel
+
+TESTDATA = Path(__file__).resolve().parents[2] / "testdata"
+
+
+def read(relative):
+    return (TESTDATA / relative).read_text()
+
+
+@pytest.fixture
+def vasp_log():
+    return lel.VaspAdapter().parse_trace(read("vasp/converged_relaxation.vasp"))
+
+
+def test_parse_trace_and_events(vasp_log):
+    assert len(vasp_log) > 0
+    assert vasp_log.validate() == []
+    events = vasp_log.events()
+    assert len(events) == len(vasp_log)
+    first = events[0]
+    assert vasp_log.event(first["id"]) == first
+    assert vasp_log.event(2**63) is None
+
+
+def test_query_filters_and_orders(vasp_log):
+    energies = vasp_log.query(kind="EnergyRecord")
+    assert energies
+    assert all("EnergyRecord" in event["kind"] for event in energies)
+
+    steps = [event["temporal"]["simulation_step"] for event in energies]
+    newest = vasp_log.query(
+        kind="EnergyRecord", order="simulation_step", descending=True, limit=1
+    )
+    assert newest[0]["temporal"]["simulation_step"] == max(steps)
+
+    assert vasp_log.query(steps=(10**6, 10**6 + 1)) == []
+    with pytest.raises(ValueError):
+        vasp_log.query(steps=(5, 2))
+    with pytest.raises(ValueError):
+        vasp_log.query(order="random")
+
+
+def test_energy_and_convergence_series(vasp_log):
+    steps, values, unit = vasp_log.energy_series()
+    assert isinstance(steps, np.ndarray) and isinstance(values, np.ndarray)
+    assert steps.dtype == np.uint64 and values.dtype == np.float64
+    assert len(steps) == len(values) == len(vasp_log.query(kind="EnergyRecord"))
+    assert unit == "eV"
+
+    iterations, values, unit = vasp_log.convergence_series("dE")
+    assert len(iterations) == len(values) > 0
+    assert unit == "eV"
+    assert list(iterations[:2]) == [1, 2]
+
+    steps, values, unit = vasp_log.energy_series("no such component")
+    assert len(steps) == 0 and len(values) == 0 and unit is None
+
+
+def test_openmm_files_and_json_roundtrip():
+    log = lel.OpenMmAdapter().parse_files(
+        [("run/state.csv", read("openmm_state_datareporter/openmm84_default_kj.csv"))]
+    )
+    steps, values, unit = log.energy_series()
+    assert list(steps[:2]) == [100, 200]
+    assert unit == "kJ/mol"
+
+    restored = lel.LayeredEventLog.from_json(log.to_json())
+    assert len(restored) == len(log)
+
+
+def test_overlay_calls_check_their_log(vasp_log):
+    overlay = lel.CausalOverlay.from_log(vasp_log)
+    assert len(overlay) == len(vasp_log)
+
+    derived = [
+        position
+        for position, event in enumerate(vasp_log.events())
+        if event["causal_refs"]
+    ]
+    assert derived
+    ancestors = overlay.transitive_ancestors(derived[-1])
+    assert ancestors and all(position < len(vasp_log) for position in ancestors)
+
+    assert overlay.compare_predictions(vasp_log) == []
+    assert overlay.detect_confounders(vasp_log, "energy", "ENCUT") == []
+
+    other = lel.VaspAdapter().parse_trace(read("vasp/nonconverged_scf.vasp"))
+    with pytest.raises(ValueError):
+        overlay.compare_predictions(other)
+
+
+def test_classify_and_schema(vasp_log):
+    classified = lel.classify_all_convergence(vasp_log, "vasp")
+    assert classified and all("pattern" in item for item in classified)
+    assert "properties" in lel.json_schema() or "$defs" in lel.json_schema()
+    assert isinstance(lel.LOG_SCHEMA_VERSION, int)